@@ -0,0 +1,39 @@
+//! Embeds provenance information (git commit, build profile, rustc version) into the runner, so
+//! that recorded results can be traced back to the build that produced them.
+
+use std::process::Command;
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8(output.stdout)
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+fn main() {
+    let commit = command_output("git", &["rev-parse", "--short", "HEAD"])
+        .unwrap_or_else(|| "unknown".to_string());
+    let dirty = command_output("git", &["status", "--porcelain"])
+        .map(|status| !status.is_empty())
+        .unwrap_or(false);
+    let commit = if dirty { format!("{}-dirty", commit) } else { commit };
+
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = command_output(&rustc, &["--version"])
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let profile = std::env::var("PROFILE").unwrap_or_else(|_| "unknown".to_string());
+
+    println!("cargo:rustc-env=AOC_GIT_COMMIT={}", commit);
+    println!("cargo:rustc-env=AOC_RUSTC_VERSION={}", rustc_version);
+    println!("cargo:rustc-env=AOC_BUILD_PROFILE={}", profile);
+
+    // Paths are relative to this crate's manifest directory
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/index");
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,32 @@
+/// Provenance of this build of the runner, recorded by build.rs
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub commit: &'static str,
+    pub profile: &'static str,
+    pub rustc: &'static str,
+}
+
+pub const BUILD_INFO: BuildInfo = BuildInfo {
+    version: env!("CARGO_PKG_VERSION"),
+    commit: env!("AOC_GIT_COMMIT"),
+    profile: env!("AOC_BUILD_PROFILE"),
+    rustc: env!("AOC_RUSTC_VERSION"),
+};
+
+impl BuildInfo {
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"version\": {}, \"commit\": {}, \"profile\": {}, \"rustc\": {}}}",
+            crate::json::string(self.version),
+            crate::json::string(self.commit),
+            crate::json::string(self.profile),
+            crate::json::string(self.rustc),
+        )
+    }
+}
+
+impl std::fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "aoc {} (commit {}, {} build, {})", self.version, self.commit, self.profile, self.rustc)
+    }
+}
@@ -0,0 +1,383 @@
+//! Command line runner for every day's solutions.
+
+mod build_info;
+mod json;
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use aoc2019::Answer;
+use build_info::BUILD_INFO;
+use intcode_vm::Backend;
+use util::cancel;
+
+const USAGE: &str = "\
+usage:
+    aoc --help
+    aoc --version
+    aoc list
+    aoc examples [--day <day>]
+    aoc run <day> [<part>] [--input <path>] [--json]
+    aoc bench <day> [<part>] [--iterations <n>] [--input <path>] [--backends] [--json]
+
+options:
+    --backends    Benchmark an Intcode day against every VM backend, checking that they all
+                  give the same answers";
+
+/// Command line arguments, split into positional arguments and `--flag [value]` options
+struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, Option<String>)>,
+}
+
+impl Args {
+    /// Options which take a value, all others are treated as boolean switches
+    const VALUED_OPTIONS: &'static [&'static str] = &["--day", "--input", "--iterations"];
+
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut positional = Vec::new();
+        let mut options = Vec::new();
+
+        let mut args = args;
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                positional.push(arg);
+            } else if Self::VALUED_OPTIONS.contains(&arg.as_str()) {
+                let value = args.next().ok_or(format!("{} requires a value", arg))?;
+                options.push((arg, Some(value)));
+            } else {
+                options.push((arg, None));
+            }
+        }
+
+        Ok(Self {
+            positional,
+            options,
+        })
+    }
+
+    fn switch(&self, name: &str) -> bool {
+        self.options.iter().any(|(option, _)| option == name)
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|(option, _)| option == name)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    fn positional_num(&self, idx: usize, name: &str) -> Result<Option<u32>, String> {
+        self.positional
+            .get(idx)
+            .map(|s| s.parse::<u32>().map_err(|_| format!("{} wasn't a valid {}", s, name)))
+            .transpose()
+    }
+}
+
+fn load_input(args: &Args, day: u32) -> Result<String, String> {
+    let path = match args.value("--input") {
+        Some(path) => PathBuf::from(path),
+        None => util::paths::input_path(day),
+    };
+
+    std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+/// The day given as the first argument after the subcommand, and the parts of it to run
+fn day_and_parts(args: &Args) -> Result<(u32, Vec<u32>), String> {
+    let day = args.positional_num(1, "day")?.ok_or("Missing <day> argument")?;
+    aoc2019::solution(day).map_err(|e| e.to_string())?;
+
+    let parts = match args.positional_num(2, "part")? {
+        Some(part) => vec![part],
+        None => vec![1, 2],
+    };
+
+    Ok((day, parts))
+}
+
+/// Lets ctrl-c stop long running solutions cooperatively, so their partial progress can be
+/// reported. A second ctrl-c exits immediately.
+fn install_interrupt_handler() {
+    let result = ctrlc::set_handler(|| {
+        if cancel::is_cancel_requested() {
+            std::process::exit(130);
+        }
+
+        eprintln!("Interrupted, stopping at the next checkpoint (ctrl-c again to abort)");
+        cancel::request_cancel();
+    });
+
+    if let Err(e) = result {
+        eprintln!("Failed to install ctrl-c handler: {}", e);
+    }
+}
+
+fn progress_report() -> String {
+    let progress = cancel::last_progress();
+    if progress.is_empty() {
+        return "No progress was reported before cancelling".to_string();
+    }
+
+    let mut report = "Progress when cancelled:".to_string();
+    for (name, value) in progress {
+        report.push_str(&format!("\n    {}: {}", name, value));
+    }
+    report
+}
+
+/// Solves a single part, attaching any partial progress to the error if it was cancelled
+fn solve(day: u32, part: u32, input: &str) -> Result<Answer, String> {
+    cancel::reset();
+    aoc2019::solve(day, part, input).map_err(|e| match e {
+        aoc2019::Error::Cancelled => format!("Day {} part {}: {}\n{}", day, part, e, progress_report()),
+        e => e.to_string(),
+    })
+}
+
+fn format_duration(d: Duration) -> String {
+    format!("{:.3}ms", d.as_secs_f64() * 1000.0)
+}
+
+fn answer_json(answer: &Answer) -> String {
+    match answer {
+        Answer::Number(num) => num.to_string(),
+        Answer::Text(text) => json::string(text),
+    }
+}
+
+fn cmd_run(args: &Args) -> Result<(), String> {
+    let (day, parts) = day_and_parts(args)?;
+    let input = load_input(args, day)?;
+
+    let mut results = Vec::new();
+    for part in parts {
+        let start = Instant::now();
+        let answer = solve(day, part, &input)?;
+        results.push((part, answer, start.elapsed()));
+    }
+
+    if args.switch("--json") {
+        let results_json = results.iter()
+            .map(|(part, answer, elapsed)| format!(
+                "{{\"day\": {}, \"part\": {}, \"answer\": {}, \"elapsed_ms\": {}}}",
+                day, part, answer_json(answer), elapsed.as_secs_f64() * 1000.0,
+            ))
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!("{{\"build\": {}, \"results\": [{}]}}", BUILD_INFO.to_json(), results_json);
+    } else {
+        for (part, answer, elapsed) in results {
+            println!("Day {} part {} ({}):\n{}", day, part, format_duration(elapsed), answer);
+        }
+    }
+
+    Ok(())
+}
+
+struct BenchResult {
+    part: u32,
+    backend: Backend,
+    iterations: u32,
+    min: Duration,
+    mean: Duration,
+    max: Duration,
+
+    /// Whether the answer was the same as with the first backend benchmarked for this part
+    answer_matches: bool,
+}
+
+fn cmd_bench(args: &Args) -> Result<(), String> {
+    let (day, parts) = day_and_parts(args)?;
+    let input = load_input(args, day)?;
+    let iterations = match args.value("--iterations") {
+        Some(n) => n.parse::<u32>().map_err(|_| format!("{} wasn't a valid iteration count", n))?,
+        None => 10,
+    };
+    if iterations == 0 {
+        return Err("Need at least one iteration".to_string());
+    }
+
+    let compare_backends = args.switch("--backends");
+    let original_backend = Backend::current_default();
+    let backends = if compare_backends {
+        if !aoc2019::solution(day).map_err(|e| e.to_string())?.puzzle.intcode {
+            return Err(format!("Day {} doesn't use the Intcode VM", day));
+        }
+        Backend::ALL.to_vec()
+    } else {
+        vec![original_backend]
+    };
+
+    let mut results = Vec::new();
+    for part in parts {
+        let mut reference_answer = None;
+        for &backend in &backends {
+            Backend::set_default(backend);
+
+            let mut timings = Vec::new();
+            let mut answer = None;
+            for _ in 0..iterations {
+                let start = Instant::now();
+                answer = Some(solve(day, part, &input)?);
+                timings.push(start.elapsed());
+            }
+
+            let answer = answer.unwrap();
+            let answer_matches = *reference_answer.get_or_insert_with(|| answer.clone()) == answer;
+
+            results.push(BenchResult {
+                part,
+                backend,
+                iterations,
+                min: *timings.iter().min().unwrap(),
+                mean: timings.iter().sum::<Duration>() / iterations,
+                max: *timings.iter().max().unwrap(),
+                answer_matches,
+            });
+        }
+    }
+    Backend::set_default(original_backend);
+
+    if args.switch("--json") {
+        let results_json = results.iter()
+            .map(|r| format!(
+                "{{\"day\": {}, \"part\": {}, \"backend\": {}, \"iterations\": {}, \"min_ms\": {}, \"mean_ms\": {}, \"max_ms\": {}, \"answer_matches\": {}}}",
+                day, r.part, json::string(r.backend.name()), r.iterations,
+                r.min.as_secs_f64() * 1000.0,
+                r.mean.as_secs_f64() * 1000.0,
+                r.max.as_secs_f64() * 1000.0,
+                r.answer_matches,
+            ))
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!("{{\"build\": {}, \"benchmarks\": [{}]}}", BUILD_INFO.to_json(), results_json);
+    } else if compare_backends {
+        println!("# {}", BUILD_INFO);
+        println!("| day | part | backend   | iterations | {:>12} | {:>12} | {:>12} | answer  |", "min", "mean", "max");
+        println!("|-----|------|-----------|------------|--------------|--------------|--------------|---------|");
+        for r in &results {
+            println!(
+                "| {:>3} | {:>4} | {:<9} | {:>10} | {:>12} | {:>12} | {:>12} | {:<7} |",
+                day, r.part, r.backend, r.iterations,
+                format_duration(r.min), format_duration(r.mean), format_duration(r.max),
+                if r.answer_matches { "same" } else { "DIFFERS" },
+            );
+        }
+    } else {
+        println!("# {}", BUILD_INFO);
+        println!("| day | part | iterations | {:>12} | {:>12} | {:>12} |", "min", "mean", "max");
+        println!("|-----|------|------------|--------------|--------------|--------------|");
+        for r in &results {
+            println!(
+                "| {:>3} | {:>4} | {:>10} | {:>12} | {:>12} | {:>12} |",
+                day, r.part, r.iterations,
+                format_duration(r.min), format_duration(r.mean), format_duration(r.max),
+            );
+        }
+    }
+
+    match results.iter().filter(|r| !r.answer_matches).count() {
+        0 => Ok(()),
+        n => Err(format!("{} backend(s) gave a different answer to the first", n)),
+    }
+}
+
+/// Overview of every day of the event, and which of them have been solved
+fn cmd_list() -> Result<(), String> {
+    let title_width = aoc2019::puzzles().iter().map(|p| p.title.len()).max().unwrap_or(0);
+
+    println!("| day | {:<width$} | intcode | solved | runtime |", "title", width = title_width);
+    println!("|-----|-{:-<width$}-|---------|--------|---------|", "", width = title_width);
+    for puzzle in aoc2019::puzzles() {
+        let runtime = aoc2019::solution(puzzle.day).ok().map(|solution| solution.runtime);
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+
+        println!(
+            "| {:>3} | {:<width$} | {:<7} | {:<6} | {:<7} |",
+            puzzle.day,
+            puzzle.title,
+            yes_no(puzzle.intcode),
+            yes_no(runtime.is_some()),
+            runtime.map(|r| r.to_string()).unwrap_or_default(),
+            width = title_width,
+        );
+    }
+
+    let solved = aoc2019::solutions().len();
+    println!("\n{} of {} days solved", solved, aoc2019::puzzles().len());
+
+    Ok(())
+}
+
+/// Checks the solutions against the worked examples from the puzzle statements
+fn cmd_examples(args: &Args) -> Result<(), String> {
+    let solutions = match args.value("--day") {
+        Some(day) => {
+            let day = day.parse::<u32>().map_err(|_| format!("{} wasn't a valid day", day))?;
+            vec![aoc2019::solution(day).map_err(|e| e.to_string())?]
+        }
+        None => aoc2019::solutions().iter().collect(),
+    };
+
+    let mut failures = 0;
+    for solution in solutions {
+        let day = solution.puzzle.day;
+        if solution.examples.is_empty() {
+            println!("Day {}: no examples", day);
+        }
+
+        for (idx, example) in solution.examples.iter().enumerate() {
+            cancel::reset();
+            let outcome = match solution.solve_example(example) {
+                Ok(answer) if answer.matches(example.answer) => format!("ok ({})", answer),
+                Ok(answer) => format!("FAILED, expected {} but got {}", example.answer, answer),
+                Err(aoc2019::Error::Cancelled) => format!("FAILED, cancelled\n{}", progress_report()),
+                Err(e) => format!("FAILED, {}", e),
+            };
+            if outcome.starts_with("FAILED") {
+                failures += 1;
+            }
+
+            println!("Day {} part {} example {}: {}", day, example.part, idx + 1, outcome);
+        }
+    }
+
+    match failures {
+        0 => Ok(()),
+        n => Err(format!("{} example(s) failed", n)),
+    }
+}
+
+fn run() -> Result<(), String> {
+    let args = Args::parse(std::env::args().skip(1))?;
+    install_interrupt_handler();
+
+    if args.switch("--help") {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+
+    if args.switch("--version") {
+        println!("{}", BUILD_INFO);
+        return Ok(());
+    }
+
+    match args.positional.first().map(|s| s.as_str()) {
+        Some("list") => cmd_list(),
+        Some("examples") => cmd_examples(&args),
+        Some("run") => cmd_run(&args),
+        Some("bench") => cmd_bench(&args),
+        Some(other) => Err(format!("Unrecognized command '{}'\n{}", other, USAGE)),
+        None => Err(USAGE.to_string()),
+    }
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,328 @@
+//! Programmatic access to every day's solutions, for embedding in other programs without going
+//! through the per-day binaries.
+
+use std::convert::TryFrom;
+
+use util::cancel::Cancelled;
+
+pub use util::example::Example;
+
+/// The answer to one part of a puzzle
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Answer {
+    Number(i64),
+
+    /// Some answers are only readable as rendered text, eg the painted registration identifier
+    Text(String),
+}
+
+impl Answer {
+    /// Whether this is the answer given in an example, which is written as it would be displayed
+    pub fn matches(&self, expected: &str) -> bool {
+        self.to_string() == expected
+    }
+}
+
+impl std::fmt::Display for Answer {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Answer::Number(num) => write!(f, "{}", num),
+            Answer::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+macro_rules! impl_answer_from_int {
+    ($($t:ty),+) => { $(
+        impl From<$t> for Answer {
+            fn from(num: $t) -> Self {
+                Answer::Number(i64::try_from(num).expect("Answer doesn't fit in an i64"))
+            }
+        }
+    )+ };
+}
+
+impl_answer_from_int!(i32, i64, i128, isize, u32, u64, usize);
+
+impl From<String> for Answer {
+    fn from(text: String) -> Self {
+        Answer::Text(text)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    UnknownDay(u32),
+    UnknownPart(u32),
+
+    /// The solution was stopped early through `util::cancel`
+    Cancelled,
+
+    /// The solution rejected its puzzle input, with a description of what was wrong with it
+    InvalidInput(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::UnknownDay(day) => write!(f, "There is no solution for day {}", day),
+            Error::UnknownPart(part) => write!(f, "Puzzles only have parts 1 and 2, not part {}", part),
+            Error::Cancelled => write!(f, "Solution was cancelled before it finished"),
+            Error::InvalidInput(reason) => write!(f, "Invalid puzzle input: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<Cancelled> for Error {
+    fn from(_: Cancelled) -> Self {
+        Error::Cancelled
+    }
+}
+
+impl From<day_8::ImageError> for Error {
+    fn from(e: day_8::ImageError) -> Self {
+        Error::InvalidInput(e.to_string())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Converts the return values of the day crates' part functions, which are either a plain answer
+/// or a `Result` for the ones that can be cancelled or can reject their input.
+trait IntoAnswer {
+    fn into_answer(self) -> Result<Answer>;
+}
+
+macro_rules! impl_into_answer {
+    ($($t:ty),+) => { $(
+        impl IntoAnswer for $t {
+            fn into_answer(self) -> Result<Answer> {
+                Ok(self.into())
+            }
+        }
+
+        impl<E: Into<Error>> IntoAnswer for std::result::Result<$t, E> {
+            fn into_answer(self) -> Result<Answer> {
+                self.map(Answer::from).map_err(Into::into)
+            }
+        }
+    )+ };
+}
+
+impl_into_answer!(i32, i64, i128, isize, u32, u64, usize, String);
+
+/// Information about one of the days of the event, whether or not it has been solved yet
+#[derive(Debug, PartialEq, Eq)]
+pub struct Puzzle {
+    pub day: u32,
+    pub title: &'static str,
+
+    /// Whether the puzzle is an Intcode program
+    pub intcode: bool,
+}
+
+macro_rules! puzzles {
+    ($($day:expr => $title:expr, $intcode:expr;)+) => {
+        const PUZZLES: &[Puzzle] = &[ $(
+            Puzzle { day: $day, title: $title, intcode: $intcode },
+        )+ ];
+    };
+}
+
+puzzles! {
+    1 => "The Tyranny of the Rocket Equation", false;
+    2 => "1202 Program Alarm", true;
+    3 => "Crossed Wires", false;
+    4 => "Secure Container", false;
+    5 => "Sunny with a Chance of Asteroids", true;
+    6 => "Universal Orbit Map", false;
+    7 => "Amplification Circuit", true;
+    8 => "Space Image Format", false;
+    9 => "Sensor Boost", true;
+    10 => "Monitoring Station", false;
+    11 => "Space Police", true;
+    12 => "The N-Body Problem", false;
+    13 => "Care Package", true;
+    14 => "Space Stoichiometry", false;
+    15 => "Oxygen System", true;
+    16 => "Flawed Frequency Transmission", false;
+    17 => "Set and Forget", true;
+    18 => "Many-Worlds Interpretation", false;
+    19 => "Tractor Beam", true;
+    20 => "Donut Maze", false;
+    21 => "Springdroid Adventure", true;
+    22 => "Slam Shuffle", false;
+    23 => "Category Six", true;
+    24 => "Planet of Discord", false;
+    25 => "Cryostasis", true;
+}
+
+/// Rough time taken for the slower of a solution's two parts in a release build
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Runtime {
+    /// Under 10ms
+    Instant,
+
+    /// Under a second
+    Fast,
+
+    /// A second or more, worth running in the background
+    Slow,
+}
+
+impl std::fmt::Display for Runtime {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            Runtime::Instant => "instant",
+            Runtime::Fast => "fast",
+            Runtime::Slow => "slow",
+        };
+        f.pad(name)
+    }
+}
+
+/// Both parts of a single day's puzzle
+pub struct Solution {
+    pub puzzle: &'static Puzzle,
+    pub runtime: Runtime,
+
+    /// Worked examples from the puzzle statement, for either part
+    pub examples: &'static [Example],
+
+    pub part_1: fn(&str) -> Result<Answer>,
+    pub part_2: fn(&str) -> Result<Answer>,
+}
+
+impl Solution {
+    pub fn solve(&self, part: u32, input: &str) -> Result<Answer> {
+        match part {
+            1 => (self.part_1)(input),
+            2 => (self.part_2)(input),
+            other => Err(Error::UnknownPart(other)),
+        }
+    }
+
+    /// Solves one of `examples`, with its own entry point if it has one
+    pub fn solve_example(&self, example: &Example) -> Result<Answer> {
+        match example.solver {
+            Some(solver) => solver(example.input).map(Answer::Text).map_err(Error::InvalidInput),
+            None => self.solve(example.part, example.input),
+        }
+    }
+}
+
+macro_rules! solutions {
+    ($($day:expr => $krate:ident, $runtime:ident;)+) => {
+        const SOLUTIONS: &[Solution] = &[ $(
+            Solution {
+                puzzle: &PUZZLES[$day - 1],
+                runtime: Runtime::$runtime,
+                examples: $krate::EXAMPLES,
+                part_1: |input| $krate::part_1(input).into_answer(),
+                part_2: |input| $krate::part_2(input).into_answer(),
+            },
+        )+ ];
+    };
+}
+
+solutions! {
+    1 => day_1, Instant;
+    2 => day_2, Instant;
+    3 => day_3, Instant;
+    4 => day_4, Fast;
+    5 => day_5, Instant;
+    6 => day_6, Instant;
+    7 => day_7, Instant;
+    8 => day_8, Instant;
+    9 => day_9, Fast;
+    10 => day_10, Instant;
+    11 => day_11, Instant;
+    12 => day_12, Instant;
+    13 => day_13, Fast;
+    14 => day_14, Instant;
+    15 => day_15, Fast;
+    16 => day_16, Fast;
+}
+
+/// Every implemented solution, ordered by day
+pub fn solutions() -> &'static [Solution] {
+    SOLUTIONS
+}
+
+pub fn solution(day: u32) -> Result<&'static Solution> {
+    SOLUTIONS
+        .iter()
+        .find(|solution| solution.puzzle.day == day)
+        .ok_or(Error::UnknownDay(day))
+}
+
+/// Every day of the event, including those without a solution yet
+pub fn puzzles() -> &'static [Puzzle] {
+    PUZZLES
+}
+
+/// Solves one part of a day's puzzle for the given puzzle input.
+///
+/// Long running solutions can be stopped early with `util::cancel::request_cancel`, in which case
+/// this returns `Err(Error::Cancelled)`.
+pub fn solve(day: u32, part: u32, input: &str) -> Result<Answer> {
+    solution(day)?.solve(part, input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve() {
+        assert_eq!(solve(1, 1, "12\n14\n1969"), Ok(Answer::Number(2 + 2 + 654)));
+        assert_eq!(solve(1, 2, "1969"), Ok(Answer::Number(966)));
+    }
+
+    #[test]
+    fn test_unknown_day() {
+        assert_eq!(solve(26, 1, ""), Err(Error::UnknownDay(26)));
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        let err = solve(8, 1, "0120\n").unwrap_err();
+        assert_eq!(err, Error::InvalidInput("Expected a multiple of 150 pixels (25x6 layers), got 4".to_string()));
+    }
+
+    #[test]
+    fn test_unknown_part() {
+        assert_eq!(solve(1, 3, ""), Err(Error::UnknownPart(3)));
+    }
+
+    #[test]
+    fn test_solutions_ordered() {
+        let days = solutions().iter().map(|s| s.puzzle.day).collect::<Vec<_>>();
+        assert_eq!(days, (1..=16).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_examples() {
+        for solution in solutions() {
+            for example in solution.examples {
+                let answer = solution.solve_example(example).unwrap();
+                assert!(
+                    answer.matches(example.answer),
+                    "Day {} part {} gave {} for an example, expected {}",
+                    solution.puzzle.day, example.part, answer, example.answer,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_puzzles() {
+        let days = puzzles().iter().map(|p| p.day).collect::<Vec<_>>();
+        assert_eq!(days, (1..=25).collect::<Vec<_>>());
+
+        let intcode_days = puzzles().iter().filter(|p| p.intcode).count();
+        assert_eq!(intcode_days, 12);
+    }
+}
@@ -0,0 +1,40 @@
+use util::example::Example;
+
+fn fuel_required(mass: u64) -> u64 {
+    std::cmp::max(mass / 3, 2) - 2
+}
+
+fn fuel_required_recursive(mass: u64) -> u64 {
+    let mut total = 0;
+    let mut extra = fuel_required(mass);
+    while extra > 0 {
+        total += extra;
+        extra = fuel_required(extra);
+    }
+
+    total
+}
+
+fn parse_masses(input: &str) -> impl Iterator<Item = u64> + '_ {
+    input.lines()
+        .map(|l| l.trim().parse::<u64>().unwrap_or_else(|_| panic!("{} wasn't a valid u64", l)))
+}
+
+pub const EXAMPLES: &[Example] = &[
+    Example::new(1, "12\n14\n1969\n100756", "34241"),
+    Example::new(2, "14\n1969\n100756", "51314"),
+];
+
+/// Sum of the fuel required for each module, ignoring the mass of the fuel itself
+pub fn part_1(input: &str) -> u64 {
+    parse_masses(input)
+        .map(fuel_required)
+        .sum()
+}
+
+/// Sum of the fuel required for each module, including the fuel needed to carry that fuel
+pub fn part_2(input: &str) -> u64 {
+    parse_masses(input)
+        .map(fuel_required_recursive)
+        .sum()
+}
@@ -0,0 +1,46 @@
+#![feature(test)]
+
+extern crate test;
+
+use test::Bencher;
+
+use day_10::{AsteroidField, best_station, best_station_naive};
+
+/// Generates a square asteroid field, where roughly `density_percent` of the cells are asteroids
+fn generate_field(size: usize, density_percent: u64, seed: u64) -> String {
+    // xorshift64, good enough for scattering asteroids around
+    let mut state = seed;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    (0..size)
+        .map(|_| (0..size)
+            .map(|_| if next() % 100 < density_percent { '#' } else { '.' })
+            .collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[bench]
+fn bench_naive(b: &mut Bencher) {
+    let field = AsteroidField::load_from_str(&generate_field(60, 25, 0x2019));
+    b.iter(|| best_station_naive(&field));
+}
+
+#[bench]
+fn bench_sweep(b: &mut Bencher) {
+    let field = AsteroidField::load_from_str(&generate_field(60, 25, 0x2019));
+    b.iter(|| best_station(&field));
+}
+
+#[test]
+fn test_generated_field_matches_naive() {
+    for seed in 1..5 {
+        let field = AsteroidField::load_from_str(&generate_field(40, 30, seed));
+        assert_eq!(best_station(&field), best_station_naive(&field));
+    }
+}
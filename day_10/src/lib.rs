@@ -0,0 +1,436 @@
+#![feature(slice_partition_dedup)]
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use util::math::gcd;
+use util::example::Example;
+
+
+enum CellContents {
+    Empty,
+    Asteroid,
+}
+
+impl CellContents {
+    fn from_char(c: char) -> Self {
+        match c {
+            '.' => CellContents::Empty,
+            '#' => CellContents::Asteroid,
+            other => panic!("Unrecognized asteroid map char: {}", other),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
+pub struct Coord {
+    x: i32,
+    y: i32,
+}
+
+impl std::ops::Sub for Coord {
+    type Output = Coord;
+
+    fn sub(self, other: Coord) -> Self::Output {
+        Coord {
+            x: self.x - other.x,
+            y: self.y - other.y,
+        }
+    }
+}
+
+impl Coord {
+    fn new(x: i32, y: i32) -> Self {
+        Self {
+            x, y
+        }
+    }
+
+    /// For a Coord of the form {N*x, N*y}, returns the tuple ({x, y}, N) where N >= 0.
+    fn simplify(self) -> (Self, i32) {
+        let n = gcd(self.y, self.x).abs();
+
+        if n == 0 {
+            (Coord {
+                x: 0,
+                y: 0,
+            }, 0)
+        } else {
+            (Coord {
+                x: self.x / n,
+                y: self.y / n,
+            }, n)
+        }
+    }
+
+    /// Clockwise angle in radians from straight up.
+    fn angle(&self) -> f32 {
+        // atan2 returns from the range [-pi, +pi] radians from (1, 0)
+        // Additionally, the y coordinate in the puzzle is backwards, ie, +ve y is down.
+        let raw = (-self.y as f32).atan2(self.x as f32);
+        let against_vertical = std::f32::consts::FRAC_PI_2 - raw;
+
+        // Normalize the angle to the range [0, 2*pi]
+        let two_pi = 2f32 * std::f32::consts::PI;
+        let normalized = (against_vertical + two_pi).rem_euclid(two_pi);
+
+        normalized
+    }
+}
+
+pub struct AsteroidField {
+    locs: Vec<Coord>,
+}
+
+impl AsteroidField {
+    pub fn load_from_str(data: &str) -> Self {
+        let mut locs = Vec::new();
+        for (y, row_str) in data.lines().enumerate() {
+            for (x, c) in row_str.chars().enumerate() {
+                match CellContents::from_char(c) {
+                    CellContents::Empty => (),
+                    CellContents::Asteroid => locs.push(Coord::new(x as i32, y as i32)),
+                }
+            }
+        }
+
+        Self {
+            locs: locs,
+        }
+    }
+}
+
+/// The asteroid that can see the most other asteroids, and how many it can see.
+///
+/// Checks every candidate by collecting the directions to all other asteroids into a HashSet.
+pub fn best_station_naive(field: &AsteroidField) -> (Coord, usize) {
+    let mut best: Option<(Coord, usize)> = None;
+    for root in field.locs.iter() {
+        let score = field.locs
+            .iter()
+            .filter(|other| *other != root)
+            .map(|other| {
+                let (base, _n) = (*other - *root).simplify();
+                base
+            })
+            .collect::<HashSet<_>>()
+            .len();
+
+        match best {
+            Some((_, curr_best_score)) if curr_best_score > score => (),
+            _ => best = Some((*root, score)),
+        }
+    }
+
+    best.expect("Asteroid field is empty")
+}
+
+/// Half 0 covers straight up through to just before straight down, half 1 the rest.
+/// The y coordinate in the puzzle is backwards, ie, +ve y is down.
+fn half(c: Coord) -> u8 {
+    if c.x > 0 || (c.x == 0 && c.y < 0) { 0 } else { 1 }
+}
+
+/// Orders two directions by clockwise angle from straight up, using only integer arithmetic.
+///
+/// Directions which point along the same ray compare as equal, regardless of their length.
+fn clockwise_cmp(a: Coord, b: Coord) -> std::cmp::Ordering {
+    // Within a half, a comes before b when the cross product is positive
+    let cross = a.x as i64 * b.y as i64 - a.y as i64 * b.x as i64;
+    half(a).cmp(&half(b)).then_with(|| 0.cmp(&cross))
+}
+
+/// A cheap stand-in for the clockwise angle from straight up, in the range [0, 4).
+///
+/// Increases monotonically with the true angle, and is exactly equal for directions along the
+/// same ray since both coordinates are scaled by the same factor before a correctly rounded
+/// division.
+fn pseudo_angle(c: Coord) -> f64 {
+    let s = c.y as f64 / (c.x.abs() + c.y.abs()) as f64;
+    match half(c) {
+        0 => 1.0 + s,
+        _ => 3.0 - s,
+    }
+}
+
+/// Scratch space for `visible_from`, reused between candidates to avoid reallocating
+#[derive(Default)]
+struct Sweep {
+    bucket_starts: Vec<usize>,
+    cursors: Vec<usize>,
+    directions: Vec<Coord>,
+}
+
+/// Counts the asteroids visible from `root` by sweeping clockwise over the directions to every
+/// other asteroid.
+///
+/// The directions are first distributed into angle buckets, so only the handful of directions in
+/// each bucket need sorting. Gives up and returns None as soon as it's certain that fewer than
+/// `to_beat` asteroids are visible.
+fn visible_from(
+    field: &AsteroidField,
+    root: Coord,
+    to_beat: usize,
+    sweep: &mut Sweep,
+) -> Option<usize> {
+    let others = field.locs.len() - 1;
+    let num_buckets = std::cmp::max(1, others);
+    let bucket_of = |c: Coord| {
+        std::cmp::min(num_buckets - 1, (pseudo_angle(c) * num_buckets as f64 / 4.0) as usize)
+    };
+
+    // Counting sort of the directions into their buckets
+    sweep.bucket_starts.clear();
+    sweep.bucket_starts.resize(num_buckets + 1, 0);
+    for other in field.locs.iter().filter(|other| **other != root) {
+        sweep.bucket_starts[bucket_of(*other - root) + 1] += 1;
+    }
+    for idx in 1..sweep.bucket_starts.len() {
+        sweep.bucket_starts[idx] += sweep.bucket_starts[idx - 1];
+    }
+
+    sweep.directions.clear();
+    sweep.directions.resize(others, Coord::new(0, 0));
+    sweep.cursors.clone_from(&sweep.bucket_starts);
+    for other in field.locs.iter().filter(|other| **other != root) {
+        let direction = *other - root;
+        let bucket = bucket_of(direction);
+        sweep.directions[sweep.cursors[bucket]] = direction;
+        sweep.cursors[bucket] += 1;
+    }
+
+    let mut visible = 0;
+    for bucket in 0..num_buckets {
+        let range = sweep.bucket_starts[bucket]..sweep.bucket_starts[bucket + 1];
+        let directions = &mut sweep.directions[range.clone()];
+        directions.sort_unstable_by(|a, b| clockwise_cmp(*a, *b));
+
+        for idx in 0..directions.len() {
+            if idx == 0 || clockwise_cmp(directions[idx - 1], directions[idx]) != std::cmp::Ordering::Equal {
+                visible += 1;
+            }
+        }
+
+        let remaining = others - range.end;
+        if visible + remaining < to_beat {
+            return None;
+        }
+    }
+
+    Some(visible)
+}
+
+/// The asteroid that can see the most other asteroids, and how many it can see.
+///
+/// Gives the same answer as `best_station_naive`, but splits the candidates between threads and
+/// abandons each candidate as soon as it can't beat the best found so far by any thread.
+pub fn best_station(field: &AsteroidField) -> (Coord, usize) {
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    let chunk_len = std::cmp::max(1, field.locs.len().div_ceil(threads));
+    let best_score = AtomicUsize::new(0);
+
+    // (index into field.locs, score), with ties going to the later index to match the naive search
+    let best = std::thread::scope(|scope| {
+        let workers = field.locs
+            .chunks(chunk_len)
+            .enumerate()
+            .map(|(chunk_idx, chunk)| {
+                let best_score = &best_score;
+                scope.spawn(move || {
+                    let mut sweep = Sweep::default();
+                    let mut local_best: Option<(usize, usize)> = None;
+                    for (offset, root) in chunk.iter().enumerate() {
+                        let to_beat = best_score.load(Ordering::Relaxed);
+                        if let Some(score) = visible_from(field, *root, to_beat, &mut sweep) {
+                            match local_best {
+                                Some((_, curr_best_score)) if curr_best_score > score => (),
+                                _ => local_best = Some((chunk_idx * chunk_len + offset, score)),
+                            }
+                            best_score.fetch_max(score, Ordering::Relaxed);
+                        }
+                    }
+                    local_best
+                })
+            })
+            .collect::<Vec<_>>();
+
+        workers.into_iter()
+            .filter_map(|worker| worker.join().expect("Visibility worker thread panicked"))
+            .max_by_key(|(idx, score)| (*score, *idx))
+    });
+
+    let (idx, score) = best.expect("Asteroid field is empty");
+    (field.locs[idx], score)
+}
+
+const SMALL_FIELD: &str = "\
+......#.#.
+#..#.#....
+..#######.
+.#.#.###..
+.#..#.....
+..#....#.#
+#..#....#.
+.##.#..###
+##...#..#.
+.#....####";
+
+const LARGE_FIELD: &str = "\
+.#..##.###...#######
+##.############..##.
+.#.######.########.#
+.###.#######.####.#.
+#####.##.#.##.###.##
+..#####..#.#########
+####################
+#.####....###.#.#.##
+##.#################
+#####.##.###..####..
+..######..##.#######
+####.##.####...##..#
+.#####..#.######.###
+##...#.##########...
+#.##########.#######
+.####.#.###.###.#.##
+....##.##.###..#####
+.#.#.###########.###
+#.#.#.#####.####.###
+###.##.####.##.#..##";
+
+pub const EXAMPLES: &[Example] = &[
+    Example::new(1, SMALL_FIELD, "33"),
+    Example::new(1, LARGE_FIELD, "210"),
+    Example::new(2, LARGE_FIELD, "802"),
+];
+
+/// How many other asteroids can be detected from the best monitoring station location
+pub fn part_1(input: &str) -> usize {
+    let field = AsteroidField::load_from_str(input);
+    best_station(&field).1
+}
+
+/// 100 * x + y for the 200th asteroid to be vaporized by the laser
+pub fn part_2(input: &str) -> i32 {
+    let field = AsteroidField::load_from_str(input);
+    let station_loc = best_station(&field).0;
+
+    let mut targets = field.locs
+        .iter()
+        .filter(|target| **target != station_loc)
+        .map(|target| {
+            let (base, n) = (*target - station_loc).simplify();
+            (target, base, n)
+        })
+        .collect::<Vec<_>>();
+
+    targets.sort_by_key(|(_, _, n)| *n);
+    targets.sort_by(|(_, a, _), (_, b, _)| a.angle().partial_cmp(&b.angle()).unwrap());
+    loop {
+        let (uniques, duplicates) = targets.partition_dedup_by_key(|(_, a, _)| *a);
+
+        if  duplicates.len() == 0 ||
+            duplicates.iter().all(|(_, base, _)| *base == uniques.last().unwrap().1)
+        {
+            break;
+        }
+    }
+
+    assert!(targets.len() >= 200);
+    let target = targets[199].0;
+    target.x * 100 + target.y
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OTHER_FIELD: &str = "\
+#.#...#.#.
+.###....#.
+.#....#...
+##.#.#.#.#
+....#.#.#.
+.##..###.#
+..#...##..
+..##....##
+......#...
+.####.###.";
+
+    #[test]
+    fn test_best_station() {
+        let field = AsteroidField::load_from_str(SMALL_FIELD);
+        assert_eq!(best_station(&field), (Coord::new(5, 8), 33));
+        assert_eq!(best_station_naive(&field), (Coord::new(5, 8), 33));
+
+        let field = AsteroidField::load_from_str(OTHER_FIELD);
+        assert_eq!(best_station(&field), (Coord::new(1, 2), 35));
+        assert_eq!(best_station_naive(&field), (Coord::new(1, 2), 35));
+    }
+
+    #[test]
+    fn test_best_station_ties_match_naive() {
+        // Every asteroid in a 3x3 block can see all 8 others except across the middle
+        let field = AsteroidField::load_from_str("###\n###\n###");
+        assert_eq!(best_station(&field), best_station_naive(&field));
+    }
+
+    #[test]
+    fn test_coord_simplify_positive() {
+        let c = Coord::new(4, 6);
+        let (simplified, n) = c.simplify();
+        assert_eq!(simplified, Coord::new(2, 3));
+        assert_eq!(n, 2);
+    }
+
+    #[test]
+    fn test_coord_simplify_negative() {
+        let c = Coord::new(-10, -20);
+        let (simplified, n) = c.simplify();
+        assert_eq!(simplified, Coord::new(-1, -2));
+        assert_eq!(n, 10);
+    }
+
+    #[test]
+    fn test_coord_simplify_mixed_1() {
+        let c = Coord::new(5, -15);
+        let (simplified, n) = c.simplify();
+        assert_eq!(simplified, Coord::new(1, -3));
+        assert_eq!(n, 5);
+    }
+
+    #[test]
+    fn test_coord_simplify_mixed_2() {
+        let c = Coord::new(-5, 15);
+        let (simplified, n) = c.simplify();
+        assert_eq!(simplified, Coord::new(-1, 3));
+        assert_eq!(n, 5);
+    }
+
+    #[test]
+    fn test_coord_simplify_zero_x() {
+        let c = Coord::new(0, 5);
+        let (simplified, n) = c.simplify();
+        assert_eq!(simplified, Coord::new(0, 1));
+        assert_eq!(n, 5);
+
+        let c = Coord::new(0, -5);
+        let (simplified, n) = c.simplify();
+        assert_eq!(simplified, Coord::new(0, -1));
+        assert_eq!(n, 5);
+    }
+
+    #[test]
+    fn test_coord_simplify_zero_y() {
+        let c = Coord::new(5, 0);
+        let (simplified, n) = c.simplify();
+        assert_eq!(simplified, Coord::new(1, 0));
+        assert_eq!(n, 5);
+
+        let c = Coord::new(-5, 0);
+        let (simplified, n) = c.simplify();
+        assert_eq!(simplified, Coord::new(-1, 0));
+        assert_eq!(n, 5);
+    }
+}
\ No newline at end of file
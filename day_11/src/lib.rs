@@ -0,0 +1,165 @@
+use std::collections::{HashSet, VecDeque};
+
+use intcode_vm::{ProgramElement, ProgramState};
+use util::geometry::{Rotation, CardDir};
+use util::vec2::Vec2;
+use util::example::Example;
+
+#[derive(Clone, Copy, Debug)]
+enum Color {
+    Black,
+    White,
+}
+
+/// The robot's notion of left and right is mirrored compared to `CardDir::vec`
+fn advance(pos: Vec2, dir: CardDir) -> Vec2 {
+    let step = match dir {
+        CardDir::Left => CardDir::Right,
+        CardDir::Right => CardDir::Left,
+        other => other,
+    };
+
+    pos + step.vec()
+}
+
+#[derive(Debug)]
+struct Board {
+    white_cells: HashSet<Vec2>,
+    painted_ever: HashSet<Vec2>,
+}
+
+impl Board {
+    /// Board starts out all black except for (0, 0), which has the given color
+    fn new(start_color: Color) -> Self {
+        let mut board = Self {
+            white_cells: HashSet::new(),
+            painted_ever: HashSet::new(),
+        };
+        if let Color::White = start_color {
+            board.white_cells.insert(Vec2::new(0, 0));
+        }
+        board
+    }
+
+    fn get_color_of(&self, coord: Vec2) -> Color {
+        if self.white_cells.contains(&coord) {
+            Color::White
+        } else {
+            Color::Black
+        }
+    }
+
+    fn set_color_of(&mut self, coord: Vec2, color: Color) {
+        self.painted_ever.insert(coord);
+
+        match color {
+            Color::White => self.white_cells.insert(coord),
+            Color::Black => self.white_cells.remove(&coord),
+        };
+    }
+
+    fn render(&self) -> String {
+        let origin = Vec2::new(0, 0);
+        let (min, max) = self.white_cells
+            .iter()
+            .fold((origin, origin), |(min, max), &c| (min.min(c), max.max(c)));
+
+        let rows = (max.y - min.y + 1) as usize;
+        let cols = (max.x - min.x + 1) as usize;
+
+        // [(min.x, min.y), (min.x + 1, min.y), ... (max.x - 1, max.y), (max.x, max.y)]
+        let mut buff = std::iter::repeat('░')
+            .take(rows * cols)
+            .collect::<Vec<char>>();
+
+        let to_buff_pos = move |c: &Vec2| {
+            let x = (c.x - min.x) as usize;
+            let y = (max.y - c.y) as usize;
+            y * cols + x
+        };
+
+        for white_coord in self.white_cells.iter() {
+            buff[to_buff_pos(white_coord)] = '█';
+        }
+
+        buff.chunks(cols)
+            .map(|row| row.iter().flat_map(|c| [c, c]).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[derive(Debug)]
+struct Robot {
+    pos: Vec2,
+    dir: CardDir,
+    board: Board,
+}
+
+impl Robot {
+    fn new(start_color: Color) -> Self {
+        Self {
+            pos: Vec2::new(0, 0),
+            dir: CardDir::Up,
+            board: Board::new(start_color),
+        }
+    }
+
+    fn sensor_reading(&self) -> ProgramElement {
+        match self.board.get_color_of(self.pos) {
+            Color::White => 1,
+            Color::Black => 0,
+        }
+    }
+
+    /// Carries out each pair of paint and movement commands the controller has output
+    fn process_commands(&mut self, outputs: &mut VecDeque<ProgramElement>) {
+        while outputs.len() >= 2 {
+            let color_command = outputs.pop_front().unwrap();
+            let movement_command = outputs.pop_front().unwrap();
+
+            match color_command {
+                0 => self.board.set_color_of(self.pos, Color::Black),
+                1 => self.board.set_color_of(self.pos, Color::White),
+                other => panic!("Unrecognized color painting command code: {}", other),
+            }
+
+            let rotation = match movement_command {
+                0 => Rotation::CounterClockwise,
+                1 => Rotation::Clockwise,
+                wat => panic!("Unrecognized movement command code: {}", wat),
+            };
+            self.dir = self.dir.turn(rotation);
+            self.pos = advance(self.pos, self.dir);
+        }
+    }
+}
+
+fn paint(program: &str, start_color: Color) -> Board {
+    let mut robot = Robot::new(start_color);
+    let mut controller = ProgramState::load_program_str(program);
+
+    controller
+        .run_with_input(|outputs| {
+            robot.process_commands(outputs);
+            Some(robot.sensor_reading())
+        })
+        .unwrap_or_else(|e| panic!("Robot controller failed: {}", e));
+
+    // Commands output after the last sensor reading
+    robot.process_commands(&mut controller.outputs);
+    robot.board
+}
+
+/// There are no example programs for the painting robot
+pub const EXAMPLES: &[Example] = &[];
+
+/// Number of panels painted at least once when starting on a black panel
+pub fn part_1(input: &str) -> usize {
+    paint(input, Color::Black).painted_ever.len()
+}
+
+/// The registration identifier painted when starting on a white panel
+pub fn part_2(input: &str) -> String {
+    paint(input, Color::White).render()
+}
@@ -0,0 +1,157 @@
+use util::{capture, example::Example, vec3::Vec3, math::lcm3, cancel::{self, Cancelled}};
+
+/// How many steps the period search takes between cancellation checkpoints
+const CHECKPOINT_INTERVAL: u64 = 1 << 16;
+
+#[derive(Clone)]
+struct Moon {
+    pos: Vec3,
+    vel: Vec3,
+}
+
+impl Moon {
+    fn new(x: i32, y: i32, z: i32) -> Self {
+        Self {
+            pos: Vec3::new(x, y, z),
+            vel: Vec3::new(0, 0, 0),
+        }
+    }
+
+    fn energy(&self) -> i32 {
+        self.pos.l1_norm() * self.vel.l1_norm()
+    }
+}
+
+impl std::fmt::Display for Moon {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "pos = {:^13} vel = {:^13}", self.pos, self.vel)
+    }
+}
+
+struct System {
+    moons: Vec<Moon>,
+}
+
+impl System {
+    fn new() -> Self {
+        Self {
+            moons: Vec::new(),
+        }
+    }
+
+    // Example 1 from the problem statement
+    //     <x=-1, y=0, z=2>
+    //     <x=2, y=-10, z=-7>
+    //     <x=4, y=-8, z=8>
+    //     <x=3, y=5, z=-1>
+    #[allow(dead_code)]
+    fn example_1() -> Self {
+        let mut s = Self::new();
+
+        s.moons.push(Moon::new(-1, 0, 2));
+        s.moons.push(Moon::new(2, -10, -7));
+        s.moons.push(Moon::new(4, -8, 8));
+        s.moons.push(Moon::new(3, 5, -1));
+
+        s
+    }
+
+    /// Parses one moon per line, each of the form `<x=-2, y=9, z=-5>`
+    fn parse(input: &str) -> Self {
+        let mut s = Self::new();
+
+        for line in input.lines().filter(|line| !line.trim().is_empty()) {
+            let (x, y, z) = capture!(line, "<x={}, y={}, z={}>", i32, i32, i32)
+                .unwrap_or_else(|e| panic!("Invalid moon: {}", e));
+            s.moons.push(Moon::new(x, y, z));
+        }
+
+        s
+    }
+
+    fn step(&mut self) {
+        for a in 0..self.moons.len() {
+            for b in (a + 1)..self.moons.len() {
+                let force = (self.moons[b].pos - self.moons[a].pos).signum();
+                self.moons[a].vel += force;
+                self.moons[b].vel -= force;
+            }
+        }
+
+        for moon in self.moons.iter_mut() {
+            moon.pos += moon.vel;
+        }
+    }
+
+    fn energy(&self) -> i32 {
+        self.moons.iter()
+            .map(|m| m.energy())
+            .sum()
+    }
+
+    fn period(&self) -> Result<u64, Cancelled> {
+        fn single_axis_period(axis: &str, positions: &[i32]) -> Result<u64, Cancelled> {
+            let mut positions = positions.iter().cloned().collect::<Vec<_>>();
+            let mut velocities = vec![0; positions.len()];
+            let target_velocities = velocities.clone();
+
+            fn do_step(positions: &mut [i32], velocities: &mut [i32]) {
+                for a in 0..velocities.len() {
+                    for b in (a + 1)..velocities.len() {
+                        let force =  (positions[b] - positions[a]).signum();
+                        velocities[a] += force;
+                        velocities[b] -= force;
+                    }
+                }
+
+                for (pos, vel) in positions.iter_mut().zip(velocities.iter()) {
+                    *pos += vel;
+                }
+            };
+
+            let mut steps = 0u64;
+            loop {
+                do_step(&mut positions, &mut velocities);
+                steps += 1;
+                if velocities == target_velocities {
+                    break;
+                }
+
+                if steps.is_multiple_of(CHECKPOINT_INTERVAL) {
+                    cancel::checkpoint(&[("axis", &axis), ("steps", &steps)])?;
+                }
+            }
+
+            Ok(steps * 2)
+        }
+
+        let x_period = single_axis_period("x", &mut self.moons.iter().map(|m| m.pos.x).collect::<Vec<_>>())?;
+        cancel::report_progress(&[("x_period", &x_period)]);
+        let y_period = single_axis_period("y", &mut self.moons.iter().map(|m| m.pos.y).collect::<Vec<_>>())?;
+        cancel::report_progress(&[("y_period", &y_period)]);
+        let z_period = single_axis_period("z", &mut self.moons.iter().map(|m| m.pos.z).collect::<Vec<_>>())?;
+
+        Ok(lcm3(x_period, y_period, z_period))
+    }
+}
+
+/// Only part 2's, as part 1's examples simulate fewer steps than the real puzzle
+pub const EXAMPLES: &[Example] = &[
+    Example::new(2, "<x=-1, y=0, z=2>\n<x=2, y=-10, z=-7>\n<x=4, y=-8, z=8>\n<x=3, y=5, z=-1>", "2772"),
+    Example::new(2, "<x=-8, y=-10, z=0>\n<x=5, y=5, z=10>\n<x=2, y=-7, z=3>\n<x=9, y=-8, z=-3>", "4686774924"),
+];
+
+/// Total energy in the system after 1000 steps
+pub fn part_1(input: &str) -> i32 {
+    let mut system = System::parse(input);
+    for _step in 0..1000 {
+        system.step();
+    }
+
+    system.energy()
+}
+
+/// Number of steps before the system returns to a previous state
+pub fn part_2(input: &str) -> Result<u64, Cancelled> {
+    System::parse(input).period()
+}
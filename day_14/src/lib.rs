@@ -0,0 +1,300 @@
+use std::collections::{BTreeMap, HashMap};
+use std::iter::FromIterator;
+use std::cmp::Ordering;
+
+use util::capture;
+use util::example::Example;
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+struct CompoundId(usize);
+
+/// Maps compound names to integer IDs.
+///
+/// Guarantees that issued IDs are in the range (0, CompoundBook::len()]
+/// ORE and FUEL have static IDs of CompoundId(0) and CompoundId(1) respectively.
+struct CompoundBook {
+    name_to_id_map: HashMap<String, CompoundId>,
+}
+
+impl CompoundBook {
+    fn new() -> Self {
+        Self {
+            name_to_id_map: HashMap::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.name_to_id_map.len()
+    }
+
+    fn get_or_add(&mut self, name: &str) -> CompoundId {
+        if let Some(id) = self.name_to_id_map.get(name) {
+            *id
+        } else {
+            let id = CompoundId(self.name_to_id_map.len());
+            self.name_to_id_map.insert(name.to_string(), id);
+            id
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct RecipeComponent {
+    compound: CompoundId,
+    quantity: u64,
+}
+
+#[derive(Debug)]
+struct Recipe {
+    inputs: Vec<RecipeComponent>,
+    output: RecipeComponent,
+}
+
+impl Recipe {
+    /// Parses a recipe of the form `7 A, 1 B => 1 C`
+    fn parse_from_str(s: &str, compounds: &mut CompoundBook) -> Self {
+        let (inputs, output) = capture!(s, "{} => {}", String, String)
+            .unwrap_or_else(|e| panic!("Invalid recipe: {}", e));
+
+        let mut parse_component = |component: &str| {
+            let (quantity, name) = capture!(component, "{} {}", u64, String)
+                .unwrap_or_else(|e| panic!("Invalid recipe component: {}", e));
+
+            RecipeComponent {
+                quantity,
+                compound: compounds.get_or_add(&name),
+            }
+        };
+
+        let inputs = inputs.split(',').map(&mut parse_component).collect();
+        let output = parse_component(&output);
+
+        Self {
+            inputs,
+            output,
+        }
+    }
+}
+
+struct RecipeBook {
+    compounds: CompoundBook,
+    recipes: Vec<Recipe>,
+
+    /// Maps a compound to the recipe that makes it
+    output_map: HashMap<CompoundId, usize>,
+}
+
+impl RecipeBook {
+    fn load_from_str(input: &str) -> Self {
+        let mut compounds = CompoundBook::new();
+
+        // Ensure ORE/FUEL get id's 0/1
+        assert_eq!(CompoundId(0), compounds.get_or_add("ORE"));
+        assert_eq!(CompoundId(1), compounds.get_or_add("FUEL"));
+
+        let recipes = input.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Recipe::parse_from_str(line, &mut compounds))
+            .collect::<Vec<_>>();
+
+        // Sanity check that there is only one way to make each thing
+        {
+            let mut outputs = std::iter::repeat(0)
+                .take(compounds.len())
+                .collect::<Vec<_>>();
+            for recipe in &recipes {
+                outputs[recipe.output.compound.0] += 1;
+            }
+            if outputs.iter().max() != Some(&1) {
+                panic!("There are multiple ways to make some compounds");
+            }
+        }
+
+        let output_map = HashMap::from_iter(recipes.iter()
+            .enumerate()
+            .map(|(idx, recipe)| (recipe.output.compound, idx))
+        );
+
+        Self {
+            compounds,
+            recipes,
+            output_map,
+        }
+    }
+
+    fn get_for_output(&self, id: CompoundId) -> &Recipe {
+        let recipe_idx = self.output_map
+            .get(&id)
+            .expect(&format!("Don't have reciped to make {:?}", id));
+        
+        &self.recipes[*recipe_idx]
+    }
+}
+
+
+/// Calculates how much ORE is needed to make a given amount of FUEL
+fn ore_for_fuel(recipes: &RecipeBook, required_fuel: u64) -> u64 {
+    ore_for_fuel_with_totals(recipes, required_fuel, None)
+}
+
+/// As ore_for_fuel, optionally adding the total amount of each compound produced along the way to
+/// `totals`, which is indexed by compound ID. The ORE total is the amount mined.
+fn ore_for_fuel_with_totals(
+    recipes: &RecipeBook,
+    required_fuel: u64,
+    mut totals: Option<&mut [u64]>,
+) -> u64 {
+    let mut needs = std::iter::repeat(0u64)
+        .take(recipes.compounds.len())
+        .collect::<Vec<_>>();
+    let mut leftovers = needs.clone();
+
+    let ore_idx = 0usize;
+    let fuel_idx = 1usize;
+
+    needs[fuel_idx] = required_fuel;
+
+    let mut any_work_done = true;
+    while any_work_done {
+        any_work_done = false;
+        for id in 1..needs.len() {
+            if needs[id] == 0 {
+                continue;
+            }
+
+            any_work_done = true;
+            let recipe = recipes.get_for_output(CompoundId(id));
+
+            // To satisfy the need for this compound, the recipe must be repeated `multiple` times
+            let mut multiple = needs[id] / recipe.output.quantity;
+            let leftover = (recipe.output.quantity - (needs[id] % recipe.output.quantity))
+                 % recipe.output.quantity;
+            if leftover != 0 {
+                multiple += 1;
+            }
+
+            if let Some(totals) = totals.as_deref_mut() {
+                totals[id] += multiple * recipe.output.quantity;
+            }
+
+            for input in &recipe.inputs {
+                let id = input.compound.0;
+                needs[id] += input.quantity * multiple;
+                let leftover_to_use = std::cmp::min(needs[id], leftovers[id]);
+                needs[id] -= leftover_to_use;
+                leftovers[id] -= leftover_to_use;
+            }
+
+            needs[id] = 0;
+            leftovers[id] += leftover;
+        }
+    }
+
+    if let Some(totals) = totals {
+        totals[ore_idx] += needs[ore_idx];
+    }
+
+    needs[ore_idx]
+}
+
+/// Total amount of every compound produced while making the given amount of FUEL, keyed by name.
+/// The ORE total is the amount that has to be mined.
+pub fn production_totals(input: &str, fuel: u64) -> BTreeMap<String, u64> {
+    let recipe_book = RecipeBook::load_from_str(input);
+    let mut totals = vec![0; recipe_book.compounds.len()];
+    ore_for_fuel_with_totals(&recipe_book, fuel, Some(&mut totals));
+
+    recipe_book.compounds.name_to_id_map
+        .iter()
+        .map(|(name, id)| (name.clone(), totals[id.0]))
+        .collect()
+}
+
+/// How much FUEL can be made from a given amount of ore
+fn fuel_for_ore(recipes: &RecipeBook, given_ore: u64) -> u64 {
+    // Just do a binary search on ore_for_fuel
+
+    let mut low = 0u64;
+    let mut high = None;
+
+    while low + 1 < high.unwrap_or(u64::max_value()) {
+        let test = match high {
+            Some(high) => (low + high) / 2,
+            None => (low * 2) + 1,
+        };
+
+        let ore_for_test = ore_for_fuel(recipes, test);
+
+        match given_ore.cmp(&ore_for_test) {
+            Ordering::Less => high = Some(test),
+            Ordering::Greater => low = test,
+            Ordering::Equal => return test,
+        }
+    }
+
+    low
+}
+
+const SIMPLE_RECIPES: &str = "\
+10 ORE => 10 A
+1 ORE => 1 B
+7 A, 1 B => 1 C
+7 A, 1 C => 1 D
+7 A, 1 D => 1 E
+7 A, 1 E => 1 FUEL";
+
+const LARGE_RECIPES: &str = "\
+157 ORE => 5 NZVS
+165 ORE => 6 DCFZ
+44 XJWVT, 5 KHKGT, 1 QDVJ, 29 NZVS, 9 GPVTF, 48 HKGWZ => 1 FUEL
+12 HKGWZ, 1 GPVTF, 8 PSHF => 9 QDVJ
+179 ORE => 7 PSHF
+177 ORE => 5 HKGWZ
+7 DCFZ, 7 PSHF => 2 XJWVT
+165 ORE => 2 GPVTF
+3 DCFZ, 7 NZVS, 5 HKGWZ, 10 PSHF => 8 KHKGT";
+
+pub const EXAMPLES: &[Example] = &[
+    Example::new(1, SIMPLE_RECIPES, "31"),
+    Example::new(1, LARGE_RECIPES, "13312"),
+    Example::new(2, LARGE_RECIPES, "82892753"),
+];
+
+/// Minimum ORE required to produce exactly 1 FUEL
+pub fn part_1(input: &str) -> u64 {
+    let recipe_book = RecipeBook::load_from_str(input);
+    ore_for_fuel(&recipe_book, 1)
+}
+
+/// Maximum FUEL that can be produced from one trillion ORE
+pub fn part_2(input: &str) -> u64 {
+    let recipe_book = RecipeBook::load_from_str(input);
+    fuel_for_ore(&recipe_book, 1000_000_000_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_production_totals() {
+        let totals = production_totals(SIMPLE_RECIPES, 1);
+        let expected = vec![
+            ("A", 30), ("B", 1), ("C", 1), ("D", 1), ("E", 1), ("FUEL", 1), ("ORE", 31),
+        ];
+        assert_eq!(
+            totals.iter().map(|(name, total)| (name.as_str(), *total)).collect::<Vec<_>>(),
+            expected,
+        );
+    }
+
+    #[test]
+    fn test_production_totals_scale() {
+        // Leftovers get reused, so making more FUEL at once is more efficient per unit
+        let one = production_totals(LARGE_RECIPES, 1);
+        let hundred = production_totals(LARGE_RECIPES, 100);
+        assert_eq!(one["ORE"], 13312);
+        assert_eq!(hundred["FUEL"], 100);
+        assert!(hundred["ORE"] < 100 * one["ORE"]);
+    }
+}
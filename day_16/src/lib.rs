@@ -0,0 +1,352 @@
+use util::cancel::{self, Cancelled};
+use util::example::Example;
+
+trait Chop {
+    fn chop(self) -> Self;
+}
+
+impl Chop for i32 {
+    fn chop(self) -> i32 {
+        self.abs() % 10
+    }
+}
+
+struct PatternIterator {
+    order: usize,
+    n1: usize,
+    n2: usize,
+}
+
+impl Iterator for PatternIterator {
+    type Item = i32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let out = match self.n2 % 4 {
+            0 => 0,
+            1 => 1,
+            2 => 0,
+            3 => -1,
+            _ => panic!("usize % 4 returned outside the set {0, 1, 2, 3}"),
+        };
+
+        self.n1 += 1;
+        if self.n1 == self.order {
+            self.n1 = 0;
+            self.n2 += 1;
+        }
+
+        Some(out)
+    }
+}
+
+fn pattern(order: usize) -> impl Iterator<Item=i32> {
+    PatternIterator {
+        order,
+        n1: 0,
+        n2: 0,
+    }
+}
+
+/// A single decimal digit of a signal, so that the arithmetic works on either compact `u8`
+/// storage or plain `i32`s
+pub trait Digit: Copy {
+    fn to_i32(self) -> i32;
+
+    /// Converts back from an `i32` that is already in the range 0..=9
+    fn from_i32(value: i32) -> Self;
+}
+
+impl Digit for u8 {
+    fn to_i32(self) -> i32 {
+        self as i32
+    }
+
+    fn from_i32(value: i32) -> Self {
+        value as u8
+    }
+}
+
+impl Digit for i32 {
+    fn to_i32(self) -> i32 {
+        self
+    }
+
+    fn from_i32(value: i32) -> Self {
+        value
+    }
+}
+
+/// A signal stored as one byte per digit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signal {
+    digits: Vec<u8>,
+}
+
+impl Signal {
+    pub fn parse(input: &str) -> Self {
+        let digits = input.trim()
+            .chars()
+            .map(|c| c.to_digit(10).expect("Input byte wasn't an ascii number") as u8)
+            .collect();
+
+        Self { digits }
+    }
+
+    pub fn from_digits<D: Digit>(digits: &[D]) -> Self {
+        Self {
+            digits: digits.iter().map(|d| d.to_i32().chop() as u8).collect(),
+        }
+    }
+
+    pub fn to_i32s(&self) -> Vec<i32> {
+        self.digits.iter().map(|&d| d as i32).collect()
+    }
+
+    pub fn digits(&self) -> &[u8] {
+        &self.digits
+    }
+
+    pub fn len(&self) -> usize {
+        self.digits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    /// The signal repeated end to end `times` times
+    pub fn repeat(&self, times: usize) -> Self {
+        Self {
+            digits: self.digits.repeat(times),
+        }
+    }
+
+    /// The number formed by the digits in the given range, eg the message offset
+    pub fn number(&self, range: std::ops::Range<usize>) -> u64 {
+        self.digits[range]
+            .iter()
+            .fold(0, |acc, &d| acc * 10 + d as u64)
+    }
+
+    pub fn fft_round(&mut self) {
+        fft_round(&mut self.digits)
+    }
+}
+
+// Mutates the input signal with a single FFT round
+pub fn fft_round<D: Digit>(signal: &mut [D]) {
+    // A single round of fft is equivalent to multiplying an upper triangular matrix by the input
+    // signal. Eg, for an input of length 5, [i1 .. i5], mapping to output [o1 .. o5]
+    // [ o1 ]   [ 1  0 -1  0  1 ] [ i1 ]
+    // [ o2 ]   [ 0  1  1  0  0 ] [ i2 ]
+    // [ o3 ] = [ 0  0  1  1  1 ] [ i3 ]
+    // [ o4 ]   [ 0  0  0  1  1 ] [ i4 ]
+    // [ o5 ]   [ 0  0  0  0  1 ] [ i5 ]
+    //
+    // This means that oN is only influenced by iM, M>=N => the input vector can
+    // be mutated in place without affecting the result iff the elements are
+    // computed in order.
+
+    for idx in 0..signal.len() {
+        let value = pattern(idx + 1)
+            .skip(1)
+            .zip(signal.iter())
+            .map(|(p, i)| p * i.to_i32())
+            .sum::<i32>()
+            .chop();
+        signal[idx] = D::from_i32(value);
+    }
+}
+
+pub const EXAMPLES: &[Example] = &[
+    Example::new(1, "80871224585914546619083218645595", "24176176"),
+    Example::new(1, "19617804207202209144916044189917", "73745418"),
+    Example::new(1, "69317163492948606335995924319873", "52432133"),
+    Example::new(2, "03036732577212944063491565474664", "84462026"),
+    Example::new(2, "02935109699940807407585447034323", "78725270"),
+    Example::new(2, "03081770884921959731165446850517", "53553731"),
+];
+
+/// First eight digits of the output after 100 rounds of FFT
+pub fn part_1(input: &str) -> Result<u64, Cancelled> {
+    let mut signal = Signal::parse(input);
+
+    // Just perform the FFT rounds.
+    // Input is only 650 long, so O(650^2 * 100) ~= O(4.2e7) operations
+
+    for round in 0..100 {
+        cancel::checkpoint(&[("rounds_completed", &round)])?;
+        signal.fft_round();
+    }
+
+    Ok(signal.number(0..8))
+}
+
+
+// An infinite iterator of multipliers for part2
+// n = 1 => all 1's
+// n = 2 => ascending numbers (1, 2, 3, 4, ...)
+// n = 3 => triangular numbers (1, 3, 6, 10, ...)
+// etc..
+// but all (mod 10), ie for n = 3, it actually outputs (1, 3, 6, 0, ...)
+fn multiplier_sequence(n: i32) -> impl Iterator<Item=i32> {
+    // Computes (a, b) (mod p) with Lucas's theorem
+    // https://en.wikipedia.org/wiki/Lucas%27s_theorem
+    fn lucas_binom(mut a: i32, mut b: i32, p: i32) -> i32 {
+        // cache[a][b] == binom(a, b)
+        let cache = [
+            [1, 0, 0, 0, 0],
+            [1, 1, 0, 0, 0],
+            [1, 2, 1, 0, 0],
+            [1, 3, 3, 1, 0],
+            [1, 4, 6, 4, 1],
+        ];
+
+        let mut binom = 1;
+        while b > 0 && binom > 0 {
+            binom = binom * cache[(a % p) as usize][(b % p) as usize];
+            a = a / p;
+            b = b / p;
+        }
+
+        binom % p
+    }
+
+    (0..).map(move |i| {
+        // Chinese remainder theorem to build x mod 10 from x mod 2 and x mod 5
+        // Bezout identity for 5 and 2:
+        //     1 * 5 + -2 * 2 = 1
+        // => x mod 10 = 5 * (x mod 2) - 4 * (x mod 5)
+        let mod_2 = lucas_binom(n + i - 1, i, 2);
+        let mod_5 = lucas_binom(n + i - 1, i, 5);
+        (5 * mod_2 + -4 * mod_5).rem_euclid(10)
+    })
+}
+
+/// The eight digit message embedded in the output of the signal repeated 10000 times
+pub fn part_2(input: &str) -> Result<u64, Cancelled> {
+    let signal = Signal::parse(input);
+    final_message(signal.digits())
+}
+
+/// The message from part 2 for any representation of the (unrepeated) signal
+pub fn final_message<D: Digit>(input: &[D]) -> Result<u64, Cancelled> {
+    // The matrix used in the FFT has the following properties:
+    //  - is square
+    //  - the Nth row (zero indexed) starts with N zeros, followed by N ones
+    //      - matrix is upper triangular
+    //      - The bottom ~1/2 of the rows are all [0, ..., 0, 1, ..., 1 ]
+    //
+    // The chop operation for non-negative numbers is just (mod 10), which is idempotent in
+    // both addition and multiplication. Ie,
+    //   ((a % 10) + (b % 10)) % 10 == (a + b) % 10
+    //   ((a % 10) * (b % 10)) % 10 == (a * b) % 10
+    //
+    // Consider the reversed signal, S, and function returning the output of N
+    // rounds of fft, f(S, N).
+    // 
+    // f(S, N)[0] = S[0].chop()
+    // f(S, 1)[1] = (S[1] + S[0]).chop()
+    // f(S, 1)[2] = (S[0] + S[1] + S[2]).chop()
+    //
+    // f(S, 1)[M] = S[M]
+    // f(S, N)[M] = \sum{i=0}{M}{ f(S, N-1)[i] k}.chop()
+    //            = \sum{i=0}{len(S) - M}{ binom(N + i - 1, i) * S[i + M] }.chop()
+    //
+    // binom(N + i - 1, i) will probably overflow for large N + i, so use Lucas's
+    // theorem + the chinese remainder theorem to compute it mod 10. That
+    // computation is in the `multiplier_sequence(N)` method.
+
+    let offset = input[0..7]
+        .iter()
+        .fold(0, |acc, num| acc * 10 + num.to_i32() as usize);
+    let signal_len = input.len() * 10_000;
+    assert!(offset as f32 / signal_len as f32 > 0.5);
+
+
+    // Access elements of the repeated signal, avoiding allocating a large buffer for it
+    let access = |idx: usize| {
+        input[idx % input.len()].to_i32()
+    };
+
+    // Value after 100 iterations of the reversed index
+    let final_value_at = |idx: usize| -> i32 {
+        (idx..(input.len() * 10_000))
+            .zip(multiplier_sequence(100))
+            .map(|(i, mul)| access(i) * mul)
+            .sum::<i32>() % 10
+    };
+
+    let mut message = 0u64;
+    for (digits_found, idx) in (offset..(offset + 8)).enumerate() {
+        cancel::checkpoint(&[("digits_found", &digits_found), ("message_so_far", &message)])?;
+        message = message * 10 + final_value_at(idx) as u64;
+    }
+
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chop() {
+        assert_eq!(0.chop(), 0);
+        assert_eq!(1.chop(), 1);
+        assert_eq!(9.chop(), 9);
+
+        assert_eq!(10.chop(), 0);
+        assert_eq!(11.chop(), 1);
+        assert_eq!(19.chop(), 9);
+
+        assert_eq!((-10).chop(), 0);
+        assert_eq!((-11).chop(), 1);
+        assert_eq!((-19).chop(), 9);
+    }
+
+    #[test]
+    fn test_pattern() {
+        assert_eq!(pattern(1).take(8).collect::<Vec<_>>(), vec![0, 1, 0, -1, 0, 1, 0, -1]);
+        assert_eq!(pattern(2).take(8).collect::<Vec<_>>(), vec![0, 0, 1, 1, 0, 0, -1, -1]);
+    }
+
+    #[test]
+    fn test_fft_round() {
+        let mut nums = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        fft_round(&mut nums);
+        assert_eq!(nums, vec![4, 8, 2, 2, 6, 1, 5, 8]);
+    }
+
+    #[test]
+    fn test_signal_fft_round() {
+        let mut signal = Signal::from_digits(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        signal.fft_round();
+        assert_eq!(signal.to_i32s(), vec![4, 8, 2, 2, 6, 1, 5, 8]);
+        assert_eq!(signal.number(0..4), 4822);
+    }
+
+    #[test]
+    fn test_signal_parse() {
+        let signal = Signal::parse("0123\n");
+        assert_eq!(signal.digits(), &[0, 1, 2, 3]);
+        assert_eq!(signal.repeat(2).digits(), &[0, 1, 2, 3, 0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_multiplier_sequence() {
+        let seq_1: Vec<_> = multiplier_sequence(1).take(5).collect();
+        assert_eq!(seq_1, vec![1, 1, 1, 1, 1]);
+
+        let seq_2: Vec<_> = multiplier_sequence(2).take(5).collect();
+        assert_eq!(seq_2, vec![1, 2, 3, 4, 5]);
+
+        let seq_3: Vec<_> = multiplier_sequence(3).take(5).collect();
+        // 1, 3, 6, 10, 15 (mod 10)
+        assert_eq!(seq_3, vec![1, 3, 6, 0, 5]);
+
+        let seq_4: Vec<_> = multiplier_sequence(4).take(5).collect();
+        // 1, 4, 10, 20, 35 (mod 10)
+        assert_eq!(seq_4, vec![1, 4, 0, 0, 5]);
+    }
+}
@@ -0,0 +1,160 @@
+use util::example::Example;
+
+const INSTR_ADD: usize = 1;
+const INSTR_MUL: usize = 2;
+const INSTR_TERM: usize = 99;
+
+trait Offset {
+    fn offset(&self, offset: isize) -> Self;
+}
+
+impl Offset for usize {
+    fn offset(&self, offset: isize) -> usize {
+        if offset > 0 {
+            self + (offset as usize)
+        } else {
+            self - (-offset as usize)
+        }
+    }
+}
+
+struct ProgramState {
+    values: Vec<usize>,
+    program_counter: usize,
+}
+
+impl ProgramState {
+    fn new(values: Vec<usize>) -> Self {
+        debug_assert!(values.len() > 0);
+
+        Self {
+            values,
+            program_counter: 0,
+        }
+    }
+
+    /// Returns the value at a given offset from the program counter
+    fn read_rel(&self, offset: isize) -> usize {
+        self.values[self.program_counter.offset(offset)]
+    }
+
+    /// Returns the value at the address stored at the given offset from the program counter
+    fn read_rel_ptr(&self, offset: isize) -> usize {
+        let idx = self.read_rel(offset);
+        self.values[idx]
+    }
+
+    fn terminated(&self) -> bool {
+        self.read_rel(0) == INSTR_TERM
+    }
+
+    fn progress_state(&mut self) {
+        if self.terminated() {
+            return;
+        }
+
+        match self.read_rel(0) {
+            INSTR_ADD => {
+                let a = self.read_rel_ptr(1);
+                let b = self.read_rel_ptr(2);
+                let c_idx = self.read_rel(3);
+                self.values[c_idx] = a + b;
+            },
+            INSTR_MUL => {
+                let a = self.read_rel_ptr(1);
+                let b = self.read_rel_ptr(2);
+                let c_idx = self.read_rel(3);
+                self.values[c_idx] = a * b;
+            },
+            other => panic!(format!("Processing unknown opcode: \"{}\"", other)),
+        }
+
+        self.program_counter += 4;
+    }
+
+    fn run_to_completion(&mut self) {
+        while !self.terminated() {
+            self.progress_state();
+        }
+    }
+}
+
+fn parse_program(input: &str) -> Vec<usize> {
+    input
+        .split(',')
+        .map(|el| el.trim())
+        .map(|el| el.parse::<usize>().unwrap_or_else(|_| panic!("Failed to parse {} as u64", el)))
+        .collect()
+}
+
+/// Runs the program with the given noun and verb, returning the value left at address 0
+fn run_with(values: &[usize], noun: usize, verb: usize) -> usize {
+    let mut program = ProgramState::new(values.to_vec());
+    program.values[1] = noun;
+    program.values[2] = verb;
+    program.run_to_completion();
+    program.values[0]
+}
+
+/// Value at address 0 after running a program with its own noun and verb, for programs other
+/// than the puzzle input
+pub fn part_1_with(input: &str, noun: usize, verb: usize) -> usize {
+    run_with(&parse_program(input), noun, verb)
+}
+
+/// The statement's programs are run as they are, so keep the noun and verb they already have
+pub const EXAMPLES: &[Example] = &[
+    Example::new(1, "1,9,10,3,2,3,11,0,99,30,40,50", "3500")
+        .solved_by(|input| Ok(part_1_with(input, 9, 10).to_string())),
+    Example::new(1, "1,0,0,0,99", "2").solved_by(|input| Ok(part_1_with(input, 0, 0).to_string())),
+    Example::new(1, "2,3,0,3,99", "2").solved_by(|input| Ok(part_1_with(input, 3, 0).to_string())),
+    Example::new(1, "2,4,4,5,99,0", "2").solved_by(|input| Ok(part_1_with(input, 4, 4).to_string())),
+    Example::new(1, "1,1,1,4,99,5,6,0,99", "30").solved_by(|input| Ok(part_1_with(input, 1, 1).to_string())),
+];
+
+/// Value at address 0 after restoring the "1202 program alarm" state
+pub fn part_1(input: &str) -> usize {
+    part_1_with(input, 12, 2)
+}
+
+/// 100 * noun + verb for the noun/verb pair that produces 19690720
+pub fn part_2(input: &str) -> usize {
+    let values = parse_program(input);
+    let target = 19690720usize;
+
+    for noun in 0..100 {
+        for verb in 0..100 {
+            if run_with(&values, noun, verb) == target {
+                return 100 * noun + verb;
+            }
+        }
+    }
+
+    panic!("No noun/verb pair produces {}", target);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add() {
+        let mut program = ProgramState::new(vec![1, 0, 0, 0, 99]);
+        program.run_to_completion();
+        assert_eq!(program.values, vec![2, 0, 0, 0, 99]);
+    }
+
+    #[test]
+    fn test_mul() {
+        let mut program = ProgramState::new(vec![2, 3, 0, 3, 99]);
+        program.run_to_completion();
+        assert_eq!(program.values, vec![2, 3, 0, 6, 99]);
+    }
+
+    #[test]
+    fn test_nontrivial() {
+        let mut program = ProgramState::new(vec![1,1,1,4,99,5,6,0,99]);
+        program.run_to_completion();
+        assert_eq!(program.values, vec![30,1,1,4,2,5,6,0,99]);
+    }
+}
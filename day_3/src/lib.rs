@@ -0,0 +1,213 @@
+//! Define the coordinate system to be one where (1, 1) is a vector pointing up and right.
+
+use util::example::Example;
+
+#[derive(Clone, Copy, Debug)]
+struct Vector2 {
+    x: i64,
+    y: i64,
+}
+
+impl Vector2 {
+    fn new(x: i64, y: i64) -> Self {
+        Self {
+            x,
+            y
+        }
+    }
+
+    fn l1_norm(&self) -> u64 {
+        self.x.abs() as u64 + self.y.abs() as u64
+    }
+}
+
+impl std::ops::Sub for Vector2 {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self::Output {
+        Self {
+            x: self.x - other.x,
+            y: self.y - other.y,
+        }
+    }
+}
+
+impl std::ops::AddAssign for Vector2 {
+    fn add_assign(&mut self, other: Self) {
+        *self = Self {
+            x: self.x + other.x,
+            y: self.y + other.y,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Line {
+    /// The starting coordinate of this line
+    origin: Vector2,
+
+    /// The vector from the start of this wire to its terminus
+    ///
+    /// Expect that this is zero in precisely one of (x, y)
+    span: Vector2,
+}
+
+impl Line {
+    fn contains_point(&self, point: Vector2) -> bool {
+        let in_x = match self.span.x.signum() {
+            0 => point.x == self.origin.x,
+            1 => point.x > self.origin.x && point.x < (self.origin.x + self.span.x),
+            -1 => point.x < self.origin.x && point.x > (self.origin.x + self.span.x),
+            _ => unreachable!(),
+        };
+
+        let in_y = match self.span.y.signum() {
+            0 => point.y == self.origin.y,
+            1 => point.y > self.origin.y && point.y < (self.origin.y + self.span.y),
+            -1 => point.y < self.origin.y && point.y > (self.origin.y + self.span.y),
+            _ => unreachable!(),
+        };
+
+        in_x && in_y
+    }
+
+    /// The distance from the origin of this line to the given point
+    fn distance_to(&self, point: Vector2) -> u64 {
+        (self.origin - point).l1_norm()
+    }
+
+    /// If this line intersects with the other, the point at which they intersect
+    fn intersection_point(&self, other: &Line) -> Option<Vector2> {
+        if self.span.x == 0 && other.span.x == 0 {
+            // The lines are parallel -> they don't intersect
+            return None;
+        }
+
+        // The point where the lines would intersect if they were infinitely long
+        // Only valid because each line is axis aligned.
+        let point = Vector2 {
+            x: self.origin.x * self.span.y.abs().signum() + other.origin.x * other.span.y.abs().signum(),
+            y: self.origin.y * self.span.x.abs().signum() + other.origin.y * other.span.x.abs().signum(),
+        };
+
+        if self.contains_point(point) && other.contains_point(point) {
+            Some(point)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct WireNode {
+    point: Vector2,
+    length_before: u64,
+}
+
+/// Represents a wire made up multiple line segments
+struct Wire {
+    /// The line segments in this wire go between the nodes.
+    ///
+    /// For AoC day 2, part 1, the first node should be (0, 0)
+    nodes: Vec<WireNode>,
+}
+
+impl Wire {
+    fn from_puzzle_input(input: &str) -> Self {
+        assert!(input.is_ascii());
+
+        let mut cursor = Vector2::new(0, 0);
+        let mut nodes = vec![WireNode {
+                point: cursor.clone(),
+                length_before: 0,
+        }];
+
+        let mut total_len: u64 = 0;
+        for instr in input.split(",") {
+            let dir = &instr[0..1];
+            let len: i64  = instr[1..]
+                .parse()
+                .expect(&format!("\"{}\" wasn't a valid instruction", instr));
+
+            match dir {
+                "U" => cursor += Vector2::new(0, len),
+                "D" => cursor += Vector2::new(0, -len),
+                "L" => cursor += Vector2::new(-len, 0),
+                "R" => cursor += Vector2::new(len, 0),
+                other => panic!(format!("Unknown direction '{}'", other)),
+            }
+
+            total_len += len.abs() as u64;
+
+            nodes.push(WireNode {
+                point: cursor.clone(),
+                length_before: total_len,
+            });
+        }
+
+        Self {
+            nodes
+        }
+    }
+
+    fn iter_lines<'a>(&'a self) -> impl Iterator<Item = (Line, u64)> + 'a {
+        self.nodes
+            .windows(2)
+            .map(|parts| (
+                    Line {
+                    origin: parts[0].point,
+                    span: parts[1].point - parts[0].point,
+                },
+                parts[0].length_before,
+            ))
+    }
+}
+
+fn parse_wires(input: &str) -> (Wire, Wire) {
+    let mut wires = input.lines()
+        .map(|l| Wire::from_puzzle_input(l.trim()));
+
+    let a = wires.next().expect("Expected exactly two wires");
+    let b = wires.next().expect("Expected exactly two wires");
+    (a, b)
+}
+
+pub const EXAMPLES: &[Example] = &[
+    Example::new(1, "R8,U5,L5,D3\nU7,R6,D4,L4", "6"),
+    Example::new(1, "R75,D30,R83,U83,L12,D49,R71,U7,L72\nU62,R66,U55,R34,D71,R55,D58,R83", "159"),
+    Example::new(1, "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51\nU98,R91,D20,R16,D67,R40,U7,R15,U6,R7", "135"),
+    Example::new(2, "R8,U5,L5,D3\nU7,R6,D4,L4", "30"),
+    Example::new(2, "R75,D30,R83,U83,L12,D49,R71,U7,L72\nU62,R66,U55,R34,D71,R55,D58,R83", "610"),
+    Example::new(2, "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51\nU98,R91,D20,R16,D67,R40,U7,R15,U6,R7", "410"),
+];
+
+/// Manhattan distance from the origin to the closest intersection
+pub fn part_1(input: &str) -> u64 {
+    let (a, b) = parse_wires(input);
+
+    a.iter_lines().filter_map(|(a_line, _)| {
+            b.iter_lines().filter_map(|(b_line, _)| {
+                a_line.intersection_point(&b_line).map(|point| point.l1_norm())
+            })
+            .min()
+    })
+    .min()
+    .expect("The wires never intersect")
+}
+
+/// Fewest combined steps the wires must take to reach an intersection
+pub fn part_2(input: &str) -> u64 {
+    let (a, b) = parse_wires(input);
+
+    a.iter_lines().filter_map(|(a_line, a_base_length)| {
+            b.iter_lines().filter_map(|(b_line, b_base_length)| {
+                a_line.intersection_point(&b_line).map(|point|
+                    a_base_length + a_line.distance_to(point) +
+                    b_base_length + b_line.distance_to(point)
+                )
+            })
+            .min()
+    })
+    .min()
+    .expect("The wires never intersect")
+}
@@ -0,0 +1,55 @@
+use util::rle::runs;
+use util::example::Example;
+
+fn has_double(candidate: &str) -> bool {
+    runs(candidate.bytes()).any(|(_digit, len)| len >= 2)
+}
+
+fn two_adjacent(candidate: &str) -> bool {
+    runs(candidate.bytes()).any(|(_digit, len)| len == 2)
+}
+
+fn ascending(candidate: &str) -> bool {
+    candidate.as_bytes()
+        .windows(2)
+        .all(|pair| pair[0] <= pair[1])
+}
+
+/// Parses a puzzle input of the form "372304-847061"
+fn parse_range(input: &str) -> std::ops::RangeInclusive<u32> {
+    let mut bounds = input.trim()
+        .split('-')
+        .map(|bound| bound.parse::<u32>().unwrap_or_else(|_| panic!("{} wasn't a valid bound", bound)));
+
+    let low = bounds.next().expect("Range has no lower bound");
+    let high = bounds.next().expect("Range has no upper bound");
+    low..=high
+}
+
+/// The examples are single passwords, written here as ranges containing just that password
+pub const EXAMPLES: &[Example] = &[
+    Example::new(1, "111111-111111", "1"),
+    Example::new(1, "223450-223450", "0"),
+    Example::new(1, "123789-123789", "0"),
+    Example::new(2, "112233-112233", "1"),
+    Example::new(2, "123444-123444", "0"),
+    Example::new(2, "111122-111122", "1"),
+];
+
+/// Number of candidate passwords with ascending digits and at least one repeated digit
+pub fn part_1(input: &str) -> usize {
+    parse_range(input)
+        .map(|x| x.to_string())
+        .filter(|candidate| has_double(candidate))
+        .filter(|candidate| ascending(candidate))
+        .count()
+}
+
+/// Number of candidate passwords with ascending digits and a run of exactly two repeated digits
+pub fn part_2(input: &str) -> usize {
+    parse_range(input)
+        .map(|x| x.to_string())
+        .filter(|candidate| two_adjacent(candidate))
+        .filter(|candidate| ascending(candidate))
+        .count()
+}
@@ -1,24 +1,6 @@
-fn two_adjacent(candidate: &String) -> bool {
-    candidate.as_bytes()
-        .windows(4)
-        .filter(|window| window[0] != window[1])
-        .filter(|window| window[2] != window[3])
-        .any(|window| window[1] == window[2])
-}
-
-fn ascending(candidate: &String) -> bool {
-    candidate.as_bytes()
-        .windows(2)
-        .filter(|pair| pair[0] != b' ' && pair[1] != b' ')
-        .all(|pair| pair[0] <= pair[1])
-}
-
 fn main() {
-    let count = (372304..847061)
-        .map(|x| format!(" {} ", x))
-        .filter(two_adjacent)
-        .filter(ascending)
-        .count();
+    let input = util::paths::read_input(4).unwrap_or_else(|e| panic!("{}", e));
 
-    println!("There were {} valid candidate passwords", count);
-}
\ No newline at end of file
+    println!("There were {} candidate passwords (part 1)", day_4::part_1(&input));
+    println!("There were {} valid candidate passwords", day_4::part_2(&input));
+}
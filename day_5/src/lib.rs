@@ -0,0 +1,28 @@
+use intcode_vm::{ProgramElement, ProgramState};
+use util::example::Example;
+
+/// Runs the diagnostic program for the given system ID, returning the diagnostic code
+fn run_diagnostic(input: &str, system_id: ProgramElement) -> ProgramElement {
+    let mut program = ProgramState::load_program_str(input);
+    program.inputs = vec![system_id].into();
+    program.run_to_completion().unwrap_or_else(|e| panic!("Diagnostic program failed: {}", e));
+    *program.outputs.back().expect("Diagnostic program produced no output")
+}
+
+/// Part 1 runs with input 1 and part 2 with input 5
+pub const EXAMPLES: &[Example] = &[
+    Example::new(1, "3,0,4,0,99", "1"),
+    Example::new(2, "3,9,8,9,10,9,4,9,99,-1,8", "0"),
+    Example::new(2, "3,3,1107,-1,8,3,4,3,99", "1"),
+    Example::new(2, "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99", "999"),
+];
+
+/// Diagnostic code for the ship's air conditioner unit
+pub fn part_1(input: &str) -> ProgramElement {
+    run_diagnostic(input, 1)
+}
+
+/// Diagnostic code for the ship's thermal radiator controller
+pub fn part_2(input: &str) -> ProgramElement {
+    run_diagnostic(input, 5)
+}
@@ -1,9 +1,5 @@
-use intcode_vm::ProgramState;
-
 fn main() {
-    let source_path = std::path::Path::new("./input.txt");
-    let mut program = ProgramState::load_program_file(&source_path);
-    program.inputs = vec![5].into();
-    program.run_to_completion();
-    println!("Program outputs = {:?}", program.outputs);
-}
\ No newline at end of file
+    let input = util::paths::read_input(5).unwrap_or_else(|e| panic!("{}", e));
+    println!("Diagnostic code (part 1) = {}", day_5::part_1(&input));
+    println!("Diagnostic code (part 2) = {}", day_5::part_2(&input));
+}
@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+
+use util::example::Example;
+
+struct Object {
+    /// Index into the storage vector for the object that this object orbits
+    ///
+    /// COM doesn't orbit anything
+    parent_id: Option<usize>,
+
+    /// Cache of all the objcts that orbit this one - objects that have this one as their parent_id
+    ///
+    /// This field is kept up to date by the inherant impl methods on ObjectMap
+    children: Vec<usize>,
+
+    /// How many transitive+direct orbits does this object have. COM has a depth of 0.
+    ///
+    /// Starts out as Some(0), as a new object doesn't orbit anything yet
+    depth: Option<u32>,
+}
+
+pub struct OrbitMap {
+    object_storage: Vec<Object>,
+
+    /// Maps object name to index into object_storage
+    object_names: HashMap<String, usize>,
+}
+
+impl OrbitMap {
+    pub fn new() -> Self {
+        OrbitMap {
+            object_storage: Vec::new(),
+            object_names: HashMap::new(),
+        }
+    }
+
+
+    /// Gets the ID for the named object, or creates a new one.
+    fn get_or_create_object(&mut self, object_name: &str) -> usize  {
+        match self.object_names.get(object_name) {
+            Some(id) => id.clone(),
+            None => {
+                let id = self.object_storage.len();
+
+                self.object_storage.push(Object {
+                    parent_id: None,
+                    children: Vec::new(),
+                    depth: Some(0),
+                });
+                self.object_names.insert(object_name.to_string(), id);
+
+                id
+            }
+        }
+    }
+
+    pub fn add_orbit(&mut self, orbit_str: &str) {
+        let mut parts = orbit_str.trim().split(")");
+
+        let parent_name = parts.next().expect("Invalid orbit definition");
+        let parent_id = self.get_or_create_object(parent_name);
+
+        let child_name = parts.next().expect("Invalid orbit definition");
+        let child_id = self.get_or_create_object(child_name);
+
+        if self.object_storage[child_id].parent_id.is_some() {
+            panic!("Object '{}' has multiple parents");
+        }
+
+        self.attach(child_id, parent_id);
+    }
+
+    fn object_id(&self, object_name: &str) -> Option<usize> {
+        self.object_names.get(object_name).copied()
+    }
+
+    /// Makes child orbit parent, updating the depths of child and everything orbiting it
+    fn attach(&mut self, child_id: usize, parent_id: usize) {
+        self.object_storage[child_id].parent_id = Some(parent_id);
+        self.object_storage[parent_id].children.push(child_id);
+        self.update_subtree_depths(child_id);
+    }
+
+    /// Stops child orbiting its parent, returning the ID of the parent if it had one
+    fn detach(&mut self, child_id: usize) -> Option<usize> {
+        let parent_id = self.object_storage[child_id].parent_id.take()?;
+        let siblings = &mut self.object_storage[parent_id].children;
+        let idx = siblings.iter().position(|&id| id == child_id).expect("Children cache is inconsistent");
+        siblings.swap_remove(idx);
+
+        Some(parent_id)
+    }
+
+    /// Removes the named object's orbit, leaving it and everything orbiting it as a separate tree
+    /// rooted at that object.
+    ///
+    /// Returns false if there's no such object, or it didn't orbit anything to begin with.
+    pub fn remove_orbit(&mut self, object_name: &str) -> bool {
+        let id = match self.object_id(object_name) {
+            Some(id) => id,
+            None => return false,
+        };
+        if self.detach(id).is_none() {
+            return false;
+        }
+
+        self.update_subtree_depths(id);
+        true
+    }
+
+    /// Moves the named object, along with everything orbiting it, to orbit a new parent
+    pub fn reparent(&mut self, object_name: &str, new_parent_name: &str) {
+        let id = self.object_id(object_name)
+            .unwrap_or_else(|| panic!("There is no object called {}", object_name));
+        let new_parent_id = self.get_or_create_object(new_parent_name);
+
+        let mut cursor = Some(new_parent_id);
+        while let Some(ancestor_id) = cursor {
+            if ancestor_id == id {
+                panic!("Moving {} to orbit {} would create a cycle", object_name, new_parent_name);
+            }
+            cursor = self.object_storage[ancestor_id].parent_id;
+        }
+
+        self.detach(id);
+        self.attach(id, new_parent_id);
+    }
+
+    /// How many direct and indirect orbits the named object has, or `None` if there's no such
+    /// object
+    pub fn depth(&self, object_name: &str) -> Option<u32> {
+        self.object_storage[self.object_id(object_name)?].depth
+    }
+
+    /// Recomputes the depth of the given object and everything that transitively orbits it, based
+    /// on the depth of its parent
+    fn update_subtree_depths(&mut self, root_id: usize) {
+        let mut process_list = vec![root_id];
+        while let Some(id) = process_list.pop() {
+            let depth = match self.object_storage[id].parent_id {
+                Some(parent_id) => self.object_storage[parent_id].depth.map(|d| d + 1),
+                None => Some(0),
+            };
+
+            self.object_storage[id].depth = depth;
+            process_list.extend(&self.object_storage[id].children);
+        }
+    }
+
+    /// Fill in the depth field of every object
+    pub fn compute_depths(&mut self) {
+        let roots: Vec<usize> = self.object_storage
+            .iter()
+            .enumerate()
+            .filter(|(_id, object)| object.parent_id.is_none())
+            .map(|(id, _object)| id)
+            .collect();
+
+        for root_id in roots {
+            self.update_subtree_depths(root_id);
+        }
+    }
+
+    /// The ID of the first common ancestor of two nodes
+    pub fn lowest_common_ancestor(&self, a: usize, b: usize) -> Option<usize> {
+        // Populate a set of A's lineage. For deep maps a HashSet would be more efficient.
+        let mut a_ancestry = Vec::new();
+        let mut cursor = Some(a);
+        while cursor.is_some() {
+            a_ancestry.push(cursor.unwrap());
+            cursor = self.object_storage[cursor.unwrap()].parent_id;
+        }
+
+        // Find the first element in B's lineage that is in A's lineage.
+        cursor = Some(b);
+        while cursor.is_some() {
+            if a_ancestry.contains(&cursor.unwrap()) {
+                return cursor;
+            }
+            cursor = self.object_storage[cursor.unwrap()].parent_id;
+        }
+
+        None
+    }
+}
+
+impl Default for OrbitMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn load_orbit_map(input: &str) -> OrbitMap {
+    let mut orbit_map = OrbitMap::new();
+    for line in input.lines() {
+        orbit_map.add_orbit(line);
+    }
+    orbit_map.compute_depths();
+    orbit_map
+}
+
+const ORBITS: &str = "\
+COM)B
+B)C
+C)D
+D)E
+E)F
+B)G
+G)H
+D)I
+E)J
+J)K
+K)L";
+
+pub const EXAMPLES: &[Example] = &[
+    Example::new(1, ORBITS, "42"),
+    Example::new(2, "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN", "4"),
+];
+
+/// Total number of direct and indirect orbits
+pub fn part_1(input: &str) -> u32 {
+    let orbit_map = load_orbit_map(input);
+    orbit_map.object_storage
+        .iter()
+        .map(|object| object.depth.expect("Object depth wasn't computed"))
+        .sum()
+}
+
+/// Minimum number of orbital transfers to move from the object YOU orbit to the object SAN orbits
+pub fn part_2(input: &str) -> u32 {
+    let orbit_map = load_orbit_map(input);
+
+    let you_id = *orbit_map.object_names.get("YOU").expect("There is no object called YOU");
+    let san_id = *orbit_map.object_names.get("SAN").expect("There is no object called SAN");
+
+    let source_id = orbit_map.object_storage[you_id].parent_id.expect("YOU is a root");
+    let target_id = orbit_map.object_storage[san_id].parent_id.expect("SAN is a root");
+
+    let lca_id = orbit_map.lowest_common_ancestor(source_id, target_id).expect("YOU and SAN share no common ancestor");
+
+    let source_depth = orbit_map.object_storage[source_id].depth.unwrap();
+    let target_depth = orbit_map.object_storage[target_id].depth.unwrap();
+    let lca_depth = orbit_map.object_storage[lca_id].depth.unwrap();
+
+    source_depth + target_depth - 2*lca_depth
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_orbit() {
+        let mut map = load_orbit_map(ORBITS);
+        assert!(map.remove_orbit("E"));
+        assert!(!map.remove_orbit("E"));
+
+        assert_eq!(map.depth("E"), Some(0));
+        assert_eq!(map.depth("L"), Some(3));
+        assert_eq!(map.depth("D"), Some(3));
+        assert!(!map.object_storage[map.object_id("D").unwrap()].children.contains(&map.object_id("E").unwrap()));
+    }
+
+    #[test]
+    fn test_unknown_object() {
+        let mut map = load_orbit_map(ORBITS);
+        assert_eq!(map.depth("X"), None);
+        assert!(!map.remove_orbit("X"));
+        assert_eq!(map.depth("X"), None);
+    }
+
+    #[test]
+    fn test_reparent() {
+        let mut map = load_orbit_map(ORBITS);
+        map.reparent("J", "H");
+        assert_eq!(map.depth("J"), Some(4));
+        assert_eq!(map.depth("L"), Some(6));
+
+        // Matches rebuilding the map from scratch
+        let rebuilt = load_orbit_map(&ORBITS.replace("E)J", "H)J"));
+        for name in rebuilt.object_names.keys() {
+            assert_eq!(map.depth(name), rebuilt.depth(name));
+        }
+    }
+
+    #[test]
+    fn test_reparent_to_new_object() {
+        let mut map = load_orbit_map(ORBITS);
+        map.reparent("K", "M");
+        assert_eq!(map.depth("M"), Some(0));
+        assert_eq!(map.depth("K"), Some(1));
+        assert_eq!(map.depth("L"), Some(2));
+
+        map.reparent("M", "COM");
+        assert_eq!(map.depth("L"), Some(3));
+    }
+
+    /// Checks every cached depth matches what `compute_depths` finds from scratch
+    fn assert_depths_consistent(map: &mut OrbitMap) {
+        let cached: Vec<_> = map.object_storage.iter().map(|object| object.depth).collect();
+        map.compute_depths();
+        let computed: Vec<_> = map.object_storage.iter().map(|object| object.depth).collect();
+        assert_eq!(cached, computed);
+    }
+
+    #[test]
+    fn test_depths_consistent() {
+        let mut map = OrbitMap::new();
+        for line in ORBITS.lines() {
+            map.add_orbit(line);
+            assert_depths_consistent(&mut map);
+        }
+
+        map.add_orbit("X)COM");
+        assert_depths_consistent(&mut map);
+        assert_eq!(map.depth("COM"), Some(1));
+        assert_eq!(map.depth("L"), Some(8));
+
+        map.reparent("K", "M");
+        assert_depths_consistent(&mut map);
+        map.remove_orbit("D");
+        assert_depths_consistent(&mut map);
+        map.reparent("M", "I");
+        assert_depths_consistent(&mut map);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_reparent_cycle() {
+        let mut map = load_orbit_map(ORBITS);
+        map.reparent("D", "L");
+    }
+}
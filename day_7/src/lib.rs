@@ -0,0 +1,56 @@
+use intcode_vm::cluster::Pipeline;
+use intcode_vm::scheduler::StopReason;
+use intcode_vm::{ProgramElement, ProgramState};
+use permutohedron;
+use util::example::Example;
+
+fn test_phase_settings(
+    phase_settings: &[ProgramElement],
+    program: &ProgramState,
+) -> ProgramElement {
+    // For a single pass the last amplifier's output just ends up being sent back to an amplifier
+    // that has already terminated
+    let pipeline = phase_settings
+        .iter()
+        .enumerate()
+        .fold(Pipeline::feedback_loop(phase_settings.len()), |pipeline, (idx, &phase)| {
+            pipeline.input(idx, phase)
+        })
+        .input(0, 0);
+
+    let report = pipeline.run(program);
+    assert_eq!(report.stop_reason, StopReason::AllTerminated, "Amplifiers didn't terminate\n{}", report);
+
+    report.vms.last().unwrap().last_output.expect("Last amplifier didn't output anything")
+}
+
+/// The highest signal that can be sent to the thrusters, and the phase settings that produce it
+fn max_signal(
+    program: &ProgramState,
+    mut phases: Vec<ProgramElement>,
+) -> (ProgramElement, Vec<ProgramElement>) {
+    let phase_settings = permutohedron::Heap::new(&mut phases);
+
+    phase_settings
+        .map(|phase_setting| (test_phase_settings(&phase_setting[..], program), phase_setting))
+        .max_by_key(|(signal, _phase_setting)| *signal)
+        .unwrap()
+}
+
+pub const EXAMPLES: &[Example] = &[
+    Example::new(1, "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0", "43210"),
+    Example::new(1, "3,23,3,24,1002,24,10,24,1002,23,-1,23,101,5,23,23,1,24,23,23,4,23,99,0,0", "54321"),
+    Example::new(2, "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5", "139629729"),
+];
+
+/// Highest signal from a single pass through the amplifiers
+pub fn part_1(input: &str) -> ProgramElement {
+    let program = ProgramState::load_program_str(input);
+    max_signal(&program, (0..5).collect()).0
+}
+
+/// Highest signal from the amplifiers wired up in a feedback loop
+pub fn part_2(input: &str) -> ProgramElement {
+    let program = ProgramState::load_program_str(input);
+    max_signal(&program, (5..10).collect()).0
+}
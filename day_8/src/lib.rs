@@ -0,0 +1,171 @@
+use util::rle::runs;
+use util::example::Example;
+
+pub const WIDTH: usize = 25;
+pub const HEIGHT: usize = 6;
+
+/// How many times each of the digits 0, 1 and 2 appear in a layer, ignoring any others
+fn digit_counts(layer: &[u32]) -> [usize; 3] {
+    let mut sorted = layer.to_vec();
+    sorted.sort();
+
+    let mut counts = [0; 3];
+    for (digit, count) in runs(sorted) {
+        if let Some(total) = counts.get_mut(digit as usize) {
+            *total = count;
+        }
+    }
+    counts
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ImageError {
+    NoLayers,
+
+    /// The number of pixels isn't a whole number of layers
+    PartialLayer {
+        pixels: usize,
+        width: usize,
+        height: usize,
+    },
+
+    /// Pixels can only be black (0), white (1) or transparent (2) when decoding the image. The
+    /// checksum counts any digits, as its example does.
+    InvalidPixel {
+        idx: usize,
+        value: u32,
+    },
+}
+
+impl std::fmt::Display for ImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ImageError::NoLayers => write!(f, "Image has no layers"),
+            ImageError::PartialLayer { pixels, width, height } => write!(
+                f,
+                "Expected a multiple of {} pixels ({}x{} layers), got {}",
+                width * height, width, height, pixels,
+            ),
+            ImageError::InvalidPixel { idx, value } => write!(f, "Pixel {} has invalid value {}", idx, value),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+/// Splits the image into layers of `width * height` pixels, ignoring any non-digit characters
+/// such as trailing newlines
+fn parse_layers(input: &str, width: usize, height: usize) -> Result<Vec<Vec<u32>>, ImageError> {
+    let levels = input
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .collect::<Vec<_>>();
+
+    if levels.is_empty() {
+        return Err(ImageError::NoLayers);
+    }
+    if width * height == 0 || levels.len() % (width * height) != 0 {
+        return Err(ImageError::PartialLayer { pixels: levels.len(), width, height });
+    }
+
+    Ok(levels[..]
+        .chunks(width * height)
+        .map(|layer| layer.to_vec())
+        .collect())
+}
+
+/// Flattens all the layers of the image, returning one character per pixel, row by row
+pub fn decode_image(input: &str, width: usize, height: usize) -> Result<Vec<Vec<char>>, ImageError> {
+    let layers = parse_layers(input, width, height)?;
+    let pixels = layers.iter().flatten();
+    if let Some((idx, &value)) = pixels.enumerate().find(|(_, &value)| value > 2) {
+        return Err(ImageError::InvalidPixel { idx, value });
+    }
+
+    let mut rendered = vec![' '; width * height];
+    for layer in layers.iter().rev() {
+        for idx in 0..(width * height) {
+            match layer[idx] {
+                0 => rendered[idx] = '░',
+                1 => rendered[idx] = '█',
+                2 => (),
+                _ => unreachable!("Pixel values were checked above"),
+            }
+        }
+    }
+
+    Ok(rendered.chunks(width).map(|row| row.to_vec()).collect())
+}
+
+/// The examples' images are smaller than the real puzzle's, so are solved with their own sizes
+pub const EXAMPLES: &[Example] = &[
+    Example::new(1, "123456789012", "1")
+        .solved_by(|input| checksum(input, 3, 2).map(|n| n.to_string()).map_err(|e| e.to_string())),
+    Example::new(2, "0222112222120000", "░█\n█░").solved_by(|input| render(input, 2, 2).map_err(|e| e.to_string())),
+];
+
+/// Number of 1 digits multiplied by the number of 2 digits in the layer with the fewest 0 digits,
+/// for an image of any size
+pub fn checksum(input: &str, width: usize, height: usize) -> Result<usize, ImageError> {
+    let checksum_counts = parse_layers(input, width, height)?
+        .iter()
+        .map(|layer| digit_counts(layer))
+        .min_by_key(|counts| counts[0])
+        .expect("parse_layers returned no layers");
+
+    Ok(checksum_counts[1] * checksum_counts[2])
+}
+
+/// The decoded image, one line of text per row of pixels, for an image of any size
+pub fn render(input: &str, width: usize, height: usize) -> Result<String, ImageError> {
+    Ok(decode_image(input, width, height)?
+        .iter()
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// The puzzle's checksum, see `checksum`
+pub fn part_1(input: &str) -> Result<usize, ImageError> {
+    checksum(input, WIDTH, HEIGHT)
+}
+
+/// The puzzle's decoded image, see `render`
+pub fn part_2(input: &str) -> Result<String, ImageError> {
+    render(input, WIDTH, HEIGHT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(digit: char) -> String {
+        digit.to_string().repeat(WIDTH * HEIGHT)
+    }
+
+    #[test]
+    fn test_trailing_newline() {
+        let input = format!("{}{}\r\n", layer('2'), layer('1'));
+        assert_eq!(parse_layers(&input, WIDTH, HEIGHT).map(|layers| layers.len()), Ok(2));
+        assert_eq!(part_1(&input), Ok(0));
+    }
+
+    #[test]
+    fn test_truncated_layer() {
+        let input = format!("{}{}", layer('0'), &layer('1')[10..]);
+        let err = part_1(&input).unwrap_err();
+        assert_eq!(err, ImageError::PartialLayer { pixels: 290, width: WIDTH, height: HEIGHT });
+        assert_eq!(err.to_string(), "Expected a multiple of 150 pixels (25x6 layers), got 290");
+    }
+
+    #[test]
+    fn test_no_layers() {
+        assert_eq!(part_2("\n"), Err(ImageError::NoLayers));
+    }
+
+    #[test]
+    fn test_invalid_pixel() {
+        let input = format!("{}3{}", &layer('0')[1..], layer('1'));
+        assert_eq!(part_2(&input), Err(ImageError::InvalidPixel { idx: 149, value: 3 }));
+    }
+}
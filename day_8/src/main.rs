@@ -1,36 +1,15 @@
-const WIDTH: usize = 25;
-const HEIGHT: usize = 6;
-
 fn main() {
-    let input = std::fs::read_to_string("./input.txt").expect("Failed to read input");
-
-    let levels = input
-        .chars()
-        .map(|c| c.to_digit(10).expect("Input character wasn't a digit"))
-        .collect::<Vec<_>>();
-
-    let layers = levels[..]
-        .chunks(WIDTH * HEIGHT)
-        .collect::<Vec<&[u32]>>();
+    let input = util::paths::read_input(8).unwrap_or_else(|e| panic!("{}", e));
 
-    let mut rendered = [' '; WIDTH * HEIGHT];
-    for layer in layers.iter().rev() {
-        for idx in 0..(WIDTH*HEIGHT) {
-            match layer[idx] {
-                0 => rendered[idx] = '░',
-                1 => rendered[idx] = '█',
-                2 => (),
-                _ => unreachable!(),
-            }
-        }
-    }
+    let rendered = day_8::decode_image(&input, day_8::WIDTH, day_8::HEIGHT).unwrap_or_else(|e| panic!("Invalid image: {}", e));
+    println!("Checksum: {}", day_8::part_1(&input).unwrap());
 
-    for row in rendered.chunks(WIDTH) {
+    for row in rendered {
         for _repeat in 0..2 {
-            for c in row {
+            for c in &row {
                 print!("{}{}{}", c, c, c);
             }
             print!("\n");
         }
     }
-}
\ No newline at end of file
+}
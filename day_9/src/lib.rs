@@ -0,0 +1,24 @@
+use intcode_vm::{ProgramElement, ProgramState};
+use util::example::Example;
+
+fn run_boost(input: &str, mode: ProgramElement) -> ProgramElement {
+    let mut program = ProgramState::load_program_str(input);
+    program.inputs.push_back(mode);
+    program.run_to_completion().unwrap_or_else(|e| panic!("BOOST program failed: {}", e));
+    *program.outputs.back().expect("BOOST program produced no output")
+}
+
+pub const EXAMPLES: &[Example] = &[
+    Example::new(1, "104,1125899906842624,99", "1125899906842624"),
+    Example::new(1, "1102,34915192,34915192,7,4,7,99,0", "1219070632396864"),
+];
+
+/// BOOST keycode produced in test mode
+pub fn part_1(input: &str) -> ProgramElement {
+    run_boost(input, 1)
+}
+
+/// Coordinates of the distress signal, produced in sensor boost mode
+pub fn part_2(input: &str) -> ProgramElement {
+    run_boost(input, 2)
+}
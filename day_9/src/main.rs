@@ -1,8 +1,5 @@
-use intcode_vm::ProgramState;
-
 fn main() {
-    let mut program = ProgramState::load_program_file(std::path::Path::new("./input.txt"));
-    program.inputs.push_back(2);
-    program.run_to_completion();
-    dbg!(&program.outputs);
+    let input = util::paths::read_input(9).unwrap_or_else(|e| panic!("{}", e));
+    dbg!(day_9::part_1(&input));
+    dbg!(day_9::part_2(&input));
 }
@@ -0,0 +1,406 @@
+//! Full-screen debugger for intcode programs, showing the disassembly around the program counter
+//! alongside the VM's state, its input and output queues, and a view of memory.
+//!
+//! usage: icdb_tui <program file>
+//!
+//! The plain `icdb` has more commands, eg watchpoints and snapshots, while this is quicker for
+//! stepping through a program and seeing what it does.
+
+use std::io;
+
+use crossterm::cursor;
+use crossterm::event::{self, Event, KeyCode};
+use crossterm::execute;
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::{Frame, Terminal};
+
+use intcode_vm::{disasm, ProgramElement, ProgramState, StopReason, VmError};
+
+const KEYS: &str = "s step  c continue  b breakpoint  i input  r reset  x hex  \u{2191}\u{2193} memory  q quit";
+
+/// Number of values on each row of the memory pane
+const MEM_ROW_LEN: usize = 8;
+
+/// Most instructions `continue` executes before handing control back, so that a program stuck in
+/// a loop doesn't freeze the debugger
+const CONTINUE_BUDGET: u64 = 10_000_000;
+
+struct App {
+    initial: ProgramState,
+    state: ProgramState,
+
+    /// First address shown in the memory pane
+    mem_start: usize,
+
+    /// Whether memory is shown in hexadecimal rather than decimal
+    hex: bool,
+
+    /// What's been typed so far while entering inputs, `None` when not entering any
+    input: Option<String>,
+
+    /// The outcome of the last key pressed
+    message: String,
+}
+
+impl App {
+    fn new(program: ProgramState) -> Self {
+        Self {
+            initial: program.clone(),
+            state: program,
+            mem_start: 0,
+            hex: false,
+            input: None,
+            message: String::new(),
+        }
+    }
+
+    fn step(&mut self) {
+        if self.state.terminated {
+            self.message = "The program has terminated".to_string();
+            return;
+        }
+
+        self.message = match self.state.progress_state() {
+            Ok(_) => String::new(),
+            Err(e) => describe_error(e),
+        };
+    }
+
+    fn resume(&mut self) {
+        self.message = match self.state.run_for(CONTINUE_BUDGET) {
+            Ok(StopReason::Terminated) => "The program has terminated".to_string(),
+            Ok(StopReason::NeedsInput) => describe_error(VmError::NoInput),
+            Ok(StopReason::Breakpoint(addr)) => format!("Stopped at breakpoint {}", addr),
+            Ok(StopReason::Watchpoint(hit)) => format!("Stopped at watchpoint: {}", hit),
+            Ok(StopReason::BudgetExhausted) => {
+                format!("Still running after {} instructions", CONTINUE_BUDGET)
+            }
+            Ok(StopReason::Output(_)) => unreachable!("Only returned by run_until_output"),
+            Ok(StopReason::Stepped) => unreachable!("Only returned by step_over and step_out"),
+            Err(e) => describe_error(e),
+        };
+    }
+
+    fn toggle_breakpoint(&mut self) {
+        let pc = self.state.program_counter;
+        if !self.state.breakpoints.remove(&pc) {
+            self.state.breakpoints.insert(pc);
+        }
+    }
+
+    fn reset(&mut self) {
+        // Breakpoints aren't part of the program, so survive the reset
+        let breakpoints = std::mem::take(&mut self.state.breakpoints);
+        self.state = self.initial.clone();
+        self.state.breakpoints = breakpoints;
+        self.message = "Reset the program".to_string();
+    }
+
+    /// Queues the inputs that have been typed, or explains why they couldn't be
+    fn submit_input(&mut self, typed: &str) {
+        let values = typed
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|v| !v.is_empty())
+            .map(|v| v.parse::<ProgramElement>().map_err(|_| v))
+            .collect::<Result<Vec<_>, _>>();
+
+        match values {
+            Ok(values) => self.state.inputs.extend(values),
+            Err(v) => self.message = format!("'{}' isn't a valid number", v),
+        }
+    }
+
+    /// Handles a single key press, returning false if the debugger should exit
+    fn handle_key(&mut self, key: KeyCode) -> bool {
+        if let Some(typed) = &mut self.input {
+            match key {
+                KeyCode::Char(c) => typed.push(c),
+                KeyCode::Backspace => {
+                    typed.pop();
+                }
+                KeyCode::Enter => {
+                    let typed = self.input.take().unwrap();
+                    self.submit_input(&typed);
+                }
+                KeyCode::Esc => self.input = None,
+                _ => (),
+            }
+            return true;
+        }
+
+        match key {
+            KeyCode::Char('s') => self.step(),
+            KeyCode::Char('c') => self.resume(),
+            KeyCode::Char('b') => self.toggle_breakpoint(),
+            KeyCode::Char('i') => {
+                self.input = Some(String::new());
+                self.message.clear();
+            }
+            KeyCode::Char('r') => self.reset(),
+            KeyCode::Char('x') => self.hex = !self.hex,
+            KeyCode::Up => self.mem_start = self.mem_start.saturating_sub(MEM_ROW_LEN),
+            KeyCode::Down => self.mem_start += MEM_ROW_LEN,
+            KeyCode::PageUp => self.mem_start = self.mem_start.saturating_sub(MEM_ROW_LEN * 8),
+            KeyCode::PageDown => self.mem_start += MEM_ROW_LEN * 8,
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            _ => (),
+        }
+
+        true
+    }
+}
+
+fn describe_error(e: VmError) -> String {
+    match e {
+        VmError::NoInput => "Waiting for input, press i to queue some".to_string(),
+        e => format!("Error: {}", e),
+    }
+}
+
+/// The disassembly lines around the program counter, as many as fit in `height`. Walking
+/// backwards from the program counter is ambiguous, so the lines before it come from walking
+/// forwards from address 0.
+fn disassembly(app: &App, height: usize) -> Vec<Line<'static>> {
+    let state = &app.state;
+    let pc = state.program_counter;
+
+    let before = disasm::disassemble(&state.mem, 0..pc)
+        .into_iter()
+        .filter(|line| line.addr + line.instruction.length() <= pc)
+        .collect::<Vec<_>>();
+    let before = &before[before.len().saturating_sub(height / 2)..];
+
+    // Instructions are at most four elements long, so this is always enough to fill the pane
+    let after = disasm::disassemble(&state.mem, pc..(pc + height * 4));
+
+    before
+        .iter()
+        .chain(&after)
+        .take(height)
+        .map(|line| {
+            let marker = if line.addr == pc { "=>" } else { "  " };
+            let breakpoint = if state.breakpoints.contains(&line.addr) { "*" } else { " " };
+            let text = format!("{}{} {}", marker, breakpoint, line);
+
+            let style = if line.addr == pc {
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            } else if state.breakpoints.contains(&line.addr) {
+                Style::default().fg(Color::Red)
+            } else {
+                Style::default()
+            };
+            Line::from(Span::styled(text, style))
+        })
+        .collect()
+}
+
+fn state_lines(app: &App) -> Vec<Line<'static>> {
+    let state = &app.state;
+    let status = if state.terminated { "terminated" } else { "running" };
+    let breakpoints = state.breakpoints.iter().map(|b| b.to_string()).collect::<Vec<_>>();
+
+    vec![
+        Line::from(format!("pc = {}", state.program_counter)),
+        Line::from(format!("rb = {}", state.relative_base)),
+        Line::from(format!("executed = {}", state.stats().total())),
+        Line::from(format!("status = {}", status)),
+        Line::from(format!("breakpoints = {}", breakpoints.join(", "))),
+    ]
+}
+
+fn io_lines(app: &App, width: usize) -> Vec<Line<'static>> {
+    let join = |values: &mut dyn Iterator<Item = &ProgramElement>| {
+        values.map(|v| v.to_string()).collect::<Vec<_>>().join(", ")
+    };
+
+    // Only the most recent outputs fit, so drop the oldest ones
+    let mut outputs = join(&mut app.state.outputs.iter());
+    let limit = width.saturating_sub(10);
+    if outputs.len() > limit {
+        let cut = outputs.len() - limit;
+        outputs = format!("...{}", &outputs[(cut + 3).min(outputs.len())..]);
+    }
+
+    vec![
+        Line::from(format!("inputs = {}", join(&mut app.state.inputs.iter()))),
+        Line::from(format!("outputs = {}", outputs)),
+    ]
+}
+
+fn memory_lines(app: &App, height: usize) -> Vec<Line<'static>> {
+    let state = &app.state;
+    (0..height)
+        .map(|row| {
+            let start = app.mem_start + row * MEM_ROW_LEN;
+            let mut values = [0; MEM_ROW_LEN];
+            state.mem.read_into(start, &mut values);
+
+            let mut spans = vec![Span::raw(format!("{:>6}:", start))];
+            for (offset, value) in values.iter().enumerate() {
+                let text = if app.hex { format!(" {:>8x}", value) } else { format!(" {:>8}", value) };
+                let style = if start + offset == state.program_counter {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                };
+                spans.push(Span::styled(text, style));
+            }
+            Line::from(spans)
+        })
+        .collect()
+}
+
+fn pane(title: &str) -> Block<'_> {
+    Block::default().borders(Borders::ALL).title(title)
+}
+
+/// Height of the inside of a pane with a border
+fn inner_height(area: Rect) -> usize {
+    area.height.saturating_sub(2) as usize
+}
+
+fn draw(f: &mut Frame, app: &App) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(2)])
+        .split(f.area());
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(rows[0]);
+    let right = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(7), Constraint::Length(4), Constraint::Min(0)])
+        .split(columns[1]);
+
+    let lines = disassembly(app, inner_height(columns[0]));
+    f.render_widget(Paragraph::new(lines).block(pane("Disassembly")), columns[0]);
+    f.render_widget(Paragraph::new(state_lines(app)).block(pane("State")), right[0]);
+
+    let lines = io_lines(app, right[1].width as usize);
+    f.render_widget(Paragraph::new(lines).block(pane("I/O")), right[1]);
+
+    let title = if app.hex { "Memory (hex)" } else { "Memory" };
+    let lines = memory_lines(app, inner_height(right[2]));
+    f.render_widget(Paragraph::new(lines).block(pane(title)), right[2]);
+
+    let status = match &app.input {
+        Some(typed) => format!("input> {}", typed),
+        None => app.message.clone(),
+    };
+    let status = vec![
+        Line::from(status),
+        Line::from(Span::styled(KEYS, Style::default().add_modifier(Modifier::DIM))),
+    ];
+    f.render_widget(Paragraph::new(status), rows[1]);
+}
+
+fn run<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<()> {
+    loop {
+        terminal.draw(|f| draw(f, app))?;
+
+        if let Event::Key(key) = event::read()? {
+            if !app.handle_key(key.code) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn main() -> io::Result<()> {
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: icdb_tui <program file>");
+            std::process::exit(1);
+        }
+    };
+
+    let source = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+        eprintln!("Failed to read {}: {}", path, e);
+        std::process::exit(1);
+    });
+
+    let program = source.parse::<ProgramState>().unwrap_or_else(|e| {
+        eprintln!("Failed to load {}: {}", path, e);
+        std::process::exit(1);
+    });
+
+    terminal::enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+
+    // A panic's message would be lost on the alternate screen, and leave the shell in raw mode
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        default_hook(info);
+    }));
+
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    let result = run(&mut terminal, &mut App::new(program));
+
+    // Put the terminal back the way it was, even if drawing failed
+    restore_terminal()?;
+    result
+}
+
+fn restore_terminal() -> io::Result<()> {
+    terminal::disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, cursor::Show)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+
+    fn press(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            let key = if c == '\n' { KeyCode::Enter } else { KeyCode::Char(c) };
+            assert!(app.handle_key(key));
+        }
+    }
+
+    #[test]
+    fn test_keys() {
+        let mut app = App::new(ProgramState::load_program_str("3,0,4,0,99"));
+        press(&mut app, "c");
+        assert_eq!(app.message, describe_error(VmError::NoInput));
+
+        press(&mut app, "i4x\n");
+        assert!(app.state.inputs.is_empty());
+        press(&mut app, "i4, 2\nc");
+        assert!(app.state.terminated);
+        assert_eq!(app.state.outputs, vec![4]);
+        assert_eq!(app.state.inputs, vec![2]);
+
+        press(&mut app, "ri1\ns");
+        press(&mut app, "b");
+        assert!(app.state.breakpoints.contains(&2));
+        press(&mut app, "r");
+        assert!(app.state.breakpoints.contains(&2));
+        assert_eq!(app.state.program_counter, 0);
+
+        assert!(!app.handle_key(KeyCode::Char('q')));
+    }
+
+    #[test]
+    fn test_draw() {
+        let mut app = App::new(ProgramState::load_program_str("1101,2,3,9,4,9,99,0,0,0"));
+        press(&mut app, "s");
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|f| draw(f, &app)).unwrap();
+        let screen = terminal.backend().buffer().content.iter().map(|c| c.symbol()).collect::<String>();
+
+        assert!(screen.contains(&format!("    {}", disasm::disassemble_one(&app.state.mem, 0))));
+        assert!(screen.contains(&format!("=>  {}", disasm::disassemble_one(&app.state.mem, 4))));
+        assert!(screen.contains("pc = 4"));
+        assert!(screen.contains("     8:        0        5"));
+    }
+}
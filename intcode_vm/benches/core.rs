@@ -0,0 +1,104 @@
+//! Criterion benchmarks for the pieces of the interpreter that the memory and decoding changes
+//! touch, run with `cargo bench -p intcode_vm --bench core`.
+//!
+//! The full runs use the synthetic programs in `benches/programs`, which stand in for the kinds of
+//! day programs whose inputs aren't all committed, plus the real day 9 program.
+
+use std::collections::VecDeque;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+
+use intcode_vm::analysis::iter_instructions;
+use intcode_vm::{asm, Backend, PagedMemory, ProgramElement, ProgramState};
+
+const BOOST: &str = include_str!("../../inputs/day_9.txt");
+
+/// Each program with the inputs it's run with, and the number of outputs that produces
+fn programs() -> Vec<(&'static str, Vec<ProgramElement>, Vec<ProgramElement>, usize)> {
+    let assemble = |source| asm::assemble(source).unwrap_or_else(|e| panic!("{}", e));
+    vec![
+        ("fib", assemble(include_str!("programs/fib.ica")), vec![20], 1),
+        ("sieve", assemble(include_str!("programs/sieve.ica")), vec![10_000], 1229),
+        ("screen", assemble(include_str!("programs/screen.ica")), vec![40, 25], 3000),
+        ("boost", intcode_vm::parse_program(BOOST).unwrap(), vec![2], 1),
+    ]
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    for (name, program, _, _) in programs() {
+        group.throughput(Throughput::Elements(program.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &program, |b, program| {
+            b.iter(|| iter_instructions(program).count())
+        });
+    }
+    group.finish();
+}
+
+/// Addresses `len` apart spread over a much bigger range, so that almost every access is to a
+/// different page
+fn sparse_addrs(len: usize) -> Vec<usize> {
+    (0..len).map(|i| (i * 7919) % len * 4096).collect()
+}
+
+fn memory(c: &mut Criterion) {
+    const LEN: usize = 4096;
+    let sequential = (0..LEN).collect::<Vec<_>>();
+    let sparse = sparse_addrs(LEN);
+
+    let mut group = c.benchmark_group("memory");
+    group.throughput(Throughput::Elements(LEN as u64));
+    for (pattern, addrs) in [("sequential", &sequential), ("sparse", &sparse)] {
+        // Only the first `LEN` addresses are dense, as with a freshly loaded program image
+        let mut paged = PagedMemory::<ProgramElement>::new();
+        let mut dense = PagedMemory::dense(vec![0; LEN]);
+        for &addr in addrs {
+            paged.write_addr(addr, addr as ProgramElement);
+            dense.write_addr(addr, addr as ProgramElement);
+        }
+
+        for (layout, mem) in [("paged", &paged), ("dense", &dense)] {
+            group.bench_function(BenchmarkId::new(format!("read_{}", layout), pattern), |b| {
+                b.iter(|| addrs.iter().map(|&addr| mem.read_addr(addr)).sum::<ProgramElement>())
+            });
+            group.bench_function(BenchmarkId::new(format!("write_{}", layout), pattern), |b| {
+                b.iter_batched_ref(
+                    || mem.clone(),
+                    |mem| {
+                        for &addr in addrs {
+                            mem.write_addr(addr, 1);
+                        }
+                    },
+                    BatchSize::SmallInput,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+fn runs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("run");
+    for (name, program, inputs, outputs) in programs() {
+        for &backend in Backend::ALL {
+            let mut state = ProgramState::new(program.iter().cloned(), VecDeque::from(inputs.clone()));
+            state.backend = backend;
+
+            let mut check = state.clone();
+            check.run_to_completion().unwrap();
+            assert_eq!(check.outputs.len(), outputs, "{} gave the wrong number of outputs", name);
+
+            group.bench_function(BenchmarkId::new(name, backend.name()), |b| {
+                b.iter_batched_ref(
+                    || state.clone(),
+                    |state| state.run_to_completion().unwrap(),
+                    BatchSize::SmallInput,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, decode, memory, runs);
+criterion_main!(benches);
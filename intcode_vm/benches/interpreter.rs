@@ -0,0 +1,84 @@
+#![feature(test)]
+
+extern crate test;
+
+use std::collections::VecDeque;
+
+use test::Bencher;
+
+use intcode_vm::{Backend, ProgramState};
+
+/// The self-replicating example program from day 9
+const QUINE: &str = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+
+const BOOST: &str = include_str!("../../inputs/day_9.txt");
+
+fn run_quine(b: &mut Bencher, backend: Backend) {
+    let mut program = ProgramState::load_program_str(QUINE);
+    program.backend = backend;
+    b.iter(|| {
+        let mut program = program.clone();
+        program.run_to_completion().unwrap();
+        program.outputs.len()
+    });
+}
+
+fn run_boost_sensor_mode(b: &mut Bencher, backend: Backend) {
+    run_boost_sensor_mode_on(b, ProgramState::load_program_str(BOOST), backend);
+}
+
+fn run_boost_sensor_mode_on(b: &mut Bencher, mut program: ProgramState, backend: Backend) {
+    program.backend = backend;
+    b.iter(|| {
+        let mut program = program.clone();
+        program.inputs = VecDeque::from(vec![2]);
+        program.run_to_completion().unwrap();
+        program.outputs[0]
+    });
+}
+
+#[bench]
+fn bench_quine(b: &mut Bencher) {
+    run_quine(b, Backend::Optimized);
+}
+
+#[bench]
+fn bench_quine_classic(b: &mut Bencher) {
+    run_quine(b, Backend::Classic);
+}
+
+#[bench]
+fn bench_quine_cached(b: &mut Bencher) {
+    run_quine(b, Backend::Cached);
+}
+
+#[bench]
+fn bench_quine_blocks(b: &mut Bencher) {
+    run_quine(b, Backend::Blocks);
+}
+
+#[bench]
+fn bench_boost_sensor_mode(b: &mut Bencher) {
+    run_boost_sensor_mode(b, Backend::Optimized);
+}
+
+#[bench]
+fn bench_boost_sensor_mode_classic(b: &mut Bencher) {
+    run_boost_sensor_mode(b, Backend::Classic);
+}
+
+#[bench]
+fn bench_boost_sensor_mode_cached(b: &mut Bencher) {
+    run_boost_sensor_mode(b, Backend::Cached);
+}
+
+#[bench]
+fn bench_boost_sensor_mode_blocks(b: &mut Bencher) {
+    run_boost_sensor_mode(b, Backend::Blocks);
+}
+
+#[bench]
+fn bench_boost_sensor_mode_dense(b: &mut Bencher) {
+    let program = ProgramState::load_program_str(BOOST).with_dense_memory();
+    run_boost_sensor_mode_on(b, program, Backend::Optimized);
+}
@@ -0,0 +1,226 @@
+//! Replaying scripted sessions of day 25's text adventure, for somewhere between playing it by
+//! hand and solving it automatically, eg to check that a known route still works.
+//!
+//! A script has one command per line, sent as it's written once the game asks for a command.
+//! Blank lines and `# comments` are skipped. A `#` followed directly by a letter starts a
+//! directive, of which there's only `#solve`, handing the game over to a solver, eg to find the
+//! right items to carry past the pressure plate:
+//!
+//! ```text
+//! # Pick up everything safe on the way to the checkpoint
+//! north
+//! take mug
+//! south
+//! #solve
+//! ```
+
+use crate::ascii::PromptError;
+use crate::{ProgramState, StopReason};
+
+/// What the game outputs when it's ready for the next command
+pub const PROMPT: &str = "Command?\n";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Step {
+    Command(String),
+
+    /// Hand the game over to the solver given to `replay`
+    Solve,
+}
+
+/// Why a script couldn't be parsed, with lines counted from 1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScriptError {
+    /// Only ASCII can be sent to the game
+    NonAscii { line: usize },
+
+    /// A directive other than `#solve`
+    UnknownDirective { line: usize, text: String },
+}
+
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ScriptError::NonAscii { line } => write!(f, "Line {} isn't ASCII", line),
+            ScriptError::UnknownDirective { line, text } => {
+                write!(f, "Line {} has unknown directive '{}'", line, text)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// Why a replay stopped before the end of its script, along with everything the game output
+/// until then
+#[derive(Debug)]
+pub struct ReplayError {
+    /// Index of the step that was being replayed, or `None` if it was waiting for the first prompt
+    pub step: Option<usize>,
+    pub error: PromptError,
+    pub transcript: String,
+}
+
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.step {
+            Some(step) => write!(f, "Replaying step {} failed: {}", step + 1, self.error),
+            None => write!(f, "Starting the game failed: {}", self.error),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Parses a script, see the module docs
+pub fn parse_script(source: &str) -> Result<Vec<Step>, ScriptError> {
+    let mut steps = Vec::new();
+    for (idx, line) in source.lines().enumerate() {
+        let text = line.trim();
+        if !text.is_ascii() {
+            return Err(ScriptError::NonAscii { line: idx + 1 });
+        }
+
+        if text == "#solve" {
+            steps.push(Step::Solve);
+        } else if text.strip_prefix('#').is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_alphabetic())) {
+            return Err(ScriptError::UnknownDirective { line: idx + 1, text: text.to_string() });
+        } else if !text.is_empty() && !text.starts_with('#') {
+            steps.push(Step::Command(text.to_string()));
+        }
+    }
+
+    Ok(steps)
+}
+
+/// Plays `script` against the game, returning a transcript of everything the game output with
+/// each command sent after the prompt it answers. `solve` is called for each `#solve` step, with
+/// the game waiting at a prompt, and should leave it either at another prompt or terminated,
+/// returning what the game output meanwhile.
+///
+/// The game terminating once the script has finished is fine, as day 25's does once it's given
+/// the password, but it terminating before then is an error.
+pub fn replay(
+    state: &mut ProgramState,
+    script: &[Step],
+    mut solve: impl FnMut(&mut ProgramState) -> Result<String, PromptError>,
+) -> Result<String, ReplayError> {
+    let mut transcript = String::new();
+    let fail = |step, error, transcript| Err(ReplayError { step, error, transcript });
+
+    match state.run_until_prompt(PROMPT) {
+        Ok(text) => transcript.push_str(&text),
+        Err(error) => return fail(None, error, transcript),
+    }
+
+    for (idx, step) in script.iter().enumerate() {
+        if state.terminated {
+            let error = PromptError::Stopped { reason: StopReason::Terminated, text: String::new() };
+            return fail(Some(idx), error, transcript);
+        }
+
+        let result = match step {
+            Step::Command(command) => {
+                transcript.push_str(command);
+                transcript.push('\n');
+                state.push_ascii_line(command);
+                state.run_until_prompt(PROMPT)
+            }
+            Step::Solve => solve(state),
+        };
+
+        match result {
+            Ok(text) => transcript.push_str(&text),
+            Err(PromptError::Stopped { reason: StopReason::Terminated, text }) => transcript.push_str(&text),
+            Err(error) => return fail(Some(idx), error, transcript),
+        }
+    }
+
+    Ok(transcript)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::asm;
+
+    /// Echoes each command back, and terminates after one starting with 'q'
+    const GAME: &str = "
+        start:
+            out 67
+            out 111
+            out 109
+            out 109
+            out 97
+            out 110
+            out 100
+            out 63
+            out 10
+            in [first]
+            add [first], 0, [c]
+        line:
+            eq [c], 10, [t]
+            jnz [t], end_line
+            out [c]
+            in [c]
+            jz 0, line
+        end_line:
+            out 10
+            eq [first], 113, [t]
+            jz [t], start
+            halt
+        first: data 0
+        c: data 0
+        t: data 0
+    ";
+
+    fn game() -> ProgramState {
+        ProgramState::new(asm::assemble(GAME).unwrap(), VecDeque::new())
+    }
+
+    #[test]
+    fn test_parse_script() {
+        let script = parse_script("north\n\n  # Comment\n#solve\n  take mug  \n").unwrap();
+        assert_eq!(script, vec![
+            Step::Command("north".to_string()),
+            Step::Solve,
+            Step::Command("take mug".to_string()),
+        ]);
+
+        assert_eq!(parse_script("north\nsüd"), Err(ScriptError::NonAscii { line: 2 }));
+        assert_eq!(
+            parse_script("#solev"),
+            Err(ScriptError::UnknownDirective { line: 1, text: "#solev".to_string() }),
+        );
+    }
+
+    #[test]
+    fn test_replay() {
+        let script = parse_script("north\n#solve\nquit").unwrap();
+        let mut solves = 0;
+        let transcript = replay(&mut game(), &script, |state| {
+            solves += 1;
+            state.push_ascii_line("west");
+            state.run_until_prompt(PROMPT)
+        })
+        .unwrap();
+
+        assert_eq!(solves, 1);
+        assert_eq!(transcript, "Command?\nnorth\nnorth\nCommand?\nwest\nCommand?\nquit\nquit\n");
+    }
+
+    #[test]
+    fn test_replay_past_end() {
+        let script = parse_script("quit\nnorth").unwrap();
+        let error = replay(&mut game(), &script, |_| unreachable!()).unwrap_err();
+        assert_eq!(error.step, Some(1));
+        assert_eq!(error.transcript, "Command?\nquit\nquit\n");
+        assert_eq!(error.to_string(), "Replaying step 2 failed: Program stopped before prompting: Terminated");
+    }
+}
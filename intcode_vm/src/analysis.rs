@@ -0,0 +1,509 @@
+//! Static analysis of program images, without running them.
+//!
+//! Intcode has no separation between code and data, and programs are free to modify themselves,
+//! so everything here is best-effort. Elements which don't decode as a valid instruction are
+//! assumed to be data.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+
+use crate::prelude::*;
+use crate::{OpCode, ParameterMode, ProgramElement};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodedInstruction {
+    /// A valid instruction, with the mode and raw contents of each of its parameters
+    Op {
+        opcode: OpCode,
+        parameters: Vec<(ParameterMode, ProgramElement)>,
+    },
+
+    /// An element which doesn't decode as a valid instruction
+    Data(ProgramElement),
+}
+
+impl DecodedInstruction {
+    /// Decodes the instruction starting at `addr`, falling back to a single data element if that
+    /// isn't a valid instruction
+    pub fn decode(program: &[ProgramElement], addr: usize) -> Self {
+        let raw = program[addr];
+        Self::try_decode(program, addr).unwrap_or(DecodedInstruction::Data(raw))
+    }
+
+    fn try_decode(program: &[ProgramElement], addr: usize) -> Option<Self> {
+        let raw = program[addr];
+        if raw < 0 {
+            return None;
+        }
+
+        let opcode = OpCode::try_from_element(&raw)?;
+        let contents = program.get((addr + 1)..(addr + opcode.length()))?;
+
+        let mut parameter_modes = raw / 100;
+        let mut parameters = Vec::with_capacity(contents.len());
+        for &value in contents {
+            let mode = ParameterMode::try_from_code((parameter_modes % 10) as u8)?;
+            parameter_modes /= 10;
+            parameters.push((mode, value));
+        }
+
+        // Leftover mode digits don't mean anything, so this probably isn't really an instruction
+        if parameter_modes != 0 {
+            return None;
+        }
+
+        if let Some(idx) = opcode.write_param_idx() {
+            if parameters[idx].0 == ParameterMode::Immediate {
+                return None;
+            }
+        }
+
+        Some(DecodedInstruction::Op { opcode, parameters })
+    }
+
+    /// Number of elements this occupies in the program image
+    pub fn length(&self) -> usize {
+        match self {
+            DecodedInstruction::Op { opcode, .. } => opcode.length(),
+            DecodedInstruction::Data(_) => 1,
+        }
+    }
+
+    /// Where control can go after executing this, as far as can be told statically
+    fn flow(&self, addr: usize) -> Flow {
+        let next = addr + self.length();
+        let (opcode, parameters) = match self {
+            DecodedInstruction::Op { opcode, parameters } => (opcode, parameters),
+            DecodedInstruction::Data(_) => return Flow::default(),
+        };
+
+        let jumps_if = match opcode {
+            OpCode::JumpIfTrue => true,
+            OpCode::JumpIfFalse => false,
+            OpCode::Terminate => return Flow::default(),
+            _ => return Flow { next: Some(next), ..Flow::default() },
+        };
+
+        // A literal test value makes the jump unconditional (or a no-op)
+        let (may_jump, may_continue) = match parameters[0] {
+            (ParameterMode::Immediate, test) => ((test != 0) == jumps_if, (test != 0) != jumps_if),
+            _ => (true, true),
+        };
+
+        let mut flow = Flow {
+            next: if may_continue { Some(next) } else { None },
+            ..Flow::default()
+        };
+
+        if may_jump {
+            match parameters[1] {
+                (ParameterMode::Immediate, target) if target >= 0 => flow.target = Some(target as usize),
+                (ParameterMode::Immediate, _) => (),
+                _ => flow.indirect = true,
+            }
+        }
+
+        flow
+    }
+}
+
+#[derive(Default)]
+struct Flow {
+    /// The following instruction, if control can fall through to it
+    next: Option<usize>,
+
+    /// The literal target of a jump that can be taken
+    target: Option<usize>,
+
+    /// Whether a jump can be taken to a target that's only known at runtime
+    indirect: bool,
+
+    /// Where control comes back to if this is a call, see `call_return`
+    call_return: Option<usize>,
+}
+
+/// Iterator over a program image from start to end, see `iter_instructions`
+pub struct Instructions<'a> {
+    program: &'a [ProgramElement],
+    addr: usize,
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = (usize, DecodedInstruction);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.addr >= self.program.len() {
+            return None;
+        }
+
+        let addr = self.addr;
+        let instruction = DecodedInstruction::decode(self.program, addr);
+        self.addr += instruction.length();
+        Some((addr, instruction))
+    }
+}
+
+/// Walks the program image linearly from address 0, decoding each instruction in turn.
+///
+/// Data embedded in between instructions can throw the walk out of alignment with the real
+/// instructions for a while, use `control_flow_graph` to only look at reachable code.
+pub fn iter_instructions(program: &[ProgramElement]) -> Instructions<'_> {
+    Instructions {
+        program,
+        addr: 0,
+    }
+}
+
+/// A straight line run of instructions, only entered at the start and only left at the end
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: usize,
+
+    /// One past the last element of the final instruction
+    pub end: usize,
+
+    /// Start addresses of the blocks that control can pass to from the end of this one
+    pub successors: Vec<usize>,
+
+    /// Whether the block ends with a jump to a target that's only known at runtime
+    pub indirect_jump: bool,
+
+    /// If the block ends by calling a function, the address the function returns to
+    pub call_return: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+    /// Every block reachable from address 0, keyed by start address
+    pub blocks: BTreeMap<usize, BasicBlock>,
+}
+
+/// A function found by looking for calls, see `ControlFlowGraph::call_graph`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Function {
+    pub entry: usize,
+
+    /// Start addresses of the blocks reachable from the entry without going through a call
+    pub blocks: BTreeSet<usize>,
+
+    /// Entries of the functions this one calls, including jumps straight into another function
+    pub calls: BTreeSet<usize>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CallGraph {
+    /// Every function, keyed by entry address. The code reachable from address 0 is treated as a
+    /// function too.
+    pub functions: BTreeMap<usize, Function>,
+}
+
+impl ControlFlowGraph {
+    /// The block containing the instruction at `addr`, if it's reachable
+    pub fn block_containing(&self, addr: usize) -> Option<&BasicBlock> {
+        self.blocks
+            .range(..=addr)
+            .next_back()
+            .map(|(_, block)| block)
+            .filter(|block| addr < block.end)
+    }
+
+    /// Addresses of every reachable jump whose target is only known at runtime, eg returns from
+    /// functions, which the graph can't follow
+    pub fn indirect_jumps(&self) -> Vec<usize> {
+        self.blocks
+            .values()
+            .filter(|block| block.indirect_jump)
+            .map(|block| block.end - OpCode::JumpIfTrue.length())
+            .collect()
+    }
+
+    /// Groups the blocks into functions, starting from address 0 and the target of every call
+    pub fn call_graph(&self) -> CallGraph {
+        let mut entries = self.blocks
+            .values()
+            .filter(|block| block.call_return.is_some())
+            .flat_map(|block| block.successors.iter().cloned())
+            .collect::<BTreeSet<_>>();
+        entries.insert(0);
+
+        let mut graph = CallGraph::default();
+        for &entry in entries.iter().filter(|entry| self.blocks.contains_key(entry)) {
+            let mut function = Function { entry, blocks: BTreeSet::new(), calls: BTreeSet::new() };
+            let mut to_visit = vec![entry];
+            while let Some(start) = to_visit.pop() {
+                if !function.blocks.insert(start) {
+                    continue;
+                }
+
+                let block = &self.blocks[&start];
+                if let Some(ret) = block.call_return {
+                    function.calls.extend(&block.successors);
+                    to_visit.extend(self.blocks.get(&ret).map(|b| b.start));
+                    continue;
+                }
+
+                for &succ in &block.successors {
+                    if succ != entry && entries.contains(&succ) {
+                        function.calls.insert(succ);
+                    } else {
+                        to_visit.push(succ);
+                    }
+                }
+            }
+
+            graph.functions.insert(entry, function);
+        }
+
+        graph
+    }
+}
+
+/// If the unconditional jump at `addr` is a call, the address it returns to. Calls store their
+/// return address, just after the jump, with an add or multiply straight before it.
+fn call_return(program: &[ProgramElement], addr: usize) -> Option<usize> {
+    let ret = addr + OpCode::JumpIfTrue.length();
+    let store = addr.checked_sub(OpCode::Add.length())?;
+    if stores_return(&DecodedInstruction::decode(program, store), ret) && ret < program.len() {
+        Some(ret)
+    } else {
+        None
+    }
+}
+
+/// Whether `instruction` stores the literal `ret`, with an add or multiply, as a call does straight
+/// before jumping
+pub(crate) fn stores_return(instruction: &DecodedInstruction, ret: usize) -> bool {
+    let (opcode, parameters) = match instruction {
+        DecodedInstruction::Op { opcode, parameters } => (opcode, parameters),
+        DecodedInstruction::Data(_) => return false,
+    };
+
+    let identity = match opcode {
+        OpCode::Add => 0,
+        OpCode::Multiply => 1,
+        _ => return false,
+    };
+    let stored = match (parameters[0], parameters[1]) {
+        ((ParameterMode::Immediate, a), (ParameterMode::Immediate, b)) if b == identity => a,
+        ((ParameterMode::Immediate, a), (ParameterMode::Immediate, b)) if a == identity => b,
+        _ => return false,
+    };
+
+    stored == ret as ProgramElement
+}
+
+/// Builds the control flow graph of the code reachable from address 0, following jumps with
+/// literal targets. Jumps with computed targets are flagged on their blocks but not followed, and
+/// self-modifying code isn't accounted for.
+///
+/// Functions usually return with a computed jump, so the code following each call is treated as
+/// reachable too.
+pub fn control_flow_graph(program: &[ProgramElement]) -> ControlFlowGraph {
+    // First find every reachable instruction, and every address which starts a block
+    let mut instructions = BTreeMap::new();
+    let mut leaders = BTreeSet::new();
+    let mut to_visit = vec![0];
+    leaders.insert(0);
+
+    while let Some(addr) = to_visit.pop() {
+        if addr >= program.len() || instructions.contains_key(&addr) {
+            continue;
+        }
+
+        let instruction = DecodedInstruction::decode(program, addr);
+        if let DecodedInstruction::Data(_) = instruction {
+            continue;
+        }
+
+        let mut flow = instruction.flow(addr);
+        if let Some(target) = flow.target {
+            leaders.insert(target);
+            to_visit.push(target);
+
+            if flow.next.is_none() {
+                flow.call_return = call_return(program, addr);
+            }
+        }
+        if let Some(ret) = flow.call_return {
+            leaders.insert(ret);
+            to_visit.push(ret);
+        }
+        if let Some(next) = flow.next {
+            if flow.target.is_some() || flow.indirect {
+                leaders.insert(next);
+            }
+            to_visit.push(next);
+        }
+
+        instructions.insert(addr, (instruction, flow));
+    }
+
+    // Then group the instructions into blocks, each running from a leader up to the next leader
+    // or the next instruction that transfers control
+    let mut graph = ControlFlowGraph::default();
+    for &start in leaders.iter().filter(|addr| instructions.contains_key(addr)) {
+        let mut addr = start;
+        loop {
+            let (instruction, flow) = &instructions[&addr];
+            let next = addr + instruction.length();
+
+            let ends_block = flow.next != Some(next)
+                || flow.target.is_some()
+                || flow.indirect
+                || leaders.contains(&next)
+                || !instructions.contains_key(&next);
+
+            if ends_block {
+                let successors = flow.target.iter()
+                    .chain(flow.next.iter())
+                    .filter(|succ| instructions.contains_key(succ))
+                    .cloned()
+                    .collect();
+
+                graph.blocks.insert(start, BasicBlock {
+                    start,
+                    end: next,
+                    successors,
+                    indirect_jump: flow.indirect,
+                    call_return: flow.call_return.filter(|ret| instructions.contains_key(ret)),
+                });
+                break;
+            }
+
+            addr = next;
+        }
+    }
+
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iter_instructions() {
+        let program = vec![1002, 4, 3, 4, 33, 3, 0, 99];
+        let decoded = iter_instructions(&program).collect::<Vec<_>>();
+        assert_eq!(decoded, vec![
+            (0, DecodedInstruction::Op {
+                opcode: OpCode::Multiply,
+                parameters: vec![
+                    (ParameterMode::Position, 4),
+                    (ParameterMode::Immediate, 3),
+                    (ParameterMode::Position, 4),
+                ],
+            }),
+            (4, DecodedInstruction::Data(33)),
+            (5, DecodedInstruction::Op {
+                opcode: OpCode::ReadInput,
+                parameters: vec![(ParameterMode::Position, 0)],
+            }),
+            (7, DecodedInstruction::Op { opcode: OpCode::Terminate, parameters: vec![] }),
+        ]);
+    }
+
+    #[test]
+    fn test_invalid_instructions_are_data() {
+        let cases = vec![
+            vec![11101, 1, 2, 3],  // Immediate write
+            vec![302, 0],          // Unknown parameter mode
+            vec![10004, 0],        // Extra mode digits
+            vec![1, 0, 0],         // Truncated
+            vec![-1],
+            vec![42],
+        ];
+
+        for program in cases {
+            assert_eq!(DecodedInstruction::decode(&program, 0), DecodedInstruction::Data(program[0]));
+        }
+    }
+
+    #[test]
+    fn test_input_destinations() {
+        let program = vec![3, 20, 203, 5, 1, 20, 21, 22, 3, 21, 99];
+        let destinations = iter_instructions(&program)
+            .filter_map(|(_, instr)| match instr {
+                DecodedInstruction::Op { opcode: OpCode::ReadInput, parameters } => Some(parameters[0]),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(destinations, vec![
+            (ParameterMode::Position, 20),
+            (ParameterMode::Relative, 5),
+            (ParameterMode::Position, 21),
+        ]);
+    }
+
+    #[test]
+    fn test_control_flow_graph() {
+        let program = vec![
+            3, 100,           // 0: read into 100
+            1005, 100, 10,    // 2: if [100] != 0 goto 10
+            104, 0,           // 5: output 0
+            1105, 1, 12,      // 7: goto 12
+            104, 1,           // 10: output 1
+            99,               // 12: halt
+            1, 1, 1, 1,       // 13: unreachable
+        ];
+
+        let graph = control_flow_graph(&program);
+        let blocks = graph.blocks.values()
+            .map(|b| (b.start, b.end, b.successors.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(blocks, vec![
+            (0, 5, vec![10, 5]),
+            (5, 10, vec![12]),
+            (10, 12, vec![12]),
+            (12, 13, vec![]),
+        ]);
+
+        assert_eq!(graph.block_containing(6).map(|b| b.start), Some(5));
+        assert_eq!(graph.block_containing(12).map(|b| b.start), Some(12));
+        assert_eq!(graph.block_containing(13), None);
+    }
+
+    #[test]
+    fn test_indirect_jump() {
+        // Jump to the address stored at 7, which has just been computed
+        let program = vec![1, 0, 0, 7, 6, 8, 7, 0, 0, 99];
+        let graph = control_flow_graph(&program);
+        assert_eq!(graph.blocks.len(), 1);
+
+        let block = &graph.blocks[&0];
+        assert_eq!(block.end, 7);
+        assert!(block.indirect_jump);
+        assert_eq!(block.successors, vec![]);
+    }
+
+    #[test]
+    fn test_call_graph() {
+        // The calling convention the Intcode compiler uses, storing the return address at [rb+0]
+        let program = vec![
+            109, 100,           // 0: rb += 100
+            21101, 0, 9, 0,     // 2: [rb+0] = 9
+            1106, 0, 10,        // 6: goto 10
+            99,                 // 9: halt
+            104, 5,             // 10: output 5
+            2106, 0, 0,         // 12: return to [rb+0]
+        ];
+
+        let graph = control_flow_graph(&program);
+        let blocks = graph.blocks.values()
+            .map(|b| (b.start, b.end, b.successors.clone(), b.call_return))
+            .collect::<Vec<_>>();
+        assert_eq!(blocks, vec![
+            (0, 9, vec![10], Some(9)),
+            (9, 10, vec![], None),
+            (10, 15, vec![], None),
+        ]);
+        assert_eq!(graph.indirect_jumps(), vec![12]);
+
+        let calls = graph.call_graph();
+        assert_eq!(calls.functions.len(), 2);
+        assert_eq!(calls.functions[&0].blocks, [0, 9].iter().cloned().collect());
+        assert_eq!(calls.functions[&0].calls, [10].iter().cloned().collect());
+        assert_eq!(calls.functions[&10].blocks, [10].iter().cloned().collect());
+        assert!(calls.functions[&10].calls.is_empty());
+    }
+}
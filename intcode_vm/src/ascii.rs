@@ -0,0 +1,419 @@
+//! Helpers for programs which communicate in ASCII text, one character per element.
+//!
+//! ```
+//! use intcode_vm::ProgramState;
+//!
+//! // Echoes two characters back, then outputs 1000
+//! let mut program = ProgramState::load_program_str("3,15,4,15,3,15,4,15,3,15,4,15,104,1000,99,0");
+//! program.push_ascii_line("hi");
+//! program.run_to_completion().unwrap();
+//!
+//! assert_eq!(program.drain_ascii_output(), "hi\n");
+//! assert_eq!(program.outputs, vec![1000]);
+//! ```
+//!
+//! Programs can also be played by hand with `ProgramState::run_interactive`, or the `icrun`
+//! binary, or hooked up to anything else that reads and writes bytes with an `AsciiStream`.
+
+use std::collections::VecDeque;
+use std::io::{BufRead, ErrorKind, Read, Write};
+
+use crate::{ProgramElement, ProgramState, StopReason, VmError};
+
+/// Whether an element is the code of an ASCII character
+pub fn is_ascii(value: ProgramElement) -> bool {
+    (0..128).contains(&value)
+}
+
+impl<O> ProgramState<VecDeque<ProgramElement>, O> {
+    /// Queues each character of `line` as an input, followed by a newline.
+    ///
+    /// Panics if `line` has any non-ASCII characters.
+    pub fn push_ascii_line(&mut self, line: &str) {
+        assert!(line.is_ascii(), "Can't send non-ASCII line '{}' to an intcode program", line);
+        self.inputs.extend(line.bytes().map(|b| b as ProgramElement));
+        self.inputs.push_back(b'\n' as ProgramElement);
+    }
+}
+
+impl<I> ProgramState<I, VecDeque<ProgramElement>> {
+    /// Takes every ASCII output from the output queue as a string. Anything that isn't an ASCII
+    /// character, eg a puzzle answer output at the end, is left in the queue.
+    pub fn drain_ascii_output(&mut self) -> String {
+        let mut text = String::new();
+        self.outputs.retain(|&value| {
+            if is_ascii(value) {
+                text.push(value as u8 as char);
+                false
+            } else {
+                true
+            }
+        });
+
+        text
+    }
+}
+
+/// Displays as whichever error it wraps
+#[derive(Debug)]
+pub enum InteractiveError {
+    Io(std::io::Error),
+    Vm(VmError),
+}
+
+impl std::fmt::Display for InteractiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            InteractiveError::Io(e) => write!(f, "{}", e),
+            InteractiveError::Vm(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for InteractiveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InteractiveError::Io(e) => std::error::Error::source(e),
+            InteractiveError::Vm(e) => std::error::Error::source(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for InteractiveError {
+    fn from(e: std::io::Error) -> Self {
+        InteractiveError::Io(e)
+    }
+}
+
+impl From<VmError> for InteractiveError {
+    fn from(e: VmError) -> Self {
+        InteractiveError::Vm(e)
+    }
+}
+
+/// Why `run_until_prompt` returned without seeing its prompt
+#[derive(Debug)]
+pub enum PromptError {
+    Vm(VmError),
+
+    /// The program stopped for some other reason first, eg terminating or needing an input. `text`
+    /// is everything it output in the meantime.
+    Stopped { reason: StopReason, text: String },
+}
+
+impl std::fmt::Display for PromptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PromptError::Vm(e) => write!(f, "{}", e),
+            PromptError::Stopped { reason, .. } => write!(f, "Program stopped before prompting: {:?}", reason),
+        }
+    }
+}
+
+impl std::error::Error for PromptError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PromptError::Vm(e) => std::error::Error::source(e),
+            PromptError::Stopped { .. } => None,
+        }
+    }
+}
+
+impl From<VmError> for PromptError {
+    fn from(e: VmError) -> Self {
+        PromptError::Vm(e)
+    }
+}
+
+impl ProgramState {
+    /// Plays the program in the terminal, see `run_interactive_with`
+    pub fn run_interactive(&mut self) -> Result<StopReason, InteractiveError> {
+        let stdin = std::io::stdin();
+        let stdout = std::io::stdout();
+        self.run_interactive_with(stdin.lock(), stdout.lock())
+    }
+
+    /// Runs the program, writing its ASCII output as text and reading a line of `input` whenever
+    /// it needs more input. Any output that isn't ASCII, eg a puzzle answer, is written as a
+    /// number on a line of its own.
+    ///
+    /// Returns once the program terminates, `input` runs out, or a breakpoint or watchpoint is
+    /// hit. Lines with anything other than ASCII in them are rejected and read again.
+    pub fn run_interactive_with(
+        &mut self,
+        mut input: impl BufRead,
+        mut output: impl Write,
+    ) -> Result<StopReason, InteractiveError> {
+        loop {
+            let reason = self.run_to_next_input();
+            for value in self.outputs.drain(..) {
+                if is_ascii(value) {
+                    output.write_all(&[value as u8])?;
+                } else {
+                    writeln!(output, "{}", value)?;
+                }
+            }
+            output.flush()?;
+
+            match reason? {
+                StopReason::NeedsInput => (),
+                reason => return Ok(reason),
+            }
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(StopReason::NeedsInput);
+            }
+
+            let line = line.trim_end_matches(&['\r', '\n'][..]);
+            if line.is_ascii() {
+                self.push_ascii_line(line);
+            } else {
+                writeln!(output, "Only ASCII input can be sent to the program")?;
+            }
+        }
+    }
+
+    /// Runs the program until its ASCII output ends with `prompt`, eg `"Command?\n"`, and returns
+    /// all of the ASCII output so far, including any that was already queued. Anything that isn't
+    /// ASCII is left in the output queue.
+    ///
+    /// Unlike `run_to_next_input`, this doesn't need the program to be blocked on an input, so
+    /// it's known to be ready for the next command even if it reads ahead of the prompt.
+    pub fn run_until_prompt(&mut self, prompt: &str) -> Result<String, PromptError> {
+        let mut tail: String = self
+            .outputs
+            .iter()
+            .filter(|&&value| is_ascii(value))
+            .map(|&value| value as u8 as char)
+            .collect();
+        if tail.ends_with(prompt) {
+            return Ok(self.drain_ascii_output());
+        }
+
+        let reason = self.run_until_output(|value| {
+            if !is_ascii(value) {
+                return false;
+            }
+            tail.push(value as u8 as char);
+            tail.ends_with(prompt)
+        })?;
+
+        let text = self.drain_ascii_output();
+        match reason {
+            StopReason::Output(_) => Ok(text),
+            reason => Err(PromptError::Stopped { reason, text }),
+        }
+    }
+}
+
+/// A VM as a `Read` and `Write` pair, for programs which communicate in ASCII text.
+///
+/// Bytes written are queued as inputs, and reading runs the program until it outputs a newline or
+/// stops, then returns whatever ASCII it output:
+///
+/// ```
+/// use std::io::{BufRead, ErrorKind, Write};
+/// use intcode_vm::ascii::AsciiStream;
+/// use intcode_vm::ProgramState;
+///
+/// // Echoes two characters back, then outputs 1000
+/// let program = ProgramState::load_program_str("3,15,4,15,3,15,4,15,3,15,4,15,104,1000,99,0");
+/// let mut stream = AsciiStream::new(program);
+/// assert_eq!(stream.fill_buf().unwrap_err().kind(), ErrorKind::WouldBlock);
+///
+/// stream.write_all(b"hi\n").unwrap();
+/// let mut line = String::new();
+/// stream.read_line(&mut line).unwrap();
+/// assert_eq!(line, "hi\n");
+///
+/// assert_eq!(stream.read_line(&mut line).unwrap(), 0);
+/// assert_eq!(stream.state.outputs, vec![1000]);
+/// ```
+///
+/// Reading fails with `ErrorKind::WouldBlock` while the program is waiting for an input, and
+/// returns nothing once it's terminated. Output that isn't ASCII, eg a puzzle answer, is left in
+/// the VM's output queue.
+#[derive(Debug)]
+pub struct AsciiStream {
+    pub state: ProgramState,
+
+    /// ASCII output that hasn't been read yet, from `pos` onwards
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl AsciiStream {
+    pub fn new(state: ProgramState) -> Self {
+        Self { state, buf: Vec::new(), pos: 0 }
+    }
+
+    /// The VM, without any output that's been taken from it but not read yet
+    pub fn into_inner(self) -> ProgramState {
+        self.state
+    }
+}
+
+impl Write for AsciiStream {
+    /// Queues every byte up to the first that isn't ASCII, failing with `ErrorKind::InvalidInput`
+    /// if that's the first
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        let len = bytes.iter().position(|b| !b.is_ascii()).unwrap_or(bytes.len());
+        if len == 0 && !bytes.is_empty() {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "Only ASCII input can be sent to the program",
+            ));
+        }
+
+        self.state.inputs.extend(bytes[..len].iter().map(|&b| b as ProgramElement));
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl BufRead for AsciiStream {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        if self.pos == self.buf.len() {
+            self.buf.clear();
+            self.pos = 0;
+
+            let reason = self.state
+                .run_until_output(|value| value == b'\n' as ProgramElement)
+                .map_err(std::io::Error::other)?;
+            self.buf.extend(self.state.drain_ascii_output().bytes());
+
+            if self.buf.is_empty() {
+                match reason {
+                    StopReason::Terminated => (),
+                    StopReason::NeedsInput => {
+                        return Err(std::io::Error::new(ErrorKind::WouldBlock, "The program is waiting for input"));
+                    }
+                    reason => {
+                        return Err(std::io::Error::other(format!("The program stopped: {:?}", reason)));
+                    }
+                }
+            }
+        }
+
+        Ok(&self.buf[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.buf.len());
+    }
+}
+
+impl Read for AsciiStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.fill_buf()?.read(buf)?;
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_ascii_line() {
+        let mut program = ProgramState::load_program_str("99");
+        program.push_ascii_line("A,B");
+        assert_eq!(program.inputs, vec![65, 44, 66, 10]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_push_non_ascii_line() {
+        ProgramState::load_program_str("99").push_ascii_line("é");
+    }
+
+    #[test]
+    fn test_drain_ascii_output() {
+        let mut program = ProgramState::load_program_str("99");
+        program.outputs.extend(vec![46, 35, -1, 10, 128, 46]);
+        assert_eq!(program.drain_ascii_output(), ".#\n.");
+        assert_eq!(program.outputs, vec![-1, 128]);
+        assert_eq!(program.drain_ascii_output(), "");
+    }
+
+    #[test]
+    fn test_run_interactive() {
+        // Prompts with "?" then echoes its input until it reads a ".", then outputs 1000
+        //   0: out 63
+        //   2: in [30]
+        //   4: eq [30], 46, [31]
+        //   8: jnz [31], 24
+        //  11: out [30]
+        //  13: jz 0, 2
+        //  16: data
+        //  24: out 1000
+        //  26: halt
+        let source = "104,63,3,30,1008,30,46,31,1005,31,24,4,30,1106,0,2,0,0,0,0,0,0,0,0,104,1000,99";
+        let mut program = ProgramState::load_program_str(source);
+        let mut output = Vec::new();
+        let result = program.run_interactive_with("hi\r\né\nok.\n".as_bytes(), &mut output);
+        assert!(matches!(result, Ok(StopReason::Terminated)));
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "?hi\nOnly ASCII input can be sent to the program\nok1000\n"
+        );
+
+        // Running out of input leaves it waiting for more
+        let mut program = ProgramState::load_program_str(source);
+        let result = program.run_interactive_with("hi\n".as_bytes(), std::io::sink());
+        assert!(matches!(result, Ok(StopReason::NeedsInput)));
+        assert_eq!(program.program_counter, 2);
+    }
+
+    #[test]
+    fn test_run_until_prompt() {
+        // Outputs "a?b?", then 1000, then halts
+        let source = "104,97,104,63,104,98,104,63,104,1000,99";
+        let mut program = ProgramState::load_program_str(source);
+        assert_eq!(program.run_until_prompt("?").unwrap(), "a?");
+        assert_eq!(program.run_until_prompt("b?").unwrap(), "b?");
+
+        match program.run_until_prompt("?") {
+            Err(PromptError::Stopped { reason: StopReason::Terminated, text }) => assert_eq!(text, ""),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(program.outputs, vec![1000]);
+
+        // Output that's already queued counts towards the prompt
+        let mut program = ProgramState::load_program_str(source);
+        program.outputs.extend(vec![120, 97]);
+        assert_eq!(program.run_until_prompt("a").unwrap(), "xa");
+        assert_eq!(program.run_until_prompt("a?").unwrap(), "a?");
+    }
+
+    #[test]
+    fn test_ascii_stream() {
+        // The echoing program from `test_run_interactive`
+        let source = "104,63,3,30,1008,30,46,31,1005,31,24,4,30,1106,0,2,0,0,0,0,0,0,0,0,104,1000,99";
+        let mut stream = AsciiStream::new(ProgramState::load_program_str(source));
+
+        let mut buf = [0; 2];
+        assert_eq!(stream.read(&mut buf).unwrap(), 1);
+        assert_eq!(buf[0], b'?');
+        assert_eq!(stream.read(&mut buf).unwrap_err().kind(), ErrorKind::WouldBlock);
+
+        // Output is read a line at a time, and partially read lines are kept
+        assert_eq!(stream.write(b"ab\ncd\xc3").unwrap(), 5);
+        assert_eq!(stream.write(b"\xc3").unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(stream.read(&mut buf).unwrap(), 2);
+        assert_eq!(&buf, b"ab");
+        assert_eq!(stream.fill_buf().unwrap(), b"\n");
+        stream.consume(1);
+
+        stream.write_all(b".").unwrap();
+        let mut rest = String::new();
+        stream.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "cd");
+        assert_eq!(stream.into_inner().outputs, vec![1000]);
+    }
+}
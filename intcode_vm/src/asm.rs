@@ -0,0 +1,314 @@
+//! Assembles programs from text, using the same syntax as the `disasm` listings.
+//!
+//! ```
+//! use intcode_vm::{asm, ProgramState};
+//!
+//! // Counts down from an input to 1, outputting each number along the way
+//! let program = asm::assemble("
+//!         in [count]
+//!     loop:
+//!         out [count]
+//!         add [count], -1, [count]
+//!         jnz [count], loop
+//!         halt
+//!     count:
+//!         data 0
+//! ").unwrap();
+//!
+//! let mut state = ProgramState::new(program, vec![3].into());
+//! state.run_to_completion().unwrap();
+//! assert_eq!(state.outputs, vec![3, 2, 1]);
+//! ```
+//!
+//! Each line holds an optional `label:`, then an instruction or a `data` directive, then an
+//! optional `; comment`. Operands are written as
+//!
+//! - `5` or `label` for an immediate value
+//! - `[5]` or `[label]` for a position mode parameter
+//! - `[rb+5]` or `[rb-5]` for a relative mode parameter
+//!
+//! A label can be offset with `label+1` or `label-1`, and refers to the address of whatever
+//! follows it. `data` is followed by a comma-separated list of values to place directly in the
+//! program.
+
+use std::collections::HashMap;
+
+use crate::symbols::SymbolTable;
+use crate::{OpCode, ParameterMode, ProgramElement};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmError {
+    /// 1-based line number in the source
+    pub line: usize,
+    pub reason: String,
+}
+
+impl std::fmt::Display for AsmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.reason)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Value {
+    Number(ProgramElement),
+    Label {
+        name: String,
+        offset: ProgramElement,
+    },
+}
+
+/// An instruction or data directive, not yet resolved to elements
+#[derive(Debug)]
+enum Item {
+    Op {
+        opcode: OpCode,
+        operands: Vec<(ParameterMode, Value)>,
+    },
+    Data(Vec<Value>),
+}
+
+impl Item {
+    fn length(&self) -> usize {
+        match self {
+            Item::Op { opcode, .. } => opcode.length(),
+            Item::Data(values) => values.len(),
+        }
+    }
+}
+
+fn is_label(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_value(s: &str) -> Result<Value, String> {
+    let s = s.trim();
+    if let Ok(num) = s.parse::<ProgramElement>() {
+        return Ok(Value::Number(num));
+    }
+
+    let (name, offset) = match s.rfind(['+', '-']) {
+        Some(idx) if idx > 0 => {
+            let offset = s[idx..]
+                .parse::<ProgramElement>()
+                .map_err(|_| format!("'{}' isn't a valid label offset", &s[idx..]))?;
+            (s[..idx].trim(), offset)
+        }
+        _ => (s, 0),
+    };
+
+    if !is_label(name) {
+        return Err(format!("'{}' isn't a number or a label", s));
+    }
+
+    Ok(Value::Label {
+        name: name.to_string(),
+        offset,
+    })
+}
+
+fn parse_operand(s: &str) -> Result<(ParameterMode, Value), String> {
+    let s = s.trim();
+    let inner = match s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        Some(inner) => inner.trim(),
+        None => return Ok((ParameterMode::Immediate, parse_value(s)?)),
+    };
+
+    match inner.strip_prefix("rb") {
+        Some(offset) if offset.starts_with('+') || offset.starts_with('-') => {
+            let offset = offset.strip_prefix('+').unwrap_or(offset);
+            Ok((ParameterMode::Relative, parse_value(offset)?))
+        }
+        _ => Ok((ParameterMode::Position, parse_value(inner)?)),
+    }
+}
+
+/// Parses the instruction or directive on a line which has had its labels and comment removed
+fn parse_item(s: &str) -> Result<Item, String> {
+    let (mnemonic, operands) = match s.find(char::is_whitespace) {
+        Some(idx) => (&s[..idx], s[idx..].trim()),
+        None => (s, ""),
+    };
+
+    let operands = if operands.is_empty() {
+        Vec::new()
+    } else {
+        operands.split(',').collect()
+    };
+
+    if mnemonic == "data" {
+        let values = operands.into_iter().map(parse_value).collect::<Result<Vec<_>, _>>()?;
+        if values.is_empty() {
+            return Err("data needs at least one value".to_string());
+        }
+        return Ok(Item::Data(values));
+    }
+
+    let opcode = OpCode::from_mnemonic(mnemonic)
+        .ok_or_else(|| format!("Unknown mnemonic '{}'", mnemonic))?;
+
+    if operands.len() != opcode.length() - 1 {
+        return Err(format!(
+            "{} takes {} operand(s), but {} were given",
+            mnemonic, opcode.length() - 1, operands.len(),
+        ));
+    }
+
+    let operands = operands.into_iter().map(parse_operand).collect::<Result<Vec<_>, _>>()?;
+    if let Some(idx) = opcode.write_param_idx() {
+        if operands[idx].0 == ParameterMode::Immediate {
+            return Err(format!("{} can't write to an immediate operand", mnemonic));
+        }
+    }
+
+    Ok(Item::Op { opcode, operands })
+}
+
+fn resolve(value: &Value, labels: &HashMap<String, usize>) -> Result<ProgramElement, String> {
+    match value {
+        Value::Number(num) => Ok(*num),
+        Value::Label { name, offset } => labels
+            .get(name)
+            .map(|&addr| addr as ProgramElement + offset)
+            .ok_or_else(|| format!("Undefined label '{}'", name)),
+    }
+}
+
+/// Assembles a program, returning the elements to load starting at address 0
+pub fn assemble(source: &str) -> Result<Vec<ProgramElement>, AsmError> {
+    assemble_with_symbols(source).map(|(program, _)| program)
+}
+
+/// As `assemble`, also returning a symbol table naming the address of each label. Where several
+/// labels are at the same address, the first one is used.
+pub fn assemble_with_symbols(source: &str) -> Result<(Vec<ProgramElement>, SymbolTable), AsmError> {
+    // First pass lays everything out to find the address of each label
+    let mut labels = HashMap::new();
+    let mut symbols = SymbolTable::default();
+    let mut items = Vec::new();
+    let mut addr = 0;
+    for (idx, line) in source.lines().enumerate() {
+        let error = |reason| AsmError { line: idx + 1, reason };
+
+        let mut rest = line.split(';').next().unwrap().trim();
+        while let Some(colon) = rest.find(':') {
+            let label = rest[..colon].trim();
+            if !is_label(label) {
+                return Err(error(format!("'{}' isn't a valid label", label)));
+            }
+            if labels.insert(label.to_string(), addr).is_some() {
+                return Err(error(format!("Label '{}' is defined more than once", label)));
+            }
+            if symbols.name(addr).is_none() {
+                symbols.insert(addr, label);
+            }
+            rest = rest[(colon + 1)..].trim();
+        }
+
+        if rest.is_empty() {
+            continue;
+        }
+
+        let item = parse_item(rest).map_err(error)?;
+        addr += item.length();
+        items.push((idx + 1, item));
+    }
+
+    // Then the second pass fills in the elements, now that every label is known
+    let mut program = Vec::with_capacity(addr);
+    for (line, item) in items {
+        let error = |reason| AsmError { line, reason };
+        match item {
+            Item::Op { opcode, operands } => {
+                let mut instruction = opcode.code();
+                let mut place = 100;
+                for (mode, _) in &operands {
+                    instruction += mode.code() as ProgramElement * place;
+                    place *= 10;
+                }
+
+                program.push(instruction);
+                for (_, value) in &operands {
+                    program.push(resolve(value, &labels).map_err(error)?);
+                }
+            }
+            Item::Data(values) => {
+                for value in &values {
+                    program.push(resolve(value, &labels).map_err(error)?);
+                }
+            }
+        }
+    }
+
+    Ok((program, symbols))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm;
+    use crate::PagedMemory;
+
+    #[test]
+    fn test_encoding() {
+        let program = assemble("
+            mul [4], 3, [4]   ; comments are ignored
+            data 33
+            in [rb-1]
+            lt 1, 2, [rb+7]
+            halt
+        ");
+        assert_eq!(program, Ok(vec![1002, 4, 3, 4, 33, 203, -1, 21107, 1, 2, 7, 99]));
+    }
+
+    #[test]
+    fn test_labels() {
+        let program = assemble("
+            start: jz 0, end
+            data start, end, end+1, end-2
+            end: done:
+            halt
+        ");
+        assert_eq!(program, Ok(vec![1106, 0, 7, 0, 7, 8, 5, 99]));
+    }
+
+    #[test]
+    fn test_symbols() {
+        let (_, symbols) = assemble_with_symbols("
+            start: jz 0, end
+            end: done:
+            halt
+        ").unwrap();
+        assert_eq!(symbols.iter().collect::<Vec<_>>(), vec![(0, "start"), (3, "end")]);
+    }
+
+    #[test]
+    fn test_disassembly_round_trip() {
+        let source = "mul [4], 3, [4]\ndata 33\nin [rb-1]\nlt 1, 2, [rb+7]\nhalt";
+        let program = assemble(source).unwrap();
+        let mem = PagedMemory::from(program.clone());
+
+        let disassembled = disasm::disassemble(&mem, 0..program.len())
+            .iter()
+            .map(|line| line.to_string()[24..].to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(disassembled, source);
+    }
+
+    #[test]
+    fn test_errors() {
+        let reason = |source| assemble(source).unwrap_err().to_string();
+        assert_eq!(reason("halt\nfoo 1"), "Line 2: Unknown mnemonic 'foo'");
+        assert_eq!(reason("out 1, 2"), "Line 1: out takes 1 operand(s), but 2 were given");
+        assert_eq!(reason("in 5"), "Line 1: in can't write to an immediate operand");
+        assert_eq!(reason("jnz 1, nowhere"), "Line 1: Undefined label 'nowhere'");
+        assert_eq!(reason("a: halt\na: halt"), "Line 2: Label 'a' is defined more than once");
+        assert_eq!(reason("out [1x]"), "Line 1: '1x' isn't a number or a label");
+        assert_eq!(reason("data"), "Line 1: data needs at least one value");
+    }
+}
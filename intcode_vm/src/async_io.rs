@@ -0,0 +1,138 @@
+//! Running VMs as futures, so that many of them can share a single-threaded executor.
+//!
+//! A VM only yields while it's waiting for an input or for its outputs to be accepted, in between
+//! it runs its instructions without interruption.
+//!
+//! ```
+//! use futures::channel::mpsc;
+//! use futures::{executor, StreamExt};
+//! use intcode_vm::ProgramState;
+//!
+//! // Adds one to each of its inputs, until there are no more
+//! let mut program = ProgramState::load_program_str("3,11,1001,11,1,11,4,11,1105,1,0,0");
+//! let (mut out_tx, out_rx) = mpsc::unbounded();
+//! let mut inputs = futures::stream::iter(vec![1, 2, 3]);
+//!
+//! executor::block_on(program.run_async(&mut inputs, &mut out_tx)).unwrap();
+//! drop(out_tx);
+//! assert_eq!(executor::block_on(out_rx.collect::<Vec<_>>()), vec![2, 3, 4]);
+//! ```
+
+use futures::{Sink, SinkExt, Stream, StreamExt};
+
+use crate::{ProgramElement, ProgramState, StopReason, VmError};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RunAsyncError<E> {
+    Vm(VmError),
+
+    /// The output sink failed to accept a value
+    Sink(E),
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for RunAsyncError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RunAsyncError::Vm(e) => write!(f, "{}", e),
+            RunAsyncError::Sink(e) => write!(f, "Failed to write an output: {}", e),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for RunAsyncError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RunAsyncError::Vm(e) => std::error::Error::source(e),
+            RunAsyncError::Sink(_) => None,
+        }
+    }
+}
+
+impl ProgramState {
+    /// As `run_to_next_input`, except that whenever the input queue runs dry the next input is
+    /// awaited from `inputs`, and every output is sent to `outputs` as soon as the VM stops to
+    /// wait. Stops with `StopReason::NeedsInput` once `inputs` ends.
+    pub async fn run_async<S, K>(
+        &mut self,
+        inputs: &mut S,
+        outputs: &mut K,
+    ) -> Result<StopReason, RunAsyncError<K::Error>>
+    where
+        S: Stream<Item = ProgramElement> + Unpin,
+        K: Sink<ProgramElement> + Unpin,
+    {
+        loop {
+            let result = self.run_to_next_input();
+
+            for value in self.outputs.drain(..) {
+                outputs.feed(value).await.map_err(RunAsyncError::Sink)?;
+            }
+            outputs.flush().await.map_err(RunAsyncError::Sink)?;
+
+            match result.map_err(RunAsyncError::Vm)? {
+                StopReason::NeedsInput => match inputs.next().await {
+                    Some(value) => self.inputs.push_back(value),
+                    None => return Ok(StopReason::NeedsInput),
+                },
+                reason => return Ok(reason),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::channel::mpsc;
+    use futures::executor;
+
+    use super::*;
+
+    #[test]
+    fn test_feedback_loop_on_one_thread() {
+        // Second example from day 7 part 2, which should output 139629729 with these phases
+        let program = ProgramState::load_program_str(
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5"
+        );
+        let phases = [9, 8, 7, 6, 5];
+
+        // Amplifier i reads from channel i and writes to channel i + 1, wrapping around
+        let (senders, receivers): (Vec<_>, Vec<_>) = phases.iter().map(|_| mpsc::unbounded()).unzip();
+        for (sender, &phase) in senders.iter().zip(&phases) {
+            sender.unbounded_send(phase).unwrap();
+        }
+        senders[0].unbounded_send(0).unwrap();
+
+        let amplifiers = receivers
+            .into_iter()
+            .enumerate()
+            .map(|(idx, mut rx)| {
+                let mut tx = senders[(idx + 1) % senders.len()].clone();
+                let mut vm = program.clone();
+                async move {
+                    let result = vm.run_async(&mut rx, &mut tx).await;
+                    (rx, result)
+                }
+            })
+            .collect::<Vec<_>>();
+        drop(senders);
+
+        let mut finished = executor::block_on(futures::future::join_all(amplifiers));
+        for (_, result) in &finished {
+            assert_eq!(result, &Ok(StopReason::Terminated));
+        }
+
+        // The last amplifier's final output is left unread in the first one's input channel
+        assert_eq!(finished[0].0.try_recv(), Ok(139629729));
+    }
+
+    #[test]
+    fn test_inputs_end() {
+        let mut program = ProgramState::load_program_str("104,7,3,0,99");
+        let mut inputs = futures::stream::empty();
+        let mut outputs = Vec::new();
+
+        let result = executor::block_on(program.run_async(&mut inputs, &mut outputs));
+        assert_eq!(result, Ok(StopReason::NeedsInput));
+        assert_eq!(outputs, vec![7]);
+    }
+}
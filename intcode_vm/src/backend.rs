@@ -0,0 +1,123 @@
+//! Interchangeable implementations of the interpreter loop.
+//!
+//! Every backend has to produce exactly the same results, they only differ in how quickly they
+//! get there. Keeping the older ones around means the faster ones can be checked against them,
+//! and that speedups can be measured against a fixed baseline with `aoc bench --backends`.
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use crate::prelude::*;
+use crate::decoded::DecodeCache;
+use crate::{InputSource, Instruction, OutputSink, ProgramState, StepEvent, VmError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// Fetches the instruction and each of its parameters with separate memory reads, as the
+    /// interpreter originally did
+    Classic,
+
+    /// Fetches whole instructions with a single memory read
+    Optimized,
+
+    /// Decodes each instruction once, then reuses it until its memory is written to, see
+    /// `decoded`
+    Cached,
+
+    /// Decodes each straight-line block of instructions once, then runs it from a cache, see
+    /// `blocks`
+    Blocks,
+}
+
+/// The backend given to newly created VMs, stored as an index into `Backend::ALL`
+static DEFAULT_BACKEND: AtomicU8 = AtomicU8::new(1);
+
+impl Backend {
+    pub const ALL: &'static [Backend] = &[Backend::Classic, Backend::Optimized, Backend::Cached, Backend::Blocks];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Classic => "classic",
+            Backend::Optimized => "optimized",
+            Backend::Cached => "cached",
+            Backend::Blocks => "blocks",
+        }
+    }
+
+    /// The backend used by VMs created from now on, `Optimized` unless changed with `set_default`
+    pub fn current_default() -> Self {
+        Self::ALL[DEFAULT_BACKEND.load(Ordering::Relaxed) as usize]
+    }
+
+    /// Changes the backend used by VMs created from now on, across every thread. VMs that already
+    /// exist keep the backend they were created with.
+    pub fn set_default(backend: Backend) {
+        let idx = Self::ALL.iter().position(|&b| b == backend).unwrap();
+        DEFAULT_BACKEND.store(idx as u8, Ordering::Relaxed);
+    }
+
+    /// Executes the instruction at the program counter
+    ///
+    /// `ProgramState::progress_state` handles the optimized backend itself, so that it can be
+    /// inlined into the interpreter loop, and only calls this for the others. The blocks backend
+    /// only uses its cache for whole runs, so single instructions are executed as the optimized
+    /// backend would.
+    #[inline(never)]
+    pub(crate) fn step<I: InputSource, O: OutputSink>(self, state: &mut ProgramState<I, O>) -> Result<StepEvent, VmError> {
+        match self {
+            Backend::Classic => Instruction::fetch_each_and_decode(state)?.execute(state),
+            Backend::Optimized | Backend::Blocks => Instruction::fetch_and_decode(state)?.execute(state),
+            Backend::Cached => DecodeCache::step(state),
+        }
+    }
+}
+
+impl core::fmt::Display for Backend {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+impl core::str::FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        Self::ALL
+            .iter()
+            .find(|b| b.name() == s)
+            .cloned()
+            .ok_or_else(|| format!("Unknown backend '{}'", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backends_agree() {
+        let program = ProgramState::load_program_str(crate::corpus::example("day_9_quine").unwrap().source);
+        let states = Backend::ALL
+            .iter()
+            .map(|&backend| {
+                let mut state = program.clone();
+                state.backend = backend;
+                state.run_to_completion().unwrap();
+                state
+            })
+            .collect::<Vec<_>>();
+
+        for state in &states[1..] {
+            assert_eq!(state.outputs, states[0].outputs);
+            assert_eq!(state.program_counter, states[0].program_counter);
+        }
+        assert_eq!(states[0].outputs.len(), 16);
+    }
+
+    #[test]
+    fn test_parse() {
+        for &backend in Backend::ALL {
+            assert_eq!(backend.name().parse(), Ok(backend));
+        }
+        assert!("jit".parse::<Backend>().is_err());
+    }
+}
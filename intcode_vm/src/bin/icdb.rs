@@ -0,0 +1,592 @@
+//! Interactive debugger for intcode programs.
+//!
+//! usage: icdb <program file> [<patch file>]
+
+use std::io::{self, BufRead, Write};
+
+use intcode_vm::checkpoint::Checkpoints;
+use intcode_vm::expr::{Expr, WatchedExprs};
+use intcode_vm::history::History;
+use intcode_vm::snapshot::Snapshot;
+use intcode_vm::symbols::SymbolTable;
+use intcode_vm::{disasm, ProgramElement, ProgramState, StopReason, VmError, Watchpoint};
+
+const HELP: &str = "\
+commands:
+    step [n]              Execute the next n instructions, or just the next one
+    next                  Execute the next instruction, running any function it calls
+                          until it returns
+    finish                Run until the current function returns
+    back [n]              Undo the last n instructions, or just the last one
+    continue              Run until the program terminates, needs an input, reaches a
+                          breakpoint or watchpoint, or hits an error
+    break [addr]          Set a breakpoint at addr, or list the breakpoints
+    delete <addr>         Remove the breakpoint at addr
+    watch <mem> [r|w|rw]  Stop after mem[addr] or mem[start..end] is read, written (the
+                          default) or either
+    watch                 List the watchpoints
+    unwatch <n>           Remove the nth watchpoint in the list
+    display <expr>        Print the value of expr, eg mem[mem[5] + 1], whenever it changes
+    display               List the displayed expressions
+    undisplay <n>         Stop displaying the nth expression in the list
+    input <v>, <v>...     Queue values on the input queue
+    print <target>        Print pc, rb, inputs, outputs, mem[addr] or mem[start..end]
+    set <target> = <v>    Change pc, rb or mem[addr]
+    disas [addr] [count]  Disassemble count instructions from addr, or around the pc
+    state                 Print the VM state
+    snapshot              Remember the current memory, to compare against later
+    diff                  Print every address that has changed since the last snapshot
+    history               Print the most recently executed instructions
+    symbols [file]        Load names for addresses from a symbol file, or list them
+    name <addr> <name>    Name addr, so that it's shown as name in disassembly
+    reset                 Restart the program from its initial state
+    help                  Print this message
+    quit                  Exit the debugger";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Target {
+    Pc,
+    RelativeBase,
+    Inputs,
+    Outputs,
+    Mem(usize),
+    MemRange(usize, usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Command {
+    Step(usize),
+    Next,
+    Finish,
+    Back(u64),
+    Continue,
+    Break(Option<usize>),
+    Delete(usize),
+    Watch(Option<Watchpoint>),
+    Unwatch(usize),
+    Display(Option<Expr>),
+    Undisplay(usize),
+    Input(Vec<ProgramElement>),
+    Print(Target),
+    Set(Target, ProgramElement),
+    Disas(Option<usize>, usize),
+    State,
+    Snapshot,
+    Diff,
+    History,
+    Symbols(Option<String>),
+    Name(usize, String),
+    Reset,
+    Help,
+    Quit,
+}
+
+fn parse_num<T: std::str::FromStr>(s: &str) -> Result<T, String> {
+    s.trim().parse().map_err(|_| format!("'{}' isn't a valid number", s.trim()))
+}
+
+fn parse_target(s: &str) -> Result<Target, String> {
+    let s = s.trim();
+    match s {
+        "pc" => return Ok(Target::Pc),
+        "rb" => return Ok(Target::RelativeBase),
+        "inputs" => return Ok(Target::Inputs),
+        "outputs" => return Ok(Target::Outputs),
+        _ => (),
+    }
+
+    let addr = s.strip_prefix("mem[")
+        .and_then(|s| s.strip_suffix(']'))
+        .ok_or_else(|| format!("Unknown target '{}'", s))?;
+
+    match addr.find("..") {
+        Some(idx) => Ok(Target::MemRange(parse_num(&addr[..idx])?, parse_num(&addr[(idx + 2)..])?)),
+        None => Ok(Target::Mem(parse_num(addr)?)),
+    }
+}
+
+impl Command {
+    fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (name, args) = match line.find(char::is_whitespace) {
+            Some(idx) => (&line[..idx], line[idx..].trim()),
+            None => (line, ""),
+        };
+        let words = args.split_whitespace().collect::<Vec<_>>();
+
+        match name {
+            "s" | "step" => match words.first() {
+                Some(n) => Ok(Command::Step(parse_num(n)?)),
+                None => Ok(Command::Step(1)),
+            },
+            "n" | "next" => Ok(Command::Next),
+            "finish" => Ok(Command::Finish),
+            "back" => match words.first() {
+                Some(n) => Ok(Command::Back(parse_num(n)?)),
+                None => Ok(Command::Back(1)),
+            },
+            "c" | "continue" => Ok(Command::Continue),
+            "b" | "break" => Ok(Command::Break(words.first().map(|a| parse_num(a)).transpose()?)),
+            "delete" => match words.first() {
+                Some(addr) => Ok(Command::Delete(parse_num(addr)?)),
+                None => Err("Expected 'delete <addr>'".to_string()),
+            },
+            "w" | "watch" => {
+                let addrs = match words.first() {
+                    Some(target) => match parse_target(target)? {
+                        Target::Mem(addr) => addr..(addr + 1),
+                        Target::MemRange(start, end) => start..end,
+                        _ => return Err("Can only watch mem[addr] or mem[start..end]".to_string()),
+                    },
+                    None => return Ok(Command::Watch(None)),
+                };
+
+                let watchpoint = match words.get(1).copied() {
+                    None | Some("w") => Watchpoint::writes(addrs),
+                    Some("r") => Watchpoint::reads(addrs),
+                    Some("rw") => Watchpoint::accesses(addrs),
+                    Some(other) => return Err(format!("Unknown access '{}', expected r, w or rw", other)),
+                };
+                Ok(Command::Watch(Some(watchpoint)))
+            }
+            "unwatch" => match words.first() {
+                Some(idx) => Ok(Command::Unwatch(parse_num(idx)?)),
+                None => Err("Expected 'unwatch <n>'".to_string()),
+            },
+            "display" if args.is_empty() => Ok(Command::Display(None)),
+            "display" => Ok(Command::Display(Some(args.parse::<Expr>().map_err(|e| e.to_string())?))),
+            "undisplay" => match words.first() {
+                Some(idx) => Ok(Command::Undisplay(parse_num(idx)?)),
+                None => Err("Expected 'undisplay <n>'".to_string()),
+            },
+            "i" | "input" => {
+                let values = args
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|v| !v.is_empty())
+                    .map(parse_num)
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Command::Input(values))
+            }
+            "p" | "print" => Ok(Command::Print(parse_target(args)?)),
+            "set" => {
+                let eq = args.find('=').ok_or("Expected 'set <target> = <value>'")?;
+                let target = parse_target(&args[..eq])?;
+                if let Target::Inputs | Target::Outputs | Target::MemRange(..) = target {
+                    return Err("Can only set pc, rb or mem[addr]".to_string());
+                }
+                Ok(Command::Set(target, parse_num(&args[(eq + 1)..])?))
+            }
+            "d" | "disas" => {
+                let addr = words.first().map(|a| parse_num(a)).transpose()?;
+                let count = words.get(1).map(|c| parse_num(c)).transpose()?.unwrap_or(10);
+                Ok(Command::Disas(addr, count))
+            }
+            "state" => Ok(Command::State),
+            "snapshot" => Ok(Command::Snapshot),
+            "diff" => Ok(Command::Diff),
+            "history" => Ok(Command::History),
+            "symbols" => Ok(Command::Symbols(words.first().map(|path| path.to_string()))),
+            "name" => match words.as_slice() {
+                [addr, name] => Ok(Command::Name(parse_num(addr)?, name.to_string())),
+                _ => Err("Expected 'name <addr> <name>'".to_string()),
+            },
+            "reset" => Ok(Command::Reset),
+            "h" | "help" => Ok(Command::Help),
+            "q" | "quit" => Ok(Command::Quit),
+            other => Err(format!("Unknown command '{}', try 'help'", other)),
+        }
+    }
+}
+
+/// Number of instructions kept for the `history` command
+const HISTORY_LEN: usize = 20;
+
+/// Number of instructions between the checkpoints used by the `back` command
+const CHECKPOINT_INTERVAL: u64 = 10_000;
+
+struct Debugger {
+    initial: ProgramState,
+    state: ProgramState,
+    snapshot: Option<Snapshot>,
+    symbols: SymbolTable,
+    displays: WatchedExprs,
+}
+
+impl Debugger {
+    fn new(mut program: ProgramState) -> Self {
+        program.history = Some(History::new(HISTORY_LEN));
+        program.checkpoints = Some(Checkpoints::new(CHECKPOINT_INTERVAL));
+        Self {
+            initial: program.clone(),
+            state: program,
+            snapshot: None,
+            symbols: SymbolTable::default(),
+            displays: WatchedExprs::default(),
+        }
+    }
+
+    fn print_state(&self) {
+        let state = &self.state;
+        println!(
+            "pc={} rb={} inputs={:?} outputs={:?}{}",
+            state.program_counter,
+            state.relative_base,
+            state.inputs,
+            state.outputs,
+            if state.terminated { " (terminated)" } else { "" },
+        );
+        if !state.terminated {
+            println!("=> {}", self.symbols.annotate(&state.next_instruction()));
+        }
+    }
+
+    /// Prints every displayed expression whose value has changed since it was last checked
+    fn print_changes(&mut self) {
+        for change in self.displays.check(&self.state) {
+            println!("{}: {} = {} (was {})", change.idx, change.expr, describe(change.new), describe(change.old));
+        }
+    }
+
+    /// Executes up to `count` instructions, stopping early if the program can't continue or
+    /// touches a watched address
+    fn step(&mut self, count: usize) {
+        let mut executed = 0;
+        while !self.state.terminated && executed < count {
+            match self.state.progress_state() {
+                Ok(_) => {
+                    executed += 1;
+                    self.print_changes();
+                    if let Some(hit) = self.state.take_watch_hit() {
+                        println!("Stopped at watchpoint: {}", hit);
+                        break;
+                    }
+                }
+                Err(e) => {
+                    report_error(&self.state, e);
+                    break;
+                }
+            }
+        }
+
+        println!("Executed {} instruction(s)", executed);
+        self.print_state();
+    }
+
+    /// Reports why a run stopped, then where
+    fn report_stop(&mut self, result: Result<StopReason, VmError>) {
+        match result {
+            Ok(StopReason::Terminated) => (),
+            Ok(StopReason::NeedsInput) => report_error(&self.state, VmError::NoInput),
+            Ok(StopReason::Breakpoint(addr)) => println!("Stopped at breakpoint {}", addr),
+            Ok(StopReason::Watchpoint(hit)) => println!("Stopped at watchpoint: {}", hit),
+            Ok(StopReason::BudgetExhausted) => unreachable!("Only returned by run_for"),
+            Ok(StopReason::Output(_)) => unreachable!("Only returned by run_until_output"),
+            Ok(StopReason::Stepped) => (),
+            Err(e) => report_error(&self.state, e),
+        }
+
+        self.print_changes();
+
+        self.print_state();
+    }
+
+    fn print(&self, target: Target) {
+        let state = &self.state;
+        match target {
+            Target::Pc => println!("pc = {}", state.program_counter),
+            Target::RelativeBase => println!("rb = {}", state.relative_base),
+            Target::Inputs => println!("inputs = {:?}", state.inputs),
+            Target::Outputs => println!("outputs = {:?}", state.outputs),
+            Target::Mem(addr) => println!("mem[{}] = {}", addr, state.mem.read_addr(addr)),
+            Target::MemRange(start, end) => {
+                let values = state.mem.read_range(start, end.saturating_sub(start));
+                println!("mem[{}..{}] = {:?}", start, end, values);
+            }
+        }
+    }
+
+    /// Handles a single command, returning false if the debugger should exit
+    fn execute(&mut self, command: Command) -> bool {
+        match command {
+            Command::Step(count) => self.step(count),
+            Command::Back(count) => {
+                let instruction = self.state.stats().total().saturating_sub(count);
+                match self.state.rewind_to(instruction) {
+                    Ok(true) => {
+                        self.print_changes();
+                        self.print_state();
+                    }
+                    Ok(false) => println!("Can't go back that far"),
+                    Err(e) => report_error(&self.state, e),
+                }
+            }
+            Command::Next => {
+                let result = self.state.step_over();
+                self.report_stop(result);
+            }
+            Command::Finish => {
+                let result = self.state.step_out();
+                self.report_stop(result);
+            }
+            Command::Continue => {
+                let result = self.state.run_to_next_input();
+                self.report_stop(result);
+            }
+            Command::Break(Some(addr)) => {
+                self.state.breakpoints.insert(addr);
+            }
+            Command::Break(None) => println!("breakpoints = {:?}", self.state.breakpoints),
+            Command::Delete(addr) => {
+                if !self.state.breakpoints.remove(&addr) {
+                    println!("There's no breakpoint at {}", addr);
+                }
+            }
+            Command::Watch(Some(watchpoint)) => self.state.watchpoints.push(watchpoint),
+            Command::Watch(None) => {
+                for (idx, w) in self.state.watchpoints.iter().enumerate() {
+                    let access = match (w.on_read, w.on_write) {
+                        (true, true) => "rw",
+                        (true, false) => "r",
+                        _ => "w",
+                    };
+                    println!("{}: mem[{}..{}] {}", idx, w.addrs.start, w.addrs.end, access);
+                }
+            }
+            Command::Unwatch(idx) => {
+                if idx < self.state.watchpoints.len() {
+                    self.state.watchpoints.remove(idx);
+                } else {
+                    println!("There's no watchpoint {}", idx);
+                }
+            }
+            Command::Display(Some(expr)) => {
+                let name = expr.to_string();
+                let value = self.displays.add(expr, &self.state);
+                println!("{}: {} = {}", self.displays.len() - 1, name, describe(value));
+            }
+            Command::Display(None) => {
+                for (idx, (expr, value)) in self.displays.iter().enumerate() {
+                    println!("{}: {} = {}", idx, expr, describe(value));
+                }
+            }
+            Command::Undisplay(idx) => {
+                if self.displays.remove(idx).is_none() {
+                    println!("There's no displayed expression {}", idx);
+                }
+            }
+            Command::Input(values) => self.state.inputs.extend(values),
+            Command::Print(target) => self.print(target),
+            Command::Set(target, value) => {
+                match target {
+                    Target::Pc => self.state.program_counter = value as usize,
+                    Target::RelativeBase => self.state.relative_base = value,
+                    Target::Mem(addr) => self.state.mem.write_addr(addr, value),
+                    _ => unreachable!("Rejected by Command::parse"),
+                }
+                self.print_changes();
+            }
+            Command::Disas(addr, count) => {
+                let mut addr = addr.unwrap_or(self.state.program_counter);
+                for _ in 0..count {
+                    let line = disasm::disassemble_one(&self.state.mem, addr);
+                    if let Some(name) = self.symbols.name(addr) {
+                        println!("{}:", name);
+                    }
+                    let marker = if addr == self.state.program_counter { "=>" } else { "  " };
+                    println!("{} {}", marker, self.symbols.annotate(&line));
+                    addr += line.instruction.length();
+                }
+            }
+            Command::State => self.print_state(),
+            Command::Snapshot => self.snapshot = Some(self.state.snapshot()),
+            Command::Diff => match &self.snapshot {
+                Some(snapshot) => {
+                    for (addr, before, after) in snapshot.diff(&self.state.snapshot()) {
+                        println!("mem[{}]: {} -> {}", addr, before, after);
+                    }
+                }
+                None => println!("There's no snapshot to compare against, take one with 'snapshot'"),
+            },
+            Command::History => {
+                if let Some(history) = &self.state.history {
+                    print!("{}", history);
+                }
+            }
+            Command::Symbols(Some(path)) => match SymbolTable::load_file(path.as_ref()) {
+                Ok(symbols) => self.symbols = symbols,
+                Err(e) => println!("Failed to load {}: {}", path, e),
+            },
+            Command::Symbols(None) => print!("{}", self.symbols),
+            Command::Name(addr, name) => self.symbols.insert(addr, name),
+            Command::Reset => {
+                // Breakpoints and watchpoints aren't part of the program, so survive the reset
+                let breakpoints = std::mem::take(&mut self.state.breakpoints);
+                let watchpoints = std::mem::take(&mut self.state.watchpoints);
+                self.state = self.initial.clone();
+                self.state.breakpoints = breakpoints;
+                self.state.watchpoints = watchpoints;
+                self.print_changes();
+                self.print_state();
+            }
+            Command::Help => println!("{}", HELP),
+            Command::Quit => return false,
+        }
+
+        true
+    }
+}
+
+/// Formats the value of a displayed expression
+fn describe(value: Option<ProgramElement>) -> String {
+    value.map_or_else(|| "invalid".to_string(), |value| value.to_string())
+}
+
+fn report_error(state: &ProgramState, e: VmError) {
+    match e {
+        VmError::NoInput => println!("Waiting for input"),
+        e => println!("Error: {}", state.error_context(e, false)),
+    }
+}
+
+fn main() {
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: icdb <program file> [<patch file>]");
+            std::process::exit(1);
+        }
+    };
+
+    let source = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+        eprintln!("Failed to read {}: {}", path, e);
+        std::process::exit(1);
+    });
+
+    let mut program = source.parse::<ProgramState>().unwrap_or_else(|e| {
+        eprintln!("Failed to load {}: {}", path, e);
+        std::process::exit(1);
+    });
+
+    if let Some(patch_path) = std::env::args().nth(2) {
+        program.apply_patch_file(patch_path.as_ref()).unwrap_or_else(|e| {
+            eprintln!("Failed to apply {}: {}", patch_path, e);
+            std::process::exit(1);
+        });
+    }
+
+    let mut debugger = Debugger::new(program);
+    debugger.print_state();
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(icdb) ");
+        io::stdout().flush().expect("Failed to flush stdout");
+
+        let line = match lines.next() {
+            Some(line) => line.expect("Failed to read from stdin"),
+            None => break,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match Command::parse(&line) {
+            Ok(command) => {
+                if !debugger.execute(command) {
+                    break;
+                }
+            }
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Command::parse("step"), Ok(Command::Step(1)));
+        assert_eq!(Command::parse("s 20"), Ok(Command::Step(20)));
+        assert_eq!(Command::parse("back"), Ok(Command::Back(1)));
+        assert_eq!(Command::parse("n"), Ok(Command::Next));
+        assert_eq!(Command::parse("finish"), Ok(Command::Finish));
+        assert_eq!(Command::parse("input 1, -2 3"), Ok(Command::Input(vec![1, -2, 3])));
+        assert_eq!(Command::parse("print mem[12]"), Ok(Command::Print(Target::Mem(12))));
+        assert_eq!(Command::parse("p mem[4..8]"), Ok(Command::Print(Target::MemRange(4, 8))));
+        assert_eq!(Command::parse("set pc = 7"), Ok(Command::Set(Target::Pc, 7)));
+        assert_eq!(Command::parse("set mem[3]=-1"), Ok(Command::Set(Target::Mem(3), -1)));
+        assert_eq!(Command::parse("disas 10"), Ok(Command::Disas(Some(10), 10)));
+        assert_eq!(Command::parse("break 4"), Ok(Command::Break(Some(4))));
+        assert_eq!(Command::parse("b"), Ok(Command::Break(None)));
+        assert_eq!(Command::parse("watch mem[3]"), Ok(Command::Watch(Some(Watchpoint::writes(3..4)))));
+        assert_eq!(Command::parse("w mem[2..6] rw"), Ok(Command::Watch(Some(Watchpoint::accesses(2..6)))));
+        assert_eq!(Command::parse("unwatch 0"), Ok(Command::Unwatch(0)));
+        assert_eq!(Command::parse("display mem[mem[5]]"), Ok(Command::Display(Some("mem[mem[5]]".parse().unwrap()))));
+        assert_eq!(Command::parse("display"), Ok(Command::Display(None)));
+        assert_eq!(Command::parse("undisplay 1"), Ok(Command::Undisplay(1)));
+        assert_eq!(Command::parse("snapshot"), Ok(Command::Snapshot));
+        assert_eq!(Command::parse("diff"), Ok(Command::Diff));
+        assert_eq!(Command::parse("history"), Ok(Command::History));
+        assert_eq!(Command::parse("symbols game.sym"), Ok(Command::Symbols(Some("game.sym".to_string()))));
+        assert_eq!(Command::parse("name 386 score"), Ok(Command::Name(386, "score".to_string())));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Command::parse("jump 5").is_err());
+        assert!(Command::parse("print mem[x]").is_err());
+        assert!(Command::parse("set outputs = 1").is_err());
+        assert!(Command::parse("set pc 1").is_err());
+        assert!(Command::parse("watch pc").is_err());
+        assert!(Command::parse("watch mem[1] x").is_err());
+        assert!(Command::parse("name score").is_err());
+        assert!(Command::parse("display mem[").is_err());
+        assert!(Command::parse("undisplay").is_err());
+    }
+
+    #[test]
+    fn test_execute() {
+        let mut debugger = Debugger::new(ProgramState::load_program_str("3,0,4,0,99"));
+        debugger.execute(Command::Step(5));
+        assert_eq!(debugger.state.program_counter, 0);
+
+        debugger.execute(Command::Input(vec![42]));
+        debugger.execute(Command::Continue);
+        assert!(debugger.state.terminated);
+        assert_eq!(debugger.state.outputs, vec![42]);
+
+        debugger.execute(Command::Back(2));
+        assert_eq!(debugger.state.program_counter, 2);
+        assert!(debugger.state.outputs.is_empty());
+        debugger.execute(Command::Continue);
+        assert_eq!(debugger.state.outputs, vec![42]);
+
+        debugger.execute(Command::Break(Some(2)));
+        debugger.execute(Command::Reset);
+        debugger.execute(Command::Input(vec![1]));
+        debugger.execute(Command::Continue);
+        assert_eq!(debugger.state.program_counter, 2);
+        assert!(debugger.state.outputs.is_empty());
+
+        debugger.execute(Command::Delete(2));
+        debugger.execute(Command::Watch(Some(Watchpoint::writes(0..1))));
+        debugger.execute(Command::Reset);
+        debugger.execute(Command::Input(vec![7]));
+        debugger.execute(Command::Continue);
+        assert_eq!(debugger.state.program_counter, 2);
+        assert_eq!(debugger.state.mem.read_addr(0), 7);
+
+        debugger.execute(Command::Set(Target::Mem(1), 5));
+        assert_eq!(debugger.state.mem.read_addr(1), 5);
+
+        debugger.execute(Command::Display(Some("mem[mem[1]] + 1".parse().unwrap())));
+        debugger.execute(Command::Set(Target::Mem(5), 2));
+        assert_eq!(debugger.displays.iter().next().map(|(_, value)| value), Some(Some(3)));
+        debugger.execute(Command::Undisplay(0));
+        assert!(debugger.displays.is_empty());
+        assert!(!debugger.execute(Command::Quit));
+    }
+}
@@ -0,0 +1,260 @@
+//! Shell for running several intcode programs at once as processes, see `intcode_vm::os`.
+//!
+//! usage: icos [<program file>...]
+
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use intcode_vm::os::{Os, Pid, Status};
+use intcode_vm::{parse_program, ProgramElement};
+
+const HELP: &str = "\
+commands:
+    spawn <file> [name]   Load the program in file as a new process, named after the file
+                          unless a name is given
+    ps                    List every process
+    kill <pid>            Stop a process
+    pipe <from> <to>      Send everything process from outputs to process to as inputs
+    unpipe <from>         Stop piping process from's outputs anywhere
+    send <pid> <v>, <v>.. Queue values on a process's inputs
+    out <pid>             Print and clear the outputs of a process that aren't piped anywhere
+    run [rounds]          Run until every process is waiting for input or has stopped, or for
+                          at most rounds rounds (1000 by default)
+    help                  Print this message
+    quit                  Exit the shell";
+
+/// Rounds the `run` command is limited to when it isn't given a number
+const DEFAULT_ROUNDS: usize = 1000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Command {
+    Spawn(String, Option<String>),
+    Ps,
+    Kill(Pid),
+    Pipe(Pid, Pid),
+    Unpipe(Pid),
+    Send(Pid, Vec<ProgramElement>),
+    Out(Pid),
+    Run(usize),
+    Help,
+    Quit,
+}
+
+fn parse_num<T: std::str::FromStr>(s: &str) -> Result<T, String> {
+    s.trim().parse().map_err(|_| format!("'{}' isn't a valid number", s.trim()))
+}
+
+impl Command {
+    fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (name, args) = match line.find(char::is_whitespace) {
+            Some(idx) => (&line[..idx], line[idx..].trim()),
+            None => (line, ""),
+        };
+        let words = args.split_whitespace().collect::<Vec<_>>();
+
+        match name {
+            "spawn" => match words.as_slice() {
+                [path] => Ok(Command::Spawn(path.to_string(), None)),
+                [path, name] => Ok(Command::Spawn(path.to_string(), Some(name.to_string()))),
+                _ => Err("Expected 'spawn <file> [name]'".to_string()),
+            },
+            "ps" => Ok(Command::Ps),
+            "kill" => match words.as_slice() {
+                [pid] => Ok(Command::Kill(parse_num(pid)?)),
+                _ => Err("Expected 'kill <pid>'".to_string()),
+            },
+            "pipe" => match words.as_slice() {
+                [from, to] => Ok(Command::Pipe(parse_num(from)?, parse_num(to)?)),
+                _ => Err("Expected 'pipe <from> <to>'".to_string()),
+            },
+            "unpipe" => match words.as_slice() {
+                [from] => Ok(Command::Unpipe(parse_num(from)?)),
+                _ => Err("Expected 'unpipe <from>'".to_string()),
+            },
+            "send" => {
+                let pid = words.first().ok_or("Expected 'send <pid> <v>, <v>...'")?;
+                let values = args[pid.len()..]
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|v| !v.is_empty())
+                    .map(parse_num)
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Command::Send(parse_num(pid)?, values))
+            }
+            "out" => match words.as_slice() {
+                [pid] => Ok(Command::Out(parse_num(pid)?)),
+                _ => Err("Expected 'out <pid>'".to_string()),
+            },
+            "r" | "run" => match words.first() {
+                Some(rounds) => Ok(Command::Run(parse_num(rounds)?)),
+                None => Ok(Command::Run(DEFAULT_ROUNDS)),
+            },
+            "h" | "help" => Ok(Command::Help),
+            "q" | "quit" => Ok(Command::Quit),
+            other => Err(format!("Unknown command '{}', try 'help'", other)),
+        }
+    }
+}
+
+struct Shell {
+    os: Os,
+}
+
+impl Shell {
+    fn spawn(&mut self, path: &str, name: Option<String>) -> Result<Pid, String> {
+        let source = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let program = parse_program(&source).map_err(|e| format!("Failed to load {}: {}", path, e))?;
+        let name = name.unwrap_or_else(|| {
+            let stem = Path::new(path).file_stem().unwrap_or_else(|| path.as_ref());
+            stem.to_string_lossy().into_owned()
+        });
+        Ok(self.os.spawn(name, program))
+    }
+
+    fn ps(&self) {
+        println!("{:>4}  {:<16} {:<24} {:>5} {:>8} {:>14}", "PID", "NAME", "STATUS", "PIPE", "OUTPUTS", "INSTRUCTIONS");
+        for process in self.os.processes() {
+            let status = match process.status {
+                Status::Failed(e) => format!("failed: {}", e),
+                status => format!("{:?}", status).to_lowercase(),
+            };
+            let pipe = process.pipe.map_or("-".to_string(), |to| to.to_string());
+            println!(
+                "{:>4}  {:<16} {:<24} {:>5} {:>8} {:>14}",
+                process.pid, process.name, status, pipe, process.outputs, process.instructions,
+            );
+        }
+    }
+
+    /// Handles a single command, returning false if the shell should exit
+    fn execute(&mut self, command: Command) -> bool {
+        let result = match command {
+            Command::Spawn(path, name) => self.spawn(&path, name).map(|pid| println!("Spawned {}", pid)),
+            Command::Ps => {
+                self.ps();
+                Ok(())
+            }
+            Command::Kill(pid) => self.os.kill(pid).map_err(|e| e.to_string()),
+            Command::Pipe(from, to) => self.os.pipe(from, to).map_err(|e| e.to_string()),
+            Command::Unpipe(from) => self.os.unpipe(from).map(|_| ()).map_err(|e| e.to_string()),
+            Command::Send(pid, values) => self.os.send(pid, &values).map_err(|e| e.to_string()),
+            Command::Out(pid) => self.os.take_outputs(pid).map(|outputs| println!("{:?}", outputs)).map_err(|e| e.to_string()),
+            Command::Run(max_rounds) => {
+                let before = self.os.processes();
+                let rounds = self.os.run(max_rounds);
+                println!("Ran {} round(s)", rounds);
+
+                for (before, after) in before.iter().zip(self.os.processes()) {
+                    if let Status::Failed(e) = after.status {
+                        if before.status != after.status {
+                            println!("Process {} ({}) failed: {}", after.pid, after.name, e);
+                        }
+                    }
+                }
+                Ok(())
+            }
+            Command::Help => {
+                println!("{}", HELP);
+                Ok(())
+            }
+            Command::Quit => return false,
+        };
+
+        if let Err(e) = result {
+            println!("{}", e);
+        }
+        true
+    }
+}
+
+fn main() {
+    let mut shell = Shell { os: Os::new() };
+    for path in std::env::args().skip(1) {
+        if let Err(e) = shell.spawn(&path, None) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(icos) ");
+        io::stdout().flush().expect("Failed to flush stdout");
+
+        let line = match lines.next() {
+            Some(line) => line.expect("Failed to read from stdin"),
+            None => break,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match Command::parse(&line) {
+            Ok(command) => {
+                if !shell.execute(command) {
+                    break;
+                }
+            }
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Command::parse("spawn a.txt"), Ok(Command::Spawn("a.txt".to_string(), None)));
+        assert_eq!(
+            Command::parse("spawn a.txt nic"),
+            Ok(Command::Spawn("a.txt".to_string(), Some("nic".to_string())))
+        );
+        assert_eq!(Command::parse("ps"), Ok(Command::Ps));
+        assert_eq!(Command::parse("kill 3"), Ok(Command::Kill(3)));
+        assert_eq!(Command::parse("pipe 0 1"), Ok(Command::Pipe(0, 1)));
+        assert_eq!(Command::parse("unpipe 0"), Ok(Command::Unpipe(0)));
+        assert_eq!(Command::parse("send 2 1, -2 3"), Ok(Command::Send(2, vec![1, -2, 3])));
+        assert_eq!(Command::parse("out 1"), Ok(Command::Out(1)));
+        assert_eq!(Command::parse("run"), Ok(Command::Run(DEFAULT_ROUNDS)));
+        assert_eq!(Command::parse("r 5"), Ok(Command::Run(5)));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Command::parse("fork 1").is_err());
+        assert!(Command::parse("spawn").is_err());
+        assert!(Command::parse("kill").is_err());
+        assert!(Command::parse("pipe 1").is_err());
+        assert!(Command::parse("send").is_err());
+        assert!(Command::parse("send 1 x").is_err());
+        assert!(Command::parse("run x").is_err());
+    }
+
+    #[test]
+    fn test_execute() {
+        let mut shell = Shell { os: Os::new() };
+        let echo = shell.os.spawn("echo", parse_program("3,7,4,7,1105,1,0,0").unwrap());
+        let sink = shell.os.spawn("sink", parse_program("3,5,1105,1,0,0").unwrap());
+
+        shell.execute(Command::Pipe(echo, sink));
+        shell.execute(Command::Send(echo, vec![4, 5]));
+        shell.execute(Command::Run(10));
+        assert_eq!(shell.os.vm(sink).unwrap().mem.read_addr(5), 5);
+
+        shell.execute(Command::Unpipe(echo));
+        shell.execute(Command::Send(echo, vec![6]));
+        shell.execute(Command::Run(10));
+        assert_eq!(shell.os.process(echo).unwrap().outputs, 1);
+        shell.execute(Command::Out(echo));
+        assert_eq!(shell.os.process(echo).unwrap().outputs, 0);
+
+        shell.execute(Command::Kill(sink));
+        assert_eq!(shell.os.process(sink).unwrap().status, Status::Killed);
+        assert!(shell.execute(Command::Spawn("missing.txt".to_string(), None)));
+        assert_eq!(shell.os.processes().len(), 2);
+        assert!(!shell.execute(Command::Quit));
+    }
+}
@@ -0,0 +1,35 @@
+//! Runs an ASCII intcode program in the terminal, so that it can be played by hand.
+//!
+//! usage: icrun <program file> [<patch file>]
+
+use intcode_vm::{ProgramState, StopReason};
+
+fn main() {
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: icrun <program file> [<patch file>]");
+            std::process::exit(1);
+        }
+    };
+
+    let mut builder = ProgramState::builder().program_file(&path);
+    if let Some(patch_path) = std::env::args().nth(2) {
+        builder = builder.patch_file(patch_path);
+    }
+
+    let mut program = builder.build().unwrap_or_else(|e| {
+        eprintln!("Failed to load {}: {}", path, e);
+        std::process::exit(1);
+    });
+
+    match program.run_interactive() {
+        Ok(StopReason::Terminated) => (),
+        Ok(StopReason::NeedsInput) => eprintln!("Out of input, stopping"),
+        Ok(reason) => eprintln!("Stopped: {:?}", reason),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! Translates an Intcode program into Rust source, see `intcode_vm::transpile`
+//!
+//! usage: intcode2rs <program file> [<function name>]
+
+use intcode_vm::parse_program;
+use intcode_vm::transpile::transpile;
+
+fn main() {
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: intcode2rs <program file> [<function name>]");
+            std::process::exit(1);
+        }
+    };
+    let name = std::env::args().nth(2).unwrap_or_else(|| "run_intcode".to_string());
+
+    let source = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+        eprintln!("Failed to read {}: {}", path, e);
+        std::process::exit(1);
+    });
+
+    let program = parse_program(&source).unwrap_or_else(|e| {
+        eprintln!("Failed to load {}: {}", path, e);
+        std::process::exit(1);
+    });
+
+    print!("{}", transpile(&program, &name));
+}
@@ -0,0 +1,350 @@
+//! The basic block backend, which decodes straight-line runs of instructions once and caches them,
+//! rather than decoding every instruction each time execution reaches it.
+//!
+//! A block starts wherever execution lands and runs up to and including the next jump or halt.
+//! Writing to an address inside a cached block throws that block away, so self-modifying programs
+//! still behave. Memory can also be changed from outside in between runs, in which case each block
+//! is checked against memory the next time it's used.
+//!
+//! Only the `run_*` methods use the cache, and only while there are no breakpoints and nothing
+//! else needs checking after each instruction, eg watchpoints or profiling. Everything else
+//! executes one instruction at a time, as the optimized backend does.
+
+use crate::prelude::*;
+use crate::memory::PageMap;
+use crate::{
+    InputSource, Instruction, OpCode, OutputSink, PagedMemory, ParameterMode, ProgramElement,
+    ProgramState, StopReason, VmError,
+};
+
+#[derive(Clone)]
+struct BlockInstruction {
+    instr: Instruction,
+
+    /// Whether any of the parameters are relative mode, in which case whether they refer to a
+    /// negative address can only be checked when the instruction executes
+    relative: bool,
+}
+
+#[derive(Clone)]
+struct Block {
+    /// The elements the block was decoded from
+    source: Vec<ProgramElement>,
+    instrs: Vec<BlockInstruction>,
+
+    /// The epoch in which `source` was last checked against memory
+    verified: u64,
+}
+
+impl BlockInstruction {
+    fn new(instr: Instruction) -> Self {
+        let relative = instr.modes.contains(&ParameterMode::Relative);
+        Self { instr, relative }
+    }
+}
+
+impl Block {
+    /// Re-decodes the instruction that `addr` is part of after it's been written to. Programs
+    /// often index arrays by rewriting an instruction's parameters, which doesn't change the
+    /// shape of the block, so only fails if the opcode changed or the instruction became invalid.
+    fn patch(&mut self, start: usize, mem: &PagedMemory<ProgramElement>, addr: usize) -> bool {
+        let mut pc = start;
+        for block_instr in &mut self.instrs {
+            let length = block_instr.instr.opcode.length();
+            if addr < pc + length {
+                let mut elements = [0; 4];
+                mem.read_into(pc, &mut elements);
+                return match Instruction::decode_at(pc, elements) {
+                    Ok(instr) if instr.opcode == block_instr.instr.opcode && !has_negative_position(&instr) => {
+                        *block_instr = BlockInstruction::new(instr);
+                        self.source[addr - start] = mem.read_addr(addr);
+                        true
+                    }
+                    _ => false,
+                };
+            }
+
+            pc += length;
+        }
+
+        false
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct BlockCache {
+    /// Keyed by the address of the first instruction. Like page indices, these are small enough
+    /// not to need the default hasher.
+    blocks: PageMap<Block>,
+
+    /// Number of cached blocks that each address is part of
+    coverage: PagedMemory<u32>,
+
+    /// Incremented whenever memory has been changed by something other than a cached block
+    epoch: u64,
+
+    /// The version of memory at the end of the last run
+    mem_version: (u64, u64),
+}
+
+impl BlockCache {
+    /// Runs as `ProgramState::run_for` does when there are no breakpoints or watchpoints
+    pub(crate) fn run<I: InputSource, O: OutputSink>(
+        &mut self,
+        state: &mut ProgramState<I, O>,
+        max_instructions: u64,
+    ) -> Result<StopReason, VmError> {
+        if state.mem.version() != self.mem_version {
+            self.epoch += 1;
+        }
+
+        let result = self.run_blocks(state, max_instructions);
+        self.mem_version = state.mem.version();
+        result
+    }
+
+    fn run_blocks<I: InputSource, O: OutputSink>(
+        &mut self,
+        state: &mut ProgramState<I, O>,
+        max_instructions: u64,
+    ) -> Result<StopReason, VmError> {
+        let mut executed = 0;
+        while !state.terminated {
+            if executed == max_instructions {
+                return Ok(StopReason::BudgetExhausted);
+            }
+
+            let start = state.program_counter;
+            if !matches!(self.blocks.get(&start), Some(block) if block.verified == self.epoch) {
+                self.load(state)?;
+            }
+
+            // Index of the next instruction in the block, kept across patches to the block
+            let mut next = 0;
+            while let Some(block) = self.blocks.get(&start) {
+                let mut modified = None;
+                for block_instr in &block.instrs[next..] {
+                    if executed == max_instructions {
+                        return Ok(StopReason::BudgetExhausted);
+                    }
+
+                    let instr = &block_instr.instr;
+                    if block_instr.relative {
+                        instr.check_addresses(state)?;
+                    }
+
+                    match instr.execute(state) {
+                        Ok(_) => executed += 1,
+                        Err(VmError::NoInput) => return Ok(StopReason::NeedsInput),
+                        Err(e) => return Err(e),
+                    }
+                    next += 1;
+
+                    if let Some(idx) = instr.opcode.write_param_idx() {
+                        let addr = instr.param_addr(idx, state) as usize;
+                        if self.coverage.read_addr(addr) > 0 {
+                            modified = Some(addr);
+                            break;
+                        }
+                    }
+                }
+
+                match modified {
+                    // The rest of the block carries on from the patched version, unless it had to
+                    // be thrown away
+                    Some(addr) => self.invalidate(&state.mem, addr),
+                    None => break,
+                }
+            }
+        }
+
+        Ok(StopReason::Terminated)
+    }
+
+    /// Makes sure that the block starting at the program counter is cached, and matches memory
+    fn load<I: InputSource, O: OutputSink>(&mut self, state: &ProgramState<I, O>) -> Result<(), VmError> {
+        let pc = state.program_counter;
+        if let Some(block) = self.blocks.get_mut(&pc) {
+            if state.mem.read_range(pc, block.source.len()) == block.source {
+                block.verified = self.epoch;
+                return Ok(());
+            }
+
+            self.remove(pc);
+        }
+
+        let block = decode_block(state, self.epoch)?;
+        for addr in pc..pc + block.source.len() {
+            *self.coverage.entry(addr) += 1;
+        }
+        self.blocks.insert(pc, block);
+
+        Ok(())
+    }
+
+    /// Updates every block that `addr` is part of after it's been written to, throwing away any
+    /// that can't simply be patched
+    fn invalidate(&mut self, mem: &PagedMemory<ProgramElement>, addr: usize) {
+        let mut unpatchable = Vec::new();
+        for (&start, block) in &mut self.blocks {
+            if (start..start + block.source.len()).contains(&addr) && !block.patch(start, mem, addr) {
+                unpatchable.push(start);
+            }
+        }
+
+        for start in unpatchable {
+            self.remove(start);
+        }
+    }
+
+    fn remove(&mut self, start: usize) {
+        if let Some(block) = self.blocks.remove(&start) {
+            for addr in start..start + block.source.len() {
+                *self.coverage.entry(addr) -= 1;
+            }
+        }
+    }
+}
+
+impl core::fmt::Debug for BlockCache {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("BlockCache")
+            .field("blocks", &self.blocks.len())
+            .finish()
+    }
+}
+
+/// Decodes the block starting at the program counter
+fn decode_block<I: InputSource, O: OutputSink>(state: &ProgramState<I, O>, epoch: u64) -> Result<Block, VmError> {
+    let start = state.program_counter;
+    let mut pc = start;
+    let mut instrs = Vec::new();
+    loop {
+        let mut elements = [0; 4];
+        state.mem.read_into(pc, &mut elements);
+
+        let instr = match Instruction::decode_at(pc, elements) {
+            Ok(instr) if !has_negative_position(&instr) => instr,
+
+            // Left to start a block of its own, which will fail to decode if execution gets there
+            _ if !instrs.is_empty() => break,
+
+            _ => {
+                // Gives exactly the same error as the other backends would
+                Instruction::decode(state, elements)?;
+                unreachable!("Instruction at {} is only invalid as part of a block", pc);
+            }
+        };
+
+        let ends_block = matches!(
+            instr.opcode,
+            OpCode::JumpIfTrue | OpCode::JumpIfFalse | OpCode::Terminate
+        );
+
+        pc += instr.opcode.length();
+        instrs.push(BlockInstruction::new(instr));
+        if ends_block {
+            break;
+        }
+    }
+
+    let source = state.mem.read_range(start, pc - start);
+    Ok(Block { source, instrs, verified: epoch })
+}
+
+/// Whether any position mode parameters refer to a negative address, which unlike relative mode
+/// ones doesn't depend on when the instruction executes
+fn has_negative_position(instr: &Instruction) -> bool {
+    instr.modes
+        .iter()
+        .zip(&instr.contents)
+        .take(instr.opcode.length() - 1)
+        .any(|(&mode, &contents)| mode == ParameterMode::Position && contents < 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::{Backend, ProgramState, StopReason};
+
+    fn blocks_program(source: &str) -> ProgramState {
+        let mut program = ProgramState::load_program_str(source);
+        program.backend = Backend::Blocks;
+        program
+    }
+
+    #[test]
+    fn test_self_modifying_block() {
+        // Adds 5 to the output instruction's parameter, which is part of the same block, until
+        // it's at least 20
+        //   0: add 5, [5], [5]
+        //   4: out 7
+        //   6: lt [5], 20, [16]
+        //  10: jnz [16], 0
+        //  13: halt
+        let mut program = blocks_program("101,5,5,5,104,7,1007,5,20,16,1005,16,0,99,0,0,0");
+        assert_eq!(program.run_to_completion(), Ok(StopReason::Terminated));
+        assert_eq!(program.outputs, vec![12, 17, 22]);
+    }
+
+    #[test]
+    fn test_overwritten_opcode() {
+        // Replaces the output later in the block with a halt
+        //   0: add 99, 0, [8]
+        //   4: add 0, 0, [100]
+        //   8: out 1
+        //  10: halt
+        let mut program = blocks_program("1101,99,0,8,1101,0,0,100,104,1,99");
+        assert_eq!(program.run_to_completion(), Ok(StopReason::Terminated));
+        assert!(program.outputs.is_empty());
+        assert_eq!(program.stats().total(), 3);
+    }
+
+    #[test]
+    fn test_memory_changed_between_runs() {
+        let mut program = blocks_program("3,100,4,100,1105,1,0");
+        program.inputs.push_back(1);
+        assert_eq!(program.run_to_next_input(), Ok(StopReason::NeedsInput));
+
+        // Output 7 instead of the input
+        program.mem.write_addr(2, 104);
+        program.mem.write_addr(3, 7);
+        program.inputs.push_back(2);
+        assert_eq!(program.run_to_next_input(), Ok(StopReason::NeedsInput));
+        assert_eq!(program.outputs, vec![1, 7]);
+    }
+
+    #[test]
+    fn test_errors_match_optimized() {
+        let sources = [
+            // Reads address -1 after setting the relative base, in the same block
+            "109,-2,204,1,99",
+            // Reads address -1 in the first instruction of a block
+            "1,-1,0,0,99",
+            // Reads address -1 in the second instruction of what would otherwise be a block
+            "1101,1,1,100,1,-1,0,0,99",
+            // Unknown opcode after the first instruction
+            "1101,1,1,100,42",
+        ];
+
+        for source in &sources {
+            let mut optimized = ProgramState::load_program_str(source);
+            optimized.backend = Backend::Optimized;
+            let mut blocks = blocks_program(source);
+
+            let expected = optimized.run_to_completion();
+            assert!(expected.is_err());
+            assert_eq!(blocks.run_to_completion(), expected);
+            assert_eq!(blocks.program_counter, optimized.program_counter);
+        }
+    }
+
+    #[test]
+    fn test_budget() {
+        let mut program = blocks_program("1101,1,1,100,1105,1,0");
+        assert_eq!(program.run_for(5), Ok(StopReason::BudgetExhausted));
+        assert_eq!(program.stats().total(), 5);
+        assert_eq!(program.program_counter, 4);
+        assert_eq!(program.run_for(0), Ok(StopReason::BudgetExhausted));
+    }
+}
@@ -0,0 +1,172 @@
+//! Building a VM with non-default settings in one expression, rather than by setting fields on
+//! an already loaded one:
+//!
+//! ```
+//! use intcode_vm::ProgramState;
+//!
+//! let mut program = ProgramState::builder()
+//!     .program("3,9,1,9,10,11,4,11,99,0,0,0")
+//!     .input([5])
+//!     .patch(10, 2)
+//!     .max_memory(1 << 20)
+//!     .build()
+//!     .unwrap();
+//!
+//! program.run_to_completion().unwrap();
+//! assert_eq!(program.outputs, vec![7]);
+//! ```
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use crate::{parse_program, Backend, LoadError, ProgramElement, ProgramState};
+
+#[derive(Clone, Debug)]
+enum Source {
+    Str(String),
+    File(PathBuf),
+    Elements(Vec<ProgramElement>),
+}
+
+/// See the module docs. Nothing is loaded until `build`, which is where any errors come from.
+#[derive(Clone, Debug, Default)]
+pub struct ProgramStateBuilder {
+    source: Option<Source>,
+    inputs: VecDeque<ProgramElement>,
+    patches: Vec<(usize, ProgramElement)>,
+    patch_files: Vec<PathBuf>,
+    memory_limit: Option<usize>,
+    backend: Option<Backend>,
+    breakpoints: Vec<usize>,
+    dense: bool,
+}
+
+impl ProgramState {
+    pub fn builder() -> ProgramStateBuilder {
+        ProgramStateBuilder::default()
+    }
+}
+
+impl ProgramStateBuilder {
+    /// The program's comma-separated source. Without a program, memory starts out empty.
+    pub fn program(mut self, source: &str) -> Self {
+        self.source = Some(Source::Str(source.to_string()));
+        self
+    }
+
+    pub fn program_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.source = Some(Source::File(path.into()));
+        self
+    }
+
+    pub fn elements(mut self, elements: impl IntoIterator<Item = ProgramElement>) -> Self {
+        self.source = Some(Source::Elements(elements.into_iter().collect()));
+        self
+    }
+
+    /// Queues inputs, after any queued by earlier calls
+    pub fn input(mut self, inputs: impl IntoIterator<Item = ProgramElement>) -> Self {
+        self.inputs.extend(inputs);
+        self
+    }
+
+    /// Writes `value` to `addr` once the program is loaded. Patches are applied in the order
+    /// they're given, after any patch files.
+    pub fn patch(mut self, addr: usize, value: ProgramElement) -> Self {
+        self.patches.push((addr, value));
+        self
+    }
+
+    /// Applies a patch file once the program is loaded, see `patch::parse_patches`
+    pub fn patch_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.patch_files.push(path.into());
+        self
+    }
+
+    /// See `ProgramState::memory_limit`
+    pub fn max_memory(mut self, limit: usize) -> Self {
+        self.memory_limit = Some(limit);
+        self
+    }
+
+    /// Overrides `Backend::current_default`
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    pub fn breakpoint(mut self, addr: usize) -> Self {
+        self.breakpoints.push(addr);
+        self
+    }
+
+    /// See `ProgramState::with_dense_memory`, which happens after patching
+    pub fn dense_memory(mut self) -> Self {
+        self.dense = true;
+        self
+    }
+
+    pub fn build(self) -> Result<ProgramState, LoadError> {
+        let elements = match self.source {
+            Some(Source::Str(source)) => parse_program(&source)?,
+            Some(Source::File(path)) => parse_program(&std::fs::read_to_string(path)?)?,
+            Some(Source::Elements(elements)) => elements,
+            None => Vec::new(),
+        };
+
+        let mut program = ProgramState::new(elements, self.inputs);
+        for path in &self.patch_files {
+            program.apply_patch_file(path)?;
+        }
+        program.apply_patches(&self.patches);
+        if self.dense {
+            program = program.with_dense_memory();
+        }
+
+        program.memory_limit = self.memory_limit;
+        if let Some(backend) = self.backend {
+            program.backend = backend;
+        }
+        program.breakpoints.extend(self.breakpoints);
+
+        Ok(program)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StopReason;
+
+    #[test]
+    fn test_build() {
+        let mut program = ProgramState::builder()
+            .elements(vec![3, 7, 4, 7, 4, 8, 99, 0, 0])
+            .input([1])
+            .input([2])
+            .patch(8, 5)
+            .backend(Backend::Classic)
+            .breakpoint(4)
+            .build()
+            .unwrap();
+
+        assert_eq!(program.backend, Backend::Classic);
+        assert_eq!(program.run_to_completion(), Ok(StopReason::Breakpoint(4)));
+        assert_eq!(program.outputs, vec![1]);
+        assert_eq!(program.inputs, vec![2]);
+        assert_eq!(program.mem.read_addr(8), 5);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            ProgramState::builder().program("1,x").build(),
+            Err(LoadError::InvalidElement { index: 1, .. })
+        ));
+        assert!(matches!(
+            ProgramState::builder().program_file("does/not/exist").build(),
+            Err(LoadError::Io(_))
+        ));
+        assert_eq!(ProgramState::builder().build().unwrap().mem, vec![]);
+    }
+}
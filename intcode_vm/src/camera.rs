@@ -0,0 +1,211 @@
+//! Parsing the pictures that ASCII programs like day 17's vacuum robot draw of their surroundings,
+//! one row per line with a blank line after each picture.
+//!
+//! ```
+//! use intcode_vm::camera::{parse_frames, Facing, Tile};
+//! use intcode_vm::ProgramElement;
+//!
+//! let output = "..#..\n##^##\n..#..\n\n".bytes().map(ProgramElement::from);
+//! let frames = parse_frames(output);
+//!
+//! assert_eq!(frames.len(), 1);
+//! assert_eq!(frames[0].get(0, 1), Some(Tile::Scaffold));
+//! assert_eq!(frames[0].robot(), Some((2, 1, Facing::Up)));
+//! ```
+
+use crate::ProgramElement;
+use crate::ascii::is_ascii;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Facing {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Tile {
+    Scaffold,
+    Open,
+
+    /// A robot on scaffold, facing this way
+    Robot(Facing),
+
+    /// A robot that's fallen off the scaffold
+    Tumbling,
+
+    Other(char),
+}
+
+impl Tile {
+    pub fn from_char(c: char) -> Self {
+        match c {
+            '#' => Tile::Scaffold,
+            '.' => Tile::Open,
+            '^' => Tile::Robot(Facing::Up),
+            'v' => Tile::Robot(Facing::Down),
+            '<' => Tile::Robot(Facing::Left),
+            '>' => Tile::Robot(Facing::Right),
+            'X' => Tile::Tumbling,
+            c => Tile::Other(c),
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            Tile::Scaffold => '#',
+            Tile::Open => '.',
+            Tile::Robot(Facing::Up) => '^',
+            Tile::Robot(Facing::Down) => 'v',
+            Tile::Robot(Facing::Left) => '<',
+            Tile::Robot(Facing::Right) => '>',
+            Tile::Tumbling => 'X',
+            Tile::Other(c) => c,
+        }
+    }
+
+    /// Whether there's scaffold here, including underneath a robot
+    pub fn is_scaffold(self) -> bool {
+        matches!(self, Tile::Scaffold | Tile::Robot(_))
+    }
+}
+
+/// A single picture. Rows can be different lengths, `width` is the longest of them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Frame {
+    pub rows: Vec<Vec<Tile>>,
+    pub width: usize,
+}
+
+impl Frame {
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// The tile in column `x` of row `y`, counting from the top left
+    pub fn get(&self, x: usize, y: usize) -> Option<Tile> {
+        self.rows.get(y).and_then(|row| row.get(x)).cloned()
+    }
+
+    /// Every tile and its position, row by row
+    pub fn tiles(&self) -> impl Iterator<Item = (usize, usize, Tile)> + '_ {
+        self.rows
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, &tile)| (x, y, tile)))
+    }
+
+    /// Where the first robot on scaffold is, and which way it's facing
+    pub fn robot(&self) -> Option<(usize, usize, Facing)> {
+        self.tiles().find_map(|(x, y, tile)| match tile {
+            Tile::Robot(facing) => Some((x, y, facing)),
+            _ => None,
+        })
+    }
+}
+
+impl std::fmt::Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for row in &self.rows {
+            let line = row.iter().map(|tile| tile.to_char()).collect::<String>();
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds frames from a stream of output values, one value at a time. Values that aren't ASCII,
+/// eg day 17's final answer, are ignored.
+#[derive(Clone, Debug, Default)]
+pub struct Camera {
+    line: Vec<Tile>,
+    frame: Frame,
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the frame that `value` completed, if any
+    pub fn push(&mut self, value: ProgramElement) -> Option<Frame> {
+        if !is_ascii(value) {
+            return None;
+        }
+
+        match value as u8 as char {
+            '\n' if self.line.is_empty() => self.finish(),
+            '\n' => {
+                let line = std::mem::take(&mut self.line);
+                self.frame.width = self.frame.width.max(line.len());
+                self.frame.rows.push(line);
+                None
+            }
+            c => {
+                self.line.push(Tile::from_char(c));
+                None
+            }
+        }
+    }
+
+    /// Takes whatever's been drawn since the last frame, for when the output stops without a
+    /// blank line. Returns `None` if that's nothing.
+    pub fn finish(&mut self) -> Option<Frame> {
+        if !self.line.is_empty() {
+            self.push('\n' as ProgramElement);
+        }
+
+        if self.frame.rows.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.frame))
+        }
+    }
+}
+
+/// Every frame in a stream of output values, including an unfinished one at the end
+pub fn parse_frames(values: impl IntoIterator<Item = ProgramElement>) -> Vec<Frame> {
+    let mut camera = Camera::new();
+    let mut frames = values.into_iter().filter_map(|value| camera.push(value)).collect::<Vec<_>>();
+    frames.extend(camera.finish());
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(text: &str) -> Vec<ProgramElement> {
+        text.bytes().map(ProgramElement::from).collect()
+    }
+
+    #[test]
+    fn test_frames() {
+        let mut output = values("#.\n#>#\n\n\n.X\n");
+        output.push(1234);
+        let frames = parse_frames(output);
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].width, 3);
+        assert_eq!(frames[0].height(), 2);
+        assert_eq!(frames[0].get(2, 0), None);
+        assert!(frames[0].get(1, 1).unwrap().is_scaffold());
+        assert_eq!(frames[0].robot(), Some((1, 1, Facing::Right)));
+        assert_eq!(frames[0].to_string(), "#.\n#>#\n");
+
+        assert_eq!(frames[1].rows, vec![vec![Tile::Open, Tile::Tumbling]]);
+        assert_eq!(frames[1].robot(), None);
+    }
+
+    #[test]
+    fn test_push() {
+        let mut camera = Camera::new();
+        for value in values("ab\n") {
+            assert_eq!(camera.push(value), None);
+        }
+        let frame = camera.push('\n' as ProgramElement).unwrap();
+        assert_eq!(frame.get(1, 0), Some(Tile::Other('b')));
+        assert_eq!(camera.finish(), None);
+    }
+}
@@ -0,0 +1,271 @@
+//! Stepping backwards through a program's execution, by keeping checkpoints of the whole VM every
+//! so many instructions, then restoring the nearest one and executing forwards again.
+//!
+//! Checkpoints share memory with the VM until either of them writes to it, as snapshots do, so
+//! keeping plenty of them is cheap:
+//!
+//! ```
+//! use intcode_vm::{checkpoint::Checkpoints, ProgramState};
+//!
+//! // Counts up forever, outputting each number
+//! let mut program = ProgramState::load_program_str("1001,9,1,9,4,9,1105,1,0,0");
+//! program.checkpoints = Some(Checkpoints::new(10));
+//! program.run_for(23).unwrap();
+//! assert_eq!(program.outputs.len(), 8);
+//!
+//! assert_eq!(program.step_back(), Ok(true));
+//! assert_eq!(program.stats().total(), 22);
+//! assert_eq!(program.outputs.len(), 7);
+//! ```
+//!
+//! Rewinding puts any inputs consumed since back at the front of the input queue, so inputs can
+//! still be queued in between runs. Only VMs whose inputs are a queue can be rewound.
+//!
+//! Profiles, coverage, histories and journals aren't rewound, and nothing is recorded to them
+//! while re-executing. Changing memory from outside makes the later checkpoints wrong, as
+//! re-executing assumes the program did exactly what it did before.
+
+use alloc::collections::VecDeque;
+
+use crate::prelude::*;
+use crate::{ExecutionStats, OutputSink, ProgramElement, ProgramState, VmError};
+use crate::snapshot::Snapshot;
+
+#[derive(Clone, Debug)]
+pub(crate) struct Checkpoint<O> {
+    /// The number of instructions executed before this was taken
+    instruction: u64,
+
+    snapshot: Snapshot,
+    terminated: bool,
+    stats: ExecutionStats,
+    outputs: O,
+
+    /// The number of inputs that had been consumed when this was taken
+    consumed: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct Checkpoints<O> {
+    interval: u64,
+
+    /// In the order they were taken
+    saved: Vec<Checkpoint<O>>,
+
+    /// Every input consumed since the first checkpoint
+    consumed: Vec<ProgramElement>,
+
+    /// Lets checkpoints be taken without every VM needing cloneable outputs
+    clone_outputs: fn(&O) -> O,
+}
+
+impl<O: Clone> Checkpoints<O> {
+    /// Takes a checkpoint every `interval` instructions, as counted by `ProgramState::stats`, and
+    /// straight away if there isn't one yet
+    pub fn new(interval: u64) -> Self {
+        Self {
+            interval: interval.max(1),
+            saved: Vec::new(),
+            consumed: Vec::new(),
+            clone_outputs: O::clone,
+        }
+    }
+}
+
+impl<O> Checkpoints<O> {
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    pub fn len(&self) -> usize {
+        self.saved.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.saved.is_empty()
+    }
+
+    /// Forgets every checkpoint, eg after the stats are reset
+    pub fn clear(&mut self) {
+        self.saved.clear();
+        self.consumed.clear();
+    }
+
+    fn due(&self, instruction: u64) -> bool {
+        match self.saved.last() {
+            Some(last) => instruction > last.instruction && instruction.is_multiple_of(self.interval),
+            None => true,
+        }
+    }
+
+    pub(crate) fn record_input(&mut self, value: ProgramElement) {
+        self.consumed.push(value);
+    }
+}
+
+impl<I, O> ProgramState<I, O> {
+    /// Called before each instruction, returning a checkpoint of the VM as it is if one is due.
+    /// It's only kept by `keep_checkpoint` once the instruction has executed successfully.
+    pub(crate) fn due_checkpoint(&self) -> Option<Checkpoint<O>> {
+        let instruction = self.stats.total();
+        let checkpoints = match &self.checkpoints {
+            Some(checkpoints) if checkpoints.due(instruction) => checkpoints,
+            _ => return None,
+        };
+
+        Some(Checkpoint {
+            instruction,
+            snapshot: Snapshot {
+                mem: self.mem.clone(),
+                program_counter: self.program_counter,
+                relative_base: self.relative_base,
+            },
+            terminated: self.terminated,
+            stats: self.stats.clone(),
+            outputs: (checkpoints.clone_outputs)(&self.outputs),
+            consumed: checkpoints.consumed.len(),
+        })
+    }
+
+    pub(crate) fn keep_checkpoint(&mut self, checkpoint: Checkpoint<O>) {
+        if let Some(checkpoints) = &mut self.checkpoints {
+            checkpoints.saved.push(checkpoint);
+        }
+    }
+}
+
+impl<O: OutputSink> ProgramState<VecDeque<ProgramElement>, O> {
+    /// Puts the program back as it was after executing `instruction` instructions in total,
+    /// restoring the nearest checkpoint and then re-executing. Returns false, having changed
+    /// nothing, if that's in the future or before the first checkpoint.
+    ///
+    /// Breakpoints and watchpoints don't stop the re-execution.
+    pub fn rewind_to(&mut self, instruction: u64) -> Result<bool, VmError> {
+        if instruction > self.stats.total() {
+            return Ok(false);
+        }
+
+        let checkpoints = match &mut self.checkpoints {
+            Some(checkpoints) => checkpoints,
+            None => return Ok(false),
+        };
+        let idx = match checkpoints.saved.iter().rposition(|c| c.instruction <= instruction) {
+            Some(idx) => idx,
+            None => return Ok(false),
+        };
+
+        checkpoints.saved.truncate(idx + 1);
+        let checkpoint = &checkpoints.saved[idx];
+        for value in checkpoints.consumed.drain(checkpoint.consumed..).rev() {
+            self.inputs.push_front(value);
+        }
+        self.outputs = (checkpoints.clone_outputs)(&checkpoint.outputs);
+        self.mem = checkpoint.snapshot.mem.clone();
+        self.program_counter = checkpoint.snapshot.program_counter;
+        self.relative_base = checkpoint.snapshot.relative_base;
+        self.terminated = checkpoint.terminated;
+        self.stats = checkpoint.stats.clone();
+
+        let recorders = (self.profile.take(), self.coverage.take(), self.history.take(), self.journal.take());
+        let mut result = Ok(true);
+        while self.stats.total() < instruction {
+            if let Err(e) = self.progress_state() {
+                result = Err(e);
+                break;
+            }
+        }
+        let (profile, coverage, history, journal) = recorders;
+        self.profile = profile;
+        self.coverage = coverage;
+        self.history = history;
+        self.journal = journal;
+        self.watch_hit = None;
+
+        result
+    }
+
+    /// Undoes the last instruction, see `rewind_to`
+    pub fn step_back(&mut self) -> Result<bool, VmError> {
+        match self.stats.total().checked_sub(1) {
+            Some(instruction) => self.rewind_to(instruction),
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StopReason;
+
+    #[test]
+    fn test_rewind_io() {
+        // Outputs double each input until one of them is 0
+        let mut program = ProgramState::load_program_str("3,12,1002,12,2,13,4,13,1005,12,0,99,0,0");
+        program.checkpoints = Some(Checkpoints::new(3));
+        program.inputs.extend(&[1, 2, 0]);
+        program.run_to_completion().unwrap();
+        assert!(program.terminated);
+        assert_eq!(program.stats().total(), 13);
+        assert_eq!(program.checkpoints.as_ref().unwrap().len(), 5);
+
+        // Back to just after reading the second input
+        assert_eq!(program.rewind_to(5), Ok(true));
+        assert!(!program.terminated);
+        assert_eq!(program.program_counter, 2);
+        assert_eq!(program.inputs, vec![0]);
+        assert_eq!(program.outputs, vec![2]);
+        assert_eq!(program.checkpoints.as_ref().unwrap().len(), 2);
+
+        program.run_to_completion().unwrap();
+        assert_eq!(program.outputs, vec![2, 4, 0]);
+        assert_eq!(program.rewind_to(14), Ok(false));
+    }
+
+    #[test]
+    fn test_inputs_queued_later() {
+        let mut program = ProgramState::load_program_str("3,12,1002,12,2,13,4,13,1005,12,0,99,0,0");
+        program.checkpoints = Some(Checkpoints::new(100));
+        assert_eq!(program.run_to_next_input(), Ok(StopReason::NeedsInput));
+
+        program.inputs.push_back(3);
+        assert_eq!(program.run_to_next_input(), Ok(StopReason::NeedsInput));
+        program.inputs.push_back(0);
+
+        assert_eq!(program.rewind_to(1), Ok(true));
+        assert_eq!(program.inputs, vec![0]);
+        assert_eq!(program.run_to_completion(), Ok(StopReason::Terminated));
+        assert_eq!(program.outputs, vec![6, 0]);
+    }
+
+    #[test]
+    fn test_failed_step() {
+        // Writes past the memory limit
+        let mut program = ProgramState::load_program_str("1101,1,1,1000,99");
+        program.checkpoints = Some(Checkpoints::new(100));
+        program.memory_limit = Some(100);
+        assert!(program.progress_state().is_err());
+        assert!(program.checkpoints.as_ref().unwrap().is_empty());
+
+        program.memory_limit = None;
+        program.run_to_completion().unwrap();
+        assert_eq!(program.checkpoints.as_ref().unwrap().len(), 1);
+        assert_eq!(program.rewind_to(0), Ok(true));
+        assert_eq!(program.mem.read_addr(1000), 0);
+    }
+
+    #[test]
+    fn test_before_first_checkpoint() {
+        let mut program = ProgramState::load_program_str("1001,9,1,9,4,9,1105,1,0,0");
+        assert_eq!(program.step_back(), Ok(false));
+
+        program.run_for(5).unwrap();
+        program.checkpoints = Some(Checkpoints::new(100));
+        program.run_for(5).unwrap();
+
+        assert_eq!(program.rewind_to(4), Ok(false));
+        assert_eq!(program.rewind_to(5), Ok(true));
+        assert_eq!(program.stats().total(), 5);
+        assert_eq!(program.mem.read_addr(9), 2);
+    }
+}
@@ -0,0 +1,198 @@
+//! Exporting execution traces as Chrome `trace_event` JSON, to explore runs in a timeline viewer
+//! like `chrome://tracing` or Perfetto.
+//!
+//! Each VM gets its own track, and every instruction it executes is a slice of it lasting one
+//! tick. Ticks are counted across every track, so VMs that are run in turn, eg day 23's NICs, line
+//! up in the order they actually ran:
+//!
+//! ```
+//! use intcode_vm::chrome_trace::ChromeTrace;
+//! use intcode_vm::ProgramState;
+//!
+//! let mut a = ProgramState::load_program_str("104,1,99");
+//! let mut b = ProgramState::load_program_str("1101,2,3,5,99");
+//!
+//! let mut trace = ChromeTrace::new();
+//! trace.name_track(0, "a");
+//! trace.name_track(1, "b");
+//! a.run_traced(&mut trace.track(0)).unwrap();
+//! b.run_traced(&mut trace.track(1)).unwrap();
+//! trace.instant(1, "done");
+//!
+//! assert_eq!(trace.ticks(), 4);
+//! let json = trace.to_json();
+//! assert!(json.contains(r#"{"name":"out","cat":"io","ph":"X","ts":0,"dur":1,"pid":0,"tid":0,"#));
+//! ```
+
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use crate::trace::{TraceEvent, Tracer};
+use crate::OpCode;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Kind {
+    Instruction(TraceEvent),
+
+    /// Something that happened between instructions, eg a packet being delivered
+    Instant(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Event {
+    tid: usize,
+    ts: u64,
+    kind: Kind,
+}
+
+/// Events from any number of tracks, see the module docs
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChromeTrace {
+    names: BTreeMap<usize, String>,
+    events: Vec<Event>,
+    clock: u64,
+}
+
+impl ChromeTrace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name the viewer shows for a track, rather than just its number
+    pub fn name_track(&mut self, tid: usize, name: impl Into<String>) {
+        self.names.insert(tid, name.into());
+    }
+
+    /// A tracer that records onto the track `tid`
+    pub fn track(&mut self, tid: usize) -> Track<'_> {
+        Track { trace: self, tid }
+    }
+
+    /// Marks the current tick on the track `tid`
+    pub fn instant(&mut self, tid: usize, name: impl Into<String>) {
+        self.events.push(Event { tid, ts: self.clock, kind: Kind::Instant(name.into()) });
+    }
+
+    /// How many instructions have been recorded, across every track
+    pub fn ticks(&self) -> u64 {
+        self.clock
+    }
+
+    pub fn write_to(&self, mut w: impl Write) -> std::io::Result<()> {
+        let names = self.names.iter().map(|(tid, name)| {
+            format!(r#"{{"name":"thread_name","ph":"M","pid":0,"tid":{},"args":{{"name":{}}}}}"#, tid, json_string(name))
+        });
+
+        let events = self.events.iter().map(|event| match &event.kind {
+            Kind::Instruction(instruction) => {
+                let category = match instruction.opcode {
+                    OpCode::ReadInput | OpCode::WriteOutput => "io",
+                    _ => "instruction",
+                };
+                let operands = instruction.operands.iter().map(|op| op.to_string()).collect::<Vec<_>>();
+                let result = instruction.result.map_or("null".to_string(), |result| result.to_string());
+                format!(
+                    r#"{{"name":"{}","cat":"{}","ph":"X","ts":{},"dur":1,"pid":0,"tid":{},"args":{{"pc":{},"operands":[{}],"result":{}}}}}"#,
+                    instruction.opcode.mnemonic(),
+                    category,
+                    event.ts,
+                    event.tid,
+                    instruction.pc,
+                    operands.join(","),
+                    result,
+                )
+            }
+            Kind::Instant(name) => format!(
+                r#"{{"name":{},"cat":"event","ph":"i","s":"t","ts":{},"pid":0,"tid":{}}}"#,
+                json_string(name),
+                event.ts,
+                event.tid,
+            ),
+        });
+
+        writeln!(w, "{{\"traceEvents\":[")?;
+        let mut empty = true;
+        for line in names.chain(events) {
+            if !std::mem::take(&mut empty) {
+                writeln!(w, ",")?;
+            }
+            write!(w, "{}", line)?;
+        }
+        if !empty {
+            writeln!(w)?;
+        }
+
+        // Ticks aren't really microseconds, but there's no unitless option
+        writeln!(w, "],\"displayTimeUnit\":\"ns\"}}")
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = Vec::new();
+        self.write_to(&mut json).expect("Writing to a Vec can't fail");
+        String::from_utf8(json).expect("Trace JSON is always UTF-8")
+    }
+}
+
+/// Records onto one track of a `ChromeTrace`, see `ChromeTrace::track`
+#[derive(Debug)]
+pub struct Track<'a> {
+    trace: &'a mut ChromeTrace,
+    tid: usize,
+}
+
+impl Tracer for Track<'_> {
+    fn trace(&mut self, event: &TraceEvent) {
+        let trace = &mut *self.trace;
+        trace.events.push(Event { tid: self.tid, ts: trace.clock, kind: Kind::Instruction(event.clone()) });
+        trace.clock += 1;
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProgramState;
+
+    #[test]
+    fn test_to_json() {
+        let mut program = ProgramState::load_program_str("3,6,1005,6,0,99,0");
+        program.inputs.push_back(0);
+
+        let mut trace = ChromeTrace::new();
+        trace.name_track(3, "vm \"3\"");
+        program.run_traced(&mut trace.track(3)).unwrap();
+        trace.instant(3, "halted");
+
+        let expected = [
+            r#"{"traceEvents":["#,
+            r#"{"name":"thread_name","ph":"M","pid":0,"tid":3,"args":{"name":"vm \"3\""}},"#,
+            r#"{"name":"in","cat":"io","ph":"X","ts":0,"dur":1,"pid":0,"tid":3,"args":{"pc":0,"operands":[6],"result":0}},"#,
+            r#"{"name":"jnz","cat":"instruction","ph":"X","ts":1,"dur":1,"pid":0,"tid":3,"args":{"pc":2,"operands":[0,0],"result":null}},"#,
+            r#"{"name":"halt","cat":"instruction","ph":"X","ts":2,"dur":1,"pid":0,"tid":3,"args":{"pc":5,"operands":[],"result":null}},"#,
+            r#"{"name":"halted","cat":"event","ph":"i","s":"t","ts":3,"pid":0,"tid":3}"#,
+            r#"],"displayTimeUnit":"ns"}"#,
+        ];
+        assert_eq!(trace.to_json(), expected.join("\n") + "\n");
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(ChromeTrace::new().to_json(), "{\"traceEvents\":[\n],\"displayTimeUnit\":\"ns\"}\n");
+    }
+}
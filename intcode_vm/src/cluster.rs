@@ -0,0 +1,160 @@
+//! Runs copies of a single program wired together, with the outputs of some feeding the inputs
+//! of others, eg day 7's amplifiers.
+//!
+//! `Pipeline` describes the topology declaratively, and builds the `scheduler::Scheduler` that
+//! runs it, with a `scheduler::fan_out` router for the connections between stages.
+
+use crate::scheduler::{fan_out, RunReport, Scheduler};
+use crate::{ProgramElement, ProgramState};
+
+/// A topology of stages, each running its own copy of the same program.
+///
+/// ```
+/// use intcode_vm::cluster::Pipeline;
+/// use intcode_vm::ProgramState;
+///
+/// // Each stage adds the two inputs it's given and outputs the result
+/// let adder = ProgramState::load_program_str("3,11,3,12,1,11,12,13,4,13,99,0,0,0");
+/// let report = Pipeline::chain(3)
+///     .input(0, 1)
+///     .input(0, 2)
+///     .input(1, 10)
+///     .input(2, 100)
+///     .run(&adder);
+///
+/// assert_eq!(report.vms[2].last_output, Some(113));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Pipeline {
+    /// Inputs queued on each stage before anything runs, in order
+    inputs: Vec<Vec<ProgramElement>>,
+
+    connections: Vec<(usize, usize)>,
+}
+
+impl Pipeline {
+    /// `stages` unconnected stages
+    pub fn new(stages: usize) -> Self {
+        Self {
+            inputs: vec![Vec::new(); stages],
+            connections: Vec::new(),
+        }
+    }
+
+    /// Stages connected one after the other, so that the output of each is the input of the next
+    pub fn chain(stages: usize) -> Self {
+        (1..stages).fold(Self::new(stages), |pipeline, idx| pipeline.connect(idx - 1, idx))
+    }
+
+    /// A chain with the output of the last stage fed back into the first
+    pub fn feedback_loop(stages: usize) -> Self {
+        match stages {
+            0 => Self::new(0),
+            stages => Self::chain(stages).connect(stages - 1, 0),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    /// Sends everything output by stage `from` to stage `to`. A stage connected to several others
+    /// sends each of them a copy of every output, and a stage that isn't connected to any keeps
+    /// its outputs in its output queue.
+    pub fn connect(mut self, from: usize, to: usize) -> Self {
+        assert!(
+            from < self.len() && to < self.len(),
+            "Can't connect stage {} to {}, there are only {}", from, to, self.len(),
+        );
+        if !self.connections.contains(&(from, to)) {
+            self.connections.push((from, to));
+        }
+        self
+    }
+
+    /// Sends everything output by stage `from` to each of the stages in `to`
+    pub fn fan_out(self, from: usize, to: impl IntoIterator<Item=usize>) -> Self {
+        to.into_iter().fold(self, |pipeline, to| pipeline.connect(from, to))
+    }
+
+    /// Queues an input on a stage ahead of anything sent to it by other stages
+    pub fn input(mut self, stage: usize, value: ProgramElement) -> Self {
+        self.inputs[stage].push(value);
+        self
+    }
+
+    /// A scheduler with a copy of `program` for each stage, connected up and with its initial
+    /// inputs queued
+    pub fn build(&self, program: &ProgramState) -> Scheduler {
+        let vms = self.inputs
+            .iter()
+            .map(|inputs| {
+                let mut vm = program.clone();
+                vm.inputs.extend(inputs.iter().cloned());
+                vm
+            })
+            .collect();
+
+        let mut routes = vec![Vec::new(); self.len()];
+        for &(from, to) in &self.connections {
+            routes[from].push(to);
+        }
+
+        Scheduler::new(vms, fan_out(routes))
+    }
+
+    /// Builds the scheduler and runs it until it stops, see `Scheduler::run`
+    pub fn run(&self, program: &ProgramState) -> RunReport {
+        self.build(program).run(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::StopReason;
+
+    #[test]
+    fn test_pipeline_tree() {
+        // Each stage doubles its input, in a binary tree three levels deep
+        let doubler = ProgramState::load_program_str("3,9,1002,9,2,9,4,9,99,0");
+        let pipeline = Pipeline::new(7)
+            .fan_out(0, 1..=2)
+            .fan_out(1, 3..=4)
+            .fan_out(2, 5..=6)
+            .input(0, 3);
+
+        let report = pipeline.run(&doubler);
+        assert_eq!(report.stop_reason, StopReason::AllTerminated);
+        let leaves = report.vms[3..].iter().map(|s| s.last_output).collect::<Vec<_>>();
+        assert_eq!(leaves, vec![Some(24); 4]);
+    }
+
+    #[test]
+    fn test_pipeline_feedback_loop() {
+        // Stage 0 passes through its input then terminates, stage 1 echoes forever
+        let pipeline = Pipeline::feedback_loop(2).input(0, 9);
+        let mut scheduler = pipeline.build(&ProgramState::load_program_str("3,0,4,0,99"));
+        scheduler.vms[1] = ProgramState::load_program_str("3,7,4,7,1105,1,0,0");
+
+        let report = scheduler.run(None);
+        assert_eq!(report.stop_reason, StopReason::Deadlocked);
+        assert_eq!(report.vms[1].last_output, Some(9));
+        assert_eq!(scheduler.vms[0].inputs, vec![9]);
+    }
+
+    #[test]
+    fn test_pipeline_chain() {
+        let pipeline = Pipeline::chain(3).connect(0, 1).input(0, 42);
+        let mut scheduler = pipeline.build(&ProgramState::load_program_str("3,0,4,0,99"));
+
+        let report = scheduler.run(None);
+        assert_eq!(report.stop_reason, StopReason::AllTerminated);
+        assert_eq!(scheduler.vms[2].outputs, vec![42]);
+        assert!(scheduler.vms[1].outputs.is_empty());
+    }
+}
@@ -0,0 +1,152 @@
+//! Errors with the state of the VM they happened in attached, for reporting failures somewhere
+//! they can be diagnosed without rerunning the program.
+//!
+//! Nothing is executed when an instruction fails, so the context can be taken from the VM any
+//! time after the error is returned, as long as it hasn't been changed since:
+//!
+//! ```
+//! use intcode_vm::ProgramState;
+//!
+//! let mut program = ProgramState::load_program_str("109,10,1101,1,2,3,143,0,0,99");
+//! let result = program.run_to_completion();
+//! let error = program.with_context(result, false).unwrap_err();
+//!
+//! assert_eq!(error.pc, 6);
+//! assert_eq!(error.relative_base, 10);
+//! assert_eq!(
+//!     error.to_string(),
+//!     "Unrecognized opcode in instruction 143 at address 6 (opcode 43, parameter modes [immediate], \
+//!      relative base 10)",
+//! );
+//! ```
+
+use alloc::boxed::Box;
+
+use crate::prelude::*;
+use crate::snapshot::Snapshot;
+use crate::{OpCode, ParameterMode, ProgramElement, ProgramState, VmError};
+
+/// A `VmError`, and the state of the VM when it happened
+#[derive(Clone, Debug)]
+pub struct ErrorContext {
+    pub error: VmError,
+
+    /// Address of the instruction that failed
+    pub pc: usize,
+
+    /// The first element of the instruction that failed
+    pub instruction: ProgramElement,
+
+    /// The instruction's opcode, if it's one the interpreter recognizes
+    pub opcode: Option<OpCode>,
+
+    /// The raw opcode, ie the last two digits of the instruction
+    pub raw_opcode: ProgramElement,
+
+    /// The mode of each of the instruction's parameters, up to the first one that isn't
+    /// recognized. Every mode digit is decoded when the opcode isn't recognized.
+    pub modes: Vec<ParameterMode>,
+
+    pub relative_base: ProgramElement,
+
+    /// The whole VM, if it was asked for
+    pub snapshot: Option<Box<Snapshot>>,
+}
+
+impl core::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let modes = self.modes
+            .iter()
+            .map(|mode| match mode {
+                ParameterMode::Position => "position",
+                ParameterMode::Immediate => "immediate",
+                ParameterMode::Relative => "relative",
+            })
+            .collect::<Vec<_>>();
+
+        write!(f, "{} (", self.error)?;
+        match self.opcode {
+            Some(opcode) => write!(f, "{}", opcode.mnemonic())?,
+            None => write!(f, "opcode {}", self.raw_opcode)?,
+        }
+        write!(f, ", parameter modes [{}], relative base {})", modes.join(", "), self.relative_base)
+    }
+}
+
+/// The source is the error itself
+#[cfg(feature = "std")]
+impl std::error::Error for ErrorContext {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl<I, O> ProgramState<I, O> {
+    /// Attaches the state of the VM to `error`, which should have just been returned by it. The
+    /// whole VM is kept as well if `snapshot` is set, which is cheap until either is changed.
+    pub fn error_context(&self, error: VmError, snapshot: bool) -> ErrorContext {
+        let pc = error.pc().unwrap_or(self.program_counter);
+        let instruction = self.mem.read_addr(pc);
+        let opcode = OpCode::try_from_element(&instruction);
+
+        // Without an opcode, there's no telling how many parameters the instruction has
+        let params = opcode.map_or(usize::MAX, |opcode| opcode.length() - 1);
+        let mut modes = Vec::new();
+        let mut digits = instruction.abs() / 100;
+        while modes.len() < params && (opcode.is_some() || digits != 0) {
+            match ParameterMode::try_from_code((digits % 10) as u8) {
+                Some(mode) => modes.push(mode),
+                None => break,
+            }
+            digits /= 10;
+        }
+
+        ErrorContext {
+            error,
+            pc,
+            instruction,
+            opcode,
+            raw_opcode: instruction % 100,
+            modes,
+            relative_base: self.relative_base,
+            snapshot: if snapshot { Some(Box::new(self.snapshot())) } else { None },
+        }
+    }
+
+    /// Attaches the VM's state to the error in `result`, if there is one, see `error_context`
+    pub fn with_context<T>(&self, result: Result<T, VmError>, snapshot: bool) -> Result<T, ErrorContext> {
+        result.map_err(|error| self.error_context(error, snapshot))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_context() {
+        let mut program = ProgramState::load_program_str("11101,1,2,3,99");
+        program.relative_base = -7;
+        let result = program.run_to_completion();
+        let context = program.with_context(result, true).unwrap_err();
+        assert_eq!(context.error, VmError::ImmediateWrite { pc: 0, instruction: 11101 });
+        assert_eq!(context.opcode, Some(OpCode::Add));
+        assert_eq!(context.modes, vec![ParameterMode::Immediate; 3]);
+        assert_eq!(context.relative_base, -7);
+        assert_eq!(context.snapshot.unwrap().mem.read_addr(0), 11101);
+
+        // Decoding stops at the first unknown mode
+        let mut program = ProgramState::load_program_str("104,0,4202,0,0,0");
+        let result = program.run_to_completion();
+        let context = program.with_context(result, false).unwrap_err();
+        assert_eq!(context.pc, 2);
+        assert_eq!(context.modes, vec![ParameterMode::Relative]);
+        assert!(context.snapshot.is_none());
+
+        // Needing an input isn't an error of an instruction, but it's still the one at the pc
+        let program = ProgramState::load_program_str("203,5");
+        let context = program.error_context(VmError::NoInput, false);
+        assert_eq!((context.pc, context.opcode), (0, Some(OpCode::ReadInput)));
+        assert_eq!(context.modes, vec![ParameterMode::Relative]);
+    }
+}
@@ -0,0 +1,308 @@
+//! The example programs from the puzzle texts, with the behaviour the puzzles give for them, for
+//! sanity checking an interpreter or anything built on one:
+//!
+//! ```
+//! use intcode_vm::corpus::{AMPLIFIER_EXAMPLES, EXAMPLES};
+//! use intcode_vm::Backend;
+//!
+//! for example in EXAMPLES {
+//!     example.check(Backend::current_default()).unwrap();
+//! }
+//! for example in AMPLIFIER_EXAMPLES {
+//!     example.check(Backend::current_default()).unwrap();
+//! }
+//! ```
+
+use alloc::collections::VecDeque;
+
+use crate::prelude::*;
+use crate::{Backend, ProgramElement, ProgramState, StopReason, VmError};
+
+/// A program, and what it does given some inputs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Example {
+    /// Named after the day it's from, eg `day_9_quine`
+    pub name: &'static str,
+
+    pub source: &'static str,
+    pub cases: &'static [Case],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Case {
+    pub inputs: &'static [ProgramElement],
+
+    /// Every output, once the program has terminated
+    pub outputs: &'static [ProgramElement],
+
+    /// The whole of memory once the program has terminated, or empty if the puzzle doesn't say
+    pub memory: &'static [ProgramElement],
+}
+
+/// One of day 7's chains of five amplifiers, each running the same program
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AmplifierExample {
+    pub name: &'static str,
+    pub source: &'static str,
+
+    /// The input each amplifier is given first
+    pub phases: [ProgramElement; 5],
+
+    /// Whether the last amplifier's outputs go back to the first, as in part 2
+    pub feedback: bool,
+
+    /// The last output of the last amplifier
+    pub signal: ProgramElement,
+}
+
+/// How an example didn't behave as expected
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Mismatch {
+    Vm { example: &'static str, case: usize, error: VmError },
+    Outputs { example: &'static str, case: usize, actual: Vec<ProgramElement> },
+    Memory { example: &'static str, case: usize, actual: Vec<ProgramElement> },
+}
+
+impl core::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Mismatch::Vm { example, case, error } => write!(f, "Case {} of {} failed: {}", case, example, error),
+            Mismatch::Outputs { example, case, actual } => {
+                write!(f, "Case {} of {} output {:?}", case, example, actual)
+            }
+            Mismatch::Memory { example, case, actual } => {
+                write!(f, "Case {} of {} left memory as {:?}", case, example, actual)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Mismatch {}
+
+/// Shorthand for cases that only check outputs
+const fn outputs(inputs: &'static [ProgramElement], outputs: &'static [ProgramElement]) -> Case {
+    Case { inputs, outputs, memory: &[] }
+}
+
+/// Shorthand for cases that only check memory
+const fn memory(memory: &'static [ProgramElement]) -> Case {
+    Case { inputs: &[], outputs: &[], memory }
+}
+
+/// Outputs 999 if its input is below 8, 1000 if it's 8, and 1001 if it's above
+const DAY_5_COMPARE: &str = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,\
+                             1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+
+const QUINE: &str = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+
+pub const EXAMPLES: &[Example] = &[
+    Example {
+        name: "day_2_example",
+        source: "1,9,10,3,2,3,11,0,99,30,40,50",
+        cases: &[memory(&[3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50])],
+    },
+    Example { name: "day_2_add", source: "1,0,0,0,99", cases: &[memory(&[2, 0, 0, 0, 99])] },
+    Example { name: "day_2_multiply", source: "2,3,0,3,99", cases: &[memory(&[2, 3, 0, 6, 99])] },
+    Example { name: "day_2_multiply_past_end", source: "2,4,4,5,99,0", cases: &[memory(&[2, 4, 4, 5, 99, 9801])] },
+    Example {
+        name: "day_2_self_modifying",
+        source: "1,1,1,4,99,5,6,0,99",
+        cases: &[memory(&[30, 1, 1, 4, 2, 5, 6, 0, 99])],
+    },
+    Example { name: "day_5_echo", source: "3,0,4,0,99", cases: &[outputs(&[42], &[42]), outputs(&[-7], &[-7])] },
+    Example { name: "day_5_modes", source: "1002,4,3,4,33", cases: &[memory(&[1002, 4, 3, 4, 99])] },
+    Example { name: "day_5_negative", source: "1101,100,-1,4,0", cases: &[memory(&[1101, 100, -1, 4, 99])] },
+    Example {
+        name: "day_5_equal_position",
+        source: "3,9,8,9,10,9,4,9,99,-1,8",
+        cases: &[outputs(&[8], &[1]), outputs(&[7], &[0])],
+    },
+    Example {
+        name: "day_5_less_than_position",
+        source: "3,9,7,9,10,9,4,9,99,-1,8",
+        cases: &[outputs(&[7], &[1]), outputs(&[8], &[0])],
+    },
+    Example {
+        name: "day_5_equal_immediate",
+        source: "3,3,1108,-1,8,3,4,3,99",
+        cases: &[outputs(&[8], &[1]), outputs(&[9], &[0])],
+    },
+    Example {
+        name: "day_5_less_than_immediate",
+        source: "3,3,1107,-1,8,3,4,3,99",
+        cases: &[outputs(&[-8], &[1]), outputs(&[8], &[0])],
+    },
+    Example {
+        name: "day_5_jump_position",
+        source: "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9",
+        cases: &[outputs(&[0], &[0]), outputs(&[5], &[1])],
+    },
+    Example {
+        name: "day_5_jump_immediate",
+        source: "3,3,1105,-1,9,1101,0,0,12,4,12,99,1",
+        cases: &[outputs(&[0], &[0]), outputs(&[-5], &[1])],
+    },
+    Example {
+        name: "day_5_compare",
+        source: DAY_5_COMPARE,
+        cases: &[outputs(&[7], &[999]), outputs(&[8], &[1000]), outputs(&[9], &[1001])],
+    },
+    Example {
+        name: "day_9_quine",
+        source: QUINE,
+        cases: &[outputs(&[], &[109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99])],
+    },
+    Example {
+        name: "day_9_16_digits",
+        source: "1102,34915192,34915192,7,4,7,99,0",
+        cases: &[outputs(&[], &[1219070632396864])],
+    },
+    Example { name: "day_9_large", source: "104,1125899906842624,99", cases: &[outputs(&[], &[1125899906842624])] },
+];
+
+pub const AMPLIFIER_EXAMPLES: &[AmplifierExample] = &[
+    AmplifierExample {
+        name: "day_7_example_1",
+        source: "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0",
+        phases: [4, 3, 2, 1, 0],
+        feedback: false,
+        signal: 43210,
+    },
+    AmplifierExample {
+        name: "day_7_example_2",
+        source: "3,23,3,24,1002,24,10,24,1002,23,-1,23,101,5,23,23,1,24,23,23,4,23,99,0,0",
+        phases: [0, 1, 2, 3, 4],
+        feedback: false,
+        signal: 54321,
+    },
+    AmplifierExample {
+        name: "day_7_example_3",
+        source: "3,31,3,32,1002,32,10,32,1001,31,-2,31,1007,31,0,33,1002,33,7,33,1,33,31,31,1,32,31,31,4,31,99,0,0,0",
+        phases: [1, 0, 4, 3, 2],
+        feedback: false,
+        signal: 65210,
+    },
+    AmplifierExample {
+        name: "day_7_feedback_1",
+        source: "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+        phases: [9, 8, 7, 6, 5],
+        feedback: true,
+        signal: 139629729,
+    },
+    AmplifierExample {
+        name: "day_7_feedback_2",
+        source: "3,52,1001,52,-5,52,3,53,1,52,56,54,1007,54,5,55,1005,55,26,1001,54,-5,54,1105,1,12,1,53,54,53,\
+                 1008,54,0,55,1001,55,1,55,2,53,55,53,4,53,1001,56,-1,56,1005,56,6,99,0,0,0,0,10",
+        phases: [9, 7, 8, 5, 6],
+        feedback: true,
+        signal: 18216,
+    },
+];
+
+/// The example in `EXAMPLES` with the given name
+pub fn example(name: &str) -> Option<&'static Example> {
+    EXAMPLES.iter().find(|example| example.name == name)
+}
+
+impl Example {
+    /// Runs each case on `backend`, returning the first that doesn't behave as expected
+    pub fn check(&self, backend: Backend) -> Result<(), Mismatch> {
+        let example = self.name;
+        for (case, expected) in self.cases.iter().enumerate() {
+            let mut state = ProgramState::load_program_str(self.source);
+            state.backend = backend;
+            state.inputs.extend(expected.inputs);
+            if let Err(error) = state.run_to_completion() {
+                return Err(Mismatch::Vm { example, case, error });
+            }
+
+            let actual = state.outputs.iter().cloned().collect::<Vec<_>>();
+            if actual != expected.outputs {
+                return Err(Mismatch::Outputs { example, case, actual });
+            }
+
+            if !expected.memory.is_empty() {
+                let actual = state.mem.read_range(0, expected.memory.len());
+                if actual != expected.memory {
+                    return Err(Mismatch::Memory { example, case, actual });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl AmplifierExample {
+    /// Runs the chain of amplifiers on `backend`, and checks its signal
+    pub fn check(&self, backend: Backend) -> Result<(), Mismatch> {
+        let example = self.name;
+        let mut amplifiers = self.phases
+            .iter()
+            .map(|&phase| {
+                let mut state = ProgramState::load_program_str(self.source);
+                state.backend = backend;
+                state.inputs.push_back(phase);
+                state
+            })
+            .collect::<Vec<_>>();
+
+        let mut signal = VecDeque::new();
+        signal.push_back(0);
+        loop {
+            for amplifier in &mut amplifiers {
+                amplifier.inputs.extend(signal.drain(..));
+                match amplifier.run_to_next_input() {
+                    Ok(StopReason::Terminated) | Ok(StopReason::NeedsInput) => (),
+                    Ok(_) => unreachable!("Amplifiers have no breakpoints or watchpoints"),
+                    Err(error) => return Err(Mismatch::Vm { example, case: 0, error }),
+                }
+                signal.append(&mut amplifier.outputs);
+            }
+
+            // Without any signal, the amplifiers are all waiting on each other
+            if !self.feedback || signal.is_empty() || amplifiers.iter().all(|amplifier| amplifier.terminated) {
+                break;
+            }
+        }
+
+        match signal.back() {
+            Some(&last) if last == self.signal => Ok(()),
+            _ => Err(Mismatch::Outputs { example, case: 0, actual: signal.into_iter().collect() }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corpus() {
+        for &backend in Backend::ALL {
+            for example in EXAMPLES {
+                assert_eq!(example.check(backend), Ok(()));
+            }
+            for example in AMPLIFIER_EXAMPLES {
+                assert_eq!(example.check(backend), Ok(()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_mismatch() {
+        const WRONG_OUTPUT: &[Case] = &[outputs(&[1], &[2])];
+        const TOO_FEW_INPUTS: &[Case] = &[outputs(&[], &[])];
+
+        let mut broken = *example("day_5_echo").unwrap();
+        broken.cases = WRONG_OUTPUT;
+        assert_eq!(
+            broken.check(Backend::current_default()),
+            Err(Mismatch::Outputs { example: "day_5_echo", case: 0, actual: vec![1] }),
+        );
+
+        broken.cases = TOO_FEW_INPUTS;
+        assert!(matches!(broken.check(Backend::current_default()), Err(Mismatch::Vm { error: VmError::NoInput, .. })));
+    }
+}
@@ -0,0 +1,126 @@
+//! Which addresses a program has executed as part of an instruction, for finding the parts of it
+//! that never run, eg paths only taken for some inputs.
+//!
+//! Like profiling, coverage is opt-in as it takes execution off the fast path:
+//!
+//! ```
+//! use intcode_vm::{coverage::Coverage, ProgramState};
+//!
+//! // Outputs its input if it's nonzero, otherwise outputs 7
+//! let mut program = ProgramState::load_program_str("3,11,1005,11,8,104,7,99,4,11,99,0");
+//! program.inputs.push_back(5);
+//! program.coverage = Some(Coverage::default());
+//! program.run_to_completion().unwrap();
+//!
+//! let coverage = program.coverage.as_ref().unwrap();
+//! assert_eq!(coverage.uncovered_regions(0..12), vec![5..8, 11..12]);
+//! print!("{}", coverage.report(&program.mem, 0..12));
+//! ```
+
+use core::ops::Range;
+
+use crate::prelude::*;
+use crate::{disasm, PagedMemory, ProgramElement};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Coverage {
+    /// One bit per address, see `bitmap`
+    bitmap: Vec<u64>,
+}
+
+impl Coverage {
+    pub(crate) fn record(&mut self, pc: usize, length: usize) {
+        let end = pc + length;
+        if end > self.bitmap.len() * 64 {
+            self.bitmap.resize(end.div_ceil(64), 0);
+        }
+
+        for addr in pc..end {
+            self.bitmap[addr / 64] |= 1 << (addr % 64);
+        }
+    }
+
+    /// Whether `addr` is part of an instruction that has been executed
+    pub fn is_covered(&self, addr: usize) -> bool {
+        self.bitmap
+            .get(addr / 64)
+            .is_some_and(|word| word & (1 << (addr % 64)) != 0)
+    }
+
+    /// Bit `addr % 64` of word `addr / 64` is set if `addr` is covered. Addresses past the end
+    /// aren't covered.
+    pub fn bitmap(&self) -> &[u64] {
+        &self.bitmap
+    }
+
+    /// Number of covered addresses
+    pub fn covered_count(&self) -> usize {
+        self.bitmap.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Every run of addresses within `addrs` that has never been executed, in address order
+    pub fn uncovered_regions(&self, addrs: Range<usize>) -> Vec<Range<usize>> {
+        let mut regions: Vec<Range<usize>> = Vec::new();
+        for addr in addrs.filter(|&addr| !self.is_covered(addr)) {
+            match regions.last_mut() {
+                Some(region) if region.end == addr => region.end += 1,
+                _ => regions.push(addr..(addr + 1)),
+            }
+        }
+
+        regions
+    }
+
+    /// How much of `addrs`, eg the program image, has been executed, followed by the disassembly
+    /// of each region that hasn't
+    pub fn report(&self, mem: &PagedMemory<ProgramElement>, addrs: Range<usize>) -> String {
+        let total = addrs.len();
+        let covered = addrs.clone().filter(|&addr| self.is_covered(addr)).count();
+
+        let mut report = format!(
+            "{} of {} addresses executed ({:.1}%)\n",
+            covered,
+            total,
+            100.0 * covered as f64 / total.max(1) as f64,
+        );
+
+        for region in self.uncovered_regions(addrs) {
+            report += &format!("{}..{}: never executed\n", region.start, region.end);
+            report += &disasm::listing(mem, region);
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProgramState;
+
+    #[test]
+    fn test_coverage() {
+        // Jumps over some data, then loops three times before terminating, using the parameter of
+        // the unreachable output as its counter
+        //   0: jnz 1, 5
+        //   3: data 7, 7
+        //   5: add [14], -1, [14]
+        //   9: jnz [14], 5
+        //  12: halt
+        //  13: out 3
+        let mut program = ProgramState::load_program_str("1105,1,5,7,7,1001,14,-1,14,1005,14,5,99,104,3");
+        program.coverage = Some(Coverage::default());
+        program.run_to_completion().unwrap();
+
+        let coverage = program.coverage.as_ref().unwrap();
+        assert_eq!(coverage.covered_count(), 11);
+        assert!(coverage.is_covered(12));
+        assert!(!coverage.is_covered(13));
+        assert_eq!(coverage.bitmap(), &[0b1_1111_1110_0111]);
+        assert_eq!(coverage.uncovered_regions(0..15), vec![3..5, 13..15]);
+
+        let report = coverage.report(&program.mem, 0..15);
+        assert!(report.starts_with("11 of 15 addresses executed (73.3%)\n3..5: never executed\n"));
+        assert!(report.ends_with("13..15: never executed\n   13: 104 0            out 0\n"));
+    }
+}
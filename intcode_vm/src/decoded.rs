@@ -0,0 +1,121 @@
+//! The cached backend, which keeps the decoded instruction at each address so that it only has to
+//! be decoded again once the memory it came from has been written to.
+//!
+//! Unlike the blocks backend this works one instruction at a time, so it's used for single steps
+//! and while there are breakpoints or watchpoints as well as for whole runs.
+
+use crate::prelude::*;
+use crate::{InputSource, Instruction, OutputSink, ProgramState, StepEvent, VmError};
+
+/// Instructions at addresses past this are decoded every time, rather than growing the cache
+/// without limit
+const MAX_CACHED_ADDR: usize = 1 << 16;
+
+#[derive(Clone, Default)]
+pub(crate) struct DecodeCache {
+    /// The instruction at each address, as decoded before its addresses were checked, since
+    /// relative mode ones depend on when it executes
+    entries: Vec<Option<Instruction>>,
+
+    /// The version of memory after the last instruction executed through the cache
+    mem_version: (u64, u64),
+}
+
+impl DecodeCache {
+    /// Executes the instruction at the program counter, decoding it only if it isn't cached
+    pub(crate) fn step<I: InputSource, O: OutputSink>(state: &mut ProgramState<I, O>) -> Result<StepEvent, VmError> {
+        let cache = &mut state.decoded;
+        if state.mem.version() != cache.mem_version {
+            // Memory was changed by something else, so any of the entries could be out of date
+            cache.entries.clear();
+            cache.mem_version = state.mem.version();
+        }
+
+        let pc = state.program_counter;
+        let instr = match cache.entries.get(pc) {
+            Some(&Some(instr)) => instr,
+            _ => {
+                let mut elements = [0; 4];
+                state.mem.read_into(pc, &mut elements);
+                let instr = Instruction::decode_at(pc, elements)?;
+                cache.insert(pc, instr);
+                instr
+            }
+        };
+
+        instr.check_addresses(state)?;
+        let event = instr.execute(state)?;
+
+        if let Some(idx) = instr.opcode.write_param_idx() {
+            let addr = instr.param_addr(idx, state) as usize;
+            state.decoded.forget(addr);
+        }
+        state.decoded.mem_version = state.mem.version();
+
+        Ok(event)
+    }
+
+    fn insert(&mut self, pc: usize, instr: Instruction) {
+        if pc >= MAX_CACHED_ADDR {
+            return;
+        }
+
+        if pc >= self.entries.len() {
+            self.entries.resize(pc + 1, None);
+        }
+        self.entries[pc] = Some(instr);
+    }
+
+    /// Forgets every instruction that `addr` could be part of, after it's been written to
+    fn forget(&mut self, addr: usize) {
+        for start in addr.saturating_sub(3)..=addr {
+            if let Some(entry) = self.entries.get_mut(start) {
+                *entry = None;
+            }
+        }
+    }
+}
+
+impl core::fmt::Debug for DecodeCache {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("DecodeCache")
+            .field("cached", &self.entries.iter().filter(|e| e.is_some()).count())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::{Backend, ProgramState, StopReason};
+
+    fn cached_program(source: &str) -> ProgramState {
+        let mut program = ProgramState::load_program_str(source);
+        program.backend = Backend::Cached;
+        program
+    }
+
+    #[test]
+    fn test_self_modifying_program() {
+        // Adds 5 to the output instruction's parameter until it's at least 20
+        //   0: add 5, [5], [5]
+        //   4: out 7
+        //   6: lt [5], 20, [16]
+        //  10: jnz [16], 0
+        //  13: halt
+        let mut program = cached_program("101,5,5,5,104,7,1007,5,20,16,1005,16,0,99,0,0,0");
+        assert_eq!(program.run_to_completion(), Ok(StopReason::Terminated));
+        assert_eq!(program.outputs, vec![12, 17, 22]);
+    }
+
+    #[test]
+    fn test_memory_changed_between_steps() {
+        let mut program = cached_program("104,1,1105,1,0");
+        program.progress_state().unwrap();
+        program.progress_state().unwrap();
+
+        program.mem.write_addr(1, 2);
+        program.progress_state().unwrap();
+        assert_eq!(program.outputs, vec![1, 2]);
+    }
+}
@@ -0,0 +1,282 @@
+//! Human readable listings of program memory.
+//!
+//! Each line shows the address, the raw elements, and the decoded instruction, eg
+//!
+//! ```text
+//!     0: 1002 4 3 4       mul [4], 3, [4]
+//!     4: 33               data 33
+//!     5: 203 -1           in [rb-1]
+//! ```
+//!
+//! Position mode parameters are shown as `[addr]`, relative mode ones as `[rb+offset]`, and
+//! immediate mode ones as plain numbers. As with `analysis`, anything which doesn't decode as an
+//! instruction is shown as data.
+
+use core::ops::Range;
+
+use crate::prelude::*;
+use crate::analysis::{self, DecodedInstruction};
+use crate::symbols::{Annotated, SymbolTable};
+use crate::{OpCode, ParameterMode, PagedMemory, ProgramElement};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisasmLine {
+    pub addr: usize,
+
+    /// The elements the instruction was decoded from
+    pub raw: Vec<ProgramElement>,
+
+    pub instruction: DecodedInstruction,
+}
+
+/// Formats a single operand in the listing syntax
+pub fn format_operand(mode: ParameterMode, value: ProgramElement) -> String {
+    match mode {
+        ParameterMode::Position => format!("[{}]", value),
+        ParameterMode::Immediate => value.to_string(),
+        ParameterMode::Relative if value < 0 => format!("[rb-{}]", -value),
+        ParameterMode::Relative => format!("[rb+{}]", value),
+    }
+}
+
+/// As `format_operand`, but with the names of any addresses that have them. Jump targets are
+/// addresses too, even when they're immediate mode.
+fn format_operand_with(
+    symbols: &SymbolTable,
+    opcode: OpCode,
+    idx: usize,
+    mode: ParameterMode,
+    value: ProgramElement,
+) -> String {
+    let is_jump_target = idx == 1 && matches!(opcode, OpCode::JumpIfTrue | OpCode::JumpIfFalse);
+    let name = if value >= 0 { symbols.name(value as usize) } else { None };
+    match (mode, name) {
+        (ParameterMode::Position, Some(name)) => format!("[{}]", name),
+        (ParameterMode::Immediate, Some(name)) if is_jump_target => name.to_string(),
+        _ => format_operand(mode, value),
+    }
+}
+
+impl DisasmLine {
+    fn write(&self, f: &mut core::fmt::Formatter, symbols: Option<&SymbolTable>) -> core::fmt::Result {
+        let raw = self.raw.iter().map(|el| el.to_string()).collect::<Vec<_>>().join(" ");
+        write!(f, "{:>5}: {:<16} ", self.addr, raw)?;
+
+        match &self.instruction {
+            DecodedInstruction::Op { opcode, parameters } => {
+                let operands = parameters
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, &(mode, value))| match symbols {
+                        Some(symbols) => format_operand_with(symbols, *opcode, idx, mode, value),
+                        None => format_operand(mode, value),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                if operands.is_empty() {
+                    write!(f, "{}", opcode.mnemonic())
+                } else {
+                    write!(f, "{} {}", opcode.mnemonic(), operands)
+                }
+            }
+            DecodedInstruction::Data(value) => write!(f, "data {}", value),
+        }
+    }
+}
+
+impl core::fmt::Display for DisasmLine {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        self.write(f, None)
+    }
+}
+
+/// Names the addresses of position mode operands and jump targets
+impl core::fmt::Display for Annotated<'_, DisasmLine> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        self.item.write(f, Some(self.symbols))
+    }
+}
+
+/// Decodes every instruction starting in `range`, walking linearly from its start.
+///
+/// An instruction starting near the end of the range can extend past it.
+pub fn disassemble(mem: &PagedMemory<ProgramElement>, range: Range<usize>) -> Vec<DisasmLine> {
+    // Instructions are at most four elements long
+    let mut elements = vec![0; range.len() + 3];
+    mem.read_into(range.start, &mut elements);
+
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    while offset < range.len() {
+        let instruction = DecodedInstruction::decode(&elements, offset);
+        let length = instruction.length();
+        lines.push(DisasmLine {
+            addr: range.start + offset,
+            raw: elements[offset..(offset + length)].to_vec(),
+            instruction,
+        });
+        offset += length;
+    }
+
+    lines
+}
+
+/// Decodes a whole program image, only treating the code that `analysis::control_flow_graph` finds
+/// reachable as instructions. Everything else is shown as data, one element per line, rather than
+/// whatever it happens to decode as.
+pub fn disassemble_reachable(program: &[ProgramElement]) -> Vec<DisasmLine> {
+    let graph = analysis::control_flow_graph(program);
+
+    let mut lines = Vec::new();
+    let mut addr = 0;
+    while addr < program.len() {
+        let instruction = match graph.block_containing(addr) {
+            Some(_) => DecodedInstruction::decode(program, addr),
+            None => DecodedInstruction::Data(program[addr]),
+        };
+        let length = instruction.length();
+        lines.push(DisasmLine {
+            addr,
+            raw: program[addr..(addr + length)].to_vec(),
+            instruction,
+        });
+        addr += length;
+    }
+
+    lines
+}
+
+/// Decodes the single instruction starting at `addr`
+pub fn disassemble_one(mem: &PagedMemory<ProgramElement>, addr: usize) -> DisasmLine {
+    disassemble(mem, addr..(addr + 1)).remove(0)
+}
+
+/// Up to `before` instructions leading up to `addr`, then the one at `addr` and up to `after`
+/// following it. There's no telling where instructions start before `addr`, so they're decoded
+/// from a little way back, which can guess wrong where code and data are mixed. Anything that
+/// would overlap `addr` is shown as data instead, so that `addr` always starts a line.
+pub fn window(mem: &PagedMemory<ProgramElement>, addr: usize, before: usize, after: usize) -> Vec<DisasmLine> {
+    let start = addr.saturating_sub(before * 4);
+    let mut lines = Vec::new();
+    for line in disassemble(mem, start..addr) {
+        if line.addr + line.raw.len() <= addr {
+            lines.push(line);
+        } else {
+            lines.extend((line.addr..addr).map(|addr| DisasmLine {
+                addr,
+                raw: vec![mem.read_addr(addr)],
+                instruction: DecodedInstruction::Data(mem.read_addr(addr)),
+            }));
+        }
+    }
+    lines.drain(..lines.len().saturating_sub(before));
+
+    lines.extend(disassemble(mem, addr..(addr + (after + 1) * 4)).into_iter().take(after + 1));
+    lines
+}
+
+/// The disassembly of `range` as a single string, one line per instruction
+pub fn listing(mem: &PagedMemory<ProgramElement>, range: Range<usize>) -> String {
+    disassemble(mem, range)
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+/// As `listing`, with names in place of addresses and a `name:` line before each named address
+pub fn listing_with_symbols(mem: &PagedMemory<ProgramElement>, range: Range<usize>, symbols: &SymbolTable) -> String {
+    let mut text = String::new();
+    for line in disassemble(mem, range) {
+        if let Some(name) = symbols.name(line.addr) {
+            text.push_str(&format!("{}:\n", name));
+        }
+        text.push_str(&format!("{}\n", symbols.annotate(&line)));
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listing() {
+        let mem = PagedMemory::from(vec![1002, 4, 3, 4, 33, 203, -1, 21107, 1, 2, 7, 99]);
+        let expected = [
+            "    0: 1002 4 3 4       mul [4], 3, [4]",
+            "    4: 33               data 33",
+            "    5: 203 -1           in [rb-1]",
+            "    7: 21107 1 2 7      lt 1, 2, [rb+7]",
+            "   11: 99               halt",
+        ];
+        assert_eq!(listing(&mem, 0..12), expected.iter().map(|l| format!("{}\n", l)).collect::<String>());
+    }
+
+    #[test]
+    fn test_listing_with_symbols() {
+        let mem = PagedMemory::from(vec![1005, 5, 0, 204, 5, 33]);
+        let mut symbols = SymbolTable::default();
+        symbols.insert(0, "loop");
+        symbols.insert(5, "count");
+
+        let expected = [
+            "loop:",
+            "    0: 1005 5 0         jnz [count], loop",
+            "    3: 204 5            out [rb+5]",
+            "count:",
+            "    5: 33               data 33",
+        ];
+        assert_eq!(
+            listing_with_symbols(&mem, 0..6, &symbols),
+            expected.iter().map(|l| format!("{}\n", l)).collect::<String>()
+        );
+    }
+
+    #[test]
+    fn test_disassemble_reachable() {
+        // The data after the halt would otherwise decode as an add
+        let program = vec![1105, 1, 4, 33, 104, 1, 99, 1, 0, 0, 0];
+        let lines = disassemble_reachable(&program)
+            .iter()
+            .map(|line| (line.addr, line.to_string().split_off(24)))
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![
+            (0, "jnz 1, 4".to_string()),
+            (3, "data 33".to_string()),
+            (4, "out 1".to_string()),
+            (6, "halt".to_string()),
+            (7, "data 1".to_string()),
+            (8, "data 0".to_string()),
+            (9, "data 0".to_string()),
+            (10, "data 0".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_window() {
+        // The instruction at 3 overlaps 5 when decoded from there
+        let mem = PagedMemory::from(vec![104, 1, 99, 1101, 2, 3, 4, 104, 5, 99]);
+        let addrs = |lines: Vec<DisasmLine>| lines.iter().map(|line| line.addr).collect::<Vec<_>>();
+
+        assert_eq!(addrs(window(&mem, 7, 1, 1)), vec![3, 7, 9]);
+        assert_eq!(addrs(window(&mem, 5, 2, 0)), vec![3, 4, 5]);
+        assert_eq!(window(&mem, 5, 2, 0)[1].to_string(), "    4: 2                data 2");
+        assert_eq!(addrs(window(&mem, 0, 3, 1)), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_range() {
+        let mem = PagedMemory::from(vec![99, 1, 0, 0, 0, 104, 5]);
+        let lines = disassemble(&mem, 1..2);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].addr, 1);
+        assert_eq!(lines[0].raw, vec![1, 0, 0, 0]);
+
+        // Reads past the end of the program as zeros
+        let lines = disassemble(&mem, 5..7);
+        assert_eq!(lines[0].raw, vec![104, 5]);
+        assert_eq!(lines.len(), 1);
+    }
+}
@@ -0,0 +1,144 @@
+//! Driving a program as a stream of events, for solutions that react to each output in turn:
+//!
+//! ```
+//! use intcode_vm::events::VmEvent;
+//! use intcode_vm::ProgramState;
+//!
+//! // Outputs double its input, forever
+//! let mut program = ProgramState::load_program_str("3,11,1002,11,2,11,4,11,1105,1,0,0");
+//! program.inputs.push_back(1);
+//!
+//! let mut events = program.events();
+//! let mut seen = Vec::new();
+//! while let Some(event) = events.next() {
+//!     match event {
+//!         VmEvent::Output(value) if value < 100 => seen.push(value),
+//!         VmEvent::Output(_) => break,
+//!         VmEvent::InputRequested => events.push_input(*seen.last().unwrap()),
+//!         VmEvent::Halted | VmEvent::Error(_) => unreachable!(),
+//!     }
+//! }
+//!
+//! assert_eq!(seen, vec![2, 4, 8, 16, 32, 64]);
+//! ```
+
+use std::collections::VecDeque;
+
+use crate::{InputSource, ProgramElement, ProgramState, StopReason, VmError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VmEvent {
+    Output(ProgramElement),
+
+    /// The program needs an input and there isn't one. If there still isn't one the next time the
+    /// iterator is advanced, it ends instead.
+    InputRequested,
+
+    /// Always the last event
+    Halted,
+
+    /// Always the last event, nothing was executed by the instruction that failed
+    Error(VmError),
+}
+
+/// See `ProgramState::events`
+#[derive(Debug)]
+pub struct Events<'a, I> {
+    state: &'a mut ProgramState<I>,
+    finished: bool,
+    waiting: bool,
+}
+
+impl<I: InputSource> ProgramState<I> {
+    /// Runs the program as it's iterated over, yielding each event as it happens. Outputs are
+    /// taken from the output queue as they're yielded, starting with any already there.
+    ///
+    /// Breakpoints and watchpoints don't stop the program, as there's no event for them.
+    pub fn events(&mut self) -> Events<'_, I> {
+        Events { state: self, finished: false, waiting: false }
+    }
+}
+
+impl<I> Events<'_, I> {
+    /// The program being run, eg for queueing inputs of other types
+    pub fn state(&mut self) -> &mut ProgramState<I> {
+        self.state
+    }
+}
+
+impl Events<'_, VecDeque<ProgramElement>> {
+    pub fn push_input(&mut self, value: ProgramElement) {
+        self.state.inputs.push_back(value);
+    }
+}
+
+impl<I: InputSource> Iterator for Events<'_, I> {
+    type Item = VmEvent;
+
+    fn next(&mut self) -> Option<VmEvent> {
+        if self.finished {
+            return None;
+        }
+
+        if let Some(value) = self.state.outputs.pop_front() {
+            return Some(VmEvent::Output(value));
+        }
+
+        loop {
+            match self.state.run_until_output(|_| true) {
+                Ok(StopReason::Output(_)) => {
+                    self.waiting = false;
+                    return self.state.outputs.pop_front().map(VmEvent::Output);
+                }
+                Ok(StopReason::NeedsInput) if self.waiting => return None,
+                Ok(StopReason::NeedsInput) => {
+                    self.waiting = true;
+                    return Some(VmEvent::InputRequested);
+                }
+                Ok(StopReason::Terminated) => {
+                    self.finished = true;
+                    return Some(VmEvent::Halted);
+                }
+                Ok(StopReason::Breakpoint(_)) | Ok(StopReason::Watchpoint(_)) | Ok(StopReason::BudgetExhausted) => {
+                    self.waiting = false;
+                }
+                Ok(StopReason::Stepped) => unreachable!("Only returned by step_over and step_out"),
+                Err(e) => {
+                    self.finished = true;
+                    return Some(VmEvent::Error(e));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events() {
+        // Outputs 5, then its input plus one, then halts
+        let mut program = ProgramState::load_program_str("104,5,3,11,101,1,11,11,4,11,99,0");
+        program.breakpoints.insert(4);
+        program.outputs.push_back(1);
+
+        let mut events = program.events();
+        assert_eq!(events.next(), Some(VmEvent::Output(1)));
+        assert_eq!(events.next(), Some(VmEvent::Output(5)));
+        assert_eq!(events.next(), Some(VmEvent::InputRequested));
+        assert_eq!(events.next(), None);
+
+        events.push_input(9);
+        assert_eq!(events.collect::<Vec<_>>(), vec![VmEvent::Output(10), VmEvent::Halted]);
+        assert!(program.outputs.is_empty());
+        assert_eq!(program.events().next(), Some(VmEvent::Halted));
+    }
+
+    #[test]
+    fn test_error() {
+        let mut program = ProgramState::load_program_str("104,1,42");
+        let events = program.events().collect::<Vec<_>>();
+        assert_eq!(events, vec![VmEvent::Output(1), VmEvent::Error(VmError::UnknownOpcode { pc: 2, instruction: 42 })]);
+    }
+}
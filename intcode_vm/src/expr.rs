@@ -0,0 +1,316 @@
+//! Expressions over a VM's state, eg `mem[386]` or `mem[mem[5] + 1]`, which a debugger can
+//! evaluate after every step to show how they change.
+//!
+//! An expression is a sum of terms, each of which is an integer, `pc`, `rb` (or `relbase`), or
+//! `mem[<expression>]`:
+//!
+//! ```
+//! use intcode_vm::expr::{Expr, WatchedExprs};
+//! use intcode_vm::ProgramState;
+//!
+//! // Counts up at 10, through a pointer at 9
+//! let mut program = ProgramState::load_program_str("1001,10,1,10,1105,1,0,0,0,10,0");
+//!
+//! let mut watched = WatchedExprs::default();
+//! watched.add("mem[mem[9]]".parse::<Expr>().unwrap(), &program);
+//! watched.add("pc - 4".parse::<Expr>().unwrap(), &program);
+//!
+//! program.progress_state().unwrap();
+//! let changes = watched.check(&program);
+//! assert_eq!(changes.len(), 2);
+//! assert_eq!((changes[0].old, changes[0].new), (Some(0), Some(1)));
+//!
+//! program.progress_state().unwrap();
+//! assert_eq!(watched.check(&program)[0].expr.to_string(), "pc - 4");
+//! ```
+//!
+//! Memory is read directly, so evaluating an expression never calls a memory-mapped device.
+
+use alloc::boxed::Box;
+use core::convert::TryFrom;
+
+use crate::prelude::*;
+use crate::{ProgramElement, ProgramState};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expr {
+    Literal(ProgramElement),
+    Pc,
+    RelativeBase,
+
+    /// The value at the address the inner expression evaluates to
+    Mem(Box<Expr>),
+
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// The expression's value for `state`, or `None` if it reads from a negative address or
+    /// overflows
+    pub fn eval<I, O>(&self, state: &ProgramState<I, O>) -> Option<ProgramElement> {
+        match self {
+            Expr::Literal(value) => Some(*value),
+            Expr::Pc => ProgramElement::try_from(state.program_counter).ok(),
+            Expr::RelativeBase => Some(state.relative_base),
+            Expr::Mem(addr) => {
+                let addr = usize::try_from(addr.eval(state)?).ok()?;
+                Some(state.mem.read_addr(addr))
+            }
+            Expr::Add(a, b) => a.eval(state)?.checked_add(b.eval(state)?),
+            Expr::Sub(a, b) => a.eval(state)?.checked_sub(b.eval(state)?),
+        }
+    }
+}
+
+impl core::fmt::Display for Expr {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Expr::Literal(value) => write!(f, "{}", value),
+            Expr::Pc => write!(f, "pc"),
+            Expr::RelativeBase => write!(f, "rb"),
+            Expr::Mem(addr) => write!(f, "mem[{}]", addr),
+            Expr::Add(a, b) => write!(f, "{} + {}", a, b),
+            Expr::Sub(a, b) => write!(f, "{} - {}", a, b),
+        }
+    }
+}
+
+/// Where an expression failed to parse, and what was expected there
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExprError {
+    /// Counting from 1
+    pub column: usize,
+
+    pub expected: &'static str,
+}
+
+impl core::fmt::Display for ExprError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "Expected {} at column {} of the expression", self.expected, self.column)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ExprError {}
+
+impl core::str::FromStr for Expr {
+    type Err = ExprError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { source, pos: 0 };
+        let expr = parser.parse_sum()?;
+        parser.skip_whitespace();
+        if parser.pos < source.len() {
+            return Err(parser.error("'+', '-' or the end"));
+        }
+        Ok(expr)
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, expected: &'static str) -> ExprError {
+        ExprError { column: self.source[..self.pos].chars().count() + 1, expected }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.pos = self.source.len() - self.rest().trim_start().len();
+    }
+
+    /// Consumes the longest prefix of the rest of the source made of characters matching `f`
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let start = self.pos;
+        let len = self.rest().find(|c: char| !f(c)).unwrap_or_else(|| self.rest().len());
+        self.pos += len;
+        &self.source[start..self.pos]
+    }
+
+    fn parse_sum(&mut self) -> Result<Expr, ExprError> {
+        let mut expr = self.parse_term()?;
+        loop {
+            self.skip_whitespace();
+            let add = match self.rest().chars().next() {
+                Some('+') => true,
+                Some('-') => false,
+                _ => return Ok(expr),
+            };
+
+            self.pos += 1;
+            let term = self.parse_term()?;
+            expr = if add {
+                Expr::Add(Box::new(expr), Box::new(term))
+            } else {
+                Expr::Sub(Box::new(expr), Box::new(term))
+            };
+        }
+    }
+
+    fn parse_term(&mut self) -> Result<Expr, ExprError> {
+        self.skip_whitespace();
+        let start = self.pos;
+
+        if self.rest().starts_with(|c: char| c == '-' || c.is_ascii_digit()) {
+            self.pos += 1;
+            self.take_while(|c| c.is_ascii_digit());
+            return match self.source[start..self.pos].parse() {
+                Ok(value) => Ok(Expr::Literal(value)),
+                Err(_) => {
+                    self.pos = start;
+                    Err(self.error("an integer"))
+                }
+            };
+        }
+
+        match self.take_while(|c| c.is_ascii_alphabetic()) {
+            "pc" => Ok(Expr::Pc),
+            "rb" | "relbase" => Ok(Expr::RelativeBase),
+            "mem" => {
+                if !self.rest().starts_with('[') {
+                    return Err(self.error("'['"));
+                }
+                self.pos += 1;
+
+                let addr = self.parse_sum()?;
+                self.skip_whitespace();
+                if !self.rest().starts_with(']') {
+                    return Err(self.error("']'"));
+                }
+                self.pos += 1;
+                Ok(Expr::Mem(Box::new(addr)))
+            }
+            _ => {
+                self.pos = start;
+                Err(self.error("an integer, pc, rb or mem[...]"))
+            }
+        }
+    }
+}
+
+/// A change to the value of a watched expression, see `WatchedExprs::check`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExprChange<'a> {
+    /// Position of the expression in the order they were added, not counting removed ones
+    pub idx: usize,
+
+    pub expr: &'a Expr,
+    pub old: Option<ProgramElement>,
+    pub new: Option<ProgramElement>,
+}
+
+/// Expressions along with their values when they were last checked
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WatchedExprs {
+    exprs: Vec<(Expr, Option<ProgramElement>)>,
+}
+
+impl WatchedExprs {
+    /// Starts watching `expr`, returning its current value
+    pub fn add<I, O>(&mut self, expr: Expr, state: &ProgramState<I, O>) -> Option<ProgramElement> {
+        let value = expr.eval(state);
+        self.exprs.push((expr, value));
+        value
+    }
+
+    /// Stops watching the `idx`th expression
+    pub fn remove(&mut self, idx: usize) -> Option<Expr> {
+        if idx < self.exprs.len() {
+            Some(self.exprs.remove(idx).0)
+        } else {
+            None
+        }
+    }
+
+    /// Every expression, with its value when it was last checked
+    pub fn iter(&self) -> impl Iterator<Item = (&Expr, Option<ProgramElement>)> {
+        self.exprs.iter().map(|(expr, value)| (expr, *value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.exprs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.exprs.is_empty()
+    }
+
+    /// Re-evaluates every expression for `state`, returning the ones whose values have changed
+    /// since they were last checked
+    pub fn check<I, O>(&mut self, state: &ProgramState<I, O>) -> Vec<ExprChange<'_>> {
+        let mut changes = Vec::new();
+        for (idx, (expr, value)) in self.exprs.iter_mut().enumerate() {
+            let new = expr.eval(state);
+            if new != *value {
+                changes.push(ExprChange { idx, expr, old: *value, new });
+                *value = new;
+            }
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<Expr, ExprError> {
+        source.parse()
+    }
+
+    #[test]
+    fn test_parse() {
+        let mem = |addr| Expr::Mem(Box::new(addr));
+        assert_eq!(parse("mem[386]"), Ok(mem(Expr::Literal(386))));
+        assert_eq!(parse(" relbase "), Ok(Expr::RelativeBase));
+        assert_eq!(parse("mem[mem[5]]"), Ok(mem(mem(Expr::Literal(5)))));
+        assert_eq!(
+            parse("mem[rb+2] - -1"),
+            Ok(Expr::Sub(
+                Box::new(mem(Expr::Add(Box::new(Expr::RelativeBase), Box::new(Expr::Literal(2))))),
+                Box::new(Expr::Literal(-1)),
+            )),
+        );
+        assert_eq!(parse("mem[ pc + 1 ]").unwrap().to_string(), "mem[pc + 1]");
+
+        assert_eq!(parse(""), Err(ExprError { column: 1, expected: "an integer, pc, rb or mem[...]" }));
+        assert_eq!(parse("mem[5").unwrap_err().column, 6);
+        assert_eq!(parse("mem 5]").unwrap_err().expected, "'['");
+        assert_eq!(parse("pc rb").unwrap_err().column, 4);
+        assert_eq!(parse("-").unwrap_err().expected, "an integer");
+    }
+
+    #[test]
+    fn test_eval() {
+        let mut state = ProgramState::load_program_str("1,2,3,4,-2");
+        state.relative_base = 3;
+        state.program_counter = 1;
+        let eval = |source: &str, state: &ProgramState| parse(source).unwrap().eval(state);
+
+        assert_eq!(eval("mem[rb + 1]", &state), Some(-2));
+        assert_eq!(eval("mem[mem[pc]] - pc", &state), Some(2));
+        assert_eq!(eval("mem[mem[4]]", &state), None);
+        assert_eq!(eval("mem[100]", &state), Some(0));
+        assert_eq!(eval(&format!("{} + 1", ProgramElement::MAX), &state), None);
+
+        let mut watched = WatchedExprs::default();
+        assert_eq!(watched.add(parse("mem[0]").unwrap(), &state), Some(1));
+        watched.add(parse("rb").unwrap(), &state);
+        state.mem.write_addr(0, 7);
+        let changes = watched.check(&state);
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].idx, changes[0].old, changes[0].new), (0, Some(1), Some(7)));
+        assert!(watched.check(&state).is_empty());
+
+        assert_eq!(watched.remove(0), Some(Expr::Mem(Box::new(Expr::Literal(0)))));
+        assert_eq!(watched.remove(1), None);
+        assert_eq!(watched.iter().collect::<Vec<_>>(), vec![(&Expr::RelativeBase, Some(3))]);
+    }
+}
@@ -0,0 +1,281 @@
+//! Handlers for opcodes the interpreter doesn't recognize, to experiment with extending intcode
+//! without changing the interpreter itself.
+//!
+//! ```
+//! use intcode_vm::extension::{CustomInstruction, OpcodeHandler};
+//! use intcode_vm::{ProgramElement, ProgramState, StepEvent, VmError};
+//!
+//! // A "syscall" which writes the sum of the numbers from 1 to its first parameter to its second
+//! struct Triangle;
+//!
+//! impl<I, O> OpcodeHandler<I, O> for Triangle {
+//!     fn parameters(&self) -> usize {
+//!         2
+//!     }
+//!
+//!     fn execute(&mut self, instr: &CustomInstruction, state: &mut ProgramState<I, O>) -> Result<StepEvent, VmError> {
+//!         let n = instr.read(0, state)?;
+//!         instr.write(1, state, n * (n + 1) / 2)?;
+//!         Ok(StepEvent::Continued)
+//!     }
+//! }
+//!
+//! let mut program = ProgramState::load_program_str("142,10,6,4,6,99,0");
+//! program.register_opcode(42, Triangle);
+//! program.run_to_completion().unwrap();
+//! assert_eq!(program.outputs, vec![55]);
+//! ```
+//!
+//! As with hooks, handlers are shared between a VM and its clones, dropped when its I/O is
+//! replaced, and take execution off the fast path. Custom instructions aren't seen by hooks,
+//! watchpoints, profiling, coverage, history or the journal, and aren't counted in `stats`.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::sync::{Arc, Mutex};
+
+use crate::{OpCode, ParameterMode, ProgramElement, ProgramState, StepEvent, VmError};
+
+/// An instruction with an opcode that's handled by an `OpcodeHandler`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CustomInstruction {
+    pub pc: usize,
+
+    /// The instruction's first element, including its parameter modes
+    pub instruction: ProgramElement,
+
+    pub parameters: Vec<(ParameterMode, ProgramElement)>,
+}
+
+impl CustomInstruction {
+    pub fn opcode(&self) -> ProgramElement {
+        self.instruction % 100
+    }
+
+    /// The address parameter `idx` refers to. Fails for an immediate mode parameter, which doesn't
+    /// refer to one.
+    pub fn addr<I, O>(&self, idx: usize, state: &ProgramState<I, O>) -> Result<usize, VmError> {
+        let addr = match self.parameters[idx] {
+            (ParameterMode::Immediate, _) => {
+                return Err(VmError::ImmediateWrite { pc: self.pc, instruction: self.instruction })
+            }
+            (ParameterMode::Relative, value) => state.relative_base + value,
+            (ParameterMode::Position, value) => value,
+        };
+        usize::try_from(addr).map_err(|_| VmError::NegativeAddress { pc: self.pc, addr })
+    }
+
+    /// The value of parameter `idx`, read from memory, or a mapped device, unless it's in immediate
+    /// mode
+    pub fn read<I, O>(&self, idx: usize, state: &ProgramState<I, O>) -> Result<ProgramElement, VmError> {
+        match self.parameters[idx] {
+            (ParameterMode::Immediate, value) => Ok(value),
+            _ => Ok(state.load(self.addr(idx, state)?)),
+        }
+    }
+
+    /// Writes `value` to the address parameter `idx` refers to. Fails without writing anything if
+    /// that's past the VM's `memory_limit`.
+    pub fn write<I, O>(&self, idx: usize, state: &mut ProgramState<I, O>, value: ProgramElement) -> Result<(), VmError> {
+        let addr = self.addr(idx, state)?;
+        if let Some(limit) = state.memory_limit.filter(|&limit| addr >= limit) {
+            return Err(VmError::OutOfMemory { pc: self.pc, addr, limit });
+        }
+        state.store(addr, value);
+        Ok(())
+    }
+}
+
+pub trait OpcodeHandler<I, O>: Send {
+    /// How many parameters instructions with the opcode take, which decides how many parameter
+    /// modes are decoded and where the following instruction starts
+    fn parameters(&self) -> usize;
+
+    /// Executes `instr`. The program counter already points to the following instruction, so
+    /// only needs changing to jump somewhere else. Returning `StepEvent::Halted` terminates the
+    /// program.
+    ///
+    /// If this fails, the program counter is put back so that the instruction can be retried,
+    /// but any other changes it made to the VM are kept.
+    fn execute(&mut self, instr: &CustomInstruction, state: &mut ProgramState<I, O>) -> Result<StepEvent, VmError>;
+}
+
+pub(crate) struct Extensions<I, O> {
+    handlers: BTreeMap<ProgramElement, Arc<Mutex<dyn OpcodeHandler<I, O>>>>,
+}
+
+impl<I, O> Extensions<I, O> {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+}
+
+impl<I, O> Default for Extensions<I, O> {
+    fn default() -> Self {
+        Self { handlers: BTreeMap::new() }
+    }
+}
+
+impl<I, O> Clone for Extensions<I, O> {
+    fn clone(&self) -> Self {
+        Self { handlers: self.handlers.clone() }
+    }
+}
+
+impl<I, O> std::fmt::Debug for Extensions<I, O> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_set().entries(self.handlers.keys()).finish()
+    }
+}
+
+/// Executes the instruction at the program counter if it has an opcode with a handler, returning
+/// `None` without doing anything if it doesn't
+pub(crate) fn step<I, O>(state: &mut ProgramState<I, O>) -> Option<Result<StepEvent, VmError>> {
+    let pc = state.program_counter;
+    let instruction = state.mem.read_addr(pc);
+    if OpCode::try_from_element(&instruction).is_some() {
+        return None;
+    }
+
+    let handler = state.extensions.handlers.get(&(instruction % 100))?.clone();
+    let mut handler = handler.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let mut modes = instruction / 100;
+    let mut parameters = Vec::new();
+    for param in 0..handler.parameters() {
+        let mode = match ParameterMode::try_from_code((modes % 10) as u8) {
+            Some(mode) => mode,
+            None => return Some(Err(VmError::UnknownParameterMode { pc, instruction, param })),
+        };
+        parameters.push((mode, state.mem.read_addr(pc + 1 + param)));
+        modes /= 10;
+    }
+
+    let instr = CustomInstruction { pc, instruction, parameters };
+    state.program_counter = pc + 1 + instr.parameters.len();
+    let result = handler.execute(&instr, state);
+    match result {
+        Ok(StepEvent::Halted) => state.terminated = true,
+        Ok(_) => (),
+        Err(_) => state.program_counter = pc,
+    }
+
+    Some(result)
+}
+
+impl<I, O> ProgramState<I, O> {
+    /// Calls `handler` to execute every instruction with `opcode`, replacing any previous handler
+    /// for it.
+    ///
+    /// Panics if `opcode` isn't between 0 and 99, or is one the interpreter already recognizes.
+    pub fn register_opcode(&mut self, opcode: ProgramElement, handler: impl OpcodeHandler<I, O> + 'static) {
+        assert!((0..100).contains(&opcode), "Opcode {} isn't between 0 and 99", opcode);
+        assert!(OpCode::try_from_element(&opcode).is_none(), "Opcode {} is already built in", opcode);
+        self.extensions.handlers.insert(opcode, Arc::new(Mutex::new(handler)));
+    }
+
+    pub fn clear_opcodes(&mut self) {
+        self.extensions = Extensions::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Jumps to its first parameter if the second is negative, and terminates if it's 0
+    struct JumpIfNegative;
+
+    impl<I, O> OpcodeHandler<I, O> for JumpIfNegative {
+        fn parameters(&self) -> usize {
+            2
+        }
+
+        fn execute(&mut self, instr: &CustomInstruction, state: &mut ProgramState<I, O>) -> Result<StepEvent, VmError> {
+            match instr.read(1, state)? {
+                0 => Ok(StepEvent::Halted),
+                value if value < 0 => {
+                    state.program_counter = instr.read(0, state)? as usize;
+                    Ok(StepEvent::Jumped)
+                }
+                _ => Ok(StepEvent::Continued),
+            }
+        }
+    }
+
+    /// Writes its first parameter to its second
+    struct Store;
+
+    impl<I, O> OpcodeHandler<I, O> for Store {
+        fn parameters(&self) -> usize {
+            2
+        }
+
+        fn execute(&mut self, instr: &CustomInstruction, state: &mut ProgramState<I, O>) -> Result<StepEvent, VmError> {
+            let value = instr.read(0, state)?;
+            instr.write(1, state, value)?;
+            Ok(StepEvent::Continued)
+        }
+    }
+
+    #[test]
+    fn test_custom_opcode() {
+        // Counts up from an input, outputting each number until it reaches 0
+        //   0: in [12]
+        //   2: out [12]
+        //   4: add [12], 1, [12]
+        //   8: op50 2, [12]
+        //  11: halt
+        //  12: data
+        let source = "3,12,4,12,1001,12,1,12,150,2,12,99,0";
+        for &backend in crate::Backend::ALL {
+            let mut program = ProgramState::load_program_str(source);
+            program.backend = backend;
+            program.register_opcode(50, JumpIfNegative);
+            program.inputs.push_back(-3);
+            assert_eq!(program.run_to_completion(), Ok(crate::StopReason::Terminated));
+            assert_eq!(program.outputs, vec![-3, -2, -1]);
+            assert_eq!(program.program_counter, 11);
+        }
+
+        // Without the handler, it's as unrecognized as ever
+        let mut program = ProgramState::load_program_str(source);
+        program.register_opcode(50, JumpIfNegative);
+        program.clear_opcodes();
+        program.inputs.push_back(-3);
+        assert_eq!(program.run_to_completion(), Err(VmError::UnknownOpcode { pc: 8, instruction: 150 }));
+    }
+
+    #[test]
+    fn test_custom_opcode_errors() {
+        let mut program = ProgramState::load_program_str("2050,0,-5,99");
+        program.register_opcode(50, JumpIfNegative);
+        assert_eq!(program.progress_state(), Err(VmError::NegativeAddress { pc: 0, addr: -5 }));
+        assert_eq!(program.program_counter, 0);
+
+        // The relative base makes the address valid
+        program.relative_base = 8;
+        assert_eq!(program.progress_state(), Ok(StepEvent::Continued));
+        assert_eq!(program.program_counter, 3);
+    }
+
+    #[test]
+    fn test_custom_opcode_memory_limit() {
+        let mut program = ProgramState::load_program_str("151,7,1000,99");
+        program.register_opcode(51, Store);
+        program.memory_limit = Some(100);
+        assert_eq!(program.progress_state(), Err(VmError::OutOfMemory { pc: 0, addr: 1000, limit: 100 }));
+        assert_eq!(program.program_counter, 0);
+        assert_eq!(program.mem.extent(), 4);
+
+        program.memory_limit = None;
+        assert_eq!(program.run_to_completion(), Ok(crate::StopReason::Terminated));
+        assert_eq!(program.mem.read_addr(1000), 7);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_register_built_in_opcode() {
+        ProgramState::load_program_str("99").register_opcode(9, JumpIfNegative);
+    }
+}
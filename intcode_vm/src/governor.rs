@@ -0,0 +1,170 @@
+//! Rate limiting, so that programs which draw something as they go, eg day 13's arcade game or
+//! day 11's painting robot, run slowly enough to watch.
+//!
+//! A `Governor` is passed to `ProgramState::run_governed`, which sleeps as needed to keep the
+//! program to its rate, so the caller never has to:
+//!
+//! ```
+//! use std::time::{Duration, Instant};
+//! use intcode_vm::governor::{Governor, Rate};
+//! use intcode_vm::{ProgramState, StopReason};
+//!
+//! // Outputs 1 to 6, then terminates
+//! let mut program = ProgramState::load_program_str("104,1,104,2,104,3,104,4,104,5,104,6,99");
+//! let mut governor = Governor::new(Rate::Frames { per_second: 100, outputs: 2 });
+//!
+//! let start = Instant::now();
+//! let mut frames = Vec::new();
+//! while let StopReason::Output(_) = program.run_governed(&mut governor).unwrap() {
+//!     frames.push(program.outputs.drain(..).collect::<Vec<_>>());
+//! }
+//!
+//! assert_eq!(frames, vec![vec![1, 2], vec![3, 4], vec![5, 6]]);
+//! assert!(start.elapsed() >= Duration::from_millis(30));
+//! ```
+//!
+//! A program that falls behind its rate, eg because the caller spent a while drawing, isn't
+//! allowed to rush to catch up.
+
+use std::time::{Duration, Instant};
+
+use crate::{InputSource, OutputSink, ProgramState, StopReason, VmError};
+
+/// How often an instruction rate is checked, in that instructions are run in slices of this
+/// fraction of a second's worth
+const SLICES_PER_SECOND: u64 = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rate {
+    /// At most this many instructions a second
+    Instructions(u64),
+
+    /// At most `per_second` frames a second, where each frame is `outputs` outputs
+    Frames { per_second: u64, outputs: usize },
+}
+
+#[derive(Clone, Debug)]
+pub struct Governor {
+    pub rate: Rate,
+
+    /// The earliest the next slice or frame can start
+    next: Option<Instant>,
+
+    /// Outputs of the frame in progress
+    outputs: usize,
+
+    /// Whether a frame has finished since the last wait
+    frame_done: bool,
+}
+
+impl Governor {
+    pub fn new(rate: Rate) -> Self {
+        Self {
+            rate,
+            next: None,
+            outputs: 0,
+            frame_done: false,
+        }
+    }
+
+    /// Lets the next slice or frame start straight away, eg after waiting on the user
+    pub fn reset(&mut self) {
+        self.next = None;
+        self.frame_done = false;
+    }
+
+    /// Sleeps until the next slice or frame can start, then works out when the one after can
+    fn wait(&mut self, period: Duration) {
+        let now = Instant::now();
+        let start = match self.next {
+            Some(next) if next > now => {
+                std::thread::sleep(next - now);
+                next
+            }
+            _ => now,
+        };
+        self.next = Some(start + period);
+    }
+}
+
+impl<I: InputSource, O: OutputSink> ProgramState<I, O> {
+    /// As `run_to_next_input`, but sleeping as needed to keep to `governor`'s rate.
+    ///
+    /// With `Rate::Frames`, this also stops with `StopReason::Output` once each frame's worth of
+    /// outputs has been produced, so that the frame can be drawn. The next call then waits for the
+    /// frame's time to be up before carrying on.
+    pub fn run_governed(&mut self, governor: &mut Governor) -> Result<StopReason, VmError> {
+        match governor.rate {
+            Rate::Instructions(per_second) => {
+                let per_second = per_second.max(1);
+                let slice = (per_second / SLICES_PER_SECOND).max(1);
+                let period = Duration::from_secs_f64(slice as f64 / per_second as f64);
+                loop {
+                    governor.wait(period);
+                    match self.run_for(slice)? {
+                        StopReason::BudgetExhausted => (),
+                        reason => return Ok(reason),
+                    }
+                }
+            }
+            Rate::Frames { per_second, outputs } => {
+                let period = Duration::from_secs_f64(1.0 / per_second.max(1) as f64);
+                if governor.frame_done {
+                    governor.wait(period);
+                    governor.frame_done = false;
+                } else if governor.next.is_none() {
+                    governor.next = Some(Instant::now() + period);
+                }
+
+                let count = &mut governor.outputs;
+                let reason = self.run_until_output(|_| {
+                    *count += 1;
+                    *count >= outputs
+                })?;
+
+                if let StopReason::Output(_) = reason {
+                    governor.outputs = 0;
+                    governor.frame_done = true;
+                }
+                Ok(reason)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instruction_rate() {
+        // Counts 25 down to 0, taking 50 instructions
+        //   0: add [9], -1, [9]
+        //   4: jt [9], 0
+        //   7: halt
+        let mut program = ProgramState::load_program_str("1001,9,-1,9,1005,9,0,99,0,25");
+        let mut governor = Governor::new(Rate::Instructions(1000));
+
+        let start = Instant::now();
+        assert_eq!(program.run_governed(&mut governor), Ok(StopReason::Terminated));
+        assert!(start.elapsed() >= Duration::from_millis(40));
+        assert_eq!(program.stats().total(), 51);
+
+        // Stops for the usual reasons too
+        let mut program = ProgramState::load_program_str("3,0,99");
+        governor.reset();
+        assert_eq!(program.run_governed(&mut governor), Ok(StopReason::NeedsInput));
+    }
+
+    #[test]
+    fn test_frame_rate() {
+        // A frame is cut short by needing input, and finished once it's given
+        let mut program = ProgramState::load_program_str("104,1,3,9,4,9,104,3,99,0");
+        let mut governor = Governor::new(Rate::Frames { per_second: 1000, outputs: 2 });
+        assert_eq!(program.run_governed(&mut governor), Ok(StopReason::NeedsInput));
+        program.inputs.push_back(2);
+        assert_eq!(program.run_governed(&mut governor), Ok(StopReason::Output(2)));
+        assert_eq!(program.run_governed(&mut governor), Ok(StopReason::Terminated));
+        assert_eq!(program.outputs, vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,89 @@
+//! A record of the last few instructions a VM executed, so that when something goes wrong the
+//! trail which led there can be printed.
+//!
+//! Like profiling, keeping a history is opt-in as it takes execution off the fast path:
+//!
+//! ```
+//! use intcode_vm::{history::History, ProgramState};
+//!
+//! // Writes 42 to address 7, then jumps there
+//! let mut program = ProgramState::load_program_str("1101,20,22,7,1105,1,7,0");
+//! program.history = Some(History::new(8));
+//!
+//! let error = program.run_to_completion().unwrap_err();
+//! eprintln!("{}, after executing:\n{}", error, program.history.as_ref().unwrap());
+//! ```
+
+use alloc::collections::VecDeque;
+
+use crate::OpCode;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct History {
+    capacity: usize,
+    entries: VecDeque<(usize, OpCode)>,
+}
+
+impl History {
+    /// Keeps the most recent `capacity` instructions
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub(crate) fn record(&mut self, pc: usize, opcode: OpCode) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((pc, opcode));
+    }
+
+    /// The address and opcode of each kept instruction, oldest first
+    pub fn entries(&self) -> impl Iterator<Item = (usize, OpCode)> + '_ {
+        self.entries.iter().cloned()
+    }
+}
+
+impl core::fmt::Display for History {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for (pc, opcode) in self.entries() {
+            writeln!(f, "{:>5}: {}", pc, opcode.mnemonic())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use crate::{ProgramState, VmError};
+
+    #[test]
+    fn test_trail_to_error() {
+        // Counts down from 2, then jumps into the middle of the first instruction
+        //   0: jz 0, 3
+        //   3: add [13], -1, [13]
+        //   7: jnz [13], 3
+        //  10: jz 0, 1
+        //  13: data 2
+        let mut program = ProgramState::load_program_str("1106,0,3,1001,13,-1,13,1005,13,3,1106,0,1,2");
+        program.history = Some(History::new(4));
+
+        assert_eq!(
+            program.run_to_completion(),
+            Err(VmError::UnknownOpcode { pc: 1, instruction: 0 })
+        );
+        assert_eq!(
+            program.history.as_ref().unwrap().entries().collect::<Vec<_>>(),
+            vec![(7, OpCode::JumpIfTrue), (3, OpCode::Add), (7, OpCode::JumpIfTrue), (10, OpCode::JumpIfFalse)],
+        );
+        assert_eq!(program.history.unwrap().to_string(), "    7: jnz\n    3: add\n    7: jnz\n   10: jz\n");
+    }
+}
@@ -0,0 +1,175 @@
+//! Callbacks run around every instruction a VM executes, for anything that wants to watch
+//! execution without a dedicated feature of its own.
+//!
+//! ```
+//! use std::sync::{Arc, Mutex};
+//! use intcode_vm::analysis::DecodedInstruction;
+//! use intcode_vm::{OpCode, ProgramState};
+//!
+//! // Counts the additions
+//! let mut program = ProgramState::load_program_str("1101,1,1,9,1001,9,5,9,99,0");
+//! let adds = Arc::new(Mutex::new(0));
+//! let counter = adds.clone();
+//! program.set_pre_hook(move |_, instruction, _| {
+//!     if let DecodedInstruction::Op { opcode: OpCode::Add, .. } = instruction {
+//!         *counter.lock().unwrap() += 1;
+//!     }
+//! });
+//!
+//! program.run_to_completion().unwrap();
+//! assert_eq!(*adds.lock().unwrap(), 2);
+//! ```
+//!
+//! Hooks are shared between a VM and its clones, and dropped when its I/O is replaced. As with
+//! watchpoints, setting one takes execution off the fast path.
+
+use std::sync::{Arc, Mutex};
+
+use crate::analysis::DecodedInstruction;
+use crate::ProgramState;
+
+/// Called with the address of the instruction, the instruction, and the VM. Pre-hooks see the VM
+/// before the instruction executes, post-hooks see it afterwards.
+///
+/// Neither is called for an instruction that fails, including an `in` without an input to read,
+/// so each is called exactly once per instruction executed. A pre-hook for an `in` sees the VM
+/// with the input it's about to read already taken from its input source.
+pub type Hook<I, O> = dyn FnMut(usize, &DecodedInstruction, &ProgramState<I, O>) + Send;
+
+pub(crate) struct Hooks<I, O> {
+    pub(crate) pre: Option<Arc<Mutex<Hook<I, O>>>>,
+    pub(crate) post: Option<Arc<Mutex<Hook<I, O>>>>,
+}
+
+impl<I, O> Hooks<I, O> {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pre.is_none() && self.post.is_none()
+    }
+}
+
+impl<I, O> Default for Hooks<I, O> {
+    fn default() -> Self {
+        Self { pre: None, post: None }
+    }
+}
+
+impl<I, O> Clone for Hooks<I, O> {
+    fn clone(&self) -> Self {
+        Self { pre: self.pre.clone(), post: self.post.clone() }
+    }
+}
+
+impl<I, O> std::fmt::Debug for Hooks<I, O> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Hooks")
+            .field("pre", &self.pre.is_some())
+            .field("post", &self.post.is_some())
+            .finish()
+    }
+}
+
+/// Calls `hook`, if it's set
+pub(crate) fn call<I, O>(
+    hook: &Option<Arc<Mutex<Hook<I, O>>>>,
+    pc: usize,
+    instruction: &DecodedInstruction,
+    state: &ProgramState<I, O>,
+) {
+    if let Some(hook) = hook {
+        let mut hook = hook.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        (*hook)(pc, instruction, state);
+    }
+}
+
+impl<I, O> ProgramState<I, O> {
+    /// Calls `hook` before each instruction executes, replacing any previous pre-hook
+    pub fn set_pre_hook(
+        &mut self,
+        hook: impl FnMut(usize, &DecodedInstruction, &ProgramState<I, O>) + Send + 'static,
+    ) {
+        self.hooks.pre = Some(Arc::new(Mutex::new(hook)));
+    }
+
+    /// Calls `hook` after each instruction executes successfully, replacing any previous
+    /// post-hook
+    pub fn set_post_hook(
+        &mut self,
+        hook: impl FnMut(usize, &DecodedInstruction, &ProgramState<I, O>) + Send + 'static,
+    ) {
+        self.hooks.post = Some(Arc::new(Mutex::new(hook)));
+    }
+
+    pub fn clear_hooks(&mut self) {
+        self.hooks = Hooks::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpCode;
+
+    #[test]
+    fn test_hooks() {
+        // Counts up forever, outputting each number
+        //   0: add [9], 1, [9]
+        //   4: out [9]
+        //   6: jnz 1, 0
+        //   9: data
+        let mut program = ProgramState::load_program_str("1001,9,1,9,4,9,1105,1,0,0");
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        let pre = seen.clone();
+        program.set_pre_hook(move |pc, instruction, state| {
+            if let DecodedInstruction::Op { opcode, .. } = instruction {
+                let value = state.mem.read_addr(9);
+                pre.lock().unwrap().push(format!("pre {} {} {}", pc, opcode.mnemonic(), value));
+            }
+        });
+        let post = seen.clone();
+        program.set_post_hook(move |pc, _, state| {
+            post.lock().unwrap().push(format!("post {} {}", pc, state.program_counter));
+        });
+
+        program.run_for(2).unwrap();
+        assert_eq!(*seen.lock().unwrap(), vec!["pre 0 add 0", "post 0 4", "pre 4 out 1", "post 4 6"]);
+
+        // Hooks aren't called for instructions that fail to decode
+        seen.lock().unwrap().clear();
+        program.mem.write_addr(6, 42);
+        assert!(program.run_for(1).is_err());
+        assert!(seen.lock().unwrap().is_empty());
+
+        program.clear_hooks();
+        program.mem.write_addr(6, 1105);
+        program.run_for(3).unwrap();
+        assert!(seen.lock().unwrap().is_empty());
+        assert_eq!(program.stats().count(OpCode::Add), 2);
+    }
+
+    #[test]
+    fn test_hooks_with_blocking_input() {
+        // Echoes a single input
+        let mut program = ProgramState::load_program_str("3,5,4,5,99,0");
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        let pre = seen.clone();
+        program.set_pre_hook(move |pc, _, state| pre.lock().unwrap().push(("pre", pc, state.inputs.len())));
+        let post = seen.clone();
+        program.set_post_hook(move |pc, _, _| post.lock().unwrap().push(("post", pc, 0)));
+
+        // Retrying an `in` without any input doesn't call either hook
+        assert_eq!(program.run_to_next_input(), Ok(crate::StopReason::NeedsInput));
+        assert_eq!(program.run_to_next_input(), Ok(crate::StopReason::NeedsInput));
+        assert!(seen.lock().unwrap().is_empty());
+
+        program.inputs.extend(&[7, 8]);
+        program.run_to_completion().unwrap();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![("pre", 0, 1), ("post", 0, 0), ("pre", 2, 1), ("post", 2, 0), ("pre", 4, 1), ("post", 4, 0)],
+        );
+        assert_eq!(program.outputs, vec![7]);
+        assert_eq!(program.inputs, vec![8]);
+    }
+}
@@ -0,0 +1,216 @@
+//! Where a VM's inputs come from and its outputs go.
+//!
+//! By default both are `VecDeque`s, which the caller fills and drains between runs. Any other
+//! `InputSource` and `OutputSink` can be attached with `ProgramState::with_io`, eg to connect a
+//! VM straight to stdin and stdout, or to another VM running on a different thread:
+//!
+//! ```
+//! use std::sync::mpsc;
+//! use intcode_vm::ProgramState;
+//!
+//! let (to_vm, inputs) = mpsc::channel();
+//! let (outputs, from_vm) = mpsc::channel();
+//!
+//! // Adds one to its input
+//! let program = ProgramState::load_program_str("3,9,1001,9,1,9,4,9,99,0");
+//! let mut program = program.with_io(inputs, outputs);
+//!
+//! to_vm.send(41).unwrap();
+//! program.run_to_completion().unwrap();
+//! assert_eq!(from_vm.recv(), Ok(42));
+//! ```
+
+use alloc::collections::VecDeque;
+#[cfg(feature = "std")]
+use std::io::BufRead;
+#[cfg(feature = "std")]
+use std::sync::mpsc;
+
+use crate::prelude::*;
+use crate::ProgramElement;
+
+pub trait InputSource {
+    /// The next input for the program, or `None` if there isn't one available. The VM stops with
+    /// `VmError::NoInput` when this returns `None`, and asks again if it's resumed.
+    fn next_input(&mut self) -> Option<ProgramElement>;
+}
+
+pub trait OutputSink {
+    fn write_output(&mut self, value: ProgramElement);
+}
+
+impl InputSource for VecDeque<ProgramElement> {
+    fn next_input(&mut self) -> Option<ProgramElement> {
+        self.pop_front()
+    }
+}
+
+impl OutputSink for VecDeque<ProgramElement> {
+    fn write_output(&mut self, value: ProgramElement) {
+        self.push_back(value);
+    }
+}
+
+impl OutputSink for Vec<ProgramElement> {
+    fn write_output(&mut self, value: ProgramElement) {
+        self.push(value);
+    }
+}
+
+/// Blocks until a value is sent, there are no more inputs once every sender has been dropped
+#[cfg(feature = "std")]
+impl InputSource for mpsc::Receiver<ProgramElement> {
+    fn next_input(&mut self) -> Option<ProgramElement> {
+        self.recv().ok()
+    }
+}
+
+/// Outputs are dropped if the receiver has been
+#[cfg(feature = "std")]
+impl OutputSink for mpsc::Sender<ProgramElement> {
+    fn write_output(&mut self, value: ProgramElement) {
+        let _ = self.send(value);
+    }
+}
+
+/// Calls a closure each time the program reads an input, see `ProgramState::with_input_fn`
+#[derive(Clone, Debug)]
+pub struct InputFn<F>(pub F);
+
+impl<F: FnMut() -> Option<ProgramElement>> InputSource for InputFn<F> {
+    fn next_input(&mut self) -> Option<ProgramElement> {
+        (self.0)()
+    }
+}
+
+/// Calls a closure with each output as the program produces it, see `ProgramState::on_output`
+#[derive(Clone, Debug)]
+pub struct OutputFn<F>(pub F);
+
+impl<F: FnMut(ProgramElement)> OutputSink for OutputFn<F> {
+    fn write_output(&mut self, value: ProgramElement) {
+        (self.0)(value)
+    }
+}
+
+/// Reads integers separated by commas or whitespace from stdin, there are no more inputs at the
+/// end of the stream
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct StdinInput {
+    pending: VecDeque<ProgramElement>,
+}
+
+#[cfg(feature = "std")]
+impl InputSource for StdinInput {
+    fn next_input(&mut self) -> Option<ProgramElement> {
+        let stdin = std::io::stdin();
+        while self.pending.is_empty() {
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+
+            for value in line.split(|c: char| c == ',' || c.is_whitespace()).filter(|v| !v.is_empty()) {
+                match value.parse() {
+                    Ok(value) => self.pending.push_back(value),
+                    Err(_) => eprintln!("Ignoring '{}', it isn't an integer", value),
+                }
+            }
+        }
+
+        self.pending.pop_front()
+    }
+}
+
+/// Prints each output to stdout on its own line
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdoutOutput;
+
+#[cfg(feature = "std")]
+impl OutputSink for StdoutOutput {
+    fn write_output(&mut self, value: ProgramElement) {
+        println!("{}", value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ProgramState, StopReason, VmError};
+
+    /// Never has any inputs, and counts how many times it was asked
+    struct Empty(usize);
+
+    impl InputSource for Empty {
+        fn next_input(&mut self) -> Option<ProgramElement> {
+            self.0 += 1;
+            None
+        }
+    }
+
+    #[test]
+    fn test_custom_io() {
+        let program = ProgramState::load_program_str("104,1,3,0,99");
+        let mut program = program.with_io(Empty(0), Vec::new());
+
+        assert_eq!(program.run_to_next_input(), Ok(StopReason::NeedsInput));
+        assert_eq!(program.run_to_completion(), Err(VmError::NoInput));
+        assert_eq!(program.inputs.0, 2);
+        assert_eq!(program.outputs, vec![1]);
+        assert_eq!(program.program_counter, 2);
+    }
+
+    #[test]
+    fn test_input_fn() {
+        // Outputs the sum of its inputs until one of them is 0
+        let program = ProgramState::load_program_str("3,13,1,13,14,14,1005,13,0,4,14,99,0,0,0");
+        let mut next = 5;
+        let mut program = program.with_input_fn(|| {
+            next -= 1;
+            Some(next)
+        });
+
+        assert_eq!(program.run_to_completion(), Ok(StopReason::Terminated));
+        assert_eq!(program.outputs, vec![10]);
+    }
+
+    #[test]
+    fn test_output_fn() {
+        // Outputs 1, 2, 3, then reads an input
+        let program = ProgramState::load_program_str("104,1,104,2,104,3,3,0,99");
+        let mut frames = Vec::new();
+        let mut frame = Vec::new();
+        let mut program = program.on_output(|value| {
+            frame.push(value);
+            if frame.len() == 3 {
+                frames.push(core::mem::take(&mut frame));
+            }
+        });
+
+        assert_eq!(program.run_to_next_input(), Ok(StopReason::NeedsInput));
+        drop(program);
+        assert_eq!(frames, vec![vec![1, 2, 3]]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_channels_between_threads() {
+        let (to_vm, inputs) = mpsc::channel();
+        let (outputs, from_vm) = mpsc::channel();
+
+        // Doubles every input until the inputs run out
+        let program = ProgramState::load_program_str("3,11,1002,11,2,11,4,11,1105,1,0,0");
+        let mut program = program.with_io(inputs, outputs);
+        let handle = std::thread::spawn(move || program.run_to_next_input());
+
+        for i in 0..5 {
+            to_vm.send(i).unwrap();
+            assert_eq!(from_vm.recv(), Ok(i * 2));
+        }
+
+        drop(to_vm);
+        assert_eq!(handle.join().unwrap(), Ok(StopReason::NeedsInput));
+    }
+}
@@ -0,0 +1,268 @@
+//! Recording every input a VM consumes and output it produces, so that an interactive session can
+//! be replayed later to check that the program still behaves the same way.
+//!
+//! Journals have a text format, one entry per line, so they can be saved alongside a solution and
+//! replayed as a regression test:
+//!
+//! ```
+//! use intcode_vm::{journal::Journal, ProgramState};
+//!
+//! // Outputs the sum of its inputs once one of them is 0
+//! let program = ProgramState::load_program_str("3,13,1,13,14,14,1005,13,0,4,14,99,0,0,0");
+//!
+//! let mut recording = program.clone();
+//! recording.journal = Some(Journal::default());
+//! recording.inputs.extend(&[2, 3, 0]);
+//! recording.run_to_completion().unwrap();
+//!
+//! let journal = recording.journal.unwrap();
+//! assert_eq!(journal.to_string(), "0 in 2\n3 in 3\n6 in 0\n9 out 5\n");
+//!
+//! let journal = journal.to_string().parse::<Journal>().unwrap();
+//! assert_eq!(program.clone().replay(&journal), Ok(()));
+//! ```
+
+use alloc::collections::VecDeque;
+
+use crate::prelude::*;
+use crate::{OutputSink, ProgramElement, ProgramState, StopReason, VmError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JournalEntry {
+    /// `instruction` is the number of instructions that had been executed before the one that
+    /// read the input, as counted by `ProgramState::stats`
+    Input { instruction: u64, value: ProgramElement },
+
+    /// As for `Input`, but for the instruction that wrote the output
+    Output { instruction: u64, value: ProgramElement },
+}
+
+impl core::fmt::Display for JournalEntry {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            JournalEntry::Input { instruction, value } => write!(f, "{} in {}", instruction, value),
+            JournalEntry::Output { instruction, value } => write!(f, "{} out {}", instruction, value),
+        }
+    }
+}
+
+impl core::str::FromStr for JournalEntry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let words = s.split_whitespace().collect::<Vec<_>>();
+        let (instruction, kind, value) = match words.as_slice() {
+            [instruction, kind, value] => (instruction, kind, value),
+            _ => return Err(format!("Expected '<instruction> in|out <value>', got '{}'", s.trim())),
+        };
+
+        let instruction = instruction
+            .parse()
+            .map_err(|_| format!("'{}' isn't a valid instruction count", instruction))?;
+        let value = value.parse().map_err(|_| format!("'{}' isn't a valid value", value))?;
+
+        match *kind {
+            "in" => Ok(JournalEntry::Input { instruction, value }),
+            "out" => Ok(JournalEntry::Output { instruction, value }),
+            other => Err(format!("Unknown entry '{}', expected in or out", other)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Journal {
+    entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    pub(crate) fn record(&mut self, entry: JournalEntry) {
+        self.entries.push(entry);
+    }
+
+    /// Every input and output, in the order they happened
+    pub fn entries(&self) -> &[JournalEntry] {
+        &self.entries
+    }
+
+    /// The value of each input, in the order they were consumed
+    pub fn inputs(&self) -> impl Iterator<Item = ProgramElement> + '_ {
+        self.entries.iter().filter_map(|entry| match entry {
+            JournalEntry::Input { value, .. } => Some(*value),
+            JournalEntry::Output { .. } => None,
+        })
+    }
+}
+
+impl core::fmt::Display for Journal {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl core::str::FromStr for Journal {
+    type Err = String;
+
+    /// Parses one entry per line, blank lines are ignored
+    fn from_str(s: &str) -> Result<Self, String> {
+        let entries = s
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| line.parse().map_err(|e| format!("Line {}: {}", idx + 1, e)))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { entries })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayError {
+    Vm(VmError),
+
+    /// The replayed program did something different from the recorded one. Either entry is `None`
+    /// if that run stopped before getting this far.
+    Diverged {
+        index: usize,
+        expected: Option<JournalEntry>,
+        actual: Option<JournalEntry>,
+    },
+}
+
+impl core::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        fn describe(entry: &Option<JournalEntry>) -> String {
+            match entry {
+                Some(entry) => format!("'{}'", entry),
+                None => "nothing".to_string(),
+            }
+        }
+
+        match self {
+            ReplayError::Vm(e) => write!(f, "{}", e),
+            ReplayError::Diverged { index, expected, actual } => write!(
+                f,
+                "Replay diverged at entry {}, expected {} but got {}",
+                index,
+                describe(expected),
+                describe(actual),
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ReplayError {}
+
+impl<O: OutputSink> ProgramState<VecDeque<ProgramElement>, O> {
+    /// Feeds the inputs from `journal` to the program, then checks that it reads and writes
+    /// exactly what it did when the journal was recorded, at the same points. The program should
+    /// start from the same state it was recorded from, including its stats.
+    ///
+    /// Runs until the program terminates or runs out of inputs, past any breakpoints.
+    pub fn replay(&mut self, journal: &Journal) -> Result<(), ReplayError> {
+        self.inputs.extend(journal.inputs());
+
+        let previous = self.journal.replace(Journal::default());
+        let result = loop {
+            match self.run_to_next_input() {
+                Ok(StopReason::Terminated) | Ok(StopReason::NeedsInput) => break Ok(()),
+                Ok(_) => continue,
+                Err(e) => break Err(ReplayError::Vm(e)),
+            }
+        };
+        let replayed = core::mem::replace(&mut self.journal, previous).unwrap_or_default();
+        result?;
+
+        let expected = journal.entries();
+        let actual = replayed.entries();
+        match expected.iter().zip(actual).position(|(e, a)| e != a) {
+            Some(index) => Err(ReplayError::Diverged {
+                index,
+                expected: Some(expected[index]),
+                actual: Some(actual[index]),
+            }),
+            None if expected.len() != actual.len() => {
+                let index = expected.len().min(actual.len());
+                Err(ReplayError::Diverged {
+                    index,
+                    expected: expected.get(index).cloned(),
+                    actual: actual.get(index).cloned(),
+                })
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Outputs the sum of its inputs once one of them is 0
+    const SUM: &str = "3,13,1,13,14,14,1005,13,0,4,14,99,0,0,0";
+
+    fn record(program: &ProgramState, inputs: &[ProgramElement]) -> Journal {
+        let mut program = program.clone();
+        program.journal = Some(Journal::default());
+        program.inputs.extend(inputs);
+        program.run_to_completion().unwrap();
+        program.journal.unwrap()
+    }
+
+    #[test]
+    fn test_record() {
+        let program = ProgramState::load_program_str(SUM);
+        let journal = record(&program, &[4, 0]);
+        assert_eq!(
+            journal.entries(),
+            &[
+                JournalEntry::Input { instruction: 0, value: 4 },
+                JournalEntry::Input { instruction: 3, value: 0 },
+                JournalEntry::Output { instruction: 6, value: 4 },
+            ]
+        );
+        assert_eq!(journal.inputs().collect::<Vec<_>>(), vec![4, 0]);
+    }
+
+    #[test]
+    fn test_replay_diverged() {
+        let program = ProgramState::load_program_str(SUM);
+        let journal = record(&program, &[2, 3, 0]);
+
+        // Outputs the last input rather than the sum
+        let mut changed = program.clone();
+        changed.mem.write_addr(10, 13);
+        assert_eq!(
+            changed.replay(&journal),
+            Err(ReplayError::Diverged {
+                index: 3,
+                expected: Some(JournalEntry::Output { instruction: 9, value: 5 }),
+                actual: Some(JournalEntry::Output { instruction: 9, value: 0 }),
+            })
+        );
+
+        // Halts without outputting anything
+        let mut changed = program;
+        changed.mem.write_addr(9, 99);
+        assert_eq!(
+            changed.replay(&journal),
+            Err(ReplayError::Diverged {
+                index: 3,
+                expected: Some(JournalEntry::Output { instruction: 9, value: 5 }),
+                actual: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!("0 in".parse::<Journal>().is_err());
+        assert!("0 read 1".parse::<Journal>().is_err());
+        assert_eq!("0 in x".parse::<Journal>(), Err("Line 1: 'x' isn't a valid value".to_string()));
+        assert_eq!("\n0 out 1\n\n".parse::<Journal>().map(|j| j.entries().len()), Ok(1));
+    }
+}
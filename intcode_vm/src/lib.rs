@@ -1,59 +1,160 @@
+//! Without the default `std` feature only `core` and `alloc` are needed, which leaves out loading
+//! programs from files and everything that uses threads or async IO.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::collections::{BTreeSet, VecDeque};
+#[cfg(feature = "std")]
 use std::fs::File;
-use std::io::{prelude::*, BufReader};
-use std::collections::{HashMap, VecDeque};
+#[cfg(feature = "std")]
+use std::io::prelude::*;
+
+use crate::prelude::*;
+
+#[cfg(feature = "std")]
+pub mod adventure;
+pub mod analysis;
+#[cfg(feature = "std")]
+pub mod ascii;
+#[cfg(feature = "std")]
+pub mod asm;
+#[cfg(feature = "std")]
+pub mod async_io;
+pub mod backend;
+mod blocks;
+#[cfg(feature = "std")]
+pub mod builder;
+#[cfg(feature = "std")]
+pub mod camera;
+pub mod checkpoint;
+#[cfg(feature = "std")]
+pub mod chrome_trace;
+#[cfg(feature = "std")]
+pub mod cluster;
+pub mod context;
+pub mod corpus;
+pub mod coverage;
+mod decoded;
+pub mod disasm;
+#[cfg(feature = "std")]
+pub mod events;
+#[cfg(feature = "std")]
+pub mod extension;
+pub mod expr;
+#[cfg(feature = "std")]
+pub mod governor;
+pub mod history;
+#[cfg(feature = "std")]
+pub mod hook;
+pub mod io;
+pub mod journal;
+#[cfg(feature = "std")]
+pub mod minimize;
+mod memory;
+#[cfg(feature = "std")]
+pub mod mmio;
+#[cfg(feature = "std")]
+pub mod network;
+#[cfg(feature = "std")]
+pub mod optimize;
+#[cfg(feature = "std")]
+pub mod os;
+#[cfg(feature = "std")]
+pub mod parallel;
+#[cfg(feature = "std")]
+pub mod patch;
+pub mod profile;
+#[cfg(feature = "std")]
+pub mod save;
+#[cfg(feature = "std")]
+pub mod scheduler;
+#[cfg(feature = "std")]
+pub mod shared;
+pub mod snapshot;
+#[cfg(feature = "std")]
+pub mod springscript;
+mod stats;
+pub mod stepping;
+pub mod symbolic;
+pub mod symbols;
+pub mod taint;
+#[cfg(feature = "std")]
+pub mod testgen;
+#[cfg(feature = "std")]
+pub mod threaded;
+#[cfg(feature = "std")]
+pub mod trace;
+#[cfg(feature = "std")]
+pub mod transpile;
+pub mod tree;
+pub mod watch;
+
+/// What the `std` prelude has from `alloc`, for the modules that are built without `std`
+mod prelude {
+    pub use alloc::format;
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec;
+    pub use alloc::vec::Vec;
+}
 
-pub type ProgramElement = isize;
+pub use backend::Backend;
+#[cfg(feature = "std")]
+pub use builder::ProgramStateBuilder;
+pub use io::{InputSource, OutputSink};
+pub use memory::{PagedMemory, DEFAULT_PAGE_SIZE};
+pub use stats::ExecutionStats;
+pub use watch::{WatchHit, Watchpoint};
 
-enum ParameterMode {
+/// The type of each value in memory, and of inputs and outputs. It's 64 bits wide whatever the
+/// target, as some of the puzzles need values that don't fit in 32 bits, or 128 bits with the
+/// `i128` feature.
+///
+/// The feature swaps this alias rather than adding anything, so enabling it from any crate
+/// changes the type for every other crate in the same build. Only enable it when building
+/// intcode_vm on its own, see `Cargo.toml`.
+#[cfg(not(feature = "i128"))]
+pub type ProgramElement = i64;
+
+#[cfg(feature = "i128")]
+pub type ProgramElement = i128;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParameterMode {
     Position,
     Immediate,
     Relative,
 }
 
-impl From<u8> for ParameterMode {
-    fn from(code: u8) -> Self {
+impl ParameterMode {
+    pub fn try_from_code(code: u8) -> Option<Self> {
         match code {
-            0 => ParameterMode::Position,
-            1 => ParameterMode::Immediate,
-            2 => ParameterMode::Relative,
-            code => panic!("Unrecognized parameter mode code: {}", code)
+            0 => Some(ParameterMode::Position),
+            1 => Some(ParameterMode::Immediate),
+            2 => Some(ParameterMode::Relative),
+            _ => None,
         }
     }
-}
 
-struct Parameter {
-    mode: ParameterMode,
-    contents: ProgramElement,
-}
-
-impl Parameter {
-    fn read(&self, state: &ProgramState) -> ProgramElement {
-        match self.mode {
-            ParameterMode::Position => state.mem.read_addr(self.contents as usize),
-            ParameterMode::Immediate => self.contents,
-            ParameterMode::Relative => {
-                let addr = (state.relative_base + self.contents) as usize;
-                state.mem.read_addr(addr)
-            }
+    pub fn code(&self) -> u8 {
+        match self {
+            ParameterMode::Position => 0,
+            ParameterMode::Immediate => 1,
+            ParameterMode::Relative => 2,
         }
     }
+}
 
-    fn write(&self, state: &mut ProgramState, value: ProgramElement) {
-        match self.mode {
-            ParameterMode::Position => {
-                let addr = self.contents as usize;
-                state.mem.write_addr(addr, value);
-            },
-            ParameterMode::Relative => {
-                let addr = (state.relative_base + self.contents) as usize;
-                state.mem.write_addr(addr, value);
-            },
-            ParameterMode::Immediate => panic!("Attempting to write to an immediate mode parameter"),
-        }
+impl From<u8> for ParameterMode {
+    fn from(code: u8) -> Self {
+        Self::try_from_code(code)
+            .unwrap_or_else(|| panic!("Unrecognized parameter mode code: {}", code))
     }
 }
 
-enum OpCode {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpCode {
     Add,
     Multiply,
     ReadInput,
@@ -67,23 +168,74 @@ enum OpCode {
 }
 
 impl OpCode {
-    fn from_element(element: &ProgramElement) -> Self {
+    pub const ALL: [OpCode; 10] = [
+        OpCode::Add,
+        OpCode::Multiply,
+        OpCode::ReadInput,
+        OpCode::WriteOutput,
+        OpCode::JumpIfTrue,
+        OpCode::JumpIfFalse,
+        OpCode::LessThan,
+        OpCode::Equals,
+        OpCode::AdjustRelativeBase,
+        OpCode::Terminate,
+    ];
+
+    /// The value of the lowest two digits of an instruction element with this opcode
+    pub fn code(&self) -> ProgramElement {
+        match self {
+            OpCode::Add => 1,
+            OpCode::Multiply => 2,
+            OpCode::ReadInput => 3,
+            OpCode::WriteOutput => 4,
+            OpCode::JumpIfTrue => 5,
+            OpCode::JumpIfFalse => 6,
+            OpCode::LessThan => 7,
+            OpCode::Equals => 8,
+            OpCode::AdjustRelativeBase => 9,
+            OpCode::Terminate => 99,
+        }
+    }
+
+    /// Decodes the opcode from the lowest two digits of an instruction element
+    pub fn try_from_element(element: &ProgramElement) -> Option<Self> {
         match element % 100 {
-            1 => OpCode::Add,
-            2 => OpCode::Multiply,
-            3 => OpCode::ReadInput,
-            4 => OpCode::WriteOutput,
-            5 => OpCode::JumpIfTrue,
-            6 => OpCode::JumpIfFalse,
-            7 => OpCode::LessThan,
-            8 => OpCode::Equals,
-            9 => OpCode::AdjustRelativeBase,
-            99 => OpCode::Terminate,
-            code => panic!("Unrecognized opcode: {}", code)
+            1 => Some(OpCode::Add),
+            2 => Some(OpCode::Multiply),
+            3 => Some(OpCode::ReadInput),
+            4 => Some(OpCode::WriteOutput),
+            5 => Some(OpCode::JumpIfTrue),
+            6 => Some(OpCode::JumpIfFalse),
+            7 => Some(OpCode::LessThan),
+            8 => Some(OpCode::Equals),
+            9 => Some(OpCode::AdjustRelativeBase),
+            99 => Some(OpCode::Terminate),
+            _ => None,
         }
     }
 
-    fn length(&self) -> usize {
+    /// Short name used in disassembly listings
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            OpCode::Add => "add",
+            OpCode::Multiply => "mul",
+            OpCode::ReadInput => "in",
+            OpCode::WriteOutput => "out",
+            OpCode::JumpIfTrue => "jnz",
+            OpCode::JumpIfFalse => "jz",
+            OpCode::LessThan => "lt",
+            OpCode::Equals => "eq",
+            OpCode::AdjustRelativeBase => "arb",
+            OpCode::Terminate => "halt",
+        }
+    }
+
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Self> {
+        Self::ALL.iter().find(|op| op.mnemonic() == mnemonic).cloned()
+    }
+
+    /// Number of elements the instruction occupies, including the opcode itself
+    pub fn length(&self) -> usize {
         match self {
             OpCode::Add => 4,
             OpCode::Multiply => 4,
@@ -97,85 +249,351 @@ impl OpCode {
             OpCode::Terminate => 1,
         }
     }
+
+    /// The index of the parameter that this opcode writes its result to, if any
+    pub fn write_param_idx(&self) -> Option<usize> {
+        match self {
+            OpCode::Add => Some(2),
+            OpCode::Multiply => Some(2),
+            OpCode::ReadInput => Some(0),
+            OpCode::LessThan => Some(2),
+            OpCode::Equals => Some(2),
+            _ => None,
+        }
+    }
+}
+
+/// Everything that can stop a VM from executing its next instruction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VmError {
+    /// The program tried to read an input while the input queue was empty. Nothing has been
+    /// executed, so the VM can carry on once another input has been queued.
+    NoInput,
+
+    /// The instruction at `pc` doesn't have a recognized opcode
+    UnknownOpcode {
+        pc: usize,
+        instruction: ProgramElement,
+    },
+
+    /// The mode of parameter `param` of the instruction at `pc` isn't recognized
+    UnknownParameterMode {
+        pc: usize,
+        instruction: ProgramElement,
+        param: usize,
+    },
+
+    /// The instruction at `pc` tried to write its result to an immediate mode parameter
+    ImmediateWrite {
+        pc: usize,
+        instruction: ProgramElement,
+    },
+
+    /// The instruction at `pc` tried to read or write a negative address
+    NegativeAddress {
+        pc: usize,
+        addr: ProgramElement,
+    },
+
+    /// The instruction at `pc` tried to write to an address at or past the VM's memory limit, see
+    /// `ProgramState::memory_limit`
+    OutOfMemory {
+        pc: usize,
+        addr: usize,
+        limit: usize,
+    },
+}
+
+impl VmError {
+    /// Address of the instruction that failed, for every error but `NoInput`
+    pub fn pc(&self) -> Option<usize> {
+        match *self {
+            VmError::NoInput => None,
+            VmError::UnknownOpcode { pc, .. }
+            | VmError::UnknownParameterMode { pc, .. }
+            | VmError::ImmediateWrite { pc, .. }
+            | VmError::NegativeAddress { pc, .. }
+            | VmError::OutOfMemory { pc, .. } => Some(pc),
+        }
+    }
+}
+
+impl core::fmt::Display for VmError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            VmError::NoInput => write!(f, "Tried to read an input, but there were none queued"),
+            VmError::UnknownOpcode { pc, instruction } => {
+                write!(f, "Unrecognized opcode in instruction {} at address {}", instruction, pc)
+            }
+            VmError::UnknownParameterMode { pc, instruction, param } => write!(
+                f,
+                "Unrecognized mode for parameter {} of instruction {} at address {}",
+                param, instruction, pc,
+            ),
+            VmError::ImmediateWrite { pc, instruction } => write!(
+                f,
+                "Instruction {} at address {} writes to an immediate mode parameter",
+                instruction, pc,
+            ),
+            VmError::NegativeAddress { pc, addr } => {
+                write!(f, "Instruction at address {} accessed negative address {}", pc, addr)
+            }
+            VmError::OutOfMemory { pc, addr, limit } => write!(
+                f,
+                "Instruction at address {} wrote to address {}, past the memory limit of {}",
+                pc, addr, limit,
+            ),
+        }
+    }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for VmError {}
+
+/// Everything that can go wrong loading a program from its comma-separated source
 #[derive(Debug)]
-pub enum ExecuteError {
-    NoInput
+pub enum LoadError {
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+
+    /// The element at `index` (counting from 0) isn't an integer
+    InvalidElement {
+        index: usize,
+        element: String,
+    },
+
+    /// Line `line` (counting from 1) of a patch file isn't a valid patch, see `patch`
+    InvalidPatch {
+        line: usize,
+        text: String,
+    },
+
+    /// Line `line` (counting from 1) of a symbol file isn't a valid symbol, see `symbols`
+    InvalidSymbol {
+        line: usize,
+        text: String,
+    },
+}
+
+impl core::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            LoadError::Io(_) => write!(f, "Failed to read program source"),
+            LoadError::InvalidElement { index, element } => {
+                write!(f, "Element {} of the program source, '{}', isn't an integer", index, element)
+            }
+            LoadError::InvalidPatch { line, text } => {
+                write!(f, "Line {} of the patch file, '{}', isn't a valid patch", line, text)
+            }
+            LoadError::InvalidSymbol { line, text } => {
+                write!(f, "Line {} of the symbol file, '{}', isn't a valid symbol", line, text)
+            }
+        }
+    }
+}
+
+/// The source of an IO error is what went wrong reading
+#[cfg(feature = "std")]
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for LoadError {
+    fn from(e: std::io::Error) -> Self {
+        LoadError::Io(e)
+    }
+}
+
+/// Parses a comma-separated program source. Whitespace around each element is ignored, as is a
+/// trailing comma.
+pub fn parse_program(source: &str) -> Result<Vec<ProgramElement>, LoadError> {
+    let source = source.trim();
+    let source = source.strip_suffix(',').unwrap_or(source);
+    if source.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    source
+        .split(',')
+        .map(|el| el.trim())
+        .enumerate()
+        .map(|(index, el)| {
+            el.parse::<ProgramElement>().map_err(|_| LoadError::InvalidElement {
+                index,
+                element: el.to_string(),
+            })
+        })
+        .collect()
 }
 
+/// A decoded instruction, along with the raw contents of its parameters.
+///
+/// Everything is stored inline so that decoding doesn't need any per-parameter bookkeeping, and
+/// the instruction and its parameters are fetched from memory together.
+#[derive(Clone, Copy, Debug)]
 struct Instruction {
     opcode: OpCode,
-    parameters: [Option<Parameter>; 4]
+    modes: [ParameterMode; 3],
+
+    /// Raw parameter contents, only the first `opcode.length() - 1` are meaningful
+    contents: [ProgramElement; 3],
 }
 
 impl Instruction {
-    fn fetch_and_decode(state: &ProgramState) -> Self {
-        let raw_instr = state.mem.read_addr(state.program_counter);
-        let opcode = OpCode::from_element(&raw_instr);
+    fn fetch_and_decode<I: InputSource, O: OutputSink>(state: &ProgramState<I, O>) -> Result<Self, VmError> {
+        let mut elements = [0; 4];
+        state.mem.read_into(state.program_counter, &mut elements);
+        Self::decode(state, elements)
+    }
 
-        let mut parameters = [None, None, None, None];
-        let mut parameter_modes = raw_instr / 100;
+    /// As `fetch_and_decode`, but reading each element from memory separately
+    fn fetch_each_and_decode<I: InputSource, O: OutputSink>(state: &ProgramState<I, O>) -> Result<Self, VmError> {
+        let pc = state.program_counter;
+        let mut elements = [0; 4];
+        elements[0] = state.mem.read_addr(pc);
+
+        let length = OpCode::try_from_element(&elements[0]).map_or(1, |opcode| opcode.length());
+        for (i, element) in elements.iter_mut().enumerate().take(length).skip(1) {
+            *element = state.mem.read_addr(pc + i);
+        }
 
-        for i in 1..opcode.length() {
-            let mode = ((parameter_modes % 10) as u8).into();
+        Self::decode(state, elements)
+    }
+
+    /// Decodes the instruction at the program counter from its first element and the three
+    /// following it, checking that none of its parameters refer to a negative address
+    fn decode<I: InputSource, O: OutputSink>(state: &ProgramState<I, O>, elements: [ProgramElement; 4]) -> Result<Self, VmError> {
+        let instr = Self::decode_at(state.program_counter, elements)?;
+        instr.check_addresses(state)?;
+        Ok(instr)
+    }
+
+    /// As `decode`, for the instruction at `pc`, but without checking the addresses of its
+    /// parameters, since relative mode ones depend on the relative base when it executes
+    fn decode_at(pc: usize, elements: [ProgramElement; 4]) -> Result<Self, VmError> {
+        let raw_instr = elements[0];
+        let opcode = OpCode::try_from_element(&raw_instr)
+            .ok_or(VmError::UnknownOpcode { pc, instruction: raw_instr })?;
+
+        let mut modes = [ParameterMode::Position; 3];
+        let mut parameter_modes = raw_instr / 100;
+        for (param, mode) in modes.iter_mut().enumerate().take(opcode.length() - 1) {
+            *mode = ParameterMode::try_from_code((parameter_modes % 10) as u8)
+                .ok_or(VmError::UnknownParameterMode { pc, instruction: raw_instr, param })?;
             parameter_modes /= 10;
-            let contents = state.mem.read_addr(state.program_counter + i);
-            parameters[i - 1] = Some(Parameter {
-                mode,
-                contents,
-            });
         }
 
-        Self {
+        if let Some(idx) = opcode.write_param_idx() {
+            if modes[idx] == ParameterMode::Immediate {
+                return Err(VmError::ImmediateWrite {
+                    pc,
+                    instruction: raw_instr,
+                });
+            }
+        }
+
+        Ok(Self {
             opcode,
-            parameters,
+            modes,
+            contents: [elements[1], elements[2], elements[3]],
+        })
+    }
+
+    /// Checks that none of the parameters refer to a negative address, were the instruction to be
+    /// executed now at the program counter
+    fn check_addresses<I: InputSource, O: OutputSink>(&self, state: &ProgramState<I, O>) -> Result<(), VmError> {
+        for (idx, &mode) in self.modes.iter().enumerate().take(self.opcode.length() - 1) {
+            let addr = self.param_addr(idx, state);
+            if mode != ParameterMode::Immediate && addr < 0 {
+                return Err(VmError::NegativeAddress { pc: state.program_counter, addr });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The address referred to by a parameter, or just its contents for an immediate mode
+    /// parameter
+    fn param_addr<I: InputSource, O: OutputSink>(&self, idx: usize, state: &ProgramState<I, O>) -> ProgramElement {
+        match self.modes[idx] {
+            ParameterMode::Relative => state.relative_base + self.contents[idx],
+            _ => self.contents[idx],
         }
     }
 
-    fn read_param(&self, idx: usize, state: &ProgramState) -> ProgramElement {
-        self.parameters[idx].as_ref().unwrap().read(state)
+    fn read_param<I: InputSource, O: OutputSink>(&self, idx: usize, state: &ProgramState<I, O>) -> ProgramElement {
+        match self.modes[idx] {
+            ParameterMode::Immediate => self.contents[idx],
+            _ => state.load(self.param_addr(idx, state) as usize),
+        }
+    }
+
+    /// Immediate mode writes and negative addresses are rejected by `decode`, so this always has
+    /// a valid address to write to
+    fn write_param<I: InputSource, O: OutputSink>(&self, idx: usize, state: &mut ProgramState<I, O>, value: ProgramElement) {
+        let addr = self.param_addr(idx, state) as usize;
+        state.store(addr, value);
     }
 
-    fn write_param(&self, idx: usize, state: &mut ProgramState, value: ProgramElement) {
-        self.parameters[idx].as_ref().unwrap().write(state, value)
+    fn jump_target<I: InputSource, O: OutputSink>(&self, state: &ProgramState<I, O>) -> Result<usize, VmError> {
+        match self.read_param(1, state) {
+            target if target < 0 => Err(VmError::NegativeAddress {
+                pc: state.program_counter,
+                addr: target,
+            }),
+            target => Ok(target as usize),
+        }
     }
 
-    fn execute(&self, state: &mut ProgramState) -> Result<(), ExecuteError> {
-        let mut jumped = false;
+    // Has more than one caller since the backends were split out, and would stop being inlined
+    // into the fast path without this
+    #[inline(always)]
+    fn execute<I: InputSource, O: OutputSink>(&self, state: &mut ProgramState<I, O>) -> Result<StepEvent, VmError> {
+        let mut event = StepEvent::Continued;
         match self.opcode {
             OpCode::Add => {
                 let a = self.read_param(0, state);
                 let b = self.read_param(1, state);
-                self.write_param(2, state, a + b);
+                self.write_param(2, state, a.wrapping_add(b));
             }
             OpCode::Multiply => {
                 let a = self.read_param(0, state);
                 let b = self.read_param(1, state);
-                self.write_param(2, state, a * b);
+                self.write_param(2, state, a.wrapping_mul(b));
             }
             OpCode::ReadInput => {
-                let input = state.inputs
-                    .pop_front()
-                    .ok_or(ExecuteError::NoInput)?;
+                let input = state.pending_input
+                    .take()
+                    .or_else(|| state.inputs.next_input())
+                    .ok_or(VmError::NoInput)?;
 
                 self.write_param(0, state, input);
+                event = StepEvent::ConsumedInput;
+            }
+            OpCode::WriteOutput => {
+                let output = self.read_param(0, state);
+                state.outputs.write_output(output);
+                event = StepEvent::ProducedOutput(output);
             }
-            OpCode::WriteOutput => state.outputs.push_back(self.read_param(0, state)),
             OpCode::JumpIfTrue => {
                 let test = self.read_param(0, state);
                 if test != 0 {
-                    let target = self.read_param(1, state) as usize;
-                    state.program_counter = target;
-                    jumped = true;
+                    state.program_counter = self.jump_target(state)?;
+                    event = StepEvent::Jumped;
                 }
             }
             OpCode::JumpIfFalse => {
                 let test = self.read_param(0, state);
                 if test == 0 {
-                    let target = self.read_param(1, state) as usize;
-                    state.program_counter = target;
-                    jumped = true;
+                    state.program_counter = self.jump_target(state)?;
+                    event = StepEvent::Jumped;
                 }
             }
             OpCode::LessThan => {
@@ -189,170 +607,740 @@ impl Instruction {
                 self.write_param(2, state, if a == b { 1 } else { 0 });
             }
             OpCode::AdjustRelativeBase => state.relative_base += self.read_param(0, state),
-            OpCode::Terminate => state.terminated = true,
+            OpCode::Terminate => {
+                state.terminated = true;
+                event = StepEvent::Halted;
+            }
         }
 
-        if !jumped {
+        if event != StepEvent::Jumped {
             state.program_counter += self.opcode.length();
         }
 
-        Ok(())
+        state.stats.record(self.opcode);
+
+        Ok(event)
     }
 }
 
-const PAGE_SIZE: usize = 256;
-
+/// The state of a single VM. Inputs and outputs are queues by default, see `io` for the
+/// alternatives.
 #[derive(Clone)]
-pub struct PagedMemory<T: Default + Copy> {
-    /// Maps page index to storage for that page, where page index is floor(addr / PAGE_SIZE)
-    pages: HashMap<usize, [T; PAGE_SIZE]>,
+pub struct ProgramState<I = VecDeque<ProgramElement>, O = VecDeque<ProgramElement>> {
+    pub mem: PagedMemory<ProgramElement>,
+    pub inputs: I,
+    pub outputs: O,
+    pub program_counter: usize,
+    pub relative_base: ProgramElement,
+    pub terminated: bool,
+
+    /// Interpreter used to execute the program, see `Backend::set_default`
+    pub backend: Backend,
+
+    /// Addresses at which `run_to_next_input` and `run_to_completion` stop before executing the
+    /// instruction there
+    pub breakpoints: BTreeSet<usize>,
+
+    /// Memory accesses at which `run_to_next_input` and `run_to_completion` stop after executing
+    /// the instruction that made them
+    pub watchpoints: Vec<Watchpoint>,
+
+    /// Counts of how many times each instruction executes, only recorded when this is set
+    pub profile: Option<profile::Profile>,
+
+    /// Which addresses have been executed as part of an instruction, only recorded when this is
+    /// set
+    pub coverage: Option<coverage::Coverage>,
+
+    /// The most recently executed instructions, only recorded when this is set
+    pub history: Option<history::History>,
+
+    /// Every input consumed and output produced, only recorded when this is set
+    pub journal: Option<journal::Journal>,
+
+    /// Lets execution be rewound, only recorded when this is set
+    pub checkpoints: Option<checkpoint::Checkpoints<O>>,
+
+    /// Writing to this address or any past it fails with `VmError::OutOfMemory`, rather than
+    /// allocating more memory. Nothing is written and the instruction isn't executed.
+    pub memory_limit: Option<usize>,
+
+    /// Called around each instruction, see `set_pre_hook` and `set_post_hook`
+    #[cfg(feature = "std")]
+    hooks: hook::Hooks<I, O>,
+
+    /// Handlers for opcodes the interpreter doesn't recognize, see `register_opcode`
+    #[cfg(feature = "std")]
+    extensions: extension::Extensions<I, O>,
+
+    /// Devices that take the place of memory, see `map_device`
+    #[cfg(feature = "std")]
+    devices: mmio::Devices,
+
+    /// Set by `progress_state` when an instruction touches a watched address
+    watch_hit: Option<WatchHit>,
+
+    /// An input already taken for the `in` instruction about to execute, so that a pre-hook is
+    /// only called once the instruction can't fail for want of one
+    pending_input: Option<ProgramElement>,
+
+    stats: ExecutionStats,
+
+    /// Only used by `Backend::Cached`
+    decoded: decoded::DecodeCache,
+
+    /// Only used by `Backend::Blocks`
+    blocks: blocks::BlockCache,
 }
 
-impl<T: Default + Copy> PagedMemory<T> {
-    pub fn new() -> Self {
-        PagedMemory {
-            pages: HashMap::new(),
+/// What happened when `ProgramState::progress_state` executed an instruction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepEvent {
+    /// Nothing other than memory or the relative base changed, and execution moved on to the
+    /// following instruction
+    Continued,
+
+    ProducedOutput(ProgramElement),
+    ConsumedInput,
+
+    /// A conditional jump was taken
+    Jumped,
+
+    Halted,
+}
+
+/// Why a call to one of the `ProgramState::run_*` methods returned
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    Terminated,
+
+    /// The program needs an input, but there are none queued
+    NeedsInput,
+
+    /// Execution reached a breakpoint, the instruction at this address hasn't been executed yet
+    Breakpoint(usize),
+
+    /// An instruction accessed a watched address, and has finished executing
+    Watchpoint(WatchHit),
+
+    /// `run_for` executed as many instructions as it was allowed to
+    BudgetExhausted,
+
+    /// The program output a value that the predicate given to `run_until_output` accepted
+    Output(ProgramElement),
+
+    /// `step_over` or `step_out` got to where it was going
+    Stepped,
+}
+
+impl ProgramState {
+    /// Loads a comma-separated program source file, leaves the input queue empty.
+    #[cfg(feature = "std")]
+    pub fn load_program_file(path: &std::path::Path) -> Self {
+        let file = File::open(path).expect("Failed to open program source");
+        Self::from_reader(file).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Loads a comma-separated program from a string, leaves the input queue empty.
+    pub fn load_program_str(source: &str) -> Self {
+        source.parse().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Loads a comma-separated program from anything readable, leaves the input queue empty.
+    #[cfg(feature = "std")]
+    pub fn from_reader(mut reader: impl Read) -> Result<Self, LoadError> {
+        let mut source = String::new();
+        reader.read_to_string(&mut source)?;
+        source.parse()
+    }
+
+    pub fn new(mem: impl IntoIterator<Item=ProgramElement>, inputs: VecDeque<ProgramElement>) -> Self {
+        Self {
+            mem: mem.into(),
+            inputs,
+            outputs: VecDeque::new(),
+            program_counter: 0,
+            relative_base: 0,
+            terminated: false,
+            backend: Backend::current_default(),
+            breakpoints: BTreeSet::new(),
+            watchpoints: Vec::new(),
+            profile: None,
+            coverage: None,
+            history: None,
+            journal: None,
+            checkpoints: None,
+            memory_limit: None,
+            #[cfg(feature = "std")]
+            hooks: hook::Hooks::default(),
+            #[cfg(feature = "std")]
+            extensions: extension::Extensions::default(),
+            #[cfg(feature = "std")]
+            devices: mmio::Devices::default(),
+            watch_hit: None,
+            pending_input: None,
+            stats: ExecutionStats::default(),
+            decoded: decoded::DecodeCache::default(),
+            blocks: blocks::BlockCache::default(),
         }
     }
+}
 
-    pub fn read_addr(&self, addr: usize) -> T {
-        let index = addr / PAGE_SIZE;
-        let offset = addr % PAGE_SIZE;
-        match self.pages.get(&index) {
-            Some(page) => page[offset],
-            None => T::default(),
+impl<I, O> ProgramState<I, O> {
+    /// Reads `addr` for an instruction's parameter, from the device mapped over it if there is one
+    #[inline(always)]
+    pub(crate) fn load(&self, addr: usize) -> ProgramElement {
+        #[cfg(feature = "std")]
+        if !self.devices.is_empty() {
+            if let Some(value) = self.devices.read(addr) {
+                return value;
+            }
         }
+
+        self.mem.read_addr(addr)
     }
 
-    pub fn write_addr(&mut self, addr: usize, value: T) {
-        let index = addr / PAGE_SIZE;
-        let offset = addr % PAGE_SIZE;
+    /// Writes `addr` for an instruction's parameter, to the device mapped over it if there is one
+    #[inline(always)]
+    pub(crate) fn store(&mut self, addr: usize, value: ProgramElement) {
+        #[cfg(feature = "std")]
+        if !self.devices.is_empty() && self.devices.write(addr, value) {
+            return;
+        }
 
-        let page = self.pages.entry(index).or_insert([T::default(); PAGE_SIZE]);
-        page[offset] = value;
+        self.mem.write_addr(addr, value);
     }
-}
 
-impl<T> std::fmt::Debug for PagedMemory<T>
-where
-    T: Default + Copy + std::fmt::Debug + std::fmt::Display + PartialEq
-{
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        writeln!(f, "PagedMemory {{")?;
-        let mut keys: Vec<_> = self.pages.keys().collect();
-        keys.sort();
-        for (&&index, page) in keys.iter().map(|k| (k, self.pages.get(k).unwrap())) {
-            let start_addr = index * PAGE_SIZE;
-            let end_addr = (index + 1) * PAGE_SIZE - 1;
-            writeln!(f, "  Page {} (0x{:06x}..0x{:06x})", index, start_addr, end_addr)?;
-
-            let row_len = 16;
-            for row in (0..(PAGE_SIZE / row_len)).map(|r| r * row_len) {
-                if page[row..(row + row_len)].iter().all(|v| *v == T::default()) {
-                    continue;
-                }
-                write!(f, "    0x{:06x}: ", start_addr + row)?;
-                for col in 0..row_len {
-                    write!(f, "{:5} ", page[row + col])?;
-                }
+    /// Replaces where the program's inputs come from and its outputs go, keeping everything else
+    pub fn with_io<I2, O2>(self, inputs: I2, outputs: O2) -> ProgramState<I2, O2> {
+        self.map_io(|_, _| (inputs, outputs))
+    }
 
-                write!(f, "\n")?;
-            }
+    /// Stores the memory allocated so far, eg the program image, contiguously so that accessing it
+    /// is faster, see `PagedMemory::make_dense`
+    pub fn with_dense_memory(mut self) -> Self {
+        self.mem.make_dense();
+        self
+    }
+
+    fn map_io<I2, O2>(self, f: impl FnOnce(I, O) -> (I2, O2)) -> ProgramState<I2, O2> {
+        let (inputs, outputs) = f(self.inputs, self.outputs);
+        ProgramState {
+            mem: self.mem,
+            inputs,
+            outputs,
+            program_counter: self.program_counter,
+            relative_base: self.relative_base,
+            terminated: self.terminated,
+            backend: self.backend,
+            breakpoints: self.breakpoints,
+            watchpoints: self.watchpoints,
+            profile: self.profile,
+            coverage: self.coverage,
+            history: self.history,
+            journal: self.journal,
+            // Checkpoints hold the old outputs
+            checkpoints: None,
+            memory_limit: self.memory_limit,
+            // Hooks are given the VM with its old I/O types
+            #[cfg(feature = "std")]
+            hooks: hook::Hooks::default(),
+            #[cfg(feature = "std")]
+            extensions: extension::Extensions::default(),
+            #[cfg(feature = "std")]
+            devices: self.devices,
+            watch_hit: self.watch_hit,
+            pending_input: self.pending_input,
+            stats: self.stats,
+            decoded: self.decoded,
+            blocks: self.blocks,
         }
+    }
 
-        writeln!(f, "}}")
+    /// The memory as comma-separated source, up to the highest address that isn't 0, which loads
+    /// back into a program with the same memory
+    pub fn dump_source(&self) -> String {
+        let elements = self.mem.read_range(0, self.mem.extent());
+        elements.iter().map(|el| el.to_string()).collect::<Vec<_>>().join(",")
+    }
+
+    /// Writes `dump_source` to a file, followed by a newline
+    #[cfg(feature = "std")]
+    pub fn dump_source_file(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let mut file = File::create(path)?;
+        writeln!(file, "{}", self.dump_source())
+    }
+
+    /// Calls `input` to get each input exactly when the program reads it, rather than queueing
+    /// them up front. The program stops with `VmError::NoInput` if `input` returns `None`.
+    pub fn with_input_fn<F>(self, input: F) -> ProgramState<io::InputFn<F>, O>
+    where
+        F: FnMut() -> Option<ProgramElement>,
+    {
+        self.map_io(|_, outputs| (io::InputFn(input), outputs))
+    }
+
+    /// Calls `output` with each output as soon as the program produces it, rather than queueing
+    /// them up. Anything already in the output queue is dropped.
+    pub fn on_output<F>(self, output: F) -> ProgramState<I, io::OutputFn<F>>
+    where
+        F: FnMut(ProgramElement),
+    {
+        self.map_io(|inputs, _| (inputs, io::OutputFn(output)))
     }
 }
 
-impl<T, I> From<I> for PagedMemory<T>
-where
-    T: Default + Copy,
-    I: IntoIterator<Item = T>
-{
-    fn from(source: I) -> PagedMemory<T> {
-        let mut mem = PagedMemory::new();
-        for (addr, value) in source.into_iter().enumerate() {
-            mem.write_addr(addr, value)
+impl<I: core::hash::Hash, O: core::hash::Hash> ProgramState<I, O> {
+    /// A hash of everything that decides what the program does next: its memory, program counter,
+    /// relative base, whether it's terminated, and its input and output queues. Running the
+    /// program into the same state twice means it's stuck in a loop, eg:
+    ///
+    /// ```
+    /// use std::collections::HashSet;
+    /// use intcode_vm::ProgramState;
+    ///
+    /// // Counts down from 3, then loops forever
+    /// let mut program = ProgramState::load_program_str("1001,10,-1,10,1005,10,0,1105,1,7,3");
+    /// let mut seen = HashSet::new();
+    /// while seen.insert(program.state_hash()) {
+    ///     program.progress_state().unwrap();
+    /// }
+    /// assert_eq!(program.program_counter, 7);
+    /// ```
+    ///
+    /// Only memory that isn't 0 is hashed, so this takes time proportional to the program's size.
+    #[cfg(feature = "std")]
+    pub fn state_hash(&self) -> u64 {
+        use core::hash::{Hash, Hasher};
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.mem.hash(&mut hasher);
+        self.program_counter.hash(&mut hasher);
+        self.relative_base.hash(&mut hasher);
+        self.terminated.hash(&mut hasher);
+        self.inputs.hash(&mut hasher);
+        self.outputs.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl<O: OutputSink> ProgramState<VecDeque<ProgramElement>, O> {
+    /// Runs until the program terminates, calling `provide_input` whenever it needs an input and
+    /// there are none queued. The callback is given the outputs so far, so that it can react to
+    /// them before choosing the input. Stops with `StopReason::NeedsInput` if it returns `None`.
+    pub fn run_with_input(
+        &mut self,
+        mut provide_input: impl FnMut(&mut O) -> Option<ProgramElement>,
+    ) -> Result<StopReason, VmError> {
+        loop {
+            match self.run_to_next_input()? {
+                StopReason::NeedsInput => match provide_input(&mut self.outputs) {
+                    Some(input) => self.inputs.push_back(input),
+                    None => return Ok(StopReason::NeedsInput),
+                },
+                reason => return Ok(reason),
+            }
         }
-        mem
     }
 }
 
-impl<T: Default + Copy + PartialEq> PartialEq<Vec<T>> for PagedMemory<T> {
-    fn eq(&self, other: &Vec<T>) -> bool {
-        for (addr, value) in other.iter().enumerate() {
-            if self.read_addr(addr) != *value {
-                return false;
+impl ProgramState {
+    /// Runs a program that answers requests until it settles, ie it's waiting for input and has
+    /// stopped producing output, then takes every output from the queue.
+    ///
+    /// Each pass runs until the program needs an input. If a pass produced any output and there's
+    /// an `idle_input`, eg -1 for day 23's NICs, it's queued and another pass is run, so that the
+    /// program can finish reacting to whatever it's been sent. Without one the first pass is
+    /// always the last, as the program can't do anything else until it's given more input.
+    ///
+    /// Also returns if the program terminates, or reaches a breakpoint or watchpoint.
+    pub fn run_until_idle(&mut self, idle_input: Option<ProgramElement>) -> Result<Vec<ProgramElement>, VmError> {
+        loop {
+            let outputs = self.outputs.len();
+            match self.run_to_next_input()? {
+                StopReason::NeedsInput if self.outputs.len() != outputs => match idle_input {
+                    Some(input) => self.inputs.push_back(input),
+                    None => break,
+                },
+                _ => break,
             }
         }
 
-        true
+        Ok(self.outputs.drain(..).collect())
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct ProgramState {
-    pub mem: PagedMemory<ProgramElement>,
-    pub inputs: VecDeque<ProgramElement>,
-    pub outputs: VecDeque<ProgramElement>,
-    pub program_counter: usize,
-    pub relative_base: ProgramElement,
-    pub terminated: bool,
-}
+impl<I: InputSource, O: OutputSink> ProgramState<I, O> {
+    /// Counts of the instructions executed since the program was loaded, or since the last call to
+    /// `reset_stats`
+    pub fn stats(&self) -> &ExecutionStats {
+        &self.stats
+    }
 
-impl ProgramState {
-    /// Loads a comma-separated program source file, leaves the input queue empty.
-    pub fn load_program_file(path: &std::path::Path) -> Self {
-        let file = File::open(path).expect("Failed to open program source");
-        let reader = BufReader::new(file);
+    /// Also forgets any checkpoints, as they're keyed by instruction count
+    pub fn reset_stats(&mut self) {
+        self.stats = ExecutionStats::default();
+        if let Some(checkpoints) = &mut self.checkpoints {
+            checkpoints.clear();
+        }
+    }
+
+    /// The instruction at the program counter, which will be executed next
+    pub fn next_instruction(&self) -> disasm::DisasmLine {
+        disasm::disassemble_one(&self.mem, self.program_counter)
+    }
 
-        let initial_mem = reader
-            .split(b',')
-            .map(|el| el.expect("Failed to read bytes from file"))
-            .map(|el| String::from_utf8(el).expect("Bytes between a comma weren't UTF8"))
-            .map(|el| el.trim().to_string())
-            .map(|el| el.parse::<ProgramElement>().expect(&format!("Failed to parse {} as u64", el)))
-            .into();
+    /// Executes a single instruction, returning what it did. Nothing is executed if this returns
+    /// an error.
+    ///
+    /// While there are any watchpoints, hooks, custom opcodes, a memory limit, or profiling,
+    /// coverage, history, journalling or checkpoints are enabled, instructions are executed by a
+    /// slower path.
+    pub fn progress_state(&mut self) -> Result<StepEvent, VmError> {
+        if self.instrumented() {
+            return self.progress_state_instrumented();
+        }
 
-        Self {
-            mem: initial_mem,
-            inputs: VecDeque::new(),
-            outputs: VecDeque::new(),
-            program_counter: 0,
-            relative_base: 0,
-            terminated: false,
+        self.progress_state_uninstrumented()
+    }
+
+    #[inline(always)]
+    fn progress_state_uninstrumented(&mut self) -> Result<StepEvent, VmError> {
+        match self.backend {
+            Backend::Optimized => {
+                let instr = Instruction::fetch_and_decode(self)?;
+                instr.execute(self)
+            }
+            backend => backend.step(self),
         }
     }
 
-    pub fn new(mem: impl IntoIterator<Item=ProgramElement>, inputs: VecDeque<ProgramElement>) -> Self {
-        Self {
-            mem: mem.into(),
-            inputs,
-            outputs: VecDeque::new(),
-            program_counter: 0,
-            relative_base: 0,
-            terminated: false,
+    /// Whether anything needs recording or checking for each instruction
+    fn instrumented(&self) -> bool {
+        !self.watchpoints.is_empty()
+            || self.profile.is_some()
+            || self.coverage.is_some()
+            || self.history.is_some()
+            || self.journal.is_some()
+            || self.checkpoints.is_some()
+            || self.memory_limit.is_some()
+            || self.hooked()
+    }
+
+    /// Whether there are any hooks or custom opcode handlers
+    #[cfg(feature = "std")]
+    fn hooked(&self) -> bool {
+        !self.hooks.is_empty() || !self.extensions.is_empty()
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn hooked(&self) -> bool {
+        false
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn progress_state_instrumented(&mut self) -> Result<StepEvent, VmError> {
+        // Taken before the instruction executes, but only kept once it's succeeded, so that a
+        // step that fails doesn't leave a checkpoint behind
+        let checkpoint = self.due_checkpoint();
+        let event = self.step_instrumented()?;
+        if let Some(checkpoint) = checkpoint {
+            self.keep_checkpoint(checkpoint);
+        }
+
+        Ok(event)
+    }
+
+    fn step_instrumented(&mut self) -> Result<StepEvent, VmError> {
+        let pc = self.program_counter;
+
+        // The instruction could overwrite where it's going to write, so that has to be found
+        // beforehand, to check it against the memory limit and so that inputs can be recorded.
+        // Decoding fails in exactly the same way the step would. Custom instructions check their
+        // own writes against the limit, see `CustomInstruction::write`.
+        let instruction = self.stats.total();
+        let built_in = OpCode::try_from_element(&self.mem.read_addr(pc)).is_some();
+        let write_addr = if built_in && (self.memory_limit.is_some() || self.journal.is_some() || self.checkpoints.is_some()) {
+            let instr = Instruction::fetch_and_decode(self)?;
+            instr.opcode.write_param_idx().map(|idx| instr.param_addr(idx, self) as usize)
+        } else {
+            None
+        };
+
+        if let (Some(limit), Some(addr)) = (self.memory_limit, write_addr) {
+            if addr >= limit {
+                return Err(VmError::OutOfMemory { pc, addr, limit });
+            }
+        }
+
+        #[cfg(feature = "std")]
+        if let Some(result) = extension::step(self) {
+            return result;
+        }
+
+        // Likewise the instruction itself
+        let opcode = if self.coverage.is_some() || self.history.is_some() || self.journal.is_some() {
+            OpCode::try_from_element(&self.mem.read_addr(pc))
+        } else {
+            None
+        };
+
+        #[cfg(feature = "std")]
+        let decoded = if self.hooks.is_empty() {
+            None
+        } else {
+            let instr = Instruction::fetch_and_decode(self)?;
+
+            // Otherwise the pre-hook would be called again every time the VM is resumed without
+            // an input having been queued
+            if instr.opcode == OpCode::ReadInput && self.hooks.pre.is_some() && self.pending_input.is_none() {
+                self.pending_input = Some(self.inputs.next_input().ok_or(VmError::NoInput)?);
+            }
+
+            let parameters = (0..(instr.opcode.length() - 1))
+                .map(|idx| (instr.modes[idx], instr.contents[idx]))
+                .collect();
+            let decoded = analysis::DecodedInstruction::Op { opcode: instr.opcode, parameters };
+            hook::call(&self.hooks.pre, pc, &decoded, self);
+            Some(decoded)
+        };
+
+        // Tracking the memory an instruction accesses needs its own interpreter, regardless of
+        // the backend
+        let event = if self.watchpoints.is_empty() {
+            self.backend.step(self)?
+        } else {
+            let (event, hit) = watch::step_watched(self)?;
+            self.watch_hit = hit.or(self.watch_hit);
+            event
+        };
+
+        #[cfg(feature = "std")]
+        if let Some(decoded) = &decoded {
+            hook::call(&self.hooks.post, pc, decoded, self);
+        }
+
+        if let Some(profile) = &mut self.profile {
+            profile.record(pc);
+        }
+
+        // It executed, so the opcode must have been valid
+        if let Some(opcode) = opcode {
+            if let Some(coverage) = &mut self.coverage {
+                coverage.record(pc, opcode.length());
+            }
+            if let Some(history) = &mut self.history {
+                history.record(pc, opcode);
+            }
+        }
+
+        if let (Some(checkpoints), StepEvent::ConsumedInput, Some(addr)) = (&mut self.checkpoints, event, write_addr) {
+            checkpoints.record_input(self.mem.read_addr(addr));
+        }
+
+        if let Some(journal) = &mut self.journal {
+            match (event, write_addr) {
+                (StepEvent::ConsumedInput, Some(addr)) => {
+                    let value = self.mem.read_addr(addr);
+                    journal.record(journal::JournalEntry::Input { instruction, value });
+                }
+                (StepEvent::ProducedOutput(value), _) => {
+                    journal.record(journal::JournalEntry::Output { instruction, value });
+                }
+                _ => (),
+            }
         }
+
+        Ok(event)
+    }
+
+    /// Takes the most recent watchpoint hit from `progress_state` that hasn't already been
+    /// reported
+    pub fn take_watch_hit(&mut self) -> Option<WatchHit> {
+        self.watch_hit.take()
+    }
+
+    /// Whether a run should stop after the instruction it just executed
+    fn should_stop(&mut self) -> Option<StopReason> {
+        if let Some(hit) = self.watch_hit.take() {
+            return Some(StopReason::Watchpoint(hit));
+        }
+
+        if !self.breakpoints.is_empty() && self.breakpoints.contains(&self.program_counter) {
+            return Some(StopReason::Breakpoint(self.program_counter));
+        }
+
+        None
     }
 
-    pub fn progress_state(&mut self) -> Result<(), ExecuteError> {
-        let instr = Instruction::fetch_and_decode(self);
-        instr.execute(self)
+    /// Runs until the program terminates, needs an input when there are none queued, reaches a
+    /// breakpoint, or accesses a watched address. The first instruction is always executed, even if it's at a breakpoint, so
+    /// that calling this again carries on from where the last call stopped.
+    pub fn run_to_next_input(&mut self) -> Result<StopReason, VmError> {
+        self.run(u64::MAX)
     }
 
-    pub fn run_to_next_input(&mut self) {
+    /// As `run_to_next_input`, but executing at most `max_instructions` instructions
+    pub fn run_for(&mut self, max_instructions: u64) -> Result<StopReason, VmError> {
+        self.run(max_instructions)
+    }
+
+    /// As `run_to_next_input`, but also stopping straight after the program outputs a value that
+    /// `predicate` returns true for. The predicate sees every output in order, so can also look for
+    /// sequences of them:
+    ///
+    /// ```
+    /// use intcode_vm::{ascii, ProgramState, StopReason};
+    ///
+    /// // Outputs "a", a blank line, then 1000
+    /// let mut program = ProgramState::load_program_str("104,97,104,10,104,10,104,1000,99");
+    ///
+    /// let mut newlines = 0;
+    /// let blank_line = program.run_until_output(|value| {
+    ///     newlines = if value == 10 { newlines + 1 } else { 0 };
+    ///     newlines == 2
+    /// });
+    /// assert_eq!(blank_line, Ok(StopReason::Output(10)));
+    ///
+    /// let score = program.run_until_output(|value| !ascii::is_ascii(value));
+    /// assert_eq!(score, Ok(StopReason::Output(1000)));
+    /// ```
+    pub fn run_until_output(
+        &mut self,
+        mut predicate: impl FnMut(ProgramElement) -> bool,
+    ) -> Result<StopReason, VmError> {
         while !self.terminated {
             match self.progress_state() {
-                Ok(()) => (),
-                Err(ExecuteError::NoInput) => break,
+                Ok(StepEvent::ProducedOutput(value)) => {
+                    if predicate(value) {
+                        return Ok(StopReason::Output(value));
+                    }
+                }
+                Ok(_) => (),
+                Err(VmError::NoInput) => return Ok(StopReason::NeedsInput),
+                Err(e) => return Err(e),
             }
+
+            if let Some(reason) = self.should_stop() {
+                return Ok(reason);
+            }
+        }
+
+        Ok(StopReason::Terminated)
+    }
+
+    /// As `run_to_next_input`, except that needing an input when there are none queued is an
+    /// error
+    pub fn run_to_completion(&mut self) -> Result<StopReason, VmError> {
+        match self.run(u64::MAX)? {
+            StopReason::NeedsInput => Err(VmError::NoInput),
+            reason => Ok(reason),
         }
     }
 
-    pub fn run_to_completion(&mut self) {
-        while !self.terminated {
-            self.progress_state().expect("Hit execution error while running to completion");
+    /// Shared by the `run_*` methods, the blocks backend runs from its cache whenever nothing needs
+    /// checking after each instruction
+    fn run(&mut self, max_instructions: u64) -> Result<StopReason, VmError> {
+        if self.backend == Backend::Blocks && self.breakpoints.is_empty() && !self.instrumented() {
+            let mut blocks = core::mem::take(&mut self.blocks);
+            let result = blocks.run(self, max_instructions);
+            self.blocks = blocks;
+            return result;
+        }
+
+        // Nothing that executes can change this, so it only has to be checked once
+        let instrumented = self.instrumented();
+        for _ in 0..max_instructions {
+            if self.terminated {
+                return Ok(StopReason::Terminated);
+            }
+
+            let result = if instrumented {
+                self.progress_state_instrumented()
+            } else {
+                self.progress_state_uninstrumented()
+            };
+
+            match result {
+                Ok(_) => (),
+                Err(VmError::NoInput) => return Ok(StopReason::NeedsInput),
+                Err(e) => return Err(e),
+            }
+
+            if let Some(reason) = self.should_stop() {
+                return Ok(reason);
+            }
         }
+
+        if self.terminated {
+            Ok(StopReason::Terminated)
+        } else {
+            Ok(StopReason::BudgetExhausted)
+        }
+    }
+}
+
+impl core::str::FromStr for ProgramState {
+    type Err = LoadError;
+
+    /// Loads a comma-separated program, leaves the input queue empty.
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(parse_program(source)?, VecDeque::new()))
+    }
+}
+
+/// A short summary of where the program is up to, without any of its memory, eg
+///
+/// ```text
+/// pc=6 rb=0 inputs=1 outputs=2
+/// =>     6: 1101 1 2 3       add 1, 2, [3]
+/// ```
+impl core::fmt::Display for ProgramState {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(
+            f,
+            "pc={} rb={} inputs={} outputs={}",
+            self.program_counter,
+            self.relative_base,
+            self.inputs.len(),
+            self.outputs.len(),
+        )?;
+
+        if self.terminated {
+            write!(f, " (terminated)")
+        } else {
+            write!(f, "\n=> {}", self.next_instruction())
+        }
+    }
+}
+
+/// The registers and I/O, the instructions around the program counter, then all of memory
+impl<I: core::fmt::Debug, O: core::fmt::Debug> core::fmt::Debug for ProgramState<I, O> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        writeln!(f, "ProgramState {{")?;
+        writeln!(
+            f,
+            "  pc={} rb={} terminated={} backend={}",
+            self.program_counter, self.relative_base, self.terminated, self.backend,
+        )?;
+        writeln!(f, "  inputs: {:?}", self.inputs)?;
+        writeln!(f, "  outputs: {:?}", self.outputs)?;
+        if !self.breakpoints.is_empty() {
+            writeln!(f, "  breakpoints: {:?}", self.breakpoints)?;
+        }
+        if !self.watchpoints.is_empty() {
+            writeln!(f, "  watchpoints: {:?}", self.watchpoints)?;
+        }
+
+        writeln!(f, "  Code")?;
+        for line in disasm::window(&self.mem, self.program_counter, 5, 5) {
+            let marker = if line.addr == self.program_counter { "=>" } else { "  " };
+            writeln!(f, "  {} {}", marker, line)?;
+        }
+
+        write!(f, "  {:?}", self.mem)?;
+        writeln!(f, "}}")
     }
 }
 
@@ -360,32 +1348,32 @@ impl ProgramState {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_paged_memory() {
-        let mut mem = PagedMemory::<i32>::new();
-        assert_eq!(mem.read_addr(1234 as usize), 0);
-        mem.write_addr(1234 as usize, 42);
-        assert_eq!(mem.read_addr(1234 as usize), 42);
-    }
-
     #[test]
     fn test_add() {
         let mut program = ProgramState::new(vec![1, 0, 0, 0, 99], VecDeque::new());
-        program.run_to_completion();
+        program.run_to_completion().unwrap();
         assert_eq!(program.mem, vec![2, 0, 0, 0, 99]);
     }
 
     #[test]
     fn test_mul() {
         let mut program = ProgramState::new(vec![2, 3, 0, 3, 99], VecDeque::new());
-        program.run_to_completion();
+        program.run_to_completion().unwrap();
         assert_eq!(program.mem, vec![2, 3, 0, 6, 99]);
     }
 
+    #[test]
+    fn test_overflow_wraps() {
+        let max = ProgramElement::MAX;
+        let mut program = ProgramState::new(vec![1101, max, 1, 9, 1102, max, 2, 10, 99, 0, 0], VecDeque::new());
+        program.run_to_completion().unwrap();
+        assert_eq!(program.mem, vec![1101, max, 1, 9, 1102, max, 2, 10, 99, ProgramElement::MIN, -2]);
+    }
+
     #[test]
     fn test_nontrivial() {
         let mut program = ProgramState::new(vec![1,1,1,4,99,5,6,0,99], VecDeque::new());
-        program.run_to_completion();
+        program.run_to_completion().unwrap();
         assert_eq!(program.mem, vec![30,1,1,4,2,5,6,0,99]);
     }
 
@@ -401,12 +1389,334 @@ mod tests {
                 inputs
             );
 
-            dbg!(&program.mem);
-            program.run_to_completion();
+            program.run_to_completion().unwrap();
             program.outputs[0]
         }
 
         assert_eq!(run(0), 0);
         assert_eq!(run(4), 1);
     }
+
+    #[test]
+    fn test_immediate_write_rejected() {
+        // Add with an immediate mode output parameter
+        let mut program = ProgramState::new(vec![11101, 1, 2, 3, 99], VecDeque::new());
+        assert_eq!(
+            program.progress_state(),
+            Err(VmError::ImmediateWrite { pc: 0, instruction: 11101 })
+        );
+
+        // Nothing should have been executed
+        assert_eq!(program.mem, vec![11101, 1, 2, 3, 99]);
+        assert_eq!(program.program_counter, 0);
+    }
+
+    #[test]
+    fn test_immediate_input_rejected() {
+        let mut inputs = VecDeque::new();
+        inputs.push_back(5);
+        let mut program = ProgramState::new(vec![1101, 1, 2, 5, 103, 0, 99], inputs);
+        assert_eq!(program.progress_state(), Ok(StepEvent::Continued));
+        assert_eq!(
+            program.progress_state(),
+            Err(VmError::ImmediateWrite { pc: 4, instruction: 103 })
+        );
+
+        // The input shouldn't have been consumed
+        assert_eq!(program.inputs.len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_opcode() {
+        let mut program = ProgramState::new(vec![1101, 1, 2, 5, 42, 0], VecDeque::new());
+        assert_eq!(
+            program.run_to_completion(),
+            Err(VmError::UnknownOpcode { pc: 4, instruction: 42 })
+        );
+        assert_eq!(program.program_counter, 4);
+    }
+
+    #[test]
+    fn test_unknown_parameter_mode() {
+        let mut program = ProgramState::new(vec![3001, 0, 0, 0, 99], VecDeque::new());
+        assert_eq!(
+            program.progress_state(),
+            Err(VmError::UnknownParameterMode { pc: 0, instruction: 3001, param: 1 })
+        );
+    }
+
+    #[test]
+    fn test_negative_address() {
+        let mut inputs = VecDeque::new();
+        inputs.push_back(5);
+
+        // Read an input into the address relative_base - 3
+        let mut program = ProgramState::new(vec![203, -3, 99], inputs);
+        assert_eq!(program.progress_state(), Err(VmError::NegativeAddress { pc: 0, addr: -3 }));
+        assert_eq!(program.inputs.len(), 1);
+
+        let mut program = ProgramState::new(vec![1105, 1, -1], VecDeque::new());
+        assert_eq!(program.progress_state(), Err(VmError::NegativeAddress { pc: 0, addr: -1 }));
+    }
+
+    #[test]
+    fn test_run_to_completion_without_input() {
+        let mut program = ProgramState::new(vec![3, 0, 99], VecDeque::new());
+        assert_eq!(program.run_to_next_input(), Ok(StopReason::NeedsInput));
+        assert_eq!(program.run_to_completion(), Err(VmError::NoInput));
+
+        program.inputs.push_back(1);
+        assert_eq!(program.run_to_completion(), Ok(StopReason::Terminated));
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        // Outputs 0, 1, 2, ... forever
+        let mut program = ProgramState::load_program_str("4,9,1001,9,1,9,1105,1,0,0");
+        program.breakpoints.insert(6);
+
+        assert_eq!(program.run_to_completion(), Ok(StopReason::Breakpoint(6)));
+        assert_eq!(program.outputs, vec![0]);
+
+        // Resumes past the breakpoint it stopped at
+        assert_eq!(program.run_to_next_input(), Ok(StopReason::Breakpoint(6)));
+        assert_eq!(program.outputs, vec![0, 1]);
+
+        program.breakpoints.insert(0);
+        assert_eq!(program.run_to_completion(), Ok(StopReason::Breakpoint(0)));
+        assert_eq!(program.run_to_completion(), Ok(StopReason::Breakpoint(6)));
+        assert_eq!(program.outputs, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_step_events() {
+        let mut program = ProgramState::load_program_str("3,9,1005,9,6,99,104,-2,99,0");
+        program.inputs.push_back(1);
+
+        let mut events = Vec::new();
+        while !program.terminated {
+            events.push(program.progress_state().unwrap());
+        }
+
+        assert_eq!(
+            events,
+            vec![StepEvent::ConsumedInput, StepEvent::Jumped, StepEvent::ProducedOutput(-2), StepEvent::Halted],
+        );
+
+        // An untaken jump just continues
+        let mut program = ProgramState::load_program_str("1105,0,7,99");
+        assert_eq!(program.progress_state(), Ok(StepEvent::Continued));
+        assert_eq!(program.program_counter, 3);
+    }
+
+    #[test]
+    fn test_run_for() {
+        // Outputs 0, 1, 2, ... forever
+        let mut program = ProgramState::load_program_str("4,9,1001,9,1,9,1105,1,0,0");
+        assert_eq!(program.run_for(7), Ok(StopReason::BudgetExhausted));
+        assert_eq!(program.outputs, vec![0, 1, 2]);
+        assert_eq!(program.stats().total(), 7);
+
+        assert_eq!(program.run_for(0), Ok(StopReason::BudgetExhausted));
+        assert_eq!(program.stats().total(), 7);
+
+        let mut program = ProgramState::load_program_str("104,1,3,0,99");
+        assert_eq!(program.run_for(10), Ok(StopReason::NeedsInput));
+        program.inputs.push_back(5);
+        assert_eq!(program.run_for(2), Ok(StopReason::Terminated));
+        assert_eq!(program.run_for(2), Ok(StopReason::Terminated));
+    }
+
+    #[test]
+    fn test_parse_program() {
+        assert_eq!(parse_program(" 1, -2,3,\n").unwrap(), vec![1, -2, 3]);
+        assert_eq!(parse_program("").unwrap(), vec![]);
+        assert!(matches!(
+            parse_program("1,2,x,4"),
+            Err(LoadError::InvalidElement { index: 2, ref element }) if element == "x"
+        ));
+        assert!(parse_program("1,,2").is_err());
+
+        #[cfg(feature = "std")]
+        {
+            let program = ProgramState::from_reader("104,5,99\n".as_bytes()).unwrap();
+            assert_eq!(program.mem, vec![104, 5, 99]);
+        }
+        assert!("1,2,three".parse::<ProgramState>().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "i128")]
+    fn test_wide_elements() {
+        let program = format!("1102,{0},{0},7,4,7,99,0", 1i128 << 40);
+        let mut program = ProgramState::load_program_str(&program);
+        program.run_to_completion().unwrap();
+        assert_eq!(program.outputs, vec![1 << 80]);
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        // Writes to address 99, then to 1000
+        let mut program = ProgramState::load_program_str("1101,1,1,99,1101,2,2,1000,99");
+        program.memory_limit = Some(100);
+        assert_eq!(
+            program.run_to_completion(),
+            Err(VmError::OutOfMemory { pc: 4, addr: 1000, limit: 100 })
+        );
+        assert_eq!(program.mem.read_addr(99), 2);
+        assert_eq!(program.mem.extent(), 100);
+
+        program.memory_limit = None;
+        assert_eq!(program.run_to_completion(), Ok(StopReason::Terminated));
+        assert_eq!(program.mem.read_addr(1000), 4);
+    }
+
+    #[test]
+    fn test_run_until_output() {
+        // Outputs 1, 2, 3, then waits for an input
+        let mut program = ProgramState::load_program_str("104,1,104,2,104,3,3,0,99");
+        assert_eq!(program.run_until_output(|v| v == 2), Ok(StopReason::Output(2)));
+        assert_eq!(program.outputs, vec![1, 2]);
+        assert_eq!(program.program_counter, 4);
+
+        assert_eq!(program.run_until_output(|v| v == 2), Ok(StopReason::NeedsInput));
+        program.inputs.push_back(0);
+        assert_eq!(program.run_until_output(|_| true), Ok(StopReason::Terminated));
+    }
+
+    #[test]
+    fn test_dump_source() {
+        let mut program = ProgramState::load_program_str("1,0,0,0,99,0,0");
+        program.mem.write_addr(1, 4);
+        assert_eq!(program.dump_source(), "1,4,0,0,99");
+
+        program.run_to_completion().unwrap();
+        let dumped = ProgramState::load_program_str(&program.dump_source());
+        assert_eq!(dumped.mem, vec![100, 4, 0, 0, 99]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_state_hash() {
+        let program = ProgramState::load_program_str("1101,2,3,5,99,0");
+        let mut dense = program.clone().with_dense_memory();
+        assert_eq!(program.state_hash(), dense.state_hash());
+
+        dense.progress_state().unwrap();
+        assert_ne!(program.state_hash(), dense.state_hash());
+
+        let mut queued = program.clone();
+        queued.inputs.push_back(0);
+        assert_ne!(program.state_hash(), queued.state_hash());
+
+        // Writing 0 to unallocated memory allocates a page, but doesn't change anything
+        let mut written = program.clone();
+        written.mem.write_addr(10_000, 0);
+        assert_eq!(program.state_hash(), written.state_hash());
+    }
+
+    #[test]
+    fn test_display() {
+        let mut program = ProgramState::load_program_str("104,1,104,2,1101,1,2,3,99");
+        program.inputs.push_back(5);
+        program.run_for(2).unwrap();
+        assert_eq!(
+            program.to_string(),
+            "pc=4 rb=0 inputs=1 outputs=2\n=>     4: 1101 1 2 3       add 1, 2, [3]"
+        );
+
+        program.run_to_completion().unwrap();
+        assert_eq!(program.to_string(), "pc=9 rb=0 inputs=1 outputs=2 (terminated)");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_errors() {
+        use std::error::Error;
+
+        let mut program = ProgramState::load_program_str("104,1,42");
+        let error = program.run_to_completion().unwrap_err();
+        assert_eq!(error.pc(), Some(2));
+        assert_eq!(VmError::NoInput.pc(), None);
+
+        let io = std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "truncated");
+        let error = LoadError::from(io);
+        assert_eq!(error.to_string(), "Failed to read program source");
+        assert_eq!(error.source().unwrap().to_string(), "truncated");
+        assert!(parse_program("1,x").unwrap_err().source().is_none());
+    }
+
+    #[test]
+    fn test_debug() {
+        let mut program = ProgramState::load_program_str("104,1,104,2,1101,1,2,3,99");
+        program.inputs.push_back(5);
+        program.run_for(2).unwrap();
+
+        let expected = [
+            "ProgramState {",
+            "  pc=4 rb=0 terminated=false backend=optimized",
+            "  inputs: [5]",
+            "  outputs: [1, 2]",
+            "  Code",
+            "         0: 104 1            out 1",
+            "         2: 104 2            out 2",
+            "  =>     4: 1101 1 2 3       add 1, 2, [3]",
+            "         8: 99               halt",
+            "         9: 0                data 0",
+        ];
+        let debug = format!("{:?}", program);
+        assert!(debug.starts_with(&(expected.join("\n") + "\n")), "{}", debug);
+        assert!(debug.contains("    0x000000:   104     1   104     2  1101"));
+    }
+
+    #[test]
+    fn test_run_with_input() {
+        // Outputs each input doubled until one of them is 0
+        let mut program = ProgramState::load_program_str("3,12,1002,12,2,13,4,13,1005,12,0,99,0,0");
+        let mut seen = Vec::new();
+        let result = program.run_with_input(|outputs| {
+            seen.extend(outputs.drain(..));
+            Some(3 - seen.len() as ProgramElement)
+        });
+
+        assert_eq!(result, Ok(StopReason::Terminated));
+        assert_eq!(seen, vec![6, 4, 2]);
+        assert_eq!(program.outputs, vec![0]);
+
+        let mut program = ProgramState::load_program_str("3,0,99");
+        assert_eq!(program.run_with_input(|_| None), Ok(StopReason::NeedsInput));
+    }
+
+    #[test]
+    fn test_run_until_idle() {
+        // Reads a count, then outputs that many 7s, one for each input after it
+        //   0: in [20]
+        //   2: in [21]
+        //   4: jz [20], 0
+        //   7: out 7
+        //   9: add [20], -1, [20]
+        //  13: jz 0, 2
+        let source = "3,20,3,21,1006,20,0,104,7,1001,20,-1,20,1106,0,2";
+        let mut program = ProgramState::load_program_str(source);
+        program.inputs.extend(&[3, -1]);
+        assert_eq!(program.run_until_idle(None), Ok(vec![7]));
+
+        // The idle input is only given after a pass with output, and it's left waiting for a count
+        program.inputs.push_back(-1);
+        assert_eq!(program.run_until_idle(Some(-1)), Ok(vec![7, 7]));
+        assert_eq!(program.inputs, vec![]);
+        assert_eq!(program.program_counter, 0);
+        assert_eq!(program.run_until_idle(Some(-1)), Ok(vec![]));
+
+        let mut program = ProgramState::load_program_str("104,1,99");
+        assert_eq!(program.run_until_idle(Some(-1)), Ok(vec![1]));
+    }
+
+    #[test]
+    fn test_immediate_non_write_params_allowed() {
+        // Immediate mode is fine for parameters that are only read
+        let mut program = ProgramState::new(vec![1105, 1, 4, 99, 104, 7, 99], VecDeque::new());
+        program.run_to_completion().unwrap();
+        assert_eq!(program.outputs, vec![7]);
+    }
 }
@@ -0,0 +1,566 @@
+use alloc::sync::Arc;
+use core::hash::{Hash, Hasher};
+use core::ops::Range;
+use core::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "std")]
+use core::hash::BuildHasherDefault;
+
+use crate::prelude::*;
+
+/// Number of values in each page, unless a `PagedMemory` is given a different size
+pub const DEFAULT_PAGE_SIZE: usize = 256;
+
+/// Page indices are small and there are few of them, so they don't need the DoS resistance of the
+/// default hasher, which dominates the cost of a memory access otherwise.
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub(crate) struct PageIndexHasher(u64);
+
+#[cfg(feature = "std")]
+impl Hasher for PageIndexHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_u64(byte as u64);
+        }
+    }
+
+    fn write_usize(&mut self, index: usize) {
+        self.write_u64(index as u64);
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        // Fibonacci hashing, spreads consecutive indices over the high bits hashbrown uses
+        self.0 = (self.0 ^ value).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    }
+}
+
+/// A map keyed by page index, or anything else as small, eg the block cache's addresses. Without
+/// `std` there's no `HashMap`, but a `BTreeMap` is nearly as quick for so few keys.
+#[cfg(feature = "std")]
+pub(crate) type PageMap<V> = std::collections::HashMap<usize, V, BuildHasherDefault<PageIndexHasher>>;
+
+#[cfg(not(feature = "std"))]
+pub(crate) type PageMap<V> = alloc::collections::BTreeMap<usize, V>;
+
+/// Source of `PagedMemory::id`
+static NEXT_MEMORY_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Memory which only allocates the parts of the address space that are used, in pages of
+/// `PAGE_SIZE` values.
+///
+/// A prefix of memory starting from address 0 can be stored contiguously instead, see `dense` and
+/// `make_dense`. Programs spend most of their time reading and writing their own image, where this
+/// avoids a hash lookup per access.
+///
+/// Cloning memory only copies pointers to its storage, which is shared until one of the copies
+/// writes to it. Each page is then copied separately, so a clone that only writes to a few pages
+/// only costs those pages, while the dense prefix is copied as a whole.
+pub struct PagedMemory<T: Default + Copy, const PAGE_SIZE: usize = DEFAULT_PAGE_SIZE> {
+    /// Values of every address below `dense.len()`
+    dense: Arc<Vec<T>>,
+
+    /// Maps page index to storage for that page, where page index is floor(addr / PAGE_SIZE).
+    /// Addresses in the dense prefix are never looked up here.
+    pages: PageMap<Arc<[T; PAGE_SIZE]>>,
+
+    /// Unique to each memory, including copies, see `version`
+    id: u64,
+
+    /// Number of times anything has been written
+    writes: u64,
+}
+
+impl<T: Default + Copy> PagedMemory<T> {
+    /// Empty memory with the default page size, use `default` for any other size
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores `values` contiguously from address 0, only paging the addresses past them
+    pub fn dense(values: impl IntoIterator<Item = T>) -> Self {
+        PagedMemory {
+            dense: Arc::new(values.into_iter().collect()),
+            ..Self::default()
+        }
+    }
+}
+
+impl<T: Default + Copy, const PAGE_SIZE: usize> PagedMemory<T, PAGE_SIZE> {
+    /// Number of addresses, starting from 0, which are stored contiguously
+    pub fn dense_len(&self) -> usize {
+        self.dense.len()
+    }
+
+    /// Moves every allocated page that follows on from the dense prefix into it, eg the pages
+    /// holding a freshly loaded program
+    pub fn make_dense(&mut self) {
+        loop {
+            let start = self.dense.len();
+            match self.pages.remove(&(start / PAGE_SIZE)) {
+                Some(page) => Arc::make_mut(&mut self.dense).extend_from_slice(&page[(start % PAGE_SIZE)..]),
+                None => break,
+            }
+        }
+    }
+
+    /// Changes whenever the memory is written to, and is never the same for two different
+    /// memories, so that anything worked out from the contents can tell when it's out of date
+    pub(crate) fn version(&self) -> (u64, u64) {
+        (self.id, self.writes)
+    }
+
+    pub fn read_addr(&self, addr: usize) -> T {
+        if let Some(&value) = self.dense.get(addr) {
+            return value;
+        }
+
+        let index = addr / PAGE_SIZE;
+        let offset = addr % PAGE_SIZE;
+        match self.pages.get(&index) {
+            Some(page) => page[offset],
+            None => T::default(),
+        }
+    }
+
+    /// Fills `buf` with the values starting at `start_addr`, looking up each page only once
+    pub fn read_into(&self, start_addr: usize, buf: &mut [T]) {
+        let dense_len = self.dense.len().saturating_sub(start_addr).min(buf.len());
+        let (dense_chunk, mut remaining) = buf.split_at_mut(dense_len);
+        if dense_len > 0 {
+            dense_chunk.copy_from_slice(&self.dense[start_addr..(start_addr + dense_len)]);
+        }
+
+        let mut addr = start_addr + dense_len;
+        while !remaining.is_empty() {
+            let offset = addr % PAGE_SIZE;
+            let len = remaining.len().min(PAGE_SIZE - offset);
+            let (chunk, rest) = remaining.split_at_mut(len);
+
+            match self.pages.get(&(addr / PAGE_SIZE)) {
+                Some(page) => chunk.copy_from_slice(&page[offset..(offset + len)]),
+                None => chunk.fill(T::default()),
+            }
+
+            addr += len;
+            remaining = rest;
+        }
+    }
+
+    /// The `len` values starting at `addr`, see `read_into`
+    pub fn read_range(&self, addr: usize, len: usize) -> Vec<T> {
+        let mut values = vec![T::default(); len];
+        self.read_into(addr, &mut values);
+        values
+    }
+
+    pub fn write_addr(&mut self, addr: usize, value: T) {
+        *self.entry(addr) = value;
+    }
+
+    /// Mutable reference to the value at the given address, allocating its page if required
+    pub fn entry(&mut self, addr: usize) -> &mut T {
+        if addr < self.dense.len() {
+            self.writes += 1;
+            return &mut Arc::make_mut(&mut self.dense)[addr];
+        }
+
+        let index = addr / PAGE_SIZE;
+        let offset = addr % PAGE_SIZE;
+        &mut self.page_mut(index)[offset]
+    }
+
+    /// Writes a contiguous run of values starting at `start_addr`, looking up each page only once
+    pub fn write_range(&mut self, start_addr: usize, values: &[T]) {
+        let dense_len = self.dense.len().saturating_sub(start_addr).min(values.len());
+        let (dense_chunk, mut remaining) = values.split_at(dense_len);
+        if dense_len > 0 {
+            self.writes += 1;
+            Arc::make_mut(&mut self.dense)[start_addr..(start_addr + dense_len)].copy_from_slice(dense_chunk);
+        }
+
+        let mut addr = start_addr + dense_len;
+        while !remaining.is_empty() {
+            let offset = addr % PAGE_SIZE;
+            let len = remaining.len().min(PAGE_SIZE - offset);
+            let (chunk, rest) = remaining.split_at(len);
+
+            self.page_mut(addr / PAGE_SIZE)[offset..(offset + len)].copy_from_slice(chunk);
+
+            addr += len;
+            remaining = rest;
+        }
+    }
+
+    /// Every address holding anything other than the default value, along with the value, in
+    /// address order
+    pub fn iter_nonzero(&self) -> impl Iterator<Item = (usize, T)> + '_
+    where
+        T: PartialEq,
+    {
+        let mut indices = self.pages.keys().copied().collect::<Vec<_>>();
+        indices.sort_unstable();
+
+        let dense_len = self.dense.len();
+        let pages = indices.into_iter().flat_map(move |index| {
+            let start = index * PAGE_SIZE;
+            self.pages[&index]
+                .iter()
+                .enumerate()
+                .map(move |(offset, &value)| (start + offset, value))
+                // Addresses covered by the dense prefix are never read from pages
+                .skip_while(move |&(addr, _)| addr < dense_len)
+        });
+
+        self.dense
+            .iter()
+            .copied()
+            .enumerate()
+            .chain(pages)
+            .filter(|(_, value)| *value != T::default())
+    }
+
+    /// One past the highest address holding anything other than the default value, 0 if there
+    /// isn't one
+    pub fn extent(&self) -> usize
+    where
+        T: PartialEq,
+    {
+        self.iter_nonzero().last().map_or(0, |(addr, _)| addr + 1)
+    }
+
+    /// Every address at which `other` holds a different value, as `(addr, ours, theirs)` in
+    /// address order. Storage that's still shared with `other`, eg since one was cloned from the
+    /// other, is skipped without comparing it.
+    pub fn diff(&self, other: &Self) -> Vec<(usize, T, T)>
+    where
+        T: PartialEq,
+    {
+        let mut changes = Vec::new();
+        let mut compare = |addrs: Range<usize>| {
+            for addr in addrs {
+                let (ours, theirs) = (self.read_addr(addr), other.read_addr(addr));
+                if ours != theirs {
+                    changes.push((addr, ours, theirs));
+                }
+            }
+        };
+
+        let dense_len = self.dense.len().max(other.dense.len());
+        if !Arc::ptr_eq(&self.dense, &other.dense) {
+            compare(0..dense_len);
+        }
+
+        let mut indices = self.pages.keys().chain(other.pages.keys()).copied().collect::<Vec<_>>();
+        indices.sort_unstable();
+        indices.dedup();
+        for index in indices {
+            let shared = match (self.pages.get(&index), other.pages.get(&index)) {
+                (Some(ours), Some(theirs)) => Arc::ptr_eq(ours, theirs),
+                _ => false,
+            };
+
+            if !shared {
+                let start = (index * PAGE_SIZE).max(dense_len);
+                compare(start..((index + 1) * PAGE_SIZE).max(start));
+            }
+        }
+
+        changes
+    }
+
+    /// Mutable access to a page, allocating it if required or copying it if it's shared
+    fn page_mut(&mut self, index: usize) -> &mut [T; PAGE_SIZE] {
+        self.writes += 1;
+        let page = self.pages
+            .entry(index)
+            .or_insert_with(|| Arc::new([T::default(); PAGE_SIZE]));
+        Arc::make_mut(page)
+    }
+}
+
+impl<T: Default + Copy, const PAGE_SIZE: usize> Clone for PagedMemory<T, PAGE_SIZE> {
+    fn clone(&self) -> Self {
+        PagedMemory {
+            dense: self.dense.clone(),
+            pages: self.pages.clone(),
+            ..Self::default()
+        }
+    }
+}
+
+impl<T: Default + Copy, const PAGE_SIZE: usize> Default for PagedMemory<T, PAGE_SIZE> {
+    fn default() -> Self {
+        PagedMemory {
+            dense: Arc::new(Vec::new()),
+            pages: PageMap::default(),
+            id: NEXT_MEMORY_ID.fetch_add(1, Ordering::Relaxed),
+            writes: 0,
+        }
+    }
+}
+
+impl<T, const PAGE_SIZE: usize> core::fmt::Debug for PagedMemory<T, PAGE_SIZE>
+where
+    T: Default + Copy + core::fmt::Debug + core::fmt::Display + PartialEq
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        writeln!(f, "PagedMemory {{")?;
+        if !self.dense.is_empty() {
+            writeln!(f, "  Dense (0x{:06x}..0x{:06x})", 0, self.dense.len() - 1)?;
+            write_rows(f, 0, &self.dense)?;
+        }
+
+        let mut keys: Vec<_> = self.pages.keys().collect();
+        keys.sort();
+        for (&&index, page) in keys.iter().map(|k| (k, self.pages.get(k).unwrap())) {
+            let start_addr = index * PAGE_SIZE;
+            let end_addr = (index + 1) * PAGE_SIZE - 1;
+            writeln!(f, "  Page {} (0x{:06x}..0x{:06x})", index, start_addr, end_addr)?;
+            write_rows(f, start_addr, &page[..])?;
+        }
+
+        writeln!(f, "}}")
+    }
+}
+
+/// Writes the rows of 16 values starting at `start_addr`, skipping any that are entirely default
+fn write_rows<T>(f: &mut core::fmt::Formatter, start_addr: usize, values: &[T]) -> core::fmt::Result
+where
+    T: Default + core::fmt::Display + PartialEq
+{
+    let row_len = 16;
+    for (row, chunk) in values.chunks(row_len).enumerate() {
+        if chunk.iter().all(|v| *v == T::default()) {
+            continue;
+        }
+        write!(f, "    0x{:06x}: ", start_addr + row * row_len)?;
+        for value in chunk {
+            write!(f, "{:5} ", value)?;
+        }
+
+        writeln!(f)?;
+    }
+
+    Ok(())
+}
+
+impl<T, I> From<I> for PagedMemory<T>
+where
+    T: Default + Copy,
+    I: IntoIterator<Item = T>
+{
+    fn from(source: I) -> PagedMemory<T> {
+        let values = source.into_iter().collect::<Vec<_>>();
+        let mut mem = PagedMemory::new();
+        mem.write_range(0, &values);
+        mem
+    }
+}
+
+impl<T, const PAGE_SIZE: usize> PartialEq<Vec<T>> for PagedMemory<T, PAGE_SIZE>
+where
+    T: Default + Copy + PartialEq
+{
+    fn eq(&self, other: &Vec<T>) -> bool {
+        for (addr, value) in other.iter().enumerate() {
+            if self.read_addr(addr) != *value {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Hashes the contents, so that memories which compare equal hash the same however they're stored
+impl<T, const PAGE_SIZE: usize> Hash for PagedMemory<T, PAGE_SIZE>
+where
+    T: Default + Copy + PartialEq + Hash
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for (addr, value) in self.iter_nonzero() {
+            addr.hash(state);
+            value.hash(state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paged_memory() {
+        let mut mem = PagedMemory::<i32>::new();
+        assert_eq!(mem.read_addr(1234 as usize), 0);
+        mem.write_addr(1234 as usize, 42);
+        assert_eq!(mem.read_addr(1234 as usize), 42);
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut mem = PagedMemory::<i32>::new();
+        *mem.entry(1000) += 5;
+        *mem.entry(1000) *= 3;
+        assert_eq!(mem.read_addr(1000), 15);
+        assert_eq!(mem.pages.len(), 1);
+    }
+
+    #[test]
+    fn test_write_range() {
+        // Spans a partial page either side of two whole pages
+        let start = DEFAULT_PAGE_SIZE - 10;
+        let values = (1..=(DEFAULT_PAGE_SIZE as i32 * 2 + 20)).collect::<Vec<_>>();
+
+        let mut mem = PagedMemory::new();
+        mem.write_range(start, &values);
+
+        assert_eq!(mem.pages.len(), 4);
+        assert_eq!(mem.read_addr(start - 1), 0);
+        for (i, value) in values.iter().enumerate() {
+            assert_eq!(mem.read_addr(start + i), *value);
+        }
+        assert_eq!(mem.read_addr(start + values.len()), 0);
+    }
+
+    #[test]
+    fn test_read_into() {
+        let mut mem = PagedMemory::new();
+        mem.write_range(DEFAULT_PAGE_SIZE - 2, &[1, 2, 3, 4]);
+
+        let mut buf = [9; 6];
+        mem.read_into(DEFAULT_PAGE_SIZE - 3, &mut buf);
+        assert_eq!(buf, [0, 1, 2, 3, 4, 0]);
+
+        // Entirely unallocated pages read as zero
+        mem.read_into(DEFAULT_PAGE_SIZE * 10, &mut buf);
+        assert_eq!(buf, [0; 6]);
+
+        assert_eq!(mem.read_range(DEFAULT_PAGE_SIZE - 1, 4), vec![2, 3, 4, 0]);
+        assert_eq!(mem.read_range(0, 0), vec![]);
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let mem = PagedMemory::from(0..10_000);
+        assert_eq!(mem.pages.len(), 10_000usize.div_ceil(DEFAULT_PAGE_SIZE));
+        assert_eq!(mem, (0..10_000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_page_size() {
+        let mut mem = PagedMemory::<i32, 16>::default();
+        mem.write_range(10, &[1; 20]);
+        assert_eq!(mem.pages.len(), 2);
+        assert_eq!(mem.read_addr(29), 1);
+        assert_eq!(mem.read_addr(30), 0);
+    }
+
+    #[test]
+    fn test_dense() {
+        let mut mem = PagedMemory::<i32>::dense(vec![1, 2, 3, 4]);
+        mem.write_range(2, &[5, 6, 7]);
+        mem.write_addr(DEFAULT_PAGE_SIZE + 1, 8);
+        assert_eq!(mem.dense_len(), 4);
+        assert_eq!(mem.pages.len(), 2);
+
+        let mut buf = [9; 6];
+        mem.read_into(1, &mut buf);
+        assert_eq!(buf, [2, 5, 6, 7, 0, 0]);
+        assert_eq!(mem.read_addr(DEFAULT_PAGE_SIZE + 1), 8);
+    }
+
+    #[test]
+    fn test_make_dense() {
+        let mut mem = PagedMemory::<i32>::from(1..=300);
+        mem.write_addr(DEFAULT_PAGE_SIZE * 10, 5);
+        let version = mem.version();
+
+        mem.make_dense();
+        assert_eq!(mem.dense_len(), DEFAULT_PAGE_SIZE * 2);
+        assert_eq!(mem.pages.len(), 1);
+        assert_eq!(mem, (1..=300).collect::<Vec<_>>());
+        assert_eq!(mem.read_addr(DEFAULT_PAGE_SIZE * 10), 5);
+
+        // Moving values between storage doesn't count as writing to them
+        assert_eq!(mem.version(), version);
+        mem.write_addr(0, 0);
+        assert_ne!(mem.version(), version);
+    }
+
+    #[test]
+    fn test_extent() {
+        let mut mem = PagedMemory::<i32>::new();
+        assert_eq!(mem.extent(), 0);
+
+        mem.write_addr(1000, 1);
+        mem.write_addr(2000, 0);
+        assert_eq!(mem.extent(), 1001);
+
+        let mut mem = PagedMemory::<i32>::dense(vec![1, 2, 0, 0]);
+        assert_eq!(mem.extent(), 2);
+        mem.write_addr(5, 3);
+        assert_eq!(mem.extent(), 6);
+    }
+
+    #[test]
+    fn test_iter_nonzero() {
+        let mut mem = PagedMemory::<i32>::dense(vec![0, 1, 0, 2]);
+        mem.write_addr(DEFAULT_PAGE_SIZE * 3, 4);
+        mem.write_addr(DEFAULT_PAGE_SIZE + 1, 3);
+        mem.write_addr(DEFAULT_PAGE_SIZE * 2, 0);
+
+        assert_eq!(
+            mem.iter_nonzero().collect::<Vec<_>>(),
+            vec![(1, 1), (3, 2), (DEFAULT_PAGE_SIZE + 1, 3), (DEFAULT_PAGE_SIZE * 3, 4)]
+        );
+        assert_eq!(PagedMemory::<i32>::new().iter_nonzero().count(), 0);
+    }
+
+    #[test]
+    fn test_clone_copies_on_write() {
+        let original = PagedMemory::from(0..1000);
+        let mut copy = original.clone();
+        copy.write_addr(5, -1);
+
+        assert_eq!(original.read_addr(5), 5);
+        assert_eq!(copy.read_addr(5), -1);
+
+        // Only the page that was written to has been copied
+        let shared = original.pages
+            .iter()
+            .filter(|(index, page)| Arc::ptr_eq(page, &copy.pages[index]))
+            .count();
+        assert_eq!(shared, original.pages.len() - 1);
+    }
+
+    #[test]
+    fn test_clone_dense_copies_on_write() {
+        let original = PagedMemory::dense(vec![1, 2, 3]);
+        let mut copy = original.clone();
+        *copy.entry(0) += 10;
+
+        assert_eq!(original, vec![1, 2, 3]);
+        assert_eq!(copy, vec![11, 2, 3]);
+    }
+
+    #[test]
+    fn test_diff() {
+        let original = PagedMemory::dense(vec![1, 2, 3]);
+        let mut copy = original.clone();
+        copy.write_addr(1, 5);
+        copy.write_addr(DEFAULT_PAGE_SIZE * 3, 7);
+
+        assert_eq!(original.diff(&copy), vec![(1, 2, 5), (DEFAULT_PAGE_SIZE * 3, 0, 7)]);
+        assert_eq!(copy.diff(&original), vec![(1, 5, 2), (DEFAULT_PAGE_SIZE * 3, 7, 0)]);
+        assert!(original.diff(&original.clone()).is_empty());
+
+        // Only part of the address space is dense in one of them
+        let paged = PagedMemory::from(vec![1, 0, 3, 4]);
+        assert_eq!(original.diff(&paged), vec![(1, 2, 0), (3, 0, 4)]);
+    }
+}
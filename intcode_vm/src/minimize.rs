@@ -0,0 +1,168 @@
+//! Shrinking a program down to the parts that some set of runs actually use, eg to make a small
+//! reproducer for a VM bug, or a disassembly listing that leaves out everything irrelevant.
+//!
+//! Each run is recorded as the addresses it executed and the addresses it read data from.
+//! Everything else is replaced with 0s, and any trailing 0s are dropped:
+//!
+//! ```
+//! use intcode_vm::minimize::{minimize, Usage};
+//! use intcode_vm::ProgramState;
+//!
+//! // Outputs its input if it's nonzero, otherwise outputs 7
+//! let mut program = ProgramState::load_program_str("3,11,1005,11,8,104,7,99,4,11,99,0");
+//! program.inputs.push_back(5);
+//! let (usage, result) = Usage::record(program.clone());
+//! assert!(result.is_ok());
+//!
+//! let source = program.mem.read_range(0, 12);
+//! let minimized = minimize(&source, &[usage]);
+//! assert_eq!(minimized.program, vec![3, 11, 1005, 11, 8, 0, 0, 0, 4, 11, 99]);
+//! println!("{}", minimized.reduction);
+//! ```
+//!
+//! The minimized program only behaves the same as the original for the recorded runs. Anything
+//! else, eg a different input that takes another branch, can run into the 0s.
+
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+
+use crate::analysis::DecodedInstruction;
+use crate::coverage::Coverage;
+use crate::{OpCode, ParameterMode, ProgramElement, ProgramState, StopReason, VmError};
+
+/// The addresses that a single run of a program depended on
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// Addresses executed as part of an instruction
+    pub executed: Coverage,
+
+    /// Addresses read by position or relative mode parameters
+    pub read: BTreeSet<usize>,
+}
+
+impl Usage {
+    /// Runs `state` to completion, recording what it uses. Any coverage or hooks it already had
+    /// are replaced.
+    pub fn record(mut state: ProgramState) -> (Self, Result<StopReason, VmError>) {
+        let read = Arc::new(Mutex::new(BTreeSet::new()));
+        let recorded = read.clone();
+        state.set_pre_hook(move |_, instruction, state| {
+            if let DecodedInstruction::Op { opcode, parameters } = instruction {
+                recorded.lock().unwrap().extend(reads(*opcode, parameters, state.relative_base));
+            }
+        });
+        state.coverage = Some(Coverage::default());
+
+        let result = state.run_to_completion();
+        state.clear_hooks();
+
+        let read = std::mem::take(&mut *read.lock().unwrap());
+        let usage = Usage { executed: state.coverage.take().unwrap_or_default(), read };
+        (usage, result)
+    }
+
+    pub fn is_used(&self, addr: usize) -> bool {
+        self.executed.is_covered(addr) || self.read.contains(&addr)
+    }
+}
+
+/// Addresses an instruction reads through its parameters, given the relative base before it runs
+fn reads(
+    opcode: OpCode,
+    parameters: &[(ParameterMode, ProgramElement)],
+    relative_base: ProgramElement,
+) -> impl Iterator<Item = usize> + '_ {
+    parameters
+        .iter()
+        .enumerate()
+        .filter(move |&(idx, _)| opcode.write_param_idx() != Some(idx))
+        .filter_map(move |(_, &(mode, value))| match mode {
+            ParameterMode::Position => Some(value),
+            ParameterMode::Relative => Some(relative_base + value),
+            ParameterMode::Immediate => None,
+        })
+        .map(|addr| addr as usize)
+}
+
+/// How much smaller `minimize` made a program
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reduction {
+    pub original_len: usize,
+    pub len: usize,
+
+    /// Elements that weren't already 0, but were replaced with 0, including any trimmed off
+    pub zeroed: usize,
+}
+
+impl std::fmt::Display for Reduction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Zeroed {} elements, and trimmed the program from {} to {} elements",
+            self.zeroed, self.original_len, self.len,
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Minimized {
+    pub program: Vec<ProgramElement>,
+    pub reduction: Reduction,
+}
+
+/// Replaces every element of `program` that none of `usages` used with 0, see the module docs
+pub fn minimize(program: &[ProgramElement], usages: &[Usage]) -> Minimized {
+    let mut minimized = program
+        .iter()
+        .enumerate()
+        .map(|(addr, &value)| if usages.iter().any(|u| u.is_used(addr)) { value } else { 0 })
+        .collect::<Vec<_>>();
+
+    let zeroed = program.iter().zip(&minimized).filter(|(a, b)| a != b).count();
+    let len = minimized.iter().rposition(|&value| value != 0).map_or(0, |idx| idx + 1);
+    minimized.truncate(len);
+
+    Minimized {
+        program: minimized,
+        reduction: Reduction { original_len: program.len(), len, zeroed },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_program;
+
+    #[test]
+    fn test_minimize() {
+        // Reads its input, then outputs the value relative to it from the table at 13
+        //   0: in [12]
+        //   2: arb [12]
+        //   4: out [rb + 13]
+        //   6: jz 0, 11
+        //   9: out 5
+        //  11: halt
+        //  12: data
+        //  13: table
+        let source = parse_program("3,12,9,12,204,13,1106,0,11,104,5,99,0,41,42,43").unwrap();
+        let program = ProgramState::new(source.iter().cloned(), vec![1].into());
+        let (usage, result) = Usage::record(program.clone());
+        assert_eq!(result, Ok(StopReason::Terminated));
+        assert_eq!(usage.read, vec![12, 14].into_iter().collect());
+
+        let mut other = program.clone();
+        other.inputs = vec![2].into();
+        let usages = [usage, Usage::record(other).0];
+
+        let minimized = minimize(&source, &usages[..1]);
+        assert_eq!(minimized.program, vec![3, 12, 9, 12, 204, 13, 1106, 0, 11, 0, 0, 99, 0, 0, 42]);
+        assert_eq!(minimized.reduction, Reduction { original_len: 16, len: 15, zeroed: 4 });
+
+        let mut rerun = ProgramState::new(minimized.program, vec![1].into());
+        rerun.run_to_completion().unwrap();
+        assert_eq!(rerun.outputs, vec![42]);
+
+        // Another run keeps what that uses as well
+        assert_eq!(minimize(&source, &usages).program.len(), 16);
+    }
+}
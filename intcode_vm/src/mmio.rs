@@ -0,0 +1,181 @@
+//! Memory-mapped devices, which take the place of memory over a range of addresses.
+//!
+//! Whenever an instruction reads or writes a mapped address through one of its parameters, the
+//! device is called instead of the VM's memory being touched:
+//!
+//! ```
+//! use std::sync::{Arc, Mutex};
+//! use intcode_vm::{mmio, ProgramState};
+//!
+//! // A clock register at 1000 counting how often it's been read, and a one-address screen at 1001
+//! let mut ticks = 0;
+//! let screen = Arc::new(Mutex::new(Vec::new()));
+//! let pixels = screen.clone();
+//!
+//! let mut program = ProgramState::load_program_str("1001,1000,0,1001,1,1000,1000,1001,4,1000,99");
+//! program.map_device(1000..1001, mmio::callbacks(move |_| { ticks += 1; ticks }, |_, _| ()));
+//! program.map_device(1001..1002, mmio::callbacks(|_| 0, move |_, value| pixels.lock().unwrap().push(value)));
+//!
+//! program.run_to_completion().unwrap();
+//! assert_eq!(*screen.lock().unwrap(), vec![1, 5]);
+//! assert_eq!(program.outputs, vec![4]);
+//! ```
+//!
+//! Instructions are always fetched from memory, even at mapped addresses, and anything that looks
+//! at memory directly, eg watchpoints or `dump_source`, sees what's stored there rather than the
+//! device. Devices are shared between a VM and its clones.
+
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use crate::{ProgramElement, ProgramState};
+
+pub trait Device: Send {
+    /// Called when an instruction reads the address `offset` past the start of the device's range
+    fn read(&mut self, offset: usize) -> ProgramElement;
+
+    /// Called when an instruction writes `value` to the address `offset` past the start of the
+    /// device's range
+    fn write(&mut self, offset: usize, value: ProgramElement);
+}
+
+/// A device made of a callback for reads and another for writes
+pub fn callbacks<R, W>(read: R, write: W) -> impl Device
+where
+    R: FnMut(usize) -> ProgramElement + Send,
+    W: FnMut(usize, ProgramElement) + Send,
+{
+    struct Callbacks<R, W>(R, W);
+
+    impl<R, W> Device for Callbacks<R, W>
+    where
+        R: FnMut(usize) -> ProgramElement + Send,
+        W: FnMut(usize, ProgramElement) + Send,
+    {
+        fn read(&mut self, offset: usize) -> ProgramElement {
+            (self.0)(offset)
+        }
+
+        fn write(&mut self, offset: usize, value: ProgramElement) {
+            (self.1)(offset, value)
+        }
+    }
+
+    Callbacks(read, write)
+}
+
+#[derive(Clone)]
+struct Mapping {
+    addrs: Range<usize>,
+    device: Arc<Mutex<dyn Device>>,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Devices {
+    mapped: Vec<Mapping>,
+}
+
+impl Devices {
+    #[inline(always)]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.mapped.is_empty()
+    }
+
+    /// Calls `f` with the device mapped over `addr` and how far into its range `addr` is, if there
+    /// is one
+    fn with_device<T>(&self, addr: usize, f: impl FnOnce(&mut dyn Device, usize) -> T) -> Option<T> {
+        let mapping = self.mapped.iter().find(|mapping| mapping.addrs.contains(&addr))?;
+        let mut device = mapping.device.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        Some(f(&mut *device, addr - mapping.addrs.start))
+    }
+
+    pub(crate) fn read(&self, addr: usize) -> Option<ProgramElement> {
+        self.with_device(addr, |device, offset| device.read(offset))
+    }
+
+    /// Returns false without doing anything if there's no device at `addr`
+    pub(crate) fn write(&self, addr: usize, value: ProgramElement) -> bool {
+        self.with_device(addr, |device, offset| device.write(offset, value)).is_some()
+    }
+}
+
+impl std::fmt::Debug for Devices {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_list().entries(self.mapped.iter().map(|mapping| &mapping.addrs)).finish()
+    }
+}
+
+impl<I, O> ProgramState<I, O> {
+    /// Sends reads and writes of `addrs` to `device` rather than memory, see the module docs.
+    ///
+    /// Panics if `addrs` overlaps a device that's already mapped.
+    pub fn map_device(&mut self, addrs: Range<usize>, device: impl Device + 'static) {
+        let overlaps = self.devices.mapped.iter().any(|m| m.addrs.start < addrs.end && addrs.start < m.addrs.end);
+        assert!(!overlaps, "Addresses {:?} overlap a device that's already mapped", addrs);
+        self.devices.mapped.push(Mapping { addrs, device: Arc::new(Mutex::new(device)) });
+    }
+
+    pub fn unmap_devices(&mut self) {
+        self.devices = Devices::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, StopReason};
+
+    /// Remembers every write, and reads back the offset
+    #[derive(Default)]
+    struct Recorder(Arc<Mutex<Vec<(usize, ProgramElement)>>>);
+
+    impl Device for Recorder {
+        fn read(&mut self, offset: usize) -> ProgramElement {
+            offset as ProgramElement
+        }
+
+        fn write(&mut self, offset: usize, value: ProgramElement) {
+            self.0.lock().unwrap().push((offset, value));
+        }
+    }
+
+    #[test]
+    fn test_devices() {
+        // Copies from 100 onwards to 200 onwards until it reaches a 3, using the relative base
+        //   0: arb 100
+        //   2: add [rb+0], 0, [rb+100]
+        //   6: arb 1
+        //   8: eq [rb+0], 3, [20]
+        //  12: jz [20], 2
+        //  15: out [102]
+        //  17: out [201]
+        //  19: halt
+        //  20: data
+        let source = "109,100,21201,0,0,100,109,1,1208,0,3,20,1006,20,2,4,102,4,201,99,0";
+        for &backend in Backend::ALL {
+            let mut program = ProgramState::load_program_str(source);
+            program.backend = backend;
+            program.mem.write_addr(102, 7);
+
+            let writes = Arc::new(Mutex::new(Vec::new()));
+            program.map_device(100..104, callbacks(|offset| offset as ProgramElement, |_, _| ()));
+            program.map_device(200..210, Recorder(writes.clone()));
+
+            assert_eq!(program.run_to_completion(), Ok(StopReason::Terminated));
+            assert_eq!(*writes.lock().unwrap(), vec![(0, 0), (1, 1), (2, 2)]);
+            assert_eq!(program.outputs, vec![2, 1]);
+
+            // Nothing was written to memory, or read from it
+            assert_eq!(program.mem.read_addr(200), 0);
+            assert_eq!(program.mem.read_addr(102), 7);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_overlapping_devices() {
+        let mut program = ProgramState::load_program_str("99");
+        program.map_device(10..20, Recorder::default());
+        program.map_device(19..21, Recorder::default());
+    }
+}
@@ -0,0 +1,324 @@
+//! A network of VMs that send each other packets, as used by the day 23 NICs.
+//!
+//! Each NIC is booted with its address as its first input. It sends a packet by outputting the
+//! destination address followed by the packet's X and Y values, and receives one by reading its X
+//! and Y values. A NIC that tries to read when it has no packets waiting reads -1 instead.
+//!
+//! Packets sent to address 255 go to the NAT, which keeps only the most recent one. Once the
+//! network has been idle for long enough, the NAT sends that packet to address 0 to wake it up.
+//!
+//! The NICs are run by a `scheduler::Scheduler`, which delivers packets between them and hands
+//! back any others for the NAT.
+
+use std::convert::TryFrom;
+
+use crate::chrome_trace::ChromeTrace;
+use crate::scheduler::{packets, Message, Scheduler, SchedulerError};
+use crate::{ProgramElement, ProgramState, VmError};
+
+/// Address of the NAT
+pub const NAT_ADDRESS: ProgramElement = 255;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Packet {
+    pub dest: ProgramElement,
+    pub x: ProgramElement,
+    pub y: ProgramElement,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetworkEvent {
+    /// A NIC sent a packet, either to another NIC or to the NAT
+    Sent { from: usize, packet: Packet },
+
+    /// The network was idle, so the NAT sent its most recent packet on to address 0
+    NatWake(Packet),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetworkError {
+    Nic { address: usize, error: VmError },
+
+    /// A NIC sent a packet to an address that doesn't exist
+    UnknownDestination { from: usize, packet: Packet },
+
+    /// Every NIC has terminated, so nothing else can happen
+    AllTerminated,
+}
+
+impl From<SchedulerError> for NetworkError {
+    fn from(error: SchedulerError) -> Self {
+        match error {
+            SchedulerError::Vm { vm, error } => NetworkError::Nic { address: vm, error },
+            SchedulerError::AllTerminated => NetworkError::AllTerminated,
+        }
+    }
+}
+
+impl std::fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            NetworkError::Nic { address, .. } => write!(f, "NIC {} failed", address),
+            NetworkError::UnknownDestination { from, packet } => write!(
+                f,
+                "NIC {} sent a packet to unknown address {}",
+                from, packet.dest,
+            ),
+            NetworkError::AllTerminated => write!(f, "Every NIC has terminated"),
+        }
+    }
+}
+
+/// The source of a NIC failing is the error it hit
+impl std::error::Error for NetworkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NetworkError::Nic { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+pub struct Network {
+    scheduler: Scheduler,
+
+    /// The most recent packet sent to the NAT
+    nat: Option<Packet>,
+
+    /// Number of rounds in a row in which every NIC was waiting for a packet and none were sent
+    idle_rounds: usize,
+
+    /// How many idle rounds in a row it takes for the NAT to wake address 0
+    pub idle_threshold: usize,
+}
+
+impl Network {
+    /// Boots `size` copies of `program`, each with its address queued as its first input
+    pub fn new(program: &ProgramState, size: usize) -> Self {
+        let nics = (0..size)
+            .map(|address| {
+                let mut nic = program.clone();
+                nic.inputs.push_back(address as ProgramElement);
+                nic
+            })
+            .collect();
+
+        let mut scheduler = Scheduler::new(nics, packets(2));
+        scheduler.idle_input = Some(-1);
+
+        Self {
+            scheduler,
+            nat: None,
+            idle_rounds: 0,
+            idle_threshold: 2,
+        }
+    }
+
+    /// Starts recording every instruction each NIC executes, with a track per NIC that every
+    /// packet it sends is marked on, and one for the NAT's wakes, replacing any trace already
+    /// being recorded
+    pub fn start_trace(&mut self) {
+        let mut trace = ChromeTrace::new();
+        for address in 0..self.scheduler.vms.len() {
+            trace.name_track(address, format!("NIC {}", address));
+        }
+        trace.name_track(NAT_ADDRESS as usize, "NAT");
+        self.scheduler.trace = Some(trace);
+    }
+
+    /// Stops recording, returning what was recorded since `start_trace`
+    pub fn take_trace(&mut self) -> Option<ChromeTrace> {
+        self.scheduler.trace.take()
+    }
+
+    pub fn nics(&self) -> &[ProgramState] {
+        &self.scheduler.vms
+    }
+
+    /// The most recent packet sent to the NAT, if there has been one
+    pub fn nat_packet(&self) -> Option<Packet> {
+        self.nat
+    }
+
+    fn deliver(&mut self, from: usize, packet: Packet) -> Result<(), NetworkError> {
+        if packet.dest == NAT_ADDRESS {
+            self.nat = Some(packet);
+            return Ok(());
+        }
+
+        let nic = usize::try_from(packet.dest)
+            .ok()
+            .and_then(|dest| self.scheduler.vms.get_mut(dest))
+            .ok_or(NetworkError::UnknownDestination { from, packet })?;
+
+        nic.inputs.extend(&[packet.x, packet.y]);
+        Ok(())
+    }
+
+    /// Marks the trace if one is being recorded, only building the name if it is
+    fn mark(&mut self, tid: usize, name: impl FnOnce() -> String) {
+        if let Some(trace) = &mut self.scheduler.trace {
+            trace.instant(tid, name());
+        }
+    }
+
+    /// Gives each NIC in turn the chance to run until it needs another input, delivering packets
+    /// between NICs as soon as they're sent. Packets for the NAT are then passed on to it, and it
+    /// wakes address 0 if the network has gone idle.
+    pub fn round(&mut self) -> Result<Vec<NetworkEvent>, NetworkError> {
+        let round = self.scheduler.round()?;
+
+        let mut events = Vec::new();
+        for (from, message) in round.sent {
+            let packet = to_packet(&message);
+            if message.to >= self.scheduler.vms.len() {
+                self.deliver(from, packet)?;
+            }
+            events.push(NetworkEvent::Sent { from, packet });
+        }
+
+        self.idle_rounds = if round.idle { self.idle_rounds + 1 } else { 0 };
+        if self.idle_rounds >= self.idle_threshold {
+            if let Some(packet) = self.nat {
+                let packet = Packet { dest: 0, ..packet };
+                self.mark(NAT_ADDRESS as usize, || format!("wake 0 with ({}, {})", packet.x, packet.y));
+                self.deliver(NAT_ADDRESS as usize, packet)?;
+                events.push(NetworkEvent::NatWake(packet));
+                self.idle_rounds = 0;
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Runs rounds until `f` returns something for one of the events
+    pub fn run_until<T>(&mut self, mut f: impl FnMut(&NetworkEvent) -> Option<T>) -> Result<T, NetworkError> {
+        loop {
+            for event in self.round()? {
+                if let Some(result) = f(&event) {
+                    return Ok(result);
+                }
+            }
+        }
+    }
+}
+
+/// Packets sent to a negative address are given `usize::MAX` by the router, which is put back as
+/// -1 here
+fn to_packet(message: &Message) -> Packet {
+    Packet {
+        dest: ProgramElement::try_from(message.to).unwrap_or(-1),
+        x: message.values[0],
+        y: message.values[1],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::asm;
+
+    /// NIC 0 starts by sending (0, 7) to NIC 1. Every NIC passes each packet it receives on to
+    /// the next address, or to the NAT from NIC 1, with its X value incremented.
+    const NIC: &str = "
+            in [addr]
+            jnz [addr], loop
+            out 1
+            out 0
+            out 7
+        loop:
+            in [x]
+            eq [x], -1, [tmp]
+            jnz [tmp], loop
+            in [y]
+            jnz [addr], to_nat
+            out 1
+            jz 0, send
+        to_nat:
+            out 255
+        send:
+            add [x], 1, [x]
+            out [x]
+            out [y]
+            jz 0, loop
+        addr: data 0
+        x: data 0
+        y: data 0
+        tmp: data 0
+    ";
+
+    fn network() -> Network {
+        let program = ProgramState::new(asm::assemble(NIC).unwrap(), VecDeque::new());
+        Network::new(&program, 2)
+    }
+
+    #[test]
+    fn test_first_nat_packet() {
+        let mut network = network();
+        let packet = network.run_until(|event| match event {
+            NetworkEvent::Sent { packet, .. } if packet.dest == NAT_ADDRESS => Some(*packet),
+            _ => None,
+        });
+        assert_eq!(packet, Ok(Packet { dest: 255, x: 1, y: 7 }));
+    }
+
+    #[test]
+    fn test_nat_wakes_idle_network() {
+        let mut network = network();
+        let mut wakes = Vec::new();
+        let repeated_y = network.run_until(|event| match event {
+            NetworkEvent::NatWake(packet) => {
+                wakes.push(*packet);
+                match wakes.as_slice() {
+                    [.., a, b] if a.y == b.y => Some(b.y),
+                    _ => None,
+                }
+            }
+            _ => None,
+        });
+
+        assert_eq!(repeated_y, Ok(7));
+        assert_eq!(wakes, vec![Packet { dest: 0, x: 1, y: 7 }, Packet { dest: 0, x: 3, y: 7 }]);
+    }
+
+    #[test]
+    fn test_trace() {
+        let mut network = network();
+        network.start_trace();
+        network.round().unwrap();
+        let json = network.take_trace().unwrap().to_json();
+
+        assert!(json.contains(r#""tid":1,"args":{"name":"NIC 1"}"#));
+        assert!(json.contains(r#"{"name":"send [0, 7] to 1","cat":"event","ph":"i","s":"t","ts":5,"pid":0,"tid":0}"#));
+
+        network.round().unwrap();
+        assert_eq!(network.take_trace(), None);
+
+        network.start_trace();
+        network.run_until(|event| matches!(event, NetworkEvent::NatWake(_)).then_some(())).unwrap();
+        assert!(network.take_trace().unwrap().to_json().contains(r#""name":"wake 0 with (1, 7)""#));
+    }
+
+    #[test]
+    fn test_nic_error() {
+        use std::error::Error;
+
+        let program = ProgramState::load_program_str("3,0,42");
+        let error = Network::new(&program, 1).round().unwrap_err();
+        assert_eq!(error.to_string(), "NIC 0 failed");
+        assert_eq!(error.source().unwrap().to_string(), "Unrecognized opcode in instruction 42 at address 2");
+    }
+
+    #[test]
+    fn test_unknown_destination() {
+        // Sends a packet to address 2, when there are only 2 NICs
+        let program = ProgramState::load_program_str("104,2,104,0,104,0,3,0,99");
+        let mut network = Network::new(&program, 2);
+        assert_eq!(
+            network.round(),
+            Err(NetworkError::UnknownDestination { from: 0, packet: Packet { dest: 2, x: 0, y: 0 } })
+        );
+    }
+}
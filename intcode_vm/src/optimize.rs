@@ -0,0 +1,217 @@
+//! Rewriting programs into simpler ones that behave the same, as far as can be proved statically.
+//!
+//! Constant folding evaluates additions and multiplications whose operands can't change while
+//! the program runs, leaving an addition of the result and 0 in their place. The program keeps
+//! its layout, so every address means the same thing, but listings show the value directly:
+//!
+//! ```
+//! use intcode_vm::optimize::{fold_constants, Fold, FoldOptions};
+//! use intcode_vm::OpCode;
+//!
+//! // Outputs the product of the values at 9 and 10
+//! let mut program = vec![2, 9, 10, 11, 4, 11, 99, 0, 0, 6, 7, 0];
+//! let folds = fold_constants(&mut program, &FoldOptions::default());
+//!
+//! assert_eq!(folds, vec![Fold { addr: 0, opcode: OpCode::Multiply, value: 42 }]);
+//! assert_eq!(program[..4], [1101, 42, 0, 11]);
+//! ```
+//!
+//! Only the program as given is considered, so changing it before it runs, eg day 2's noun and
+//! verb, can make folds wrong.
+
+use std::collections::BTreeSet;
+
+use crate::analysis::{self, DecodedInstruction};
+use crate::{OpCode, ParameterMode, ProgramElement};
+
+#[derive(Clone, Debug, Default)]
+pub struct FoldOptions {
+    /// Assume that relative mode parameters never access the program image, which holds when the
+    /// relative base only ever points at a stack past its end, as in day 9's programs onwards.
+    /// Otherwise any relative mode parameter stops anything being folded.
+    pub trust_relative_mode: bool,
+}
+
+/// An instruction that was folded, and the value it now writes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fold {
+    pub addr: usize,
+
+    /// What the instruction was before it was folded
+    pub opcode: OpCode,
+
+    pub value: ProgramElement,
+}
+
+/// Folds every addition and multiplication that's reachable from address 0 and provably constant,
+/// see the module docs. An operand is constant if it's immediate, or is in position mode and read
+/// from somewhere in the program that nothing could write to. Instructions that the program
+/// reads as data aren't folded, and nothing is if the program rewrites a position mode parameter,
+/// as that instruction could then write anywhere.
+pub fn fold_constants(program: &mut [ProgramElement], options: &FoldOptions) -> Vec<Fold> {
+    let graph = analysis::control_flow_graph(program);
+    let reachable = graph
+        .blocks
+        .values()
+        .flat_map(|block| {
+            let program = &*program;
+            std::iter::successors(Some(block.start), move |&addr| {
+                Some(addr + DecodedInstruction::decode(program, addr).length()).filter(|&next| next < block.end)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Where a jump could go to at runtime could be any instruction at all, including one that's
+    // only there if the program is decoded from an unusual offset
+    let may_execute = if graph.indirect_jumps().is_empty() {
+        reachable.clone()
+    } else {
+        (0..program.len()).collect()
+    };
+
+    // Every address that could be written to or read from as data
+    let mut written = BTreeSet::new();
+    let mut read = BTreeSet::new();
+    for &addr in &may_execute {
+        let (opcode, parameters) = match DecodedInstruction::decode(program, addr) {
+            DecodedInstruction::Op { opcode, parameters } => (opcode, parameters),
+            DecodedInstruction::Data(_) => continue,
+        };
+        for (idx, &(mode, value)) in parameters.iter().enumerate() {
+            let accessed = if opcode.write_param_idx() == Some(idx) { &mut written } else { &mut read };
+            match mode {
+                ParameterMode::Position => {
+                    accessed.insert(value);
+                }
+                ParameterMode::Relative if !options.trust_relative_mode => return Vec::new(),
+                _ => (),
+            }
+        }
+    }
+
+    // A position mode parameter that's itself written to could access anywhere at all, so then
+    // nothing can be shown to be constant
+    let rewritten_parameter = may_execute.iter().any(|&addr| match DecodedInstruction::decode(program, addr) {
+        DecodedInstruction::Op { parameters, .. } => parameters.iter().enumerate().any(|(idx, &(mode, _))| {
+            mode == ParameterMode::Position && written.contains(&((addr + idx + 1) as ProgramElement))
+        }),
+        DecodedInstruction::Data(_) => false,
+    });
+    if rewritten_parameter {
+        return Vec::new();
+    }
+
+    // Operands come from the program as it was, as folding changes it
+    let original = program.to_vec();
+    let constant = |addr: usize| original.get(addr).filter(|_| !written.contains(&(addr as ProgramElement))).cloned();
+
+    let mut folds = Vec::new();
+    for addr in reachable {
+        let (opcode, parameters) = match DecodedInstruction::decode(program, addr) {
+            DecodedInstruction::Op { opcode: opcode @ (OpCode::Add | OpCode::Multiply), parameters } => {
+                (opcode, parameters)
+            }
+            _ => continue,
+        };
+
+        // The instruction has to stay as it is for the fold to be right, and nothing can read the
+        // parts of it that change
+        let addrs = (addr as ProgramElement)..((addr + opcode.length()) as ProgramElement);
+        if addrs.clone().any(|a| written.contains(&a)) || (addrs.start..(addrs.start + 3)).any(|a| read.contains(&a)) {
+            continue;
+        }
+
+        let operand = |idx: usize| match parameters[idx] {
+            (ParameterMode::Immediate, value) => Some(value),
+            (ParameterMode::Position, a) if a >= 0 => constant(a as usize),
+            _ => None,
+        };
+        let value = match (opcode, operand(0), operand(1)) {
+            (OpCode::Add, Some(a), Some(b)) => a.checked_add(b),
+            (OpCode::Multiply, Some(a), Some(b)) => a.checked_mul(b),
+            _ => None,
+        };
+        let value = match value {
+            Some(value) => value,
+            None => continue,
+        };
+
+        let dest_mode = parameters[2].0.code() as ProgramElement;
+        let folded = [dest_mode * 10_000 + 1100 + OpCode::Add.code(), value, 0];
+        if program[addr..(addr + 3)] != folded {
+            program[addr..(addr + 3)].copy_from_slice(&folded);
+            folds.push(Fold { addr, opcode, value });
+        }
+    }
+
+    folds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProgramState;
+
+    #[test]
+    fn test_fold_constants() {
+        //   0: add [16], 5, [17]
+        //   4: mul [17], 2, [18]  (17 is written, so isn't constant)
+        //   8: out [18]
+        //  10: add 1, 1, [rb + 20]
+        //  14: halt
+        //  15: data
+        let source = vec![1001, 16, 5, 17, 1002, 17, 2, 18, 4, 18, 21101, 1, 1, 20, 99, 0, 3, 0, 0];
+        let mut program = source.clone();
+        assert!(fold_constants(&mut program, &FoldOptions::default()).is_empty());
+        assert_eq!(program, source);
+
+        let folds = fold_constants(&mut program, &FoldOptions { trust_relative_mode: true });
+        assert_eq!(folds, vec![
+            Fold { addr: 0, opcode: OpCode::Add, value: 8 },
+            Fold { addr: 10, opcode: OpCode::Add, value: 2 },
+        ]);
+        assert_eq!(program[..4], [1101, 8, 0, 17]);
+        assert_eq!(program[10..14], [21101, 2, 0, 20]);
+        assert!(fold_constants(&mut program, &FoldOptions { trust_relative_mode: true }).is_empty());
+
+        let mut original = ProgramState::new(source, Default::default());
+        let mut folded = ProgramState::new(program, Default::default());
+        original.run_to_completion().unwrap();
+        folded.run_to_completion().unwrap();
+        assert_eq!(original.outputs, vec![16]);
+        assert_eq!(folded.outputs, original.outputs);
+    }
+
+    #[test]
+    fn test_self_modifying() {
+        // The add at 4 is overwritten by the first instruction before it runs
+        let mut program = vec![1101, 2, 2, 5, 1101, 1, 1, 12, 4, 12, 99, 0, 0];
+        let folds = fold_constants(&mut program, &FoldOptions::default());
+        assert_eq!(folds, vec![Fold { addr: 0, opcode: OpCode::Add, value: 4 }]);
+        assert_eq!(program[4..8], [1101, 1, 1, 12]);
+
+        // The first instruction reads the second's first operand, which folding would change
+        let mut program = vec![1, 5, 5, 13, 1101, 1, 1, 14, 4, 13, 4, 14, 99, 0, 0];
+        let folds = fold_constants(&mut program, &FoldOptions::default());
+        assert_eq!(folds, vec![Fold { addr: 0, opcode: OpCode::Add, value: 2 }]);
+        assert_eq!(program[4..8], [1101, 1, 1, 14]);
+    }
+
+    #[test]
+    fn test_rewritten_destination() {
+        //   0: add 15, 0, [7]   (changes where the next instruction writes to 15)
+        //   4: add 5, 5, [20]
+        //   8: add [15], 0, [16]
+        //  12: out [16]
+        //  14: halt
+        //  15: data
+        let source = vec![1101, 15, 0, 7, 1101, 5, 5, 20, 1001, 15, 0, 16, 4, 16, 99, 7, 0, 0, 0, 0, 0];
+        let mut program = source.clone();
+        assert!(fold_constants(&mut program, &FoldOptions::default()).is_empty());
+        assert_eq!(program, source);
+
+        let mut state = ProgramState::new(program, Default::default());
+        state.run_to_completion().unwrap();
+        assert_eq!(state.outputs, vec![10]);
+    }
+}
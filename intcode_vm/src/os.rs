@@ -0,0 +1,292 @@
+//! A tiny operating system, which runs any number of programs as processes under a `Scheduler`.
+//!
+//! Processes can be spawned and killed at any time, and each one's outputs either wait to be
+//! taken, as if printed to a terminal, or are piped straight to another process's inputs:
+//!
+//! ```
+//! use intcode_vm::os::{Os, Status};
+//! use intcode_vm::parse_program;
+//!
+//! // Outputs each of its inputs doubled
+//! let double = parse_program("3,11,1002,11,2,11,4,11,1105,1,0,0").unwrap();
+//!
+//! let mut os = Os::new();
+//! let first = os.spawn("first", double.clone());
+//! let second = os.spawn("second", double);
+//! os.pipe(first, second).unwrap();
+//!
+//! os.send(first, &[1, 2, 3]).unwrap();
+//! os.run(100);
+//! assert_eq!(os.take_outputs(second), Ok(vec![4, 8, 12]));
+//!
+//! os.kill(first).unwrap();
+//! assert_eq!(os.process(first).unwrap().status, Status::Killed);
+//! assert_eq!(os.process(second).unwrap().status, Status::Waiting);
+//! ```
+//!
+//! Process IDs are never reused, so one stays valid, and can still be inspected, after its
+//! process has stopped.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use crate::scheduler::{Message, Round, Scheduler, SchedulerError};
+use crate::{OpCode, ProgramElement, ProgramState, VmError};
+
+pub type Pid = usize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// Has instructions to run before it next needs an input
+    Runnable,
+
+    /// Needs an input, and none have been sent to it
+    Waiting,
+
+    Terminated,
+    Killed,
+    Failed(VmError),
+}
+
+/// A summary of a process, see `Os::process`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub pid: Pid,
+    pub name: String,
+    pub status: Status,
+
+    /// The process its outputs are piped to, if they are
+    pub pipe: Option<Pid>,
+
+    /// Outputs waiting to be taken with `Os::take_outputs`
+    pub outputs: usize,
+
+    pub instructions: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OsError {
+    NoSuchProcess(Pid),
+
+    /// The process has already terminated, been killed, or failed
+    NotRunning(Pid),
+}
+
+impl std::fmt::Display for OsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            OsError::NoSuchProcess(pid) => write!(f, "There's no process {}", pid),
+            OsError::NotRunning(pid) => write!(f, "Process {} isn't running", pid),
+        }
+    }
+}
+
+impl std::error::Error for OsError {}
+
+pub struct Os {
+    scheduler: Scheduler,
+    names: Vec<String>,
+
+    /// Why each process stopped, if it was killed or failed rather than terminating
+    ended: Vec<Option<Status>>,
+
+    /// Where each process's outputs are piped to, shared with the scheduler's router
+    pipes: Arc<Mutex<BTreeMap<Pid, Pid>>>,
+}
+
+impl Os {
+    pub fn new() -> Self {
+        let pipes = Arc::new(Mutex::new(BTreeMap::<Pid, Pid>::new()));
+        let routes = pipes.clone();
+        let router = Box::new(move |from, outputs: &mut VecDeque<ProgramElement>| {
+            let routes = routes.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            match routes.get(&from) {
+                Some(&to) if !outputs.is_empty() => vec![Message { to, values: outputs.drain(..).collect() }],
+                _ => Vec::new(),
+            }
+        });
+
+        Self {
+            scheduler: Scheduler::new(Vec::new(), router),
+            names: Vec::new(),
+            ended: Vec::new(),
+            pipes,
+        }
+    }
+
+    /// Most instructions a process can execute each time it's scheduled
+    pub fn set_quantum(&mut self, quantum: u64) {
+        self.scheduler.quantum = quantum;
+    }
+
+    /// Loads `program` as a new process, which first runs the next time the OS is stepped
+    pub fn spawn(&mut self, name: impl Into<String>, program: impl IntoIterator<Item = ProgramElement>) -> Pid {
+        self.scheduler.vms.push(ProgramState::new(program, VecDeque::new()));
+        self.names.push(name.into());
+        self.ended.push(None);
+        self.scheduler.vms.len() - 1
+    }
+
+    pub fn kill(&mut self, pid: Pid) -> Result<(), OsError> {
+        self.running(pid)?;
+        self.scheduler.vms[pid].terminated = true;
+        self.ended[pid] = Some(Status::Killed);
+        Ok(())
+    }
+
+    /// Sends every output `from` produces from now on to `to` as inputs, rather than leaving them
+    /// to be taken
+    pub fn pipe(&mut self, from: Pid, to: Pid) -> Result<(), OsError> {
+        self.vm(from)?;
+        self.vm(to)?;
+        self.lock_pipes().insert(from, to);
+        Ok(())
+    }
+
+    /// Leaves `from`'s outputs to be taken again, returning where they were piped to
+    pub fn unpipe(&mut self, from: Pid) -> Result<Option<Pid>, OsError> {
+        self.vm(from)?;
+        Ok(self.lock_pipes().remove(&from))
+    }
+
+    /// Queues `values` as inputs for `pid`
+    pub fn send(&mut self, pid: Pid, values: &[ProgramElement]) -> Result<(), OsError> {
+        self.running(pid)?;
+        self.scheduler.vms[pid].inputs.extend(values);
+        Ok(())
+    }
+
+    /// Takes every output of `pid` that hasn't been piped elsewhere
+    pub fn take_outputs(&mut self, pid: Pid) -> Result<Vec<ProgramElement>, OsError> {
+        self.vm(pid)?;
+        Ok(self.scheduler.vms[pid].outputs.drain(..).collect())
+    }
+
+    pub fn vm(&self, pid: Pid) -> Result<&ProgramState, OsError> {
+        self.scheduler.vms.get(pid).ok_or(OsError::NoSuchProcess(pid))
+    }
+
+    pub fn process(&self, pid: Pid) -> Result<ProcessInfo, OsError> {
+        let vm = self.vm(pid)?;
+        let status = match self.ended[pid] {
+            Some(status) => status,
+            None if vm.terminated => Status::Terminated,
+            None if vm.inputs.is_empty() && is_reading(vm) => Status::Waiting,
+            None => Status::Runnable,
+        };
+
+        Ok(ProcessInfo {
+            pid,
+            name: self.names[pid].clone(),
+            status,
+            pipe: self.lock_pipes().get(&pid).cloned(),
+            outputs: vm.outputs.len(),
+            instructions: vm.stats().total(),
+        })
+    }
+
+    /// Every process that has ever been spawned, in order of their IDs
+    pub fn processes(&self) -> Vec<ProcessInfo> {
+        (0..self.names.len()).map(|pid| self.process(pid).unwrap()).collect()
+    }
+
+    /// Gives each running process a turn. A process that fails is stopped with
+    /// `Status::Failed`, and the round ends early.
+    pub fn step(&mut self) -> Round {
+        match self.scheduler.round() {
+            Ok(round) => round,
+            Err(SchedulerError::Vm { vm, error }) => {
+                self.scheduler.vms[vm].terminated = true;
+                self.ended[vm] = Some(Status::Failed(error));
+                Round::default()
+            }
+            Err(_) => Round { idle: true, ..Round::default() },
+        }
+    }
+
+    /// Steps until every process is waiting for input or has stopped, or for at most
+    /// `max_rounds` rounds, returning how many rounds it took
+    pub fn run(&mut self, max_rounds: usize) -> usize {
+        for rounds in 1..=max_rounds {
+            if self.step().idle {
+                return rounds;
+            }
+        }
+        max_rounds
+    }
+
+    fn running(&self, pid: Pid) -> Result<(), OsError> {
+        match self.vm(pid)?.terminated {
+            true => Err(OsError::NotRunning(pid)),
+            false => Ok(()),
+        }
+    }
+
+    fn lock_pipes(&self) -> std::sync::MutexGuard<'_, BTreeMap<Pid, Pid>> {
+        self.pipes.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for Os {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether the next instruction the VM will execute reads an input
+fn is_reading(vm: &ProgramState) -> bool {
+    OpCode::try_from_element(&vm.mem.read_addr(vm.program_counter)) == Some(OpCode::ReadInput)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_program;
+
+    #[test]
+    fn test_processes() {
+        let mut os = Os::new();
+        let spin = os.spawn("spin", parse_program("1105,1,0").unwrap());
+        let echo = os.spawn("echo", parse_program("3,7,4,7,1105,1,0,0").unwrap());
+        let bad = os.spawn("bad", parse_program("104,1,42").unwrap());
+        os.set_quantum(100);
+
+        // The spinning process never waits, so the OS never goes idle
+        assert_eq!(os.run(5), 5);
+        assert_eq!(os.process(spin).unwrap().status, Status::Runnable);
+        assert_eq!(os.process(echo).unwrap().status, Status::Waiting);
+
+        let failed = os.process(bad).unwrap();
+        assert_eq!(failed.status, Status::Failed(VmError::UnknownOpcode { pc: 2, instruction: 42 }));
+        assert_eq!(failed.outputs, 1);
+        assert_eq!(os.send(bad, &[1]), Err(OsError::NotRunning(bad)));
+
+        os.kill(spin).unwrap();
+        assert_eq!(os.kill(spin), Err(OsError::NotRunning(spin)));
+        os.send(echo, &[5, 6]).unwrap();
+        assert_eq!(os.run(5), 2);
+        assert_eq!(os.take_outputs(echo), Ok(vec![5, 6]));
+        assert_eq!(os.take_outputs(echo), Ok(vec![]));
+
+        let names = os.processes().into_iter().map(|p| p.name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["spin", "echo", "bad"]);
+        assert_eq!(os.process(3), Err(OsError::NoSuchProcess(3)));
+    }
+
+    #[test]
+    fn test_pipes() {
+        // Outputs 1, 2, 3, then terminates
+        let mut os = Os::new();
+        let source = os.spawn("source", parse_program("104,1,104,2,104,3,99").unwrap());
+        let sink = os.spawn("sink", parse_program("3,5,1105,1,0,0").unwrap());
+        os.pipe(source, sink).unwrap();
+        assert_eq!(os.pipe(source, 2), Err(OsError::NoSuchProcess(2)));
+
+        os.run(10);
+        assert_eq!(os.process(source).unwrap().status, Status::Terminated);
+        assert_eq!(os.process(source).unwrap().pipe, Some(sink));
+        assert_eq!(os.vm(sink).unwrap().mem.read_addr(5), 3);
+        assert_eq!(os.unpipe(source), Ok(Some(sink)));
+        assert_eq!(os.take_outputs(source), Ok(vec![]));
+    }
+}
@@ -0,0 +1,146 @@
+//! Running many copies of a program at once, each set up slightly differently, for brute-force
+//! searches like day 2's noun/verb search.
+//!
+//! ```
+//! use intcode_vm::parallel::{run_parallel, Setup};
+//! use intcode_vm::ProgramState;
+//!
+//! // Multiplies the values at 1 and 2 into 0
+//! let program = ProgramState::load_program_str("1102,0,0,0,99");
+//! let setups = (0..10).flat_map(|a| (0..10).map(move |b| Setup::patches(vec![(1, a), (2, b)])));
+//!
+//! let found = run_parallel(
+//!     &program,
+//!     setups,
+//!     |run| if run.state.mem.read_addr(0) == 42 { Some(run.setup) } else { None },
+//!     |a, b| a.or(b),
+//! );
+//! assert_eq!(found.flatten(), Some(Setup::patches(vec![(1, 6), (2, 7)])));
+//! ```
+
+use crate::{ProgramElement, ProgramState, StopReason, VmError};
+
+/// How a single copy of the program differs from the base
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Setup {
+    /// Queued after any inputs the base already has
+    pub inputs: Vec<ProgramElement>,
+
+    /// Applied in order, see `ProgramState::apply_patches`
+    pub patches: Vec<(usize, ProgramElement)>,
+}
+
+impl Setup {
+    pub fn inputs(inputs: Vec<ProgramElement>) -> Self {
+        Self { inputs, patches: Vec::new() }
+    }
+
+    pub fn patches(patches: Vec<(usize, ProgramElement)>) -> Self {
+        Self { inputs: Vec::new(), patches }
+    }
+}
+
+/// A copy of the program after it was run
+#[derive(Debug)]
+pub struct Run {
+    pub setup: Setup,
+    pub state: ProgramState,
+    pub result: Result<StopReason, VmError>,
+}
+
+/// Runs a copy of `base` for each setup with `run_to_completion`, splitting them between threads.
+/// Each run is turned into a result by `evaluate`, and the results are combined by `reduce`, which
+/// returns `None` if there weren't any setups.
+///
+/// Results are always combined in the order of their setups, so `reduce` only has to be
+/// associative, eg for finding the first setup that does something.
+pub fn run_parallel<R, F, G>(base: &ProgramState, setups: impl IntoIterator<Item = Setup>, evaluate: F, reduce: G) -> Option<R>
+where
+    R: Send,
+    F: Fn(Run) -> R + Sync,
+    G: Fn(R, R) -> R + Sync,
+{
+    run_parallel_for(base, setups, u64::MAX, evaluate, reduce)
+}
+
+/// As `run_parallel`, but each run stops with `BudgetExhausted` after `max_instructions`
+pub(crate) fn run_parallel_for<R, F, G>(
+    base: &ProgramState,
+    setups: impl IntoIterator<Item = Setup>,
+    max_instructions: u64,
+    evaluate: F,
+    reduce: G,
+) -> Option<R>
+where
+    R: Send,
+    F: Fn(Run) -> R + Sync,
+    G: Fn(R, R) -> R + Sync,
+{
+    let setups = setups.into_iter().collect::<Vec<_>>();
+    let chunk_len = std::cmp::max(1, setups.len().div_ceil(thread_count()));
+
+    std::thread::scope(|scope| {
+        let workers = setups
+            .chunks(chunk_len)
+            .map(|chunk| {
+                let (evaluate, reduce) = (&evaluate, &reduce);
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|setup| evaluate(run_one(base, setup.clone(), max_instructions)))
+                        .reduce(reduce)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        workers
+            .into_iter()
+            .filter_map(|worker| worker.join().expect("Parallel VM worker thread panicked"))
+            .reduce(&reduce)
+    })
+}
+
+pub(crate) fn thread_count() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+/// Runs a copy of `base` as `run_to_completion` does, but stopping with `BudgetExhausted` after
+/// `max_instructions`
+pub(crate) fn run_one(base: &ProgramState, setup: Setup, max_instructions: u64) -> Run {
+    let mut state = base.clone();
+    state.apply_patches(&setup.patches);
+    state.inputs.extend(&setup.inputs);
+    let result = match state.run_for(max_instructions) {
+        Ok(StopReason::NeedsInput) => Err(VmError::NoInput),
+        result => result,
+    };
+    Run { setup, state, result }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_parallel() {
+        // Outputs the product of its two inputs
+        let mut program = ProgramState::load_program_str("3,11,3,12,2,11,12,13,4,13,99,0,0,0");
+        program.inputs.push_back(3);
+
+        let setups = (0..100).map(|n| Setup::inputs(vec![n]));
+        let total = run_parallel(&program, setups, |run| run.state.outputs[0], |a, b| a + b);
+        assert_eq!(total, Some(3 * 4950));
+
+        // Runs that fail are passed on too
+        let setups = vec![Setup::default(), Setup::inputs(vec![2])];
+        let results = run_parallel(&program, setups, |run| vec![run.result], |mut a, b| {
+            a.extend(b);
+            a
+        });
+        assert_eq!(results, Some(vec![Err(VmError::NoInput), Ok(StopReason::Terminated)]));
+
+        assert_eq!(run_parallel(&program, vec![], |_| 0, |a, b| a + b), None);
+    }
+}
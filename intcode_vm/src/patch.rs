@@ -0,0 +1,269 @@
+//! Changes to make to a program's memory before it runs, eg the values that days 2 and 13 ask for
+//! at the start of memory, or tweaks found while reverse engineering a program.
+//!
+//! Patch files have one line per run of values, giving the address to write the first one to:
+//!
+//! ```text
+//! # Play for free
+//! 0 = 2
+//! 1500 = 1, 1, 1
+//! ```
+//!
+//! Anything changed while poking at a program can be saved as patches by diffing snapshots:
+//!
+//! ```
+//! use intcode_vm::{patch, ProgramState};
+//!
+//! let mut program = ProgramState::load_program_str("1,0,0,0,99");
+//! let before = program.snapshot();
+//! program.mem.write_range(1, &[9, 10]);
+//!
+//! let patches = before
+//!     .diff(&program.snapshot())
+//!     .into_iter()
+//!     .map(|(addr, _, after)| (addr, after))
+//!     .collect::<Vec<_>>();
+//! assert_eq!(patch::format_patches(&patches), "1 = 9, 10\n");
+//! ```
+//!
+//! Searching for the patches that make a program do something, eg day 2's noun and verb, is done
+//! with `search_patches`, or `best_patches` to score every candidate.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::parallel::{run_one, run_parallel_for, thread_count, Run, Setup};
+use crate::{LoadError, ProgramElement, ProgramState, StopReason};
+
+/// Parses the patches in a patch file, as `(address, value)` pairs in the order they're written.
+/// Blank lines and anything following a `#` are ignored.
+pub fn parse_patches(source: &str) -> Result<Vec<(usize, ProgramElement)>, LoadError> {
+    let mut patches = Vec::new();
+    for (idx, line) in source.lines().enumerate() {
+        let text = line.split('#').next().unwrap().trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        let invalid = || LoadError::InvalidPatch { line: idx + 1, text: line.trim().to_string() };
+        let eq = text.find('=').ok_or_else(invalid)?;
+        let start = text[..eq].trim().parse::<usize>().map_err(|_| invalid())?;
+        for (offset, value) in text[(eq + 1)..].split(',').enumerate() {
+            let value = value.trim().parse().map_err(|_| invalid())?;
+            patches.push((start + offset, value));
+        }
+    }
+
+    Ok(patches)
+}
+
+/// Formats patches as a patch file, with consecutive addresses written on the same line
+pub fn format_patches(patches: &[(usize, ProgramElement)]) -> String {
+    let mut text = String::new();
+    let mut next_addr = None;
+    for &(addr, value) in patches {
+        if next_addr == Some(addr) {
+            text.push_str(&format!(", {}", value));
+        } else {
+            if next_addr.is_some() {
+                text.push('\n');
+            }
+            text.push_str(&format!("{} = {}", addr, value));
+        }
+        next_addr = Some(addr + 1);
+    }
+
+    if next_addr.is_some() {
+        text.push('\n');
+    }
+    text
+}
+
+impl<I, O> ProgramState<I, O> {
+    /// Writes each value to its address, in order
+    pub fn apply_patches(&mut self, patches: &[(usize, ProgramElement)]) {
+        for &(addr, value) in patches {
+            self.mem.write_addr(addr, value);
+        }
+    }
+
+    /// Applies the patches in a patch file, see `parse_patches`
+    pub fn apply_patch_file(&mut self, path: &std::path::Path) -> Result<(), LoadError> {
+        let source = std::fs::read_to_string(path)?;
+        self.apply_patches(&parse_patches(&source)?);
+        Ok(())
+    }
+}
+
+/// Runs a copy of `base` with each candidate's patches applied, as `run_to_completion` does but for
+/// at most `max_instructions` each, until one satisfies `predicate`, and returns that run. Patches
+/// that send a program into an infinite loop are common, so runs that use up their budget never
+/// match, and aren't passed to `predicate`.
+///
+/// ```
+/// use intcode_vm::{patch, ProgramState};
+///
+/// // Multiplies the values at 1 and 2 into 0
+/// let program = ProgramState::load_program_str("1102,0,0,0,99");
+/// let candidates = (0..100).flat_map(|noun| (0..100).map(move |verb| vec![(1, noun), (2, verb)]));
+///
+/// let run = patch::search_patches(&program, candidates, 1000, false, |run| run.state.mem.read_addr(0) == 42);
+/// assert_eq!(run.unwrap().setup.patches, vec![(1, 1), (2, 42)]);
+/// ```
+///
+/// With `parallel` set, the candidates are shared out between threads, which stop once a match is
+/// found earlier in the candidates than anything they have left to try. The run returned is still
+/// the first to match, in the order of the candidates.
+pub fn search_patches<C, F>(
+    base: &ProgramState,
+    candidates: C,
+    max_instructions: u64,
+    parallel: bool,
+    predicate: F,
+) -> Option<Run>
+where
+    C: IntoIterator<Item = Vec<(usize, ProgramElement)>>,
+    F: Fn(&Run) -> bool + Sync,
+{
+    let matches = |run: &Run| run.result != Ok(StopReason::BudgetExhausted) && predicate(run);
+    if !parallel {
+        return candidates
+            .into_iter()
+            .map(|patches| run_one(base, Setup::patches(patches), max_instructions))
+            .find(matches);
+    }
+
+    let candidates = candidates.into_iter().collect::<Vec<_>>();
+    let threads = thread_count();
+
+    // The index of the earliest candidate found to match so far. Each thread takes every
+    // `threads`th candidate, so tries them in order and can stop at its first match.
+    let first_match = AtomicUsize::new(usize::MAX);
+    std::thread::scope(|scope| {
+        let workers = (0..threads)
+            .map(|offset| {
+                let (candidates, first_match, matches) = (&candidates, &first_match, &matches);
+                scope.spawn(move || {
+                    for idx in (offset..candidates.len()).step_by(threads) {
+                        if idx > first_match.load(Ordering::Relaxed) {
+                            break;
+                        }
+
+                        let run = run_one(base, Setup::patches(candidates[idx].clone()), max_instructions);
+                        if matches(&run) {
+                            first_match.fetch_min(idx, Ordering::Relaxed);
+                            return Some((idx, run));
+                        }
+                    }
+                    None
+                })
+            })
+            .collect::<Vec<_>>();
+
+        workers
+            .into_iter()
+            .filter_map(|worker| worker.join().expect("Patch search worker thread panicked"))
+            .min_by_key(|(idx, _)| *idx)
+            .map(|(_, run)| run)
+    })
+}
+
+/// As `search_patches` with `parallel` set, but running every candidate and returning the run that
+/// `score` scores highest, along with its score. Runs scored `None` are ignored, as are runs that
+/// use up their budget, and ties go to the earliest candidate.
+pub fn best_patches<C, F, S>(base: &ProgramState, candidates: C, max_instructions: u64, score: F) -> Option<(Run, S)>
+where
+    C: IntoIterator<Item = Vec<(usize, ProgramElement)>>,
+    F: Fn(&Run) -> Option<S> + Sync,
+    S: Ord + Send,
+{
+    let setups = candidates.into_iter().map(Setup::patches);
+    let evaluate = |run: Run| {
+        if run.result == Ok(StopReason::BudgetExhausted) {
+            return None;
+        }
+        score(&run).map(|score| (run, score))
+    };
+    let reduce = |a: Option<(Run, S)>, b: Option<(Run, S)>| match (a, b) {
+        (Some(a), Some(b)) => Some(if b.1 > a.1 { b } else { a }),
+        (a, b) => a.or(b),
+    };
+    run_parallel_for(base, setups, max_instructions, evaluate, reduce).flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_patches() {
+        let source = "
+            # Play for free
+            0 = 2
+            10 = 1, -1  # Two at once
+        ";
+        let patches = parse_patches(source).unwrap();
+        assert_eq!(patches, vec![(0, 2), (10, 1), (11, -1)]);
+        assert_eq!(format_patches(&patches), "0 = 2\n10 = 1, -1\n");
+        assert_eq!(parse_patches(&format_patches(&patches)).unwrap(), patches);
+
+        assert!(matches!(
+            parse_patches("0 = 2\n-1 = 5"),
+            Err(LoadError::InvalidPatch { line: 2, ref text }) if text == "-1 = 5"
+        ));
+        assert!(parse_patches("0 2").is_err());
+        assert!(parse_patches("0 = 1,,2").is_err());
+    }
+
+    #[test]
+    fn test_apply_patches() {
+        let mut program = ProgramState::load_program_str("1,0,0,0,99");
+        program.apply_patches(&[(1, 5), (2, 6), (5, 7), (1, 4)]);
+        assert_eq!(program.mem, vec![1, 4, 6, 0, 99, 7]);
+    }
+
+    #[test]
+    fn test_search_patches() {
+        // Multiplies the values at 1 and 2 into 0, then outputs 1 if the value at 5 is set
+        //   0: mul 0, 0, [0]
+        //   4: jz 0, 11
+        //   7: out 1
+        //   9: halt
+        //  10: data
+        //  11: halt
+        let program = ProgramState::load_program_str("1102,0,0,0,1106,0,11,104,1,99,0,99");
+        let candidates = || (0..10).flat_map(|a| (0..10).map(move |b| vec![(1, a), (2, b), (5, a % 2)]));
+
+        for &parallel in &[false, true] {
+            let found = search_patches(&program, candidates(), 100, parallel, |run| run.state.mem.read_addr(0) == 12);
+            assert_eq!(found.unwrap().setup.patches, vec![(1, 2), (2, 6), (5, 0)]);
+            assert!(search_patches(&program, candidates(), 100, parallel, |run| run.result.is_err()).is_none());
+
+            // Jumping to 4 rather than 11 loops forever, which every candidate with a below 5 does
+            let looping = (0..10).flat_map(|a| {
+                let target = if a < 5 { 4 } else { 11 };
+                (0..10).map(move |b| vec![(1, a), (2, b), (6, target)])
+            });
+            let found = search_patches(&program, looping, 100, parallel, |run| run.state.mem.read_addr(0) == 12);
+            assert_eq!(found.unwrap().setup.patches, vec![(1, 6), (2, 2), (6, 11)]);
+        }
+
+        // The largest even product below 50 that doesn't output anything
+        let (run, score) = best_patches(&program, candidates(), 100, |run| {
+            let product = run.state.mem.read_addr(0);
+            if product < 50 && product % 2 == 0 && run.state.outputs.is_empty() {
+                Some(product)
+            } else {
+                None
+            }
+        })
+        .unwrap();
+        assert_eq!(score, 48);
+        assert_eq!(run.setup.patches, vec![(1, 6), (2, 8), (5, 0)]);
+
+        assert!(best_patches(&program, candidates(), 100, |_| None::<i64>).is_none());
+
+        // Every run that loops uses up its budget, and isn't scored
+        let looping = (0..10).map(|a| vec![(1, a), (2, a), (5, 0), (6, 4)]);
+        assert!(best_patches(&program, looping, 100, |run| Some(run.state.mem.read_addr(0))).is_none());
+    }
+}
@@ -0,0 +1,155 @@
+//! Counts of how many times each instruction in a program executes.
+//!
+//! Profiling is opt-in, as it takes execution off the fast path:
+//!
+//! ```
+//! use intcode_vm::{profile::Profile, ProgramState};
+//!
+//! // Counts down from 3, outputting each number along the way
+//! let mut program = ProgramState::load_program_str("3,12,4,12,1001,12,-1,12,1005,12,2,99,0");
+//! program.inputs.push_back(3);
+//! program.profile = Some(Profile::default());
+//! program.run_to_completion().unwrap();
+//!
+//! let profile = program.profile.as_ref().unwrap();
+//! assert_eq!(profile.count(2), 3);
+//!
+//! let regions = profile.hot_regions(&program.mem);
+//! assert_eq!(regions[0].addrs, 0..12);
+//! print!("{}", profile.report(&program.mem));
+//! ```
+
+use core::ops::Range;
+
+use crate::prelude::*;
+use crate::{disasm, PagedMemory, ProgramElement};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Profile {
+    /// Indexed by the address of the instruction
+    counts: Vec<u64>,
+}
+
+/// A run of instructions which were each executed at least once, and which follow on directly
+/// from one another in memory
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HotRegion {
+    /// From the first instruction up to the end of the last one
+    pub addrs: Range<usize>,
+
+    /// Total number of instructions executed in the region
+    pub executions: u64,
+}
+
+impl Profile {
+    pub(crate) fn record(&mut self, pc: usize) {
+        if pc >= self.counts.len() {
+            self.counts.resize(pc + 1, 0);
+        }
+        self.counts[pc] += 1;
+    }
+
+    /// Number of times the instruction at `addr` was executed
+    pub fn count(&self, addr: usize) -> u64 {
+        self.counts.get(addr).cloned().unwrap_or(0)
+    }
+
+    /// Total number of instructions executed
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Addresses of every executed instruction along with its count, in address order
+    pub fn executed(&self) -> impl Iterator<Item = (usize, u64)> + '_ {
+        self.counts
+            .iter()
+            .cloned()
+            .enumerate()
+            .filter(|&(_, count)| count > 0)
+    }
+
+    /// Groups the executed instructions into regions, hottest first. Instruction lengths are
+    /// taken from `mem`, so it should be the memory the profile was recorded against.
+    pub fn hot_regions(&self, mem: &PagedMemory<ProgramElement>) -> Vec<HotRegion> {
+        let mut regions: Vec<HotRegion> = Vec::new();
+        for (addr, count) in self.executed() {
+            let end = addr + disasm::disassemble_one(mem, addr).instruction.length();
+            match regions.last_mut() {
+                Some(region) if region.addrs.end == addr => {
+                    region.addrs.end = end;
+                    region.executions += count;
+                }
+                _ => regions.push(HotRegion {
+                    addrs: addr..end,
+                    executions: count,
+                }),
+            }
+        }
+
+        regions.sort_by(|a, b| b.executions.cmp(&a.executions).then(a.addrs.start.cmp(&b.addrs.start)));
+        regions
+    }
+
+    /// The disassembly of every hot region, hottest first, with each instruction's count
+    /// alongside it
+    pub fn report(&self, mem: &PagedMemory<ProgramElement>) -> String {
+        let total = self.total().max(1) as f64;
+
+        let mut report = String::new();
+        for region in self.hot_regions(mem) {
+            report += &format!(
+                "{}..{}: {} executions ({:.1}%)\n",
+                region.addrs.start,
+                region.addrs.end,
+                region.executions,
+                100.0 * region.executions as f64 / total,
+            );
+
+            for line in disasm::disassemble(mem, region.addrs.clone()) {
+                report += &format!("{:>12} {}\n", self.count(line.addr), line);
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProgramState;
+
+    #[test]
+    fn test_hot_regions() {
+        // Outputs 3, 2, 1 with a loop at 13, then jumps over some data to terminate at 30
+        let mut program = ProgramState::load_program_str(
+            "3,12,1105,1,13,0,0,0,0,0,0,0,0,4,12,1001,12,-1,12,1005,12,13,1105,1,30,0,0,0,0,0,99"
+        );
+        program.inputs.push_back(3);
+        program.profile = Some(Profile::default());
+        program.run_to_completion().unwrap();
+        assert_eq!(program.outputs, vec![3, 2, 1]);
+
+        let profile = program.profile.as_ref().unwrap();
+        assert_eq!(profile.total(), 13);
+        assert_eq!(
+            profile.hot_regions(&program.mem),
+            vec![
+                HotRegion { addrs: 13..25, executions: 10 },
+                HotRegion { addrs: 0..5, executions: 2 },
+                HotRegion { addrs: 30..31, executions: 1 },
+            ]
+        );
+
+        let report = profile.report(&program.mem);
+        assert!(report.starts_with("13..25: 10 executions (76.9%)\n"));
+        assert!(report.contains("           3    13: 4 12             out [12]\n"));
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        let mut program = ProgramState::load_program_str("104,1,99");
+        program.run_to_completion().unwrap();
+        assert!(program.profile.is_none());
+    }
+}
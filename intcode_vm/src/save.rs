@@ -0,0 +1,289 @@
+//! Saving a VM part way through a run to a file, so that it can be resumed later, eg by a long
+//! search that might be interrupted.
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use intcode_vm::ProgramState;
+//!
+//! let mut program = ProgramState::load_program_file(Path::new("inputs/day_25.txt"));
+//! program.run_to_next_input().unwrap();
+//! program.save_to(Path::new("day_25.icvm")).unwrap();
+//!
+//! let resumed = ProgramState::resume_from(Path::new("day_25.icvm")).unwrap();
+//! assert_eq!(resumed.program_counter, program.program_counter);
+//! ```
+//!
+//! Everything the program itself can see is saved: memory, the input and output queues, the
+//! program counter, the relative base and whether it's terminated. Debugging state, eg
+//! breakpoints or profiling, isn't, and the resumed VM uses the default backend.
+//!
+//! The format is stable across versions of this crate. All integers are little-endian:
+//!
+//! - The magic bytes `ICVM`, then the format version as a `u32`, currently 1
+//! - The size of each element in bytes as a `u8`, 8 or 16 with the `i128` feature
+//! - The program counter as a `u64`, then the relative base as an element
+//! - Whether the program has terminated as a `u8`, 0 or 1
+//! - The number of non-zero addresses in memory as a `u64`, then each one in ascending order as
+//!   a `u64` address followed by an element
+//! - The number of queued inputs as a `u64`, then each input as an element, then the same for
+//!   the outputs
+
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::{PagedMemory, ProgramElement, ProgramState};
+
+const MAGIC: &[u8; 4] = b"ICVM";
+const VERSION: u32 = 1;
+const ELEMENT_SIZE: usize = std::mem::size_of::<ProgramElement>();
+
+#[derive(Debug)]
+pub enum ResumeError {
+    Io(std::io::Error),
+
+    /// The file doesn't start with the magic bytes, so isn't a saved VM at all
+    NotSaved,
+
+    /// The file was saved by a newer version of the format
+    UnsupportedVersion(u32),
+
+    /// The file was saved with a different size of `ProgramElement`, ie with the `i128` feature
+    /// enabled when it isn't now or vice versa
+    ElementSize(u8),
+
+    /// The file is a saved VM, but something in it doesn't make sense
+    Corrupt(&'static str),
+}
+
+impl std::fmt::Display for ResumeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ResumeError::Io(_) => write!(f, "Failed to read saved VM"),
+            ResumeError::NotSaved => write!(f, "Not a saved VM"),
+            ResumeError::UnsupportedVersion(version) => write!(f, "Saved VM format version {} isn't supported", version),
+            ResumeError::ElementSize(size) => {
+                write!(f, "Saved VM has {} byte elements, rather than {}", size, ELEMENT_SIZE)
+            }
+            ResumeError::Corrupt(reason) => write!(f, "Saved VM is corrupt: {}", reason),
+        }
+    }
+}
+
+/// The source of an IO error is what went wrong reading
+impl std::error::Error for ResumeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ResumeError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ResumeError {
+    fn from(e: std::io::Error) -> Self {
+        // Running out of file part way through is the file's fault, not the reader's
+        match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => ResumeError::Corrupt("it ends part way through"),
+            _ => ResumeError::Io(e),
+        }
+    }
+}
+
+fn write_u64(w: &mut impl Write, value: u64) -> std::io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_element(w: &mut impl Write, value: ProgramElement) -> std::io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn read_u64(r: &mut impl Read) -> std::io::Result<u64> {
+    let mut bytes = [0; 8];
+    r.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_element(r: &mut impl Read) -> std::io::Result<ProgramElement> {
+    let mut bytes = [0; ELEMENT_SIZE];
+    r.read_exact(&mut bytes)?;
+    Ok(ProgramElement::from_le_bytes(bytes))
+}
+
+fn read_len(r: &mut impl Read) -> Result<usize, ResumeError> {
+    usize::try_from(read_u64(r)?).map_err(|_| ResumeError::Corrupt("a length is too long"))
+}
+
+fn read_queue(r: &mut impl Read) -> Result<VecDeque<ProgramElement>, ResumeError> {
+    let len = read_len(r)?;
+    // Not allocated up front, so that a corrupt length runs out of file rather than memory
+    let mut queue = VecDeque::new();
+    for _ in 0..len {
+        queue.push_back(read_element(r)?);
+    }
+    Ok(queue)
+}
+
+impl ProgramState {
+    /// Saves the VM to `path`, see the module docs. The file is written in full before it
+    /// replaces anything already at `path`, so an interrupted save leaves the previous one intact.
+    pub fn save_to(&self, path: &Path) -> std::io::Result<()> {
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+
+        let mut w = BufWriter::new(File::create(&partial)?);
+        self.write_state(&mut w)?;
+        w.into_inner()?.sync_all()?;
+        std::fs::rename(&partial, path)
+    }
+
+    /// Loads a VM saved with `save_to`
+    pub fn resume_from(path: &Path) -> Result<Self, ResumeError> {
+        Self::read_state(BufReader::new(File::open(path).map_err(ResumeError::Io)?))
+    }
+
+    /// Writes the VM in the format `save_to` does
+    pub fn write_state(&self, mut w: impl Write) -> std::io::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        w.write_all(&[ELEMENT_SIZE as u8])?;
+        write_u64(&mut w, self.program_counter as u64)?;
+        write_element(&mut w, self.relative_base)?;
+        w.write_all(&[self.terminated as u8])?;
+
+        let nonzero = self.mem.iter_nonzero().collect::<Vec<_>>();
+        write_u64(&mut w, nonzero.len() as u64)?;
+        for (addr, value) in nonzero {
+            write_u64(&mut w, addr as u64)?;
+            write_element(&mut w, value)?;
+        }
+
+        for queue in &[&self.inputs, &self.outputs] {
+            write_u64(&mut w, queue.len() as u64)?;
+            for &value in queue.iter() {
+                write_element(&mut w, value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads a VM in the format `save_to` writes
+    pub fn read_state(mut r: impl Read) -> Result<Self, ResumeError> {
+        let mut magic = [0; 4];
+        match r.read_exact(&mut magic) {
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Err(ResumeError::NotSaved),
+            result => result.map_err(ResumeError::Io)?,
+        }
+        if &magic != MAGIC {
+            return Err(ResumeError::NotSaved);
+        }
+
+        let mut version = [0; 4];
+        r.read_exact(&mut version)?;
+        match u32::from_le_bytes(version) {
+            VERSION => (),
+            version => return Err(ResumeError::UnsupportedVersion(version)),
+        }
+
+        let mut byte = [0];
+        r.read_exact(&mut byte)?;
+        if byte[0] as usize != ELEMENT_SIZE {
+            return Err(ResumeError::ElementSize(byte[0]));
+        }
+
+        let program_counter = read_len(&mut r)?;
+        let relative_base = read_element(&mut r)?;
+        r.read_exact(&mut byte)?;
+        let terminated = match byte[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(ResumeError::Corrupt("whether it's terminated isn't 0 or 1")),
+        };
+
+        let mut mem = PagedMemory::new();
+        let mut next_addr = 0;
+        for _ in 0..read_len(&mut r)? {
+            let addr = read_len(&mut r)?;
+            if addr < next_addr {
+                return Err(ResumeError::Corrupt("memory addresses aren't in ascending order"));
+            }
+            mem.write_addr(addr, read_element(&mut r)?);
+            next_addr = addr + 1;
+        }
+        mem.make_dense();
+
+        let inputs = read_queue(&mut r)?;
+        let outputs = read_queue(&mut r)?;
+
+        let mut state = ProgramState::new(Vec::new(), inputs);
+        state.mem = mem;
+        state.outputs = outputs;
+        state.program_counter = program_counter;
+        state.relative_base = relative_base;
+        state.terminated = terminated;
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        // Stores each input at 1000 onwards, outputting each one doubled
+        //   0: in [rb+1000]
+        //   2: mul [rb+1000], 2, [20]
+        //   6: out [20]
+        //   8: arb 1
+        //  10: jz 0, 0
+        let mut program = ProgramState::load_program_str("203,1000,1202,1000,2,20,4,20,109,1,1106,0,0");
+        program.inputs.extend(&[5, -6, 7]);
+        program.run_for(10).unwrap();
+
+        let mut saved = Vec::new();
+        program.write_state(&mut saved).unwrap();
+        assert_eq!(&saved[..8], b"ICVM\x01\x00\x00\x00");
+        let mut resumed = ProgramState::read_state(&saved[..]).unwrap();
+
+        assert_eq!(resumed.state_hash(), program.state_hash());
+        assert_eq!(resumed.inputs, program.inputs);
+        assert_eq!(resumed.outputs, program.outputs);
+
+        program.run_to_next_input().unwrap();
+        resumed.run_to_next_input().unwrap();
+        assert_eq!(resumed.outputs, vec![10, -12, 14]);
+        assert_eq!(resumed.state_hash(), program.state_hash());
+    }
+
+    #[test]
+    fn test_save_to_file() {
+        let path = std::env::temp_dir().join(format!("intcode_vm_save_{}.icvm", std::process::id()));
+        let mut program = ProgramState::load_program_str("3,5,99,0,0,0");
+        program.run_to_next_input().unwrap();
+        program.save_to(&path).unwrap();
+
+        let mut resumed = ProgramState::resume_from(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        resumed.inputs.push_back(42);
+        resumed.run_to_completion().unwrap();
+        assert_eq!(resumed.mem.read_addr(5), 42);
+    }
+
+    #[test]
+    fn test_bad_files() {
+        let mut saved = Vec::new();
+        ProgramState::load_program_str("1,2,3,99").write_state(&mut saved).unwrap();
+
+        let resume = |bytes: &[u8]| ProgramState::read_state(bytes).map(|_| ());
+        assert!(matches!(resume(b"1,2,3,99"), Err(ResumeError::NotSaved)));
+        assert!(matches!(resume(b"IC"), Err(ResumeError::NotSaved)));
+        assert!(matches!(resume(b"ICVM\x02\x00\x00\x00"), Err(ResumeError::UnsupportedVersion(2))));
+        assert!(matches!(resume(b"ICVM\x01\x00\x00\x00\x04"), Err(ResumeError::ElementSize(4))));
+        assert!(matches!(resume(&saved[..saved.len() - 1]), Err(ResumeError::Corrupt(_))));
+        assert!(resume(&saved).is_ok());
+    }
+}
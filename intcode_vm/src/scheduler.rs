@@ -0,0 +1,788 @@
+//! Fair interleaving of VMs that send each other messages, eg day 7's feedback loop or day 23's
+//! network.
+//!
+//! Each round, every VM that hasn't terminated gets a turn, in order. A turn lasts until the VM
+//! needs an input that isn't queued, terminates, or executes `quantum` instructions, so one that
+//! spins can't starve the others. After each turn a router turns the VM's outputs into messages,
+//! which are delivered before the next turn starts.
+//!
+//! Where outputs go is up to the router, whether that's fixed connections between the VMs, as with
+//! `fan_out` and `cluster::Pipeline`, or decided by the outputs themselves, as with `packets`.
+//! Messages addressed past the last VM aren't delivered anywhere, and are handed back from `round`
+//! instead, which is how `network::Network` picks out the packets for its NAT:
+//!
+//! ```
+//! use intcode_vm::scheduler::{packets, Message, Scheduler};
+//! use intcode_vm::ProgramState;
+//!
+//! // Sends a packet of its input and 7 to address 255
+//! let nic = ProgramState::load_program_str("3,11,104,255,4,11,104,7,1105,1,0,0");
+//! let mut scheduler = Scheduler::new(vec![nic.clone(), nic], packets(2));
+//! scheduler.vms[1].inputs.push_back(4);
+//! scheduler.idle_input = Some(-1);
+//!
+//! let round = scheduler.round().unwrap();
+//! assert_eq!(round.external, vec![
+//!     (0, Message { to: 255, values: vec![-1, 7] }),
+//!     (1, Message { to: 255, values: vec![4, 7] }),
+//! ]);
+//! ```
+//!
+//! Alternatively every VM can run on its own thread at once with `Scheduler::run_threaded`, which
+//! notices when they're all waiting on each other for input rather than hanging.
+
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::sync::{Condvar, Mutex};
+
+use crate::chrome_trace::ChromeTrace;
+use crate::{InputSource, OutputSink, ProgramElement, ProgramState, VmError};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    /// Index of the VM the message is for
+    pub to: usize,
+
+    /// Queued as the recipient's inputs, in order
+    pub values: Vec<ProgramElement>,
+}
+
+/// Takes the messages a VM's outputs make up from its output queue, given the VM's index. Outputs
+/// that aren't part of a complete message yet can be left for after its next turn.
+pub type Router = dyn FnMut(usize, &mut VecDeque<ProgramElement>) -> Vec<Message> + Send;
+
+/// Sends every output to the next VM, with the last sending to the first, as day 7's amplifiers do
+pub fn ring(len: usize) -> Box<Router> {
+    Box::new(move |from, outputs: &mut VecDeque<ProgramElement>| {
+        if outputs.is_empty() {
+            return Vec::new();
+        }
+        vec![Message { to: (from + 1) % len, values: outputs.drain(..).collect() }]
+    })
+}
+
+/// Sends a copy of every output from VM `from` to each of the VMs in `routes[from]`. A VM without
+/// any routes keeps its outputs in its output queue.
+pub fn fan_out(routes: Vec<Vec<usize>>) -> Box<Router> {
+    Box::new(move |from, outputs: &mut VecDeque<ProgramElement>| {
+        let to = match routes.get(from) {
+            Some(to) if !to.is_empty() && !outputs.is_empty() => to,
+            _ => return Vec::new(),
+        };
+        let values: Vec<_> = outputs.drain(..).collect();
+        to.iter().map(|&to| Message { to, values: values.clone() }).collect()
+    })
+}
+
+/// Reads messages as an address followed by `len` values, as day 23's packets are. Negative
+/// addresses are never delivered.
+pub fn packets(len: usize) -> Box<Router> {
+    Box::new(move |_, outputs: &mut VecDeque<ProgramElement>| {
+        let mut messages = Vec::new();
+        while outputs.len() > len {
+            let to = outputs.pop_front().unwrap();
+            messages.push(Message {
+                to: usize::try_from(to).unwrap_or(usize::MAX),
+                values: outputs.drain(..len).collect(),
+            });
+        }
+        messages
+    })
+}
+
+/// What happened during a call to `Scheduler::round`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Round {
+    /// Every message sent, in the order they were sent, with the index of the VM that sent each
+    pub sent: Vec<(usize, Message)>,
+
+    /// Those of `sent` that weren't addressed to one of the VMs
+    pub external: Vec<(usize, Message)>,
+
+    /// Instructions executed across every VM
+    pub instructions: u64,
+
+    /// Whether every VM that ran was only waiting for input, ie none of them had any queued,
+    /// used up its quantum, terminated, or sent a message
+    pub idle: bool,
+}
+
+/// Why `Scheduler::round` couldn't give the VMs their turns
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SchedulerError {
+    Vm { vm: usize, error: VmError },
+
+    /// Every VM has terminated, so nothing else can happen
+    AllTerminated,
+}
+
+impl std::fmt::Display for SchedulerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SchedulerError::Vm { vm, .. } => write!(f, "VM {} failed", vm),
+            SchedulerError::AllTerminated => write!(f, "Every VM has terminated"),
+        }
+    }
+}
+
+impl std::error::Error for SchedulerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SchedulerError::Vm { error, .. } => Some(error),
+            SchedulerError::AllTerminated => None,
+        }
+    }
+}
+
+/// Why running rounds of the scheduler stopped
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// Every VM has terminated
+    AllTerminated,
+
+    /// Every VM that hasn't terminated is waiting for an input that none of the others will send,
+    /// and there's no `idle_input` to give them
+    Deadlocked,
+
+    /// The instruction budget given to `Scheduler::run` ran out
+    BudgetExhausted,
+
+    /// A VM hit an error other than running out of input. It can't make any more progress, but
+    /// the others can still be run.
+    Faulted { vm: usize, error: VmError },
+}
+
+impl From<SchedulerError> for StopReason {
+    fn from(error: SchedulerError) -> Self {
+        match error {
+            SchedulerError::Vm { vm, error } => StopReason::Faulted { vm, error },
+            SchedulerError::AllTerminated => StopReason::AllTerminated,
+        }
+    }
+}
+
+impl std::fmt::Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            StopReason::AllTerminated => write!(f, "Every VM has terminated"),
+            StopReason::Deadlocked => write!(f, "Every VM is waiting for input from another"),
+            StopReason::BudgetExhausted => write!(f, "The instruction budget ran out"),
+            StopReason::Faulted { vm, .. } => write!(f, "VM {} failed", vm),
+        }
+    }
+}
+
+impl std::error::Error for StopReason {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StopReason::Faulted { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Counters for a single VM, accumulated over every round it's been given a turn in
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VmStats {
+    /// Instructions executed
+    pub instructions: u64,
+
+    /// Turns in which at least one instruction was executed
+    pub turns: u64,
+
+    /// Turns which ended because the VM used up its quantum
+    pub preemptions: u64,
+
+    /// Values output by the VM, whether or not they were routed to another VM
+    pub outputs: u64,
+
+    pub last_output: Option<ProgramElement>,
+}
+
+/// What happened during a call to `Scheduler::run` or `Scheduler::run_threaded`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RunReport {
+    pub stop_reason: StopReason,
+
+    /// Instructions executed across all VMs during this run
+    pub instructions: u64,
+
+    /// Number of times every VM was given a turn during this run
+    pub rounds: u64,
+
+    /// Cumulative stats for each VM, indexed the same as `Scheduler::vms`
+    pub vms: Vec<VmStats>,
+}
+
+impl std::fmt::Display for RunReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:?} after {} instructions in {} rounds",
+            self.stop_reason, self.instructions, self.rounds
+        )?;
+
+        let total = self.vms.iter().map(|s| s.instructions).sum::<u64>().max(1);
+        writeln!(f, "| vm  | instructions | share  | turns | preemptions | outputs |")?;
+        for (idx, stats) in self.vms.iter().enumerate() {
+            writeln!(
+                f,
+                "| {:>3} | {:>12} | {:>5.1}% | {:>5} | {:>11} | {:>7} |",
+                idx,
+                stats.instructions,
+                100.0 * stats.instructions as f64 / total as f64,
+                stats.turns,
+                stats.preemptions,
+                stats.outputs,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct Scheduler {
+    pub vms: Vec<ProgramState>,
+
+    /// Most instructions a VM can execute in a single turn
+    pub quantum: u64,
+
+    /// Queued for a VM that has no inputs at the start of its turn, eg -1 for day 23's NICs. If
+    /// this is `None`, a VM without inputs just doesn't run.
+    pub idle_input: Option<ProgramElement>,
+
+    /// If set, every instruction each VM executes is recorded on the track with its index, along
+    /// with each message it sends
+    pub trace: Option<ChromeTrace>,
+
+    router: Box<Router>,
+    stats: Vec<VmStats>,
+}
+
+impl Scheduler {
+    pub fn new(vms: Vec<ProgramState>, router: Box<Router>) -> Self {
+        Self {
+            vms,
+            quantum: 10_000,
+            idle_input: None,
+            trace: None,
+            router,
+            stats: Vec::new(),
+        }
+    }
+
+    /// Cumulative stats for each VM, indexed the same as `vms`
+    pub fn stats(&self) -> &[VmStats] {
+        &self.stats
+    }
+
+    /// Gives every VM that hasn't terminated a turn, see the module docs
+    pub fn round(&mut self) -> Result<Round, SchedulerError> {
+        self.round_within(u64::MAX)
+    }
+
+    /// As `round`, but stops handing out turns once `budget` instructions have been executed, so
+    /// the last VM to run may get a shorter turn and those after it none at all
+    fn round_within(&mut self, budget: u64) -> Result<Round, SchedulerError> {
+        if self.vms.iter().all(|vm| vm.terminated) {
+            return Err(SchedulerError::AllTerminated);
+        }
+
+        self.stats.resize(self.vms.len(), VmStats::default());
+        let mut round = Round { idle: true, ..Round::default() };
+        for idx in 0..self.vms.len() {
+            let vm = &mut self.vms[idx];
+            if vm.terminated {
+                continue;
+            }
+            if round.instructions >= budget {
+                break;
+            }
+
+            if !vm.inputs.is_empty() {
+                round.idle = false;
+            } else if let Some(input) = self.idle_input {
+                vm.inputs.push_back(input);
+            }
+
+            let limit = self.quantum.min(budget - round.instructions);
+            let instructions = vm.stats().total();
+            let outputs = vm.outputs.len();
+            let result = match &mut self.trace {
+                Some(trace) => vm.run_traced_for(&mut trace.track(idx), limit),
+                None => vm.run_for(limit),
+            };
+            let executed = vm.stats().total() - instructions;
+            round.instructions += executed;
+
+            let stats = &mut self.stats[idx];
+            stats.instructions += executed;
+            if executed > 0 {
+                stats.turns += 1;
+            }
+            if executed == limit && !vm.terminated {
+                stats.preemptions += 1;
+            }
+            if vm.outputs.len() > outputs {
+                stats.outputs += (vm.outputs.len() - outputs) as u64;
+                stats.last_output = vm.outputs.back().cloned();
+            }
+
+            match result {
+                Ok(crate::StopReason::NeedsInput) => (),
+                Ok(_) => round.idle = false,
+                Err(error) => return Err(SchedulerError::Vm { vm: idx, error }),
+            }
+
+            for message in (self.router)(idx, &mut vm.outputs) {
+                round.idle = false;
+                if let Some(trace) = &mut self.trace {
+                    trace.instant(idx, format!("send {:?} to {}", message.values, message.to));
+                }
+                match self.vms.get_mut(message.to) {
+                    Some(to) => to.inputs.extend(&message.values),
+                    None => round.external.push((idx, message.clone())),
+                }
+                round.sent.push((idx, message));
+            }
+        }
+
+        Ok(round)
+    }
+
+    /// Runs rounds until `f` returns something for one of them. Fails if the VMs deadlock, rather
+    /// than running forever.
+    pub fn run_until<T>(&mut self, mut f: impl FnMut(&Round) -> Option<T>) -> Result<T, StopReason> {
+        loop {
+            let round = self.round()?;
+            if let Some(result) = f(&round) {
+                return Ok(result);
+            }
+            if round.idle && self.idle_input.is_none() {
+                return Err(StopReason::Deadlocked);
+            }
+        }
+    }
+
+    /// Runs rounds until the VMs have all terminated or deadlocked, one of them faults, or
+    /// `budget` instructions have been executed across all of them. Running again after a fault
+    /// will hit the same fault straight away.
+    pub fn run(&mut self, budget: Option<u64>) -> RunReport {
+        let instructions_before = self.total_instructions();
+        let mut instructions = 0;
+        let mut rounds = 0;
+
+        let stop_reason = loop {
+            let round = match self.round_within(budget.map_or(u64::MAX, |budget| budget - instructions)) {
+                Ok(round) => round,
+                Err(error) => break error.into(),
+            };
+            instructions += round.instructions;
+            rounds += 1;
+
+            if budget.is_some_and(|budget| budget <= instructions) {
+                break StopReason::BudgetExhausted;
+            }
+            if round.idle && self.idle_input.is_none() {
+                break StopReason::Deadlocked;
+            }
+        };
+
+        RunReport {
+            stop_reason,
+            instructions: self.total_instructions() - instructions_before,
+            rounds,
+            vms: self.stats.clone(),
+        }
+    }
+
+    fn total_instructions(&self) -> u64 {
+        self.stats.iter().map(|stats| stats.instructions).sum()
+    }
+
+    /// As `run`, but with each VM running on its own thread until they've all terminated or are
+    /// all waiting for input that none of the others will send, or one of them faults. There's no
+    /// budget, quantum, `idle_input` or trace, so this hangs if a VM spins forever without reading
+    /// input, and each VM counts as having had a single turn. Messages addressed past the last VM
+    /// are dropped.
+    ///
+    /// The VMs' I/O is replaced while they run, which drops their hooks and checkpoints.
+    pub fn run_threaded(&mut self) -> RunReport {
+        let vms = std::mem::take(&mut self.vms);
+        self.stats.resize(vms.len(), VmStats::default());
+        let shared = Shared {
+            state: Mutex::new(SharedState {
+                queues: vms.iter().map(|vm| vm.inputs.clone()).collect(),
+                pending: vms.iter().map(|vm| vm.outputs.clone()).collect(),
+                router: &mut *self.router,
+                blocked: vec![false; vms.len()],
+                finished: vec![false; vms.len()],
+                fault: None,
+                stopped: false,
+            }),
+            wake: Condvar::new(),
+        };
+
+        let finished = std::thread::scope(|scope| {
+            let workers = vms
+                .into_iter()
+                .enumerate()
+                .map(|(idx, vm)| {
+                    let input = SharedInput { shared: &shared, idx };
+                    let output = SharedOutput { shared: &shared, idx, sent: 0, last: None };
+                    let mut vm = vm.with_io(input, output);
+                    let shared = &shared;
+                    scope.spawn(move || {
+                        let instructions_before = vm.stats().total();
+                        shared.finish(idx, run_shared(&mut vm));
+                        let executed = vm.stats().total() - instructions_before;
+                        (vm, executed)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            workers
+                .into_iter()
+                .map(|worker| worker.join().expect("Scheduler VM thread panicked"))
+                .collect::<Vec<_>>()
+        });
+
+        let mut state = shared.lock();
+        let mut instructions = 0;
+        for (idx, (vm, executed)) in finished.into_iter().enumerate() {
+            let stats = &mut self.stats[idx];
+            stats.instructions += executed;
+            if executed > 0 {
+                stats.turns += 1;
+            }
+            stats.outputs += vm.outputs.sent;
+            stats.last_output = vm.outputs.last.or(stats.last_output);
+
+            instructions += executed;
+            let inputs = std::mem::take(&mut state.queues[idx]);
+            let outputs = std::mem::take(&mut state.pending[idx]);
+            self.vms.push(vm.map_io(|_, _| (inputs, outputs)));
+        }
+
+        let stop_reason = match state.fault {
+            Some((vm, error)) => StopReason::Faulted { vm, error },
+            None if self.vms.iter().all(|vm| vm.terminated) => StopReason::AllTerminated,
+            None => StopReason::Deadlocked,
+        };
+
+        RunReport {
+            stop_reason,
+            instructions,
+            rounds: 1,
+            vms: self.stats.clone(),
+        }
+    }
+}
+
+/// Runs a VM on its own thread for `Scheduler::run_threaded`, until it can't make any more
+/// progress
+fn run_shared(vm: &mut ProgramState<SharedInput, SharedOutput>) -> Option<VmError> {
+    loop {
+        match vm.run_to_next_input() {
+            // Only once the VMs have gone idle or another has faulted
+            Ok(crate::StopReason::NeedsInput) | Ok(crate::StopReason::Terminated) => return None,
+            Ok(_) => (),
+            Err(e) => return Some(e),
+        }
+    }
+}
+
+/// Input queues shared between VMs running on their own threads
+struct Shared<'r> {
+    state: Mutex<SharedState<'r>>,
+
+    /// Notified whenever anything is queued, or the VMs have stopped
+    wake: Condvar,
+}
+
+struct SharedState<'r> {
+    queues: Vec<VecDeque<ProgramElement>>,
+
+    /// Each VM's outputs that the router hasn't taken yet
+    pending: Vec<VecDeque<ProgramElement>>,
+    router: &'r mut Router,
+
+    /// VMs waiting on an empty queue
+    blocked: Vec<bool>,
+
+    /// VMs that have terminated, faulted, or been stopped
+    finished: Vec<bool>,
+
+    /// The first VM to fault
+    fault: Option<(usize, VmError)>,
+
+    /// Set once every VM is idle, or one has faulted, so that the rest stop waiting for input
+    stopped: bool,
+}
+
+impl SharedState<'_> {
+    fn idle(&self) -> bool {
+        (0..self.queues.len()).all(|idx| self.finished[idx] || (self.blocked[idx] && self.queues[idx].is_empty()))
+    }
+}
+
+impl<'r> Shared<'r> {
+    fn lock(&self) -> std::sync::MutexGuard<'_, SharedState<'r>> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn finish(&self, idx: usize, fault: Option<VmError>) {
+        let mut state = self.lock();
+        state.finished[idx] = true;
+        if let Some(error) = fault {
+            state.fault = state.fault.or(Some((idx, error)));
+            state.stopped = true;
+        }
+        if state.idle() {
+            state.stopped = true;
+        }
+        self.wake.notify_all();
+    }
+}
+
+struct SharedInput<'a, 'r> {
+    shared: &'a Shared<'r>,
+    idx: usize,
+}
+
+impl InputSource for SharedInput<'_, '_> {
+    fn next_input(&mut self) -> Option<ProgramElement> {
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(value) = state.queues[self.idx].pop_front() {
+                state.blocked[self.idx] = false;
+                return Some(value);
+            }
+            if state.stopped {
+                return None;
+            }
+
+            state.blocked[self.idx] = true;
+            if state.idle() {
+                state.stopped = true;
+                self.shared.wake.notify_all();
+                return None;
+            }
+            state = self.shared.wake.wait(state).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+}
+
+struct SharedOutput<'a, 'r> {
+    shared: &'a Shared<'r>,
+    idx: usize,
+
+    /// Every value output, whether or not it was routed
+    sent: u64,
+    last: Option<ProgramElement>,
+}
+
+impl OutputSink for SharedOutput<'_, '_> {
+    fn write_output(&mut self, value: ProgramElement) {
+        self.sent += 1;
+        self.last = Some(value);
+
+        let mut guard = self.shared.lock();
+        let state = &mut *guard;
+        state.pending[self.idx].push_back(value);
+        let messages = (state.router)(self.idx, &mut state.pending[self.idx]);
+        for message in messages {
+            if let Some(queue) = state.queues.get_mut(message.to) {
+                queue.extend(&message.values);
+                self.shared.wake.notify_all();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads an input, outputs it, then terminates
+    const ECHO: [ProgramElement; 5] = [3, 0, 4, 0, 99];
+
+    /// Jumps to itself forever
+    const SPIN: [ProgramElement; 3] = [1105, 1, 0];
+
+    fn vm(program: &[ProgramElement]) -> ProgramState {
+        ProgramState::new(program.iter().cloned(), VecDeque::new())
+    }
+
+    #[test]
+    fn test_feedback_loop() {
+        // The first of day 7's part 2 examples
+        let amplifier = ProgramState::load_program_str(
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+        );
+        let vms = [9, 8, 7, 6, 5]
+            .iter()
+            .map(|&phase| {
+                let mut vm = amplifier.clone();
+                vm.inputs.push_back(phase);
+                vm
+            })
+            .collect();
+
+        let mut scheduler = Scheduler::new(vms, ring(5));
+        scheduler.vms[0].inputs.push_back(0);
+        assert_eq!(scheduler.run_until(|_| None::<()>), Err(StopReason::AllTerminated));
+        assert_eq!(scheduler.vms[0].inputs, vec![139629729]);
+    }
+
+    #[test]
+    fn test_quantum() {
+        // The first VM spins forever, but the second still gets to send its packet
+        let spin = ProgramState::load_program_str("1105,1,0");
+        let sender = ProgramState::load_program_str("104,-3,104,1,104,2,99");
+        let mut scheduler = Scheduler::new(vec![spin, sender], packets(2));
+        scheduler.quantum = 10;
+
+        let round = scheduler.round().unwrap();
+        assert_eq!(round.instructions, 14);
+        assert_eq!(round.external, vec![(1, Message { to: usize::MAX, values: vec![1, 2] })]);
+        assert!(!round.idle);
+
+        assert_eq!(scheduler.round().unwrap().instructions, 10);
+    }
+
+    #[test]
+    fn test_deadlock() {
+        // Each VM passes on one input, but neither has one to start with
+        let echo = ProgramState::load_program_str("3,5,4,5,99,0");
+        let mut scheduler = Scheduler::new(vec![echo.clone(), echo], ring(2));
+        assert_eq!(scheduler.run_until(|_| None::<()>), Err(StopReason::Deadlocked));
+
+        scheduler.vms[1].inputs.push_back(3);
+        let sent = scheduler.run_until(|round| Some(round.sent.clone()));
+        assert_eq!(sent, Ok(vec![(1, Message { to: 0, values: vec![3] })]));
+        assert_eq!(scheduler.run_until(|_| None::<()>), Err(StopReason::AllTerminated));
+        assert_eq!(scheduler.vms[1].inputs, vec![3]);
+    }
+
+    #[test]
+    fn test_fan_out() {
+        let routes = vec![vec![1, 2], Vec::new(), Vec::new()];
+        let mut scheduler = Scheduler::new(vec![vm(&ECHO), vm(&ECHO), vm(&ECHO)], fan_out(routes));
+        scheduler.vms[0].inputs.push_back(5);
+
+        let report = scheduler.run(None);
+        assert_eq!(report.stop_reason, StopReason::AllTerminated);
+        assert_eq!(report.instructions, 9);
+        assert_eq!(scheduler.vms[1].outputs, vec![5]);
+        assert_eq!(scheduler.vms[2].outputs, vec![5]);
+        assert!(scheduler.vms[0].outputs.is_empty());
+        assert_eq!(report.vms.iter().map(|s| s.outputs).collect::<Vec<_>>(), vec![1, 1, 1]);
+        assert_eq!(report.vms[2].last_output, Some(5));
+    }
+
+    #[test]
+    fn test_run_deadlock() {
+        let mut scheduler = Scheduler::new(vec![vm(&ECHO), vm(&ECHO)], fan_out(vec![vec![1], Vec::new()]));
+
+        let report = scheduler.run(None);
+        assert_eq!(report.stop_reason, StopReason::Deadlocked);
+        assert_eq!(report.instructions, 0);
+
+        // Can pick up again once there's something to do
+        scheduler.vms[0].inputs.push_back(1);
+        assert_eq!(scheduler.run(None).stop_reason, StopReason::AllTerminated);
+    }
+
+    #[test]
+    fn test_fault() {
+        let mut scheduler = Scheduler::new(vec![vm(&ECHO), vm(&[3, 4, 4, 4, 0])], fan_out(vec![vec![1], Vec::new()]));
+        scheduler.vms[0].inputs.push_back(98);
+
+        // The second VM outputs its input, then tries to execute it
+        let report = scheduler.run(None);
+        assert_eq!(
+            report.stop_reason,
+            StopReason::Faulted {
+                vm: 1,
+                error: VmError::UnknownOpcode { pc: 4, instruction: 98 },
+            },
+        );
+        assert_eq!(report.instructions, 5);
+        assert!(scheduler.vms[0].terminated);
+        assert_eq!(scheduler.vms[1].outputs, vec![98]);
+    }
+
+    #[test]
+    fn test_spinning_vm_gets_fair_share() {
+        let mut scheduler = Scheduler::new(vec![vm(&SPIN), vm(&ECHO)], fan_out(Vec::new()));
+        scheduler.quantum = 10;
+        scheduler.vms[1].inputs.push_back(7);
+
+        let report = scheduler.run(Some(1000));
+        assert_eq!(report.stop_reason, StopReason::BudgetExhausted);
+        assert_eq!(report.instructions, 1000);
+        assert_eq!(report.vms[0].instructions, 997);
+        assert_eq!(report.vms[0].preemptions, report.vms[0].turns);
+        assert_eq!(report.vms[1].instructions, 3);
+        assert!(scheduler.vms[1].terminated);
+
+        // The budget applies to each run separately, stats accumulate
+        let report = scheduler.run(Some(1000));
+        assert_eq!(report.instructions, 1000);
+        assert_eq!(report.vms[0].instructions, 1997);
+    }
+
+    #[test]
+    fn test_run_threaded() {
+        // The same feedback loop as `test_feedback_loop`
+        let amplifier = ProgramState::load_program_str(
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+        );
+        let vms = [9, 8, 7, 6, 5]
+            .iter()
+            .map(|&phase| {
+                let mut vm = amplifier.clone();
+                vm.inputs.push_back(phase);
+                vm
+            })
+            .collect();
+
+        let mut scheduler = Scheduler::new(vms, ring(5));
+        scheduler.vms[0].inputs.push_back(0);
+
+        let report = scheduler.run_threaded();
+        assert_eq!(report.stop_reason, StopReason::AllTerminated);
+        assert_eq!(report.vms[4].last_output, Some(139629729));
+        assert_eq!(scheduler.vms[0].inputs, vec![139629729]);
+        assert_eq!(scheduler.run(None).instructions, 0);
+    }
+
+    #[test]
+    fn test_run_threaded_deadlock() {
+        // Each VM waits for the other to send it something
+        let routes = vec![vec![1], vec![0], Vec::new()];
+        let mut scheduler = Scheduler::new(vec![vm(&ECHO), vm(&ECHO), vm(&[104, 3, 99])], fan_out(routes));
+
+        let report = scheduler.run_threaded();
+        assert_eq!(report.stop_reason, StopReason::Deadlocked);
+        assert_eq!(report.instructions, 2);
+        assert_eq!(scheduler.vms[2].outputs, vec![3]);
+        assert_eq!(report.vms[2].outputs, 1);
+
+        scheduler.vms[1].inputs.push_back(6);
+        assert_eq!(scheduler.run_threaded().stop_reason, StopReason::AllTerminated);
+        assert_eq!(scheduler.vms[1].outputs, vec![]);
+        assert_eq!(scheduler.stats()[0].last_output, Some(6));
+
+        let mut scheduler = Scheduler::new(vec![vm(&ECHO), vm(&[3, 4, 4, 4, 0])], fan_out(vec![vec![1], Vec::new()]));
+        scheduler.vms[0].inputs.push_back(98);
+        assert_eq!(
+            scheduler.run_threaded().stop_reason,
+            StopReason::Faulted {
+                vm: 1,
+                error: VmError::UnknownOpcode { pc: 4, instruction: 98 },
+            },
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Memory shared between VMs, so that one program's writes can be seen by another.
+//!
+//! Each VM keeps its own copy of the shared addresses, and only exchanges changes with the others
+//! when it's synchronized, so what a program sees never depends on how the VMs happen to be
+//! interleaved:
+//!
+//! ```
+//! use intcode_vm::shared::SharedSegment;
+//! use intcode_vm::ProgramState;
+//!
+//! // Writes its input to 100, and outputs whatever is at 100
+//! let mut writer = ProgramState::load_program_str("3,100,99");
+//! let mut reader = ProgramState::load_program_str("4,100,99");
+//!
+//! let segment = SharedSegment::new(100..101);
+//! let mut writer_mapping = segment.attach(&mut writer);
+//! let mut reader_mapping = segment.attach(&mut reader);
+//!
+//! writer.inputs.push_back(42);
+//! writer.run_to_completion().unwrap();
+//! writer_mapping.sync(&mut writer);
+//! reader_mapping.sync(&mut reader);
+//!
+//! reader.run_to_completion().unwrap();
+//! assert_eq!(reader.outputs, vec![42]);
+//! ```
+//!
+//! Syncing takes time in proportion to the size of the segment, rather than how much of it was
+//! written to.
+
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use crate::{ProgramElement, ProgramState};
+
+/// A range of addresses that any number of VMs can map, see the module docs. Clones refer to the
+/// same segment.
+#[derive(Clone, Debug)]
+pub struct SharedSegment {
+    addrs: Range<usize>,
+    contents: Arc<Mutex<Vec<ProgramElement>>>,
+}
+
+impl SharedSegment {
+    /// A segment covering `addrs` which starts off holding 0 at each address
+    pub fn new(addrs: Range<usize>) -> Self {
+        let contents = vec![0; addrs.len()];
+        Self { addrs, contents: Arc::new(Mutex::new(contents)) }
+    }
+
+    pub fn addrs(&self) -> Range<usize> {
+        self.addrs.clone()
+    }
+
+    /// The value at `addr` as of the last sync of any VM, `addr` has to be in the segment
+    pub fn read(&self, addr: usize) -> ProgramElement {
+        assert!(self.addrs.contains(&addr), "Address {} isn't in the segment {:?}", addr, self.addrs);
+        self.lock()[addr - self.addrs.start]
+    }
+
+    /// Overwrites `vm`'s memory over the segment's addresses with the segment's contents, and
+    /// returns the mapping that keeps it synchronized
+    pub fn attach<I, O>(&self, vm: &mut ProgramState<I, O>) -> Mapping {
+        let contents = self.lock().clone();
+        for (addr, &value) in self.addrs.clone().zip(&contents) {
+            vm.mem.write_addr(addr, value);
+        }
+
+        Mapping { segment: self.clone(), synced: contents }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<ProgramElement>> {
+        self.contents.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// One VM's view of a `SharedSegment`, see `SharedSegment::attach`
+#[derive(Clone, Debug)]
+pub struct Mapping {
+    segment: SharedSegment,
+
+    /// The VM's copy of the segment as of its last sync
+    synced: Vec<ProgramElement>,
+}
+
+impl Mapping {
+    /// Publishes every address in the segment that `vm` has changed since it was last synced, then
+    /// updates `vm` with everything the other VMs have published. Where more than one VM changed
+    /// the same address, the last one to sync wins.
+    ///
+    /// Returns how many addresses `vm` published. `vm` must be the VM the mapping was attached to.
+    pub fn sync<I, O>(&mut self, vm: &mut ProgramState<I, O>) -> usize {
+        let addrs = self.segment.addrs();
+        let mut contents = self.segment.lock();
+
+        let mut published = 0;
+        for (idx, addr) in addrs.clone().enumerate() {
+            let value = vm.mem.read_addr(addr);
+            if value != self.synced[idx] {
+                contents[idx] = value;
+                published += 1;
+            }
+        }
+
+        for (idx, addr) in addrs.enumerate() {
+            if vm.mem.read_addr(addr) != contents[idx] {
+                vm.mem.write_addr(addr, contents[idx]);
+            }
+        }
+
+        self.synced.copy_from_slice(&contents);
+        published
+    }
+
+    pub fn segment(&self) -> &SharedSegment {
+        &self.segment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync() {
+        // Adds its input to 10, and outputs 11
+        let program = ProgramState::load_program_str("3,9,1,9,10,10,4,11,99,0");
+        let segment = SharedSegment::new(10..12);
+        let mut a = program.clone();
+        let mut b = program;
+        let mut a_mapping = segment.attach(&mut a);
+        let mut b_mapping = segment.attach(&mut b);
+
+        a.inputs.push_back(3);
+        b.inputs.push_back(4);
+        b.mem.write_addr(11, 5);
+        a.run_to_completion().unwrap();
+        b.run_to_completion().unwrap();
+        assert_eq!(a.outputs, vec![0]);
+
+        // Both wrote 10, so b's write wins as it syncs last. Only b wrote 11, so a sees it.
+        assert_eq!(a_mapping.sync(&mut a), 1);
+        assert_eq!(b_mapping.sync(&mut b), 2);
+        assert_eq!(segment.read(10), 4);
+        assert_eq!(segment.read(11), 5);
+        assert_eq!(a.mem.read_addr(10), 3);
+
+        assert_eq!(a_mapping.sync(&mut a), 0);
+        assert_eq!(a.mem.read_range(10, 2), vec![4, 5]);
+
+        // Attaching later picks up what's been published so far
+        let mut c = ProgramState::load_program_str("4,11,99");
+        segment.attach(&mut c);
+        c.run_to_completion().unwrap();
+        assert_eq!(c.outputs, vec![5]);
+    }
+}
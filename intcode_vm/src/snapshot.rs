@@ -0,0 +1,69 @@
+//! Snapshots of a VM, for finding out exactly which memory a stretch of execution changed.
+//!
+//! A snapshot shares its memory with the VM until either of them writes to it, so taking one is
+//! cheap, and diffing two only has to compare the pages written to in between.
+//!
+//! ```
+//! use intcode_vm::ProgramState;
+//!
+//! let mut program = ProgramState::load_program_str("1101,2,3,7,99,0,0,0");
+//! let before = program.snapshot();
+//! program.run_to_completion().unwrap();
+//!
+//! assert_eq!(before.diff(&program.snapshot()), vec![(7, 0, 5)]);
+//! ```
+
+use crate::prelude::*;
+use crate::{PagedMemory, ProgramElement, ProgramState};
+
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub mem: PagedMemory<ProgramElement>,
+    pub program_counter: usize,
+    pub relative_base: ProgramElement,
+}
+
+impl Snapshot {
+    /// Every address whose value changed between this snapshot and `later`, as
+    /// `(addr, before, after)` in address order
+    pub fn diff(&self, later: &Snapshot) -> Vec<(usize, ProgramElement, ProgramElement)> {
+        self.mem.diff(&later.mem)
+    }
+}
+
+impl<I, O> ProgramState<I, O> {
+    /// Captures the memory, program counter and relative base as they are now
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            mem: self.mem.clone(),
+            program_counter: self.program_counter,
+            relative_base: self.relative_base,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_across_inputs() {
+        // Stores each input at 100 onwards, counting them at 99
+        //   0: in [rb + 100]
+        //   2: arb 1
+        //   4: add [99], 1, [99]
+        //   8: jz 0, 0
+        let mut program = ProgramState::load_program_str("203,100,109,1,1001,99,1,99,1106,0,0");
+        program.inputs.extend(&[5, 6]);
+        program.run_to_next_input().unwrap();
+        let first = program.snapshot();
+
+        program.inputs.push_back(7);
+        program.run_to_next_input().unwrap();
+        let second = program.snapshot();
+
+        assert_eq!(first.relative_base, 2);
+        assert_eq!(second.relative_base, 3);
+        assert_eq!(first.diff(&second), vec![(99, 2, 3), (102, 0, 7)]);
+    }
+}
@@ -0,0 +1,267 @@
+//! Writing springscript, the language a springdroid program takes as its ASCII input, with the
+//! mistakes that the droid would only report as a wrong answer caught up front.
+//!
+//! ```
+//! use intcode_vm::springscript::{Mode, Register::*, Springscript};
+//!
+//! // Jump if there's a hole in the next three tiles, and ground to land on
+//! let script = Springscript::new()
+//!     .not(A, J)
+//!     .not(B, T)
+//!     .or(T, J)
+//!     .not(C, T)
+//!     .or(T, J)
+//!     .and(D, J);
+//!
+//! assert_eq!(
+//!     script.compile(Mode::Walk).unwrap(),
+//!     "NOT A J\nNOT B T\nOR T J\nNOT C T\nOR T J\nAND D J\nWALK\n",
+//! );
+//! ```
+
+use std::collections::VecDeque;
+
+use crate::{ProgramElement, ProgramState};
+
+/// The most instructions the springdroid's memory can hold
+pub const MAX_INSTRUCTIONS: usize = 15;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Register {
+    /// Whether there's ground 1 to 9 tiles ahead. Only `A` to `D` can be used when walking.
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+
+    /// Temporary value, starts out false
+    T,
+
+    /// Whether to jump, starts out false
+    J,
+}
+
+impl Register {
+    pub fn name(self) -> char {
+        match self {
+            Register::A => 'A',
+            Register::B => 'B',
+            Register::C => 'C',
+            Register::D => 'D',
+            Register::E => 'E',
+            Register::F => 'F',
+            Register::G => 'G',
+            Register::H => 'H',
+            Register::I => 'I',
+            Register::T => 'T',
+            Register::J => 'J',
+        }
+    }
+
+    /// Only `T` and `J` can be written to
+    pub fn is_writable(self) -> bool {
+        matches!(self, Register::T | Register::J)
+    }
+
+    /// Whether the droid can read this register in `mode`
+    pub fn is_available(self, mode: Mode) -> bool {
+        match self {
+            Register::E | Register::F | Register::G | Register::H | Register::I => mode == Mode::Run,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Op {
+    /// `dest = src && dest`
+    And,
+
+    /// `dest = src || dest`
+    Or,
+
+    /// `dest = !src`
+    Not,
+}
+
+impl Op {
+    pub fn name(self) -> &'static str {
+        match self {
+            Op::And => "AND",
+            Op::Or => "OR",
+            Op::Not => "NOT",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Instruction {
+    pub op: Op,
+    pub src: Register,
+    pub dest: Register,
+}
+
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.op.name(), self.src.name(), self.dest.name())
+    }
+}
+
+/// The command that ends a script and starts the droid moving
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Mode {
+    Walk,
+
+    /// Sees further ahead, so can use registers `E` to `I`
+    Run,
+}
+
+impl Mode {
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Walk => "WALK",
+            Mode::Run => "RUN",
+        }
+    }
+}
+
+/// Everything that makes the droid reject a script
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpringscriptError {
+    TooManyInstructions(usize),
+
+    /// Instruction `index` (counting from 0) writes to a register other than `T` or `J`
+    NotWritable {
+        index: usize,
+        register: Register,
+    },
+
+    /// Instruction `index` (counting from 0) reads a register that isn't available when walking
+    NotAvailable {
+        index: usize,
+        register: Register,
+    },
+}
+
+impl std::fmt::Display for SpringscriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SpringscriptError::TooManyInstructions(count) => {
+                write!(f, "Script has {} instructions, the limit is {}", count, MAX_INSTRUCTIONS)
+            }
+            SpringscriptError::NotWritable { index, register } => {
+                write!(f, "Instruction {} writes to {}, which isn't writable", index, register.name())
+            }
+            SpringscriptError::NotAvailable { index, register } => {
+                write!(f, "Instruction {} reads {}, which is only available when running", index, register.name())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SpringscriptError {}
+
+/// A script, built up an instruction at a time. Nothing is checked until `compile`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Springscript {
+    pub instructions: Vec<Instruction>,
+}
+
+impl Springscript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn instruction(mut self, op: Op, src: Register, dest: Register) -> Self {
+        self.instructions.push(Instruction { op, src, dest });
+        self
+    }
+
+    pub fn and(self, src: Register, dest: Register) -> Self {
+        self.instruction(Op::And, src, dest)
+    }
+
+    pub fn or(self, src: Register, dest: Register) -> Self {
+        self.instruction(Op::Or, src, dest)
+    }
+
+    pub fn not(self, src: Register, dest: Register) -> Self {
+        self.instruction(Op::Not, src, dest)
+    }
+
+    pub fn validate(&self, mode: Mode) -> Result<(), SpringscriptError> {
+        if self.instructions.len() > MAX_INSTRUCTIONS {
+            return Err(SpringscriptError::TooManyInstructions(self.instructions.len()));
+        }
+
+        for (index, instruction) in self.instructions.iter().enumerate() {
+            if !instruction.dest.is_writable() {
+                return Err(SpringscriptError::NotWritable { index, register: instruction.dest });
+            }
+            if !instruction.src.is_available(mode) {
+                return Err(SpringscriptError::NotAvailable { index, register: instruction.src });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The ASCII text to send to the droid, a line per instruction followed by the mode
+    pub fn compile(&self, mode: Mode) -> Result<String, SpringscriptError> {
+        self.validate(mode)?;
+
+        let mut text = String::new();
+        for instruction in &self.instructions {
+            text.push_str(&instruction.to_string());
+            text.push('\n');
+        }
+        text.push_str(mode.name());
+        text.push('\n');
+        Ok(text)
+    }
+}
+
+impl<O> ProgramState<VecDeque<ProgramElement>, O> {
+    /// Queues the compiled script as input, see `Springscript::compile`
+    pub fn push_springscript(&mut self, script: &Springscript, mode: Mode) -> Result<(), SpringscriptError> {
+        let text = script.compile(mode)?;
+        self.inputs.extend(text.bytes().map(ProgramElement::from));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::Register::*;
+
+    #[test]
+    fn test_validate() {
+        let script = Springscript::new().not(A, J).and(H, J);
+        assert_eq!(script.compile(Mode::Run), Ok("NOT A J\nAND H J\nRUN\n".to_string()));
+        assert_eq!(script.validate(Mode::Walk), Err(SpringscriptError::NotAvailable { index: 1, register: H }));
+
+        let script = Springscript::new().or(A, T).or(T, D);
+        assert_eq!(script.validate(Mode::Walk), Err(SpringscriptError::NotWritable { index: 1, register: D }));
+
+        let script = (0..16).fold(Springscript::new(), |script, _| script.not(A, J));
+        assert_eq!(script.validate(Mode::Walk), Err(SpringscriptError::TooManyInstructions(16)));
+    }
+
+    #[test]
+    fn test_push_springscript() {
+        let mut program = ProgramState::load_program_str("99");
+        program.push_springscript(&Springscript::new().not(D, J), Mode::Walk).unwrap();
+        assert_eq!(program.inputs.len(), 13);
+        assert_eq!(program.inputs[0], 'N' as ProgramElement);
+        assert_eq!(program.inputs[12], '\n' as ProgramElement);
+
+        assert!(program.push_springscript(&Springscript::new().not(D, A), Mode::Walk).is_err());
+        assert_eq!(program.inputs.len(), 13);
+    }
+}
@@ -0,0 +1,66 @@
+use crate::OpCode;
+
+/// Counts of the instructions a VM has executed, see `ProgramState::stats`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExecutionStats {
+    /// Indexed by the opcode's position in `OpCode::ALL`
+    counts: [u64; OpCode::ALL.len()],
+}
+
+impl ExecutionStats {
+    #[inline(always)]
+    pub(crate) fn record(&mut self, opcode: OpCode) {
+        self.counts[opcode as usize] += 1;
+    }
+
+    /// Total number of instructions executed
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Number of instructions executed with the given opcode
+    pub fn count(&self, opcode: OpCode) -> u64 {
+        self.counts[opcode as usize]
+    }
+
+    /// The count for every opcode, in the order of `OpCode::ALL`
+    pub fn iter(&self) -> impl Iterator<Item = (OpCode, u64)> + '_ {
+        OpCode::ALL.iter().cloned().zip(self.counts.iter().cloned())
+    }
+}
+
+impl core::fmt::Display for ExecutionStats {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        writeln!(f, "{:<5} {:>12}", "total", self.total())?;
+        for (opcode, count) in self.iter().filter(|&(_, count)| count > 0) {
+            writeln!(f, "{:<5} {:>12}", opcode.mnemonic(), count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::{OpCode, ProgramState};
+
+    #[test]
+    fn test_counts() {
+        // Counts down from 3, outputting each number along the way
+        let mut program = ProgramState::load_program_str("3,12,4,12,1001,12,-1,12,1005,12,2,99,0");
+        program.inputs.push_back(3);
+        program.run_to_completion().unwrap();
+        assert_eq!(program.outputs, vec![3, 2, 1]);
+
+        let stats = program.stats();
+        assert_eq!(stats.count(OpCode::ReadInput), 1);
+        assert_eq!(stats.count(OpCode::WriteOutput), 3);
+        assert_eq!(stats.count(OpCode::Add), 3);
+        assert_eq!(stats.count(OpCode::JumpIfTrue), 3);
+        assert_eq!(stats.count(OpCode::Terminate), 1);
+        assert_eq!(stats.total(), 11);
+
+        program.reset_stats();
+        assert_eq!(program.stats().total(), 0);
+    }
+}
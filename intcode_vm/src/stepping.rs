@@ -0,0 +1,185 @@
+//! Stepping over calls and out of functions, so that a debugger doesn't have to single step
+//! through every routine a program calls.
+//!
+//! Intcode has no call or return instructions, so they're inferred as the program runs, the same
+//! way `analysis::control_flow_graph` finds calls. A call is a jump to a literal address straight
+//! after an add or multiply that stores the address following the jump, and a return is any jump
+//! taken to an address read from memory:
+//!
+//! ```
+//! use intcode_vm::{ProgramState, StopReason};
+//!
+//! //   0: add 7, 0, [100]
+//! //   4: jt 1, 10       ; call 10, returning to 7
+//! //   7: out [101]
+//! //   9: halt
+//! //  10: add [101], 5, [101]
+//! //  14: jt 1, [100]    ; return
+//! let mut program = ProgramState::load_program_str("1101,7,0,100,1105,1,10,4,101,99,1001,101,5,101,105,1,100");
+//! assert_eq!(program.step_over(), Ok(StopReason::Stepped));
+//! assert_eq!(program.step_over(), Ok(StopReason::Stepped));
+//! assert_eq!(program.program_counter, 7);
+//! assert_eq!(program.mem.read_addr(101), 5);
+//! ```
+//!
+//! Programs that don't follow these conventions, eg by jumping through tables of addresses, can
+//! make either of these stop early or run on past where they should have stopped.
+
+use crate::analysis::{self, DecodedInstruction};
+use crate::{InputSource, OpCode, OutputSink, ParameterMode, ProgramState, StopReason, VmError};
+
+/// How executing an instruction moves between functions, if it jumps
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Transfer {
+    Call,
+    Return,
+    None,
+}
+
+impl<I: InputSource, O: OutputSink> ProgramState<I, O> {
+    /// Executes the next instruction, and if it's a call, carries on until the function returns.
+    /// Returns `StopReason::Stepped` once it's done, or the reason it stopped early, as for
+    /// `run_to_next_input`.
+    pub fn step_over(&mut self) -> Result<StopReason, VmError> {
+        match self.transfer() {
+            Transfer::Call => self.run_until_depth(0),
+            _ => {
+                match self.progress_state() {
+                    Ok(_) => (),
+                    Err(VmError::NoInput) => return Ok(StopReason::NeedsInput),
+                    Err(e) => return Err(e),
+                }
+
+                if self.terminated {
+                    return Ok(StopReason::Terminated);
+                }
+                Ok(self.should_stop().unwrap_or(StopReason::Stepped))
+            }
+        }
+    }
+
+    /// Runs until the function the program counter is in returns, then returns
+    /// `StopReason::Stepped`. Any calls made before then are run to completion. If the program is
+    /// stopped early, returns why, as for `run_to_next_input`.
+    pub fn step_out(&mut self) -> Result<StopReason, VmError> {
+        self.run_until_depth(-1)
+    }
+
+    /// Runs until there have been `target` more calls than returns, checked after each one
+    fn run_until_depth(&mut self, target: isize) -> Result<StopReason, VmError> {
+        let mut depth = 0;
+        while !self.terminated {
+            let transfer = self.transfer();
+            let next = self.program_counter + OpCode::JumpIfTrue.length();
+            match self.progress_state() {
+                Ok(_) => (),
+                Err(VmError::NoInput) => return Ok(StopReason::NeedsInput),
+                Err(e) => return Err(e),
+            }
+
+            if let Some(reason) = self.should_stop() {
+                return Ok(reason);
+            }
+
+            // A jump that isn't taken doesn't go anywhere
+            if transfer != Transfer::None && self.program_counter != next {
+                depth += if transfer == Transfer::Call { 1 } else { -1 };
+                if depth == target {
+                    return Ok(StopReason::Stepped);
+                }
+            }
+        }
+
+        Ok(StopReason::Terminated)
+    }
+
+    /// What the instruction at the program counter does if it jumps
+    fn transfer(&self) -> Transfer {
+        let pc = self.program_counter;
+        let window = self.mem.read_range(pc, OpCode::JumpIfTrue.length());
+        let target = match DecodedInstruction::decode(&window, 0) {
+            DecodedInstruction::Op { opcode: OpCode::JumpIfTrue, parameters }
+            | DecodedInstruction::Op { opcode: OpCode::JumpIfFalse, parameters } => parameters[1],
+            _ => return Transfer::None,
+        };
+
+        if target.0 != ParameterMode::Immediate {
+            return Transfer::Return;
+        }
+
+        // The return address is stored by the instruction before the jump
+        let store = match pc.checked_sub(OpCode::Add.length()) {
+            Some(store) => self.mem.read_range(store, OpCode::Add.length()),
+            None => return Transfer::None,
+        };
+        if analysis::stores_return(&DecodedInstruction::decode(&store, 0), pc + OpCode::JumpIfTrue.length()) {
+            Transfer::Call
+        } else {
+            Transfer::None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::{ProgramState, StopReason};
+
+    /// Adds 1 to 101 in an inner function, then 5 in an outer one that calls it, then outputs 101
+    ///   0: add 7, 0, [100]
+    ///   4: jt 1, 20        ; call 20
+    ///   7: out [101]
+    ///   9: halt
+    ///  20: add 27, 0, [102]
+    ///  24: jt 1, 40        ; call 40
+    ///  27: add [101], 5, [101]
+    ///  31: jt 1, [100]     ; return
+    ///  40: add [101], 1, [101]
+    ///  44: jt 1, [102]     ; return
+    const NESTED: &str = "1101,7,0,100,1105,1,20,4,101,99,0,0,0,0,0,0,0,0,0,0,\
+                          1101,27,0,102,1105,1,40,1001,101,5,101,105,1,100,0,0,0,0,0,0,\
+                          1001,101,1,101,105,1,102";
+
+    #[test]
+    fn test_step_over() {
+        let mut program = ProgramState::load_program_str(NESTED);
+        assert_eq!(program.step_over(), Ok(StopReason::Stepped));
+        assert_eq!(program.program_counter, 4);
+        assert_eq!(program.step_over(), Ok(StopReason::Stepped));
+        assert_eq!(program.program_counter, 7);
+        assert_eq!(program.mem.read_addr(101), 6);
+        assert_eq!(program.step_over(), Ok(StopReason::Stepped));
+        assert_eq!(program.step_over(), Ok(StopReason::Terminated));
+        assert_eq!(program.outputs, vec![6]);
+
+        // Breakpoints inside the call still stop it
+        let mut program = ProgramState::load_program_str(NESTED);
+        program.breakpoints.insert(40);
+        program.program_counter = 4;
+        program.mem.write_addr(100, 7);
+        assert_eq!(program.step_over(), Ok(StopReason::Breakpoint(40)));
+    }
+
+    #[test]
+    fn test_step_out() {
+        let mut program = ProgramState::load_program_str(NESTED);
+        program.run_for(3).unwrap();
+        assert_eq!(program.program_counter, 24);
+
+        // Steps over the inner call on the way out of the outer function
+        let mut outer = program.clone();
+        assert_eq!(outer.step_out(), Ok(StopReason::Stepped));
+        assert_eq!(outer.program_counter, 7);
+        assert_eq!(outer.mem.read_addr(101), 6);
+
+        program.progress_state().unwrap();
+        assert_eq!(program.program_counter, 40);
+        assert_eq!(program.step_out(), Ok(StopReason::Stepped));
+        assert_eq!(program.program_counter, 27);
+        assert_eq!(program.mem.read_addr(101), 1);
+
+        // Then out of the outer function, after which there's nothing left to return from
+        assert_eq!(program.step_out(), Ok(StopReason::Stepped));
+        assert_eq!(program.step_out(), Ok(StopReason::Terminated));
+    }
+}
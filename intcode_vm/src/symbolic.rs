@@ -0,0 +1,735 @@
+//! An experimental symbolic execution engine, for working out which inputs or initial memory make
+//! a program do something, eg output 1 from a password check, without trying every combination.
+//!
+//! Chosen inputs and memory cells are treated as symbols, each with a range of values it can
+//! take. Values computed from them are kept as linear combinations of the symbols, and every
+//! branch on one splits execution in two, each side constrained by which way the branch went.
+//! Once a path reaches the goal, its constraints are solved for values of the symbols:
+//!
+//! ```
+//! use intcode_vm::symbolic::{Goal, SymbolicEngine};
+//! use intcode_vm::ProgramState;
+//!
+//! // Reads three characters, and outputs 1 if they're "abc", or 0 otherwise
+//! let program = ProgramState::load_program_str(
+//!     "3,100,1008,100,97,101,1006,101,30,3,100,1008,100,98,101,1006,101,30,\
+//!      3,100,1008,100,99,101,1006,101,30,104,1,99,104,0,99",
+//! );
+//!
+//! let mut engine = SymbolicEngine::new(&program);
+//! for _ in 0..3 {
+//!     engine.symbolic_input(0..=127);
+//! }
+//!
+//! let solution = engine.solve(Goal::Output { idx: 0, value: 1 }).unwrap().unwrap();
+//! assert_eq!(solution.inputs, vec![97, 98, 99]);
+//! ```
+//!
+//! Only a limited subset of programs can be handled. Multiplying two symbolic values, or reading
+//! through a symbolic address, gives a value that can't be used for anything but overwriting, and
+//! branching on one, writing through a symbolic address, jumping to one or adjusting the relative
+//! base by one all fail with `SymbolicError::Unsupported`. Solving is a search over the symbols'
+//! ranges, so they should be kept small, and arithmetic wraps rather than overflowing.
+
+use alloc::collections::BTreeMap;
+use core::ops::RangeInclusive;
+
+use crate::prelude::*;
+use crate::{Instruction, OpCode, PagedMemory, ParameterMode, ProgramElement, ProgramState, VmError};
+
+/// Where a symbol's value goes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Symbol {
+    /// The `n`th input read, from 0
+    Input(usize),
+
+    /// The initial value at an address
+    Memory(usize),
+}
+
+/// A constant plus a multiple of each symbol, identified by the order they were added in
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Linear {
+    pub constant: ProgramElement,
+
+    /// Coefficients of each symbol, none of which are 0
+    pub terms: BTreeMap<usize, ProgramElement>,
+}
+
+impl Linear {
+    pub fn constant(constant: ProgramElement) -> Self {
+        Self { constant, terms: BTreeMap::new() }
+    }
+
+    pub fn symbol(symbol: usize) -> Self {
+        let mut terms = BTreeMap::new();
+        terms.insert(symbol, 1);
+        Self { constant: 0, terms }
+    }
+
+    /// The value, if it doesn't depend on any symbols
+    pub fn as_constant(&self) -> Option<ProgramElement> {
+        if self.terms.is_empty() {
+            Some(self.constant)
+        } else {
+            None
+        }
+    }
+
+    pub fn add(&self, other: &Linear) -> Linear {
+        let mut sum = self.clone();
+        sum.constant = sum.constant.wrapping_add(other.constant);
+        for (&symbol, &coefficient) in &other.terms {
+            let term = sum.terms.entry(symbol).or_insert(0);
+            *term = term.wrapping_add(coefficient);
+            if *term == 0 {
+                sum.terms.remove(&symbol);
+            }
+        }
+        sum
+    }
+
+    pub fn scale(&self, factor: ProgramElement) -> Linear {
+        Linear {
+            constant: self.constant.wrapping_mul(factor),
+            terms: self.terms
+                .iter()
+                .map(|(&symbol, &coefficient)| (symbol, coefficient.wrapping_mul(factor)))
+                .filter(|&(_, coefficient)| coefficient != 0)
+                .collect(),
+        }
+    }
+
+    pub fn sub(&self, other: &Linear) -> Linear {
+        self.add(&other.scale(-1))
+    }
+
+    /// The value given the value of every symbol
+    pub fn eval(&self, values: &[ProgramElement]) -> ProgramElement {
+        self.terms.iter().fold(self.constant, |total, (&symbol, &coefficient)| {
+            total.wrapping_add(coefficient.wrapping_mul(values[symbol]))
+        })
+    }
+
+    /// The smallest and largest the value can be, given the symbols that already have values
+    /// and the ranges of the rest
+    fn bounds(&self, ranges: &[RangeInclusive<ProgramElement>], values: &[Option<ProgramElement>]) -> (ProgramElement, ProgramElement) {
+        let (mut lo, mut hi) = (self.constant, self.constant);
+        for (&symbol, &coefficient) in &self.terms {
+            let (a, b) = match values[symbol] {
+                Some(value) => (value, value),
+                None => (*ranges[symbol].start(), *ranges[symbol].end()),
+            };
+            let (a, b) = (coefficient.saturating_mul(a), coefficient.saturating_mul(b));
+            lo = lo.saturating_add(a.min(b));
+            hi = hi.saturating_add(a.max(b));
+        }
+        (lo, hi)
+    }
+}
+
+/// How a `Constraint`'s expression compares to 0
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Relation {
+    Zero,
+    NonZero,
+    Negative,
+    NonNegative,
+}
+
+impl Relation {
+    /// Whether a value between `lo` and `hi` inclusive could satisfy the relation
+    fn possible(&self, lo: ProgramElement, hi: ProgramElement) -> bool {
+        match self {
+            Relation::Zero => lo <= 0 && hi >= 0,
+            Relation::NonZero => lo != 0 || hi != 0,
+            Relation::Negative => lo < 0,
+            Relation::NonNegative => hi >= 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Constraint {
+    pub expr: Linear,
+    pub relation: Relation,
+}
+
+/// What the program should do for the values being solved for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Goal {
+    /// Output `value` as its `idx`th output, from 0
+    Output { idx: usize, value: ProgramElement },
+
+    /// Terminate with `value` at `addr`, as day 2 asks for
+    Memory { addr: usize, value: ProgramElement },
+}
+
+/// Values for the symbols that reach a goal
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Solution {
+    /// Values of the symbolic inputs, in the order they're read
+    pub inputs: Vec<ProgramElement>,
+
+    /// Values of the symbolic memory cells, see `ProgramState::apply_patches`
+    pub patches: Vec<(usize, ProgramElement)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SymbolicError {
+    /// The instruction at `pc` does something with a symbolic value that the engine can't follow
+    Unsupported { pc: usize, what: &'static str },
+
+    /// A path fails as the program would, eg on an unknown opcode
+    Vm(VmError),
+
+    /// More than `SymbolicEngine::max_paths` paths were explored without reaching the goal
+    TooManyPaths,
+
+    /// More than `SymbolicEngine::max_instructions` were executed without reaching the goal
+    TooManyInstructions,
+}
+
+impl core::fmt::Display for SymbolicError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            SymbolicError::Unsupported { pc, what } => {
+                write!(f, "Can't execute the instruction at address {} symbolically, as it's {}", pc, what)
+            }
+            SymbolicError::Vm(e) => write!(f, "{}", e),
+            SymbolicError::TooManyPaths => write!(f, "Gave up after exploring too many paths"),
+            SymbolicError::TooManyInstructions => write!(f, "Gave up after executing too many instructions"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SymbolicError {}
+
+impl From<VmError> for SymbolicError {
+    fn from(e: VmError) -> Self {
+        SymbolicError::Vm(e)
+    }
+}
+
+/// A value in memory, which is `Unknown` once it's been computed from symbols non-linearly
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Value {
+    Linear(Linear),
+    Unknown,
+}
+
+/// What executing an instruction on a path did
+enum Step {
+    Continue,
+
+    /// The path split in two, and this is the other side
+    Fork(Path),
+
+    Terminated,
+
+    /// The program read more inputs than there are symbolic ones
+    OutOfInputs,
+}
+
+/// One way through the program
+#[derive(Clone)]
+struct Path {
+    /// Concrete memory, ignored wherever `symbolic` has a value
+    mem: PagedMemory<ProgramElement>,
+    symbolic: BTreeMap<usize, Value>,
+    pc: usize,
+    relative_base: ProgramElement,
+    inputs_read: usize,
+    outputs: Vec<Value>,
+    constraints: Vec<Constraint>,
+}
+
+impl Path {
+    fn read(&self, addr: usize) -> Value {
+        match self.symbolic.get(&addr) {
+            Some(value) => value.clone(),
+            None => Value::Linear(Linear::constant(self.mem.read_addr(addr))),
+        }
+    }
+
+    fn write(&mut self, addr: usize, value: Value) {
+        match value {
+            Value::Linear(linear) if linear.terms.is_empty() => {
+                self.symbolic.remove(&addr);
+                self.mem.write_addr(addr, linear.constant);
+            }
+            value => {
+                self.symbolic.insert(addr, value);
+            }
+        }
+    }
+
+    fn unsupported(&self, what: &'static str) -> SymbolicError {
+        SymbolicError::Unsupported { pc: self.pc, what }
+    }
+
+    /// The value of `value`, which has to be known
+    fn concrete(&self, value: &Value, what: &'static str) -> Result<ProgramElement, SymbolicError> {
+        match value {
+            Value::Linear(linear) => linear.as_constant().ok_or_else(|| self.unsupported(what)),
+            Value::Unknown => Err(self.unsupported(what)),
+        }
+    }
+
+    /// The address a position or relative mode parameter refers to, which fails as `what` if
+    /// it's symbolic
+    fn param_addr(&self, instr: &Instruction, idx: usize, what: &'static str) -> Result<usize, SymbolicError> {
+        let raw = self.read(self.pc + 1 + idx);
+        let raw = self.concrete(&raw, what)?;
+        let addr = match instr.modes[idx] {
+            ParameterMode::Relative => self.relative_base + raw,
+            _ => raw,
+        };
+        if addr < 0 {
+            return Err(VmError::NegativeAddress { pc: self.pc, addr }.into());
+        }
+        Ok(addr as usize)
+    }
+
+    fn read_param(&self, instr: &Instruction, idx: usize) -> Result<Value, SymbolicError> {
+        if instr.modes[idx] == ParameterMode::Immediate {
+            return Ok(self.read(self.pc + 1 + idx));
+        }
+
+        // Reading through an address computed from a symbol could read anything
+        match self.param_addr(instr, idx, "reading from a symbolic address") {
+            Ok(addr) => Ok(self.read(addr)),
+            Err(SymbolicError::Unsupported { .. }) => Ok(Value::Unknown),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Splits the path on `value` compared to 0, returning the other side, with this side taking
+    /// `relation` and the other its opposite
+    fn fork(&mut self, value: Linear, relation: Relation, opposite: Relation) -> Path {
+        let mut other = self.clone();
+        other.constraints.push(Constraint { expr: value.clone(), relation: opposite });
+        self.constraints.push(Constraint { expr: value, relation });
+        other
+    }
+
+    fn step(&mut self, input_symbols: &[usize]) -> Result<Step, SymbolicError> {
+        let mut elements = [0; 4];
+        elements[0] = match self.read(self.pc) {
+            Value::Linear(linear) => linear.as_constant().ok_or_else(|| self.unsupported("a symbolic instruction"))?,
+            Value::Unknown => return Err(self.unsupported("a symbolic instruction")),
+        };
+        let instr = Instruction::decode_at(self.pc, elements)?;
+        let next_pc = self.pc + instr.opcode.length();
+
+        match instr.opcode {
+            OpCode::Add | OpCode::Multiply | OpCode::LessThan | OpCode::Equals => {
+                let (a, b) = (self.read_param(&instr, 0)?, self.read_param(&instr, 1)?);
+                let dest = self.param_addr(&instr, 2, "writing to a symbolic address")?;
+
+                let (a, b) = match (a, b) {
+                    (Value::Linear(a), Value::Linear(b)) => (a, b),
+                    _ if instr.opcode == OpCode::Add || instr.opcode == OpCode::Multiply => {
+                        self.write(dest, Value::Unknown);
+                        self.pc = next_pc;
+                        return Ok(Step::Continue);
+                    }
+                    _ => return Err(self.unsupported("comparing a non-linear value")),
+                };
+
+                let mut fork = None;
+                let result = match instr.opcode {
+                    OpCode::Add => Value::Linear(a.add(&b)),
+                    OpCode::Multiply => match (a.as_constant(), b.as_constant()) {
+                        (Some(a), _) => Value::Linear(b.scale(a)),
+                        (_, Some(b)) => Value::Linear(a.scale(b)),
+                        (None, None) => Value::Unknown,
+                    },
+                    _ => {
+                        let diff = a.sub(&b);
+                        let (relation, opposite) = if instr.opcode == OpCode::LessThan {
+                            (Relation::Negative, Relation::NonNegative)
+                        } else {
+                            (Relation::Zero, Relation::NonZero)
+                        };
+                        let holds = match diff.as_constant() {
+                            Some(diff) => relation.possible(diff, diff),
+                            None => {
+                                let mut other = self.fork(diff, relation, opposite);
+                                other.write(dest, Value::Linear(Linear::constant(0)));
+                                other.pc = next_pc;
+                                fork = Some(other);
+                                true
+                            }
+                        };
+                        Value::Linear(Linear::constant(holds as ProgramElement))
+                    }
+                };
+
+                self.write(dest, result);
+                self.pc = next_pc;
+                return Ok(fork.map_or(Step::Continue, Step::Fork));
+            }
+            OpCode::ReadInput => {
+                let dest = self.param_addr(&instr, 0, "writing to a symbolic address")?;
+                let symbol = match input_symbols.get(self.inputs_read) {
+                    Some(&symbol) => symbol,
+                    None => return Ok(Step::OutOfInputs),
+                };
+                self.write(dest, Value::Linear(Linear::symbol(symbol)));
+                self.inputs_read += 1;
+            }
+            OpCode::WriteOutput => {
+                let value = self.read_param(&instr, 0)?;
+                self.outputs.push(value);
+            }
+            OpCode::JumpIfTrue | OpCode::JumpIfFalse => {
+                let cond = match self.read_param(&instr, 0)? {
+                    Value::Linear(linear) => linear,
+                    Value::Unknown => return Err(self.unsupported("branching on a non-linear value")),
+                };
+                let target = self.read_param(&instr, 1)?;
+                let target = self.concrete(&target, "jumping to a symbolic address")? as usize;
+                let (jump, fall) = if instr.opcode == OpCode::JumpIfTrue {
+                    (Relation::NonZero, Relation::Zero)
+                } else {
+                    (Relation::Zero, Relation::NonZero)
+                };
+
+                match cond.as_constant() {
+                    Some(cond) if jump.possible(cond, cond) => self.pc = target,
+                    Some(_) => self.pc = next_pc,
+                    None => {
+                        let mut other = self.fork(cond, jump, fall);
+                        self.pc = target;
+                        other.pc = next_pc;
+                        return Ok(Step::Fork(other));
+                    }
+                }
+                return Ok(Step::Continue);
+            }
+            OpCode::AdjustRelativeBase => {
+                let value = self.read_param(&instr, 0)?;
+                self.relative_base += self.concrete(&value, "adjusting the relative base by a symbolic value")?;
+            }
+            OpCode::Terminate => return Ok(Step::Terminated),
+        }
+
+        self.pc = next_pc;
+        Ok(Step::Continue)
+    }
+}
+
+/// Values for every symbol within its range that satisfy every constraint, if there are any
+pub fn solve(constraints: &[Constraint], ranges: &[RangeInclusive<ProgramElement>]) -> Option<Vec<ProgramElement>> {
+    // Symbols that aren't constrained can be anything, so there's no need to search them
+    let mut values = ranges
+        .iter()
+        .enumerate()
+        .map(|(symbol, range)| {
+            if constraints.iter().any(|constraint| constraint.expr.terms.contains_key(&symbol)) {
+                None
+            } else {
+                Some(*range.start())
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if search(constraints, ranges, &mut values) {
+        Some(values.into_iter().map(Option::unwrap).collect())
+    } else {
+        None
+    }
+}
+
+/// Backtracking search for values of the symbols which don't have one yet
+fn search(
+    constraints: &[Constraint],
+    ranges: &[RangeInclusive<ProgramElement>],
+    values: &mut Vec<Option<ProgramElement>>,
+) -> bool {
+    for constraint in constraints {
+        let (lo, hi) = constraint.expr.bounds(ranges, values);
+        if !constraint.relation.possible(lo, hi) {
+            return false;
+        }
+    }
+
+    // An equality with only one symbol left to decide decides it
+    for constraint in constraints.iter().filter(|constraint| constraint.relation == Relation::Zero) {
+        let mut unknown = constraint.expr.terms.iter().filter(|&(&symbol, _)| values[symbol].is_none());
+        if let (Some((&symbol, &coefficient)), None) = (unknown.next(), unknown.next()) {
+            let rest = constraint.expr.eval(&values.iter().map(|value| value.unwrap_or(0)).collect::<Vec<_>>());
+            if rest % coefficient != 0 || !ranges[symbol].contains(&(-rest / coefficient)) {
+                return false;
+            }
+
+            values[symbol] = Some(-rest / coefficient);
+            if search(constraints, ranges, values) {
+                return true;
+            }
+            values[symbol] = None;
+            return false;
+        }
+    }
+
+    let symbol = match values.iter().position(Option::is_none) {
+        Some(symbol) => symbol,
+        None => return true,
+    };
+    for value in ranges[symbol].clone() {
+        values[symbol] = Some(value);
+        if search(constraints, ranges, values) {
+            return true;
+        }
+    }
+    values[symbol] = None;
+    false
+}
+
+/// See the module docs
+#[derive(Clone, Debug)]
+pub struct SymbolicEngine {
+    mem: PagedMemory<ProgramElement>,
+    program_counter: usize,
+    relative_base: ProgramElement,
+    symbols: Vec<(Symbol, RangeInclusive<ProgramElement>)>,
+
+    /// Gives up once this many paths have been explored, 10,000 by default
+    pub max_paths: usize,
+
+    /// Gives up once this many instructions have been executed across every path, 10 million by
+    /// default
+    pub max_instructions: u64,
+}
+
+impl SymbolicEngine {
+    /// An engine for the program as it is now, which doesn't have any symbols yet. Any inputs
+    /// already queued for the program are ignored.
+    pub fn new<I, O>(program: &ProgramState<I, O>) -> Self {
+        Self {
+            mem: program.mem.clone(),
+            program_counter: program.program_counter,
+            relative_base: program.relative_base,
+            symbols: Vec::new(),
+            max_paths: 10_000,
+            max_instructions: 10_000_000,
+        }
+    }
+
+    /// Makes the next input read a symbol with a value in `range`. Paths that read more inputs
+    /// than there are symbols for stop there, without reaching any goal.
+    pub fn symbolic_input(&mut self, range: RangeInclusive<ProgramElement>) {
+        let n = self.symbols.iter().filter(|(symbol, _)| matches!(symbol, Symbol::Input(_))).count();
+        self.symbols.push((Symbol::Input(n), range));
+    }
+
+    /// Makes the initial value at `addr` a symbol with a value in `range`
+    pub fn symbolic_memory(&mut self, addr: usize, range: RangeInclusive<ProgramElement>) {
+        self.symbols.push((Symbol::Memory(addr), range));
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = Symbol> + '_ {
+        self.symbols.iter().map(|(symbol, _)| *symbol)
+    }
+
+    /// Explores paths through the program until one can reach `goal`, and returns the values of
+    /// the symbols that make it do so, or `None` if no path can
+    pub fn solve(&self, goal: Goal) -> Result<Option<Solution>, SymbolicError> {
+        let ranges = self.symbols.iter().map(|(_, range)| range.clone()).collect::<Vec<_>>();
+        let input_symbols = self.symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, (symbol, _))| matches!(symbol, Symbol::Input(_)))
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+
+        let mut start = Path {
+            mem: self.mem.clone(),
+            symbolic: BTreeMap::new(),
+            pc: self.program_counter,
+            relative_base: self.relative_base,
+            inputs_read: 0,
+            outputs: Vec::new(),
+            constraints: Vec::new(),
+        };
+        for (idx, (symbol, _)) in self.symbols.iter().enumerate() {
+            if let Symbol::Memory(addr) = *symbol {
+                start.symbolic.insert(addr, Value::Linear(Linear::symbol(idx)));
+            }
+        }
+
+        let mut stack = vec![start];
+        let mut paths = 0;
+        let mut instructions = 0;
+        while let Some(mut path) = stack.pop() {
+            paths += 1;
+            if paths > self.max_paths {
+                return Err(SymbolicError::TooManyPaths);
+            }
+
+            let reached = loop {
+                instructions += 1;
+                if instructions > self.max_instructions {
+                    return Err(SymbolicError::TooManyInstructions);
+                }
+
+                match path.step(&input_symbols)? {
+                    Step::Continue => (),
+                    Step::Fork(other) => {
+                        if solve(&other.constraints, &ranges).is_some() {
+                            stack.push(other);
+                        }
+                        if solve(&path.constraints, &ranges).is_none() {
+                            break None;
+                        }
+                    }
+                    Step::Terminated => match goal {
+                        Goal::Memory { addr, value } => break Some((path.read(addr), value)),
+                        Goal::Output { .. } => break None,
+                    },
+                    Step::OutOfInputs => break None,
+                }
+
+                if let Goal::Output { idx, value } = goal {
+                    if let Some(output) = path.outputs.get(idx) {
+                        break Some((output.clone(), value));
+                    }
+                }
+            };
+
+            let (actual, expected) = match reached {
+                Some(reached) => reached,
+                None => continue,
+            };
+            let actual = match actual {
+                Value::Linear(linear) => linear,
+                Value::Unknown => return Err(path.unsupported("computing the goal non-linearly")),
+            };
+
+            let mut constraints = path.constraints;
+            constraints.push(Constraint { expr: actual.sub(&Linear::constant(expected)), relation: Relation::Zero });
+            if let Some(values) = solve(&constraints, &ranges) {
+                let mut solution = Solution::default();
+                for ((symbol, _), value) in self.symbols.iter().zip(values) {
+                    match *symbol {
+                        Symbol::Input(_) => solution.inputs.push(value),
+                        Symbol::Memory(addr) => solution.patches.push((addr, value)),
+                    }
+                }
+                return Ok(Some(solution));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve_memory() {
+        // As day 2, but much shorter
+        //   0: add [noun], [verb], [3]    ; overwritten straight away
+        //   4: mul [1], [17], [3]
+        //   8: add [3], [2], [0]
+        //  12: add [0], [18], [0]
+        //  16: halt
+        //  17: data
+        let program = ProgramState::load_program_str("1,0,0,3,2,1,17,3,1,3,2,0,1,0,18,0,99,1000,7");
+        let mut engine = SymbolicEngine::new(&program);
+        engine.symbolic_memory(1, 0..=99);
+        engine.symbolic_memory(2, 0..=99);
+
+        let solution = engine.solve(Goal::Memory { addr: 0, value: 42057 }).unwrap().unwrap();
+        assert_eq!(solution.patches, vec![(1, 42), (2, 50)]);
+        assert!(solution.inputs.is_empty());
+
+        let mut checked = program.clone();
+        for &(addr, value) in &solution.patches {
+            checked.mem.write_addr(addr, value);
+        }
+        checked.run_to_completion().unwrap();
+        assert_eq!(checked.mem.read_addr(0), 42057);
+
+        assert_eq!(engine.solve(Goal::Memory { addr: 0, value: 100_000 }), Ok(None));
+    }
+
+    #[test]
+    fn test_solve_branches() {
+        // Outputs 1 if the first input is less than 10 and the second is 3 more than the first
+        //   0: in [100]
+        //   2: in [101]
+        //   4: lt [100], 10, [102]
+        //   8: jz [102], 24
+        //  11: add [100], 3, [103]
+        //  15: eq [103], [101], [102]
+        //  19: jz [102], 24
+        //  22: out 1
+        //  24: out 0
+        //  26: halt
+        let source = "3,100,3,101,1007,100,10,102,1006,102,24,1001,100,3,103,8,103,101,102,1006,102,24,104,1,104,0,99";
+        let program = ProgramState::load_program_str(source);
+
+        let mut engine = SymbolicEngine::new(&program);
+        engine.symbolic_input(5..=20);
+        engine.symbolic_input(0..=20);
+        let solution = engine.solve(Goal::Output { idx: 0, value: 1 }).unwrap().unwrap();
+        assert_eq!(solution.inputs, vec![5, 8]);
+
+        let mut engine = SymbolicEngine::new(&program);
+        engine.symbolic_input(10..=20);
+        engine.symbolic_input(0..=20);
+        assert_eq!(engine.solve(Goal::Output { idx: 0, value: 1 }), Ok(None));
+        assert!(engine.solve(Goal::Output { idx: 0, value: 0 }).unwrap().is_some());
+
+        // Without enough inputs, no path reaches any output
+        let mut engine = SymbolicEngine::new(&program);
+        engine.symbolic_input(0..=20);
+        assert_eq!(engine.solve(Goal::Output { idx: 0, value: 0 }), Ok(None));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        // A non-linear value is fine as long as nothing depends on it
+        let program = ProgramState::load_program_str("3,11,3,12,2,11,12,13,1105,1,0,0,0,0");
+        let mut engine = SymbolicEngine::new(&program);
+        engine.symbolic_input(0..=9);
+        engine.symbolic_input(0..=9);
+        assert_eq!(engine.solve(Goal::Output { idx: 0, value: 0 }), Ok(None));
+
+        // Branches on the product of two inputs
+        let program = ProgramState::load_program_str("3,11,3,12,2,11,12,13,1005,13,0,0,0,0");
+        let mut engine = SymbolicEngine::new(&program);
+        engine.symbolic_input(0..=9);
+        engine.symbolic_input(0..=9);
+        assert_eq!(
+            engine.solve(Goal::Output { idx: 0, value: 0 }),
+            Err(SymbolicError::Unsupported { pc: 8, what: "branching on a non-linear value" }),
+        );
+
+        // Loops forever without reading anything
+        let mut engine = SymbolicEngine::new(&ProgramState::load_program_str("1105,1,0"));
+        engine.max_instructions = 100;
+        assert_eq!(engine.solve(Goal::Output { idx: 0, value: 0 }), Err(SymbolicError::TooManyInstructions));
+    }
+
+    #[test]
+    fn test_solver() {
+        let x = Linear::symbol(0);
+        let y = Linear::symbol(1);
+        let mut constraints = vec![
+            Constraint { expr: x.scale(3).add(&y).sub(&Linear::constant(20)), relation: Relation::Zero },
+            Constraint { expr: y.sub(&x), relation: Relation::Negative },
+        ];
+        assert_eq!(solve(&constraints, &[0..=10, 0..=10]), Some(vec![6, 2]));
+        assert_eq!(solve(&constraints, &[0..=10, 0..=10, -5..=5]), Some(vec![6, 2, -5]));
+
+        constraints.push(Constraint { expr: x.sub(&Linear::constant(6)), relation: Relation::NonZero });
+        assert_eq!(solve(&constraints, &[0..=10, 0..=10]), None);
+        assert_eq!(solve(&constraints, &[0..=10, -5..=10]), Some(vec![7, -1]));
+    }
+}
@@ -0,0 +1,150 @@
+//! Names for addresses in a program, eg the labels from assembling it or ones worked out while
+//! reverse engineering it, which listings and traces can show in place of the raw addresses.
+//!
+//! Symbol files have one symbol per line, giving the address then its name:
+//!
+//! ```text
+//! # Game state
+//! 386 score
+//! 388 ball_x
+//! ```
+//!
+//! Anything that can show symbols is formatted through `SymbolTable::annotate`:
+//!
+//! ```
+//! use intcode_vm::{asm, disasm, PagedMemory};
+//!
+//! let (program, symbols) = asm::assemble_with_symbols("
+//!     loop:
+//!         out [count]
+//!         jnz [count], loop
+//!     count:
+//!         data 0
+//! ").unwrap();
+//!
+//! let line = disasm::disassemble_one(&PagedMemory::from(program), 2);
+//! assert_eq!(symbols.annotate(&line).to_string(), "    2: 1005 5 0         jnz [count], loop");
+//! ```
+
+use alloc::collections::BTreeMap;
+
+use crate::prelude::*;
+use crate::LoadError;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SymbolTable {
+    names: BTreeMap<usize, String>,
+}
+
+/// Something formatted with the names from a symbol table, see `SymbolTable::annotate`
+pub struct Annotated<'a, T> {
+    pub item: &'a T,
+    pub symbols: &'a SymbolTable,
+}
+
+impl SymbolTable {
+    /// Names `addr`, replacing any name it already had
+    pub fn insert(&mut self, addr: usize, name: impl Into<String>) {
+        self.names.insert(addr, name.into());
+    }
+
+    pub fn remove(&mut self, addr: usize) -> Option<String> {
+        self.names.remove(&addr)
+    }
+
+    pub fn name(&self, addr: usize) -> Option<&str> {
+        self.names.get(&addr).map(|name| name.as_str())
+    }
+
+    /// The address with the given name, if there is one
+    pub fn addr(&self, name: &str) -> Option<usize> {
+        self.names.iter().find(|(_, n)| *n == name).map(|(&addr, _)| addr)
+    }
+
+    /// Every symbol, in address order
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.names.iter().map(|(&addr, name)| (addr, name.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Wraps `item` so that it's displayed with names in place of the addresses that have them
+    pub fn annotate<'a, T>(&'a self, item: &'a T) -> Annotated<'a, T> {
+        Annotated { item, symbols: self }
+    }
+
+    /// Formats `addr` as its name if it has one, or as the number otherwise
+    pub fn format_addr(&self, addr: usize) -> String {
+        match self.name(addr) {
+            Some(name) => name.to_string(),
+            None => addr.to_string(),
+        }
+    }
+
+    /// Parses a symbol file. Blank lines and anything following a `#` are ignored.
+    pub fn parse(source: &str) -> Result<Self, LoadError> {
+        let mut symbols = Self::default();
+        for (idx, line) in source.lines().enumerate() {
+            let text = line.split('#').next().unwrap().trim();
+            if text.is_empty() {
+                continue;
+            }
+
+            let invalid = || LoadError::InvalidSymbol { line: idx + 1, text: line.trim().to_string() };
+            let words = text.split_whitespace().collect::<Vec<_>>();
+            match words.as_slice() {
+                [addr, name] => symbols.insert(addr.parse().map_err(|_| invalid())?, *name),
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(symbols)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn load_file(path: &std::path::Path) -> Result<Self, LoadError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// Formats the table as a symbol file
+impl core::fmt::Display for SymbolTable {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for (addr, name) in self.iter() {
+            writeln!(f, "{} {}", addr, name)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let source = "
+            # Game state
+            386 score
+            388 ball_x  # Updated every frame
+        ";
+        let symbols = SymbolTable::parse(source).unwrap();
+        assert_eq!(symbols.iter().collect::<Vec<_>>(), vec![(386, "score"), (388, "ball_x")]);
+        assert_eq!(symbols.addr("ball_x"), Some(388));
+        assert_eq!(symbols.format_addr(387), "387");
+        assert_eq!(SymbolTable::parse(&symbols.to_string()).unwrap(), symbols);
+
+        assert!(matches!(
+            SymbolTable::parse("1 a\n2 b c"),
+            Err(LoadError::InvalidSymbol { line: 2, ref text }) if text == "2 b c"
+        ));
+        assert!(SymbolTable::parse("x name").is_err());
+    }
+}
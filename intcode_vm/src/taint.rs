@@ -0,0 +1,170 @@
+//! Taint tracking, which works out which inputs each output was computed from.
+//!
+//! Every input is tagged with its position in the order they're read, and the tags are carried
+//! along with each value as it's copied and combined, so an output is tagged with every input it
+//! was derived from:
+//!
+//! ```
+//! use intcode_vm::taint::Taint;
+//! use intcode_vm::ProgramState;
+//!
+//! // Reads three inputs, then outputs the sum of the first two and the third doubled
+//! let mut program = ProgramState::load_program_str("3,100,3,101,3,102,1,100,101,103,4,103,1002,102,2,104,4,104,99");
+//! program.inputs.extend(&[5, 6, 7]);
+//!
+//! let mut taint = Taint::default();
+//! program.run_tainted(&mut taint).unwrap();
+//! assert_eq!(taint.outputs[0].value, 11);
+//! assert_eq!(taint.outputs[0].inputs.iter().collect::<Vec<_>>(), vec![&0, &1]);
+//! assert_eq!(taint.outputs[1].inputs.iter().collect::<Vec<_>>(), vec![&2]);
+//! ```
+//!
+//! Only the flow of values is followed. Addresses computed from inputs, eg through the relative
+//! base, and jumps taken because of them don't taint anything, so a program that branches on an
+//! input and outputs a constant on each branch produces untainted outputs. Custom opcodes aren't
+//! supported, as with tracing.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use core::convert::TryFrom;
+
+use crate::prelude::*;
+use crate::{
+    InputSource, Instruction, OpCode, OutputSink, ParameterMode, ProgramElement, ProgramState, StepEvent, StopReason,
+    VmError,
+};
+
+/// An output, and the inputs it was derived from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TaintedOutput {
+    pub value: ProgramElement,
+
+    /// Positions of the inputs, in the order they were read from 0
+    pub inputs: BTreeSet<usize>,
+}
+
+/// The taint of every address in memory, see the module docs
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Taint {
+    /// Only addresses with a non-empty taint are kept
+    memory: BTreeMap<usize, BTreeSet<usize>>,
+
+    /// How many inputs have been read
+    pub inputs_read: usize,
+
+    /// Every output produced while tracking, in order
+    pub outputs: Vec<TaintedOutput>,
+}
+
+impl Taint {
+    /// The inputs the value at `addr` was derived from
+    pub fn of(&self, addr: usize) -> BTreeSet<usize> {
+        self.memory.get(&addr).cloned().unwrap_or_default()
+    }
+
+    /// Every address holding a value derived from an input, in order
+    pub fn tainted_addrs(&self) -> impl Iterator<Item = usize> + '_ {
+        self.memory.keys().cloned()
+    }
+
+    fn set(&mut self, addr: usize, taint: BTreeSet<usize>) {
+        if taint.is_empty() {
+            self.memory.remove(&addr);
+        } else {
+            self.memory.insert(addr, taint);
+        }
+    }
+}
+
+impl<I: InputSource, O: OutputSink> ProgramState<I, O> {
+    /// As `progress_state`, updating `taint` with what the instruction did. Nothing is updated if
+    /// this returns an error.
+    pub fn progress_state_tainted(&mut self, taint: &mut Taint) -> Result<StepEvent, VmError> {
+        let instr = Instruction::fetch_and_decode(self)?;
+
+        // Addresses have to be worked out before executing, as the instruction might change them
+        let write_idx = instr.opcode.write_param_idx();
+        let addr = |idx: usize| usize::try_from(instr.param_addr(idx, self)).ok();
+        let sources = (0..(instr.opcode.length() - 1))
+            .filter(|&idx| Some(idx) != write_idx && instr.modes[idx] != ParameterMode::Immediate)
+            .filter_map(addr)
+            .flat_map(|addr| taint.of(addr))
+            .collect::<BTreeSet<_>>();
+        let dest = write_idx.and_then(addr);
+
+        let event = self.progress_state()?;
+        match (instr.opcode, dest) {
+            (OpCode::ReadInput, Some(dest)) => {
+                taint.set(dest, core::iter::once(taint.inputs_read).collect());
+                taint.inputs_read += 1;
+            }
+            (OpCode::WriteOutput, _) => {
+                if let StepEvent::ProducedOutput(value) = event {
+                    taint.outputs.push(TaintedOutput { value, inputs: sources });
+                }
+            }
+            (_, Some(dest)) => taint.set(dest, sources),
+            (_, None) => (),
+        }
+
+        Ok(event)
+    }
+
+    /// As `run_to_next_input`, updating `taint` with everything executed
+    pub fn run_tainted(&mut self, taint: &mut Taint) -> Result<StopReason, VmError> {
+        while !self.terminated {
+            match self.progress_state_tainted(taint) {
+                Ok(_) => (),
+                Err(VmError::NoInput) => return Ok(StopReason::NeedsInput),
+                Err(e) => return Err(e),
+            }
+
+            if let Some(reason) = self.should_stop() {
+                return Ok(reason);
+            }
+        }
+
+        Ok(StopReason::Terminated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_taint() {
+        //   0: in [100]
+        //   2: in [101]
+        //   4: in [102]
+        //   6: add [100], [101], [103]
+        //  10: out [103]
+        //  12: mul [102], 2, [104]
+        //  16: out [104]
+        //  18: out 7
+        //  20: add [101], 0, [100]    ; copy
+        //  24: out [100]
+        //  26: add 1, 2, [101]        ; overwrite with a constant
+        //  30: out [101]
+        //  32: halt
+        let source = "3,100,3,101,3,102,1,100,101,103,4,103,1002,102,2,104,4,104,104,7,\
+                      1001,101,0,100,4,100,1101,1,2,101,4,101,99";
+        let mut program = ProgramState::load_program_str(source);
+        program.inputs.extend(&[5, 6]);
+
+        let mut taint = Taint::default();
+        assert_eq!(program.run_tainted(&mut taint), Ok(StopReason::NeedsInput));
+        assert_eq!(taint.inputs_read, 2);
+        program.inputs.push_back(7);
+        assert_eq!(program.run_tainted(&mut taint), Ok(StopReason::Terminated));
+
+        let outputs = taint.outputs
+            .iter()
+            .map(|output| (output.value, output.inputs.iter().cloned().collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        assert_eq!(outputs, vec![(11, vec![0, 1]), (14, vec![2]), (7, vec![]), (6, vec![1]), (3, vec![])]);
+
+        assert_eq!(taint.tainted_addrs().collect::<Vec<_>>(), vec![100, 102, 103, 104]);
+        assert_eq!(taint.of(100), core::iter::once(1).collect());
+        assert!(taint.of(101).is_empty());
+    }
+}
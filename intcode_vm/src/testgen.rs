@@ -0,0 +1,318 @@
+//! Random programs for fuzz-style testing of the VM, eg checking that every backend behaves
+//! identically, including when things go wrong.
+//!
+//! Generated programs are structurally valid. Every jump goes forwards to the start of a later
+//! instruction, so they always halt, and the code is followed by a data region which is the only
+//! thing they write to. Values stay small enough that arithmetic can't overflow.
+//!
+//! With `GenOptions::invalid` set, some instructions are corrupted as well, so that the programs
+//! exercise error handling. Corrupted programs can do anything, including loop forever, so they
+//! should be run with a budget:
+//!
+//! ```
+//! use intcode_vm::testgen::{generate, GenOptions, Rng};
+//! use intcode_vm::{Backend, StopReason};
+//!
+//! let mut rng = Rng::new(2019);
+//! let generated = generate(&mut rng, &GenOptions::default());
+//!
+//! let mut program = generated.state();
+//! program.backend = Backend::Blocks;
+//! assert_eq!(program.run_for(1000), Ok(StopReason::Terminated));
+//! ```
+
+use std::collections::VecDeque;
+
+use crate::{OpCode, ParameterMode, ProgramElement, ProgramState};
+
+/// A small xorshift generator, so that programs can be reproduced from a seed
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // The state must never be zero, or every value after it is zero too. Only one seed gives
+        // zero here, so that one is moved somewhere else.
+        let state = match seed ^ 0x2545_f491_4f6c_dd1d {
+            0 => 0x9e37_79b9_7f4a_7c15,
+            state => state,
+        };
+        Self { state }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// A value in `0..n`, which must be non-empty
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// A value in `lo..=hi`
+    pub fn range(&mut self, lo: ProgramElement, hi: ProgramElement) -> ProgramElement {
+        lo + (self.next_u64() % (hi - lo + 1) as u64) as ProgramElement
+    }
+
+    /// True with probability `1 / n`
+    pub fn one_in(&mut self, n: usize) -> bool {
+        self.below(n) == 0
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GenOptions {
+    /// Number of instructions, not counting the final halt
+    pub instructions: usize,
+
+    /// Length of the data region following the code
+    pub data_len: usize,
+
+    /// Whether to corrupt roughly one in twenty instructions
+    pub invalid: bool,
+}
+
+impl Default for GenOptions {
+    fn default() -> Self {
+        Self {
+            instructions: 50,
+            data_len: 16,
+            invalid: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeneratedProgram {
+    pub program: Vec<ProgramElement>,
+
+    /// One input for every input instruction, some of which may be jumped over
+    pub inputs: Vec<ProgramElement>,
+}
+
+impl GeneratedProgram {
+    /// A VM loaded with the program, with all of its inputs queued
+    pub fn state(&self) -> ProgramState {
+        ProgramState::new(self.program.iter().cloned(), self.inputs.iter().cloned().collect::<VecDeque<_>>())
+    }
+}
+
+const OPCODES: [OpCode; 10] = OpCode::ALL;
+
+/// Generates a random program, see the module docs
+pub fn generate(rng: &mut Rng, options: &GenOptions) -> GeneratedProgram {
+    // Every instruction bar the final halt is chosen up front, so that jumps know where the later
+    // instructions start
+    let mut opcodes = Vec::with_capacity(options.instructions + 1);
+    while opcodes.len() < options.instructions {
+        // Halting early is much less interesting than anything else
+        let opcode = OPCODES[rng.below(OPCODES.len())];
+        if opcode != OpCode::Terminate || rng.one_in(4) {
+            opcodes.push(opcode);
+        }
+    }
+    opcodes.push(OpCode::Terminate);
+
+    let starts = opcodes
+        .iter()
+        .scan(0, |addr, opcode| {
+            let start = *addr;
+            *addr += opcode.length();
+            Some(start)
+        })
+        .collect::<Vec<_>>();
+    let data_start = starts.last().unwrap() + 1;
+
+    let mut generator = Generator {
+        rng,
+        data_start,
+        data_len: options.data_len.max(1),
+        program: Vec::new(),
+        inputs: Vec::new(),
+    };
+    for (idx, &opcode) in opcodes.iter().enumerate() {
+        generator.instruction(opcode, &starts[(idx + 1)..]);
+    }
+
+    for _ in 0..generator.data_len {
+        let value = generator.rng.range(-100, 100);
+        generator.program.push(value);
+    }
+
+    if options.invalid {
+        for (&start, &opcode) in starts.iter().zip(&opcodes) {
+            if generator.rng.one_in(20) {
+                generator.corrupt(start, opcode);
+            }
+        }
+    }
+
+    GeneratedProgram { program: generator.program, inputs: generator.inputs }
+}
+
+struct Generator<'a> {
+    rng: &'a mut Rng,
+    data_start: usize,
+    data_len: usize,
+    program: Vec<ProgramElement>,
+    inputs: Vec<ProgramElement>,
+}
+
+impl Generator<'_> {
+    /// Appends an instruction. `later` holds the start of every instruction after this one, the
+    /// last of which is the final halt.
+    fn instruction(&mut self, opcode: OpCode, later: &[usize]) {
+        let parameters = match opcode {
+            // Only ever adding a literal to a cell keeps values growing slowly, as does only ever
+            // multiplying cells by -1, 0 or 1
+            OpCode::Add => {
+                let literal = self.small();
+                let (a, b) = self.one_in_memory(literal);
+                vec![a, b, self.destination()]
+            }
+            OpCode::Multiply => {
+                let factor = (ParameterMode::Immediate, self.rng.range(-1, 1));
+                let (a, b) = self.one_in_memory(factor);
+                vec![a, b, self.destination()]
+            }
+            OpCode::LessThan | OpCode::Equals => vec![self.source(), self.source(), self.destination()],
+            OpCode::ReadInput => {
+                let value = self.rng.range(-100, 100);
+                self.inputs.push(value);
+                vec![self.destination()]
+            }
+            OpCode::WriteOutput => vec![self.source()],
+            OpCode::JumpIfTrue | OpCode::JumpIfFalse => {
+                let target = later[self.rng.below(later.len())];
+                vec![self.source(), (ParameterMode::Immediate, target as ProgramElement)]
+            }
+
+            // Never moving the relative base down keeps relative addresses in the data region
+            OpCode::AdjustRelativeBase => vec![(ParameterMode::Immediate, self.rng.range(0, self.data_len as ProgramElement))],
+            OpCode::Terminate => vec![],
+        };
+
+        let modes = parameters
+            .iter()
+            .rev()
+            .fold(0, |modes, (mode, _)| modes * 10 + mode.code() as ProgramElement);
+        self.program.push(modes * 100 + opcode.code());
+        self.program.extend(parameters.iter().map(|&(_, value)| value));
+    }
+
+    fn small(&mut self) -> (ParameterMode, ProgramElement) {
+        (ParameterMode::Immediate, self.rng.range(-100, 100))
+    }
+
+    /// A parameter referring to a cell in the data region
+    fn memory(&mut self) -> (ParameterMode, ProgramElement) {
+        let offset = (self.data_start + self.rng.below(self.data_len)) as ProgramElement;
+        if self.rng.one_in(2) {
+            (ParameterMode::Position, offset)
+        } else {
+            (ParameterMode::Relative, offset)
+        }
+    }
+
+    fn source(&mut self) -> (ParameterMode, ProgramElement) {
+        if self.rng.one_in(2) {
+            self.small()
+        } else {
+            self.memory()
+        }
+    }
+
+    fn destination(&mut self) -> (ParameterMode, ProgramElement) {
+        self.memory()
+    }
+
+    /// `literal` and a small value, in either order, where either may be swapped for a cell in
+    /// memory
+    fn one_in_memory(
+        &mut self,
+        literal: (ParameterMode, ProgramElement),
+    ) -> ((ParameterMode, ProgramElement), (ParameterMode, ProgramElement)) {
+        let other = self.source();
+        if self.rng.one_in(2) {
+            (literal, other)
+        } else {
+            (other, literal)
+        }
+    }
+
+    /// Breaks the instruction starting at `start` in one of the ways that a VM should reject.
+    /// Opcodes are only ever replaced with invalid ones, as a multiply of two cells in a loop
+    /// could overflow.
+    fn corrupt(&mut self, start: usize, opcode: OpCode) {
+        match self.rng.below(5) {
+            0 => self.program[start] = self.rng.range(10, 98),
+            // An unknown parameter mode
+            1 => self.program[start] += 100 * self.rng.range(3, 9),
+            // Usually an out of range parameter or a jump somewhere unexpected
+            2 if opcode.length() > 1 => {
+                let addr = start + 1 + self.rng.below(opcode.length() - 1);
+                self.program[addr] = self.rng.range(-1000, 1000);
+            }
+            // A literal written to, or a mode for a parameter that doesn't exist
+            2 | 3 => self.program[start] += 10_000,
+            // Moves the relative base below zero
+            _ => {
+                self.program[start] = 109;
+                self.program[start + 1] = -(self.data_start as ProgramElement) - 100;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Backend, StopReason};
+
+    #[test]
+    fn test_rng_any_seed() {
+        for seed in [0, 1, 0x2545_f491_4f6c_dd1d, u64::MAX] {
+            let mut rng = Rng::new(seed);
+            let values = (0..4).map(|_| rng.next_u64()).collect::<Vec<_>>();
+            assert!(values.iter().all(|&value| value != 0), "Seed {:#x} gave {:?}", seed, values);
+        }
+    }
+
+    #[test]
+    fn test_valid_programs_halt() {
+        let mut rng = Rng::new(1);
+        for _ in 0..200 {
+            let generated = generate(&mut rng, &GenOptions::default());
+            let mut program = generated.state();
+            assert_eq!(program.run_for(1000), Ok(StopReason::Terminated), "{:?}", generated.program);
+        }
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        let mut rng = Rng::new(2);
+        let options = GenOptions { invalid: true, ..GenOptions::default() };
+        for _ in 0..500 {
+            let generated = generate(&mut rng, &options);
+
+            let results = Backend::ALL
+                .iter()
+                .map(|&backend| {
+                    let mut program = generated.state();
+                    program.backend = backend;
+                    let result = program.run_for(1000);
+                    (result, program.program_counter, program.stats().total(), program.outputs)
+                })
+                .collect::<Vec<_>>();
+
+            for (backend, result) in Backend::ALL.iter().zip(&results) {
+                assert_eq!(result, &results[0], "{} differs on {:?}", backend.name(), generated.program);
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Running VMs on their own threads, connected to each other with channels.
+//!
+//! ```
+//! use intcode_vm::ProgramState;
+//!
+//! // Adds one to each of its inputs, until there are no more
+//! let program = ProgramState::load_program_str("3,11,1001,11,1,11,4,11,1105,1,0,0");
+//! let (inputs, outputs, handle) = program.spawn_threaded();
+//!
+//! inputs.send(41).unwrap();
+//! assert_eq!(outputs.recv(), Ok(42));
+//!
+//! drop(inputs);
+//! assert!(handle.join().unwrap().1.is_ok());
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::JoinHandle;
+
+use crate::{InputSource, OutputSink, ProgramElement, ProgramState, StopReason, VmError};
+
+/// A VM that reads its inputs from one channel and writes its outputs to another
+pub type ThreadedState = ProgramState<Receiver<ProgramElement>, Sender<ProgramElement>>;
+
+/// Finishes with the VM and why it stopped running
+pub type ThreadedHandle = JoinHandle<(ThreadedState, Result<StopReason, VmError>)>;
+
+impl<I, O> ProgramState<I, O>
+where
+    I: InputSource + Send + 'static,
+    O: OutputSink + Send + 'static,
+{
+    /// Runs the program on a new thread, as with `run_to_next_input`. The thread returns the VM
+    /// along with why it stopped, eg once its inputs are exhausted.
+    pub fn spawn(mut self) -> JoinHandle<(Self, Result<StopReason, VmError>)> {
+        std::thread::spawn(move || {
+            let result = self.run_to_next_input();
+            (self, result)
+        })
+    }
+}
+
+impl ProgramState {
+    /// Runs the program on a new thread, fed by the returned sender and writing to the returned
+    /// receiver. Anything already in the input queue is sent first, and the program reads inputs
+    /// until every sender has been dropped.
+    pub fn spawn_threaded(mut self) -> (Sender<ProgramElement>, Receiver<ProgramElement>, ThreadedHandle) {
+        let (input_tx, input_rx) = mpsc::channel();
+        let (output_tx, output_rx) = mpsc::channel();
+
+        send_all(&input_tx, std::mem::take(&mut self.inputs));
+        send_all(&output_tx, std::mem::take(&mut self.outputs));
+
+        let handle = self.with_io(input_rx, output_tx).spawn();
+        (input_tx, output_rx, handle)
+    }
+}
+
+fn send_all(tx: &Sender<ProgramElement>, values: VecDeque<ProgramElement>) {
+    for value in values {
+        // Can't fail, the receiver is still held by the caller
+        tx.send(value).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feedback_loop() {
+        // Second example from day 7 part 2, which should output 139629729 with these phases
+        let program = ProgramState::load_program_str(
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5"
+        );
+        let phases = [9, 8, 7, 6, 5];
+
+        // Amplifier i reads from channel i and writes to channel i + 1, wrapping around
+        let (senders, receivers): (Vec<_>, Vec<_>) = phases.iter().map(|_| mpsc::channel()).unzip();
+        for (sender, &phase) in senders.iter().zip(&phases) {
+            sender.send(phase).unwrap();
+        }
+        senders[0].send(0).unwrap();
+
+        let handles = receivers
+            .into_iter()
+            .enumerate()
+            .map(|(idx, rx)| {
+                let tx = senders[(idx + 1) % senders.len()].clone();
+                program.clone().with_io(rx, tx).spawn()
+            })
+            .collect::<Vec<_>>();
+        drop(senders);
+
+        let states = handles.into_iter().map(|h| h.join().unwrap()).collect::<Vec<_>>();
+        for (state, result) in &states {
+            assert_eq!(result, &Ok(StopReason::Terminated));
+            assert!(state.terminated);
+        }
+
+        // The last amplifier's final output is left unread in the first one's input channel
+        assert_eq!(states[0].0.inputs.try_recv(), Ok(139629729));
+    }
+
+    #[test]
+    fn test_spawn_threaded_sends_queued_values() {
+        let mut program = ProgramState::load_program_str("3,0,4,0,99");
+        program.inputs.push_back(7);
+        program.outputs.push_back(1);
+
+        let (_inputs, outputs, handle) = program.spawn_threaded();
+        let (state, result) = handle.join().unwrap();
+        assert_eq!(result, Ok(StopReason::Terminated));
+        assert!(state.terminated);
+
+        // Iterating only finishes once the VM's sender has been dropped
+        drop(state);
+        assert_eq!(outputs.iter().collect::<Vec<_>>(), vec![1, 7]);
+    }
+}
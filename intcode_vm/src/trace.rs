@@ -0,0 +1,292 @@
+//! Execution tracing, which reports every instruction a VM executes to a `Tracer`.
+//!
+//! ```
+//! use intcode_vm::{trace, ProgramState};
+//!
+//! let mut a = ProgramState::load_program_str("1101,2,3,5,4,5,99");
+//! let mut b = a.clone();
+//! b.mem.write_addr(1, 4);
+//!
+//! let (mut trace_a, mut trace_b) = (Vec::new(), Vec::new());
+//! a.run_traced(&mut trace_a).unwrap();
+//! b.run_traced(&mut trace_b).unwrap();
+//!
+//! assert_eq!(trace_a[0].to_string(), "    0: add 2, 3, 5 -> 5");
+//! assert_eq!(trace::first_divergence(&trace_a, &trace_b), Some(0));
+//! ```
+
+use std::collections::VecDeque;
+
+use crate::symbols::{Annotated, SymbolTable};
+use crate::{
+    InputSource, Instruction, OpCode, OutputSink, ProgramElement, ProgramState, StepEvent, StopReason, VmError,
+};
+
+/// A single executed instruction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceEvent {
+    pub pc: usize,
+    pub opcode: OpCode,
+
+    /// The value of each parameter that's read, or the address written to for the one that's
+    /// written
+    pub operands: Vec<ProgramElement>,
+
+    /// The value written to memory or output, the new relative base, or the target of a jump
+    /// that was taken
+    pub result: Option<ProgramElement>,
+}
+
+impl TraceEvent {
+    fn write(&self, f: &mut std::fmt::Formatter, symbols: Option<&SymbolTable>) -> std::fmt::Result {
+        // Only addresses are named, ie the operand written to and where jumps go
+        let is_jump = matches!(self.opcode, OpCode::JumpIfTrue | OpCode::JumpIfFalse);
+        let format_addr = |value: ProgramElement| match symbols {
+            Some(symbols) if value >= 0 => symbols.format_addr(value as usize),
+            _ => value.to_string(),
+        };
+
+        match symbols.and_then(|symbols| symbols.name(self.pc)) {
+            Some(name) => write!(f, "{:>5} {}: {}", self.pc, name, self.opcode.mnemonic())?,
+            None => write!(f, "{:>5}: {}", self.pc, self.opcode.mnemonic())?,
+        }
+
+        let write_idx = self.opcode.write_param_idx();
+        let operands = self.operands
+            .iter()
+            .enumerate()
+            .map(|(idx, &op)| {
+                if Some(idx) == write_idx || (is_jump && idx == 1) {
+                    format_addr(op)
+                } else {
+                    op.to_string()
+                }
+            })
+            .collect::<Vec<_>>();
+        if !operands.is_empty() {
+            write!(f, " {}", operands.join(", "))?;
+        }
+
+        match self.result {
+            Some(result) if is_jump => write!(f, " -> {}", format_addr(result)),
+            Some(result) => write!(f, " -> {}", result),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.write(f, None)
+    }
+}
+
+/// Names the instruction's address, the address it writes to, and where it jumps
+impl std::fmt::Display for Annotated<'_, TraceEvent> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.item.write(f, Some(self.symbols))
+    }
+}
+
+pub trait Tracer {
+    fn trace(&mut self, event: &TraceEvent);
+}
+
+/// Keeps every event, for comparing whole runs
+impl Tracer for Vec<TraceEvent> {
+    fn trace(&mut self, event: &TraceEvent) {
+        self.push(event.clone());
+    }
+}
+
+/// Prints each event to stderr
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StderrTracer;
+
+impl Tracer for StderrTracer {
+    fn trace(&mut self, event: &TraceEvent) {
+        eprintln!("{}", event);
+    }
+}
+
+/// Keeps only the most recent `capacity` events
+#[derive(Clone, Debug)]
+pub struct RingBufferTracer {
+    capacity: usize,
+    events: VecDeque<TraceEvent>,
+}
+
+impl RingBufferTracer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            events: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// The kept events, oldest first
+    pub fn events(&self) -> impl Iterator<Item = &TraceEvent> {
+        self.events.iter()
+    }
+}
+
+impl Tracer for RingBufferTracer {
+    fn trace(&mut self, event: &TraceEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event.clone());
+    }
+}
+
+/// The index of the first event that differs between two traces, or the length of the shorter
+/// one if it's a prefix of the other. `None` if the traces are identical.
+pub fn first_divergence(a: &[TraceEvent], b: &[TraceEvent]) -> Option<usize> {
+    match a.iter().zip(b).position(|(a, b)| a != b) {
+        Some(idx) => Some(idx),
+        None if a.len() != b.len() => Some(a.len().min(b.len())),
+        None => None,
+    }
+}
+
+impl<I: InputSource, O: OutputSink> ProgramState<I, O> {
+    /// As `progress_state`, reporting the executed instruction to `tracer`. Nothing is reported
+    /// if this returns an error.
+    pub fn progress_state_traced(&mut self, tracer: &mut dyn Tracer) -> Result<StepEvent, VmError> {
+        let pc = self.program_counter;
+        let instr = Instruction::fetch_and_decode(self)?;
+
+        let write_idx = instr.opcode.write_param_idx();
+        let operands = (0..(instr.opcode.length() - 1))
+            .map(|idx| match write_idx {
+                Some(write_idx) if write_idx == idx => instr.param_addr(idx, self),
+                _ => instr.read_param(idx, self),
+            })
+            .collect::<Vec<_>>();
+
+        let event = self.progress_state()?;
+
+        let result = match instr.opcode {
+            OpCode::WriteOutput => Some(operands[0]),
+            OpCode::AdjustRelativeBase => Some(self.relative_base),
+            OpCode::JumpIfTrue | OpCode::JumpIfFalse => {
+                let taken = (operands[0] != 0) == (instr.opcode == OpCode::JumpIfTrue);
+                if taken { Some(operands[1]) } else { None }
+            }
+            OpCode::Terminate => None,
+            _ => write_idx.map(|idx| self.mem.read_addr(operands[idx] as usize)),
+        };
+
+        tracer.trace(&TraceEvent { pc, opcode: instr.opcode, operands, result });
+        Ok(event)
+    }
+
+    /// As `run_to_next_input`, reporting every executed instruction to `tracer`
+    pub fn run_traced(&mut self, tracer: &mut dyn Tracer) -> Result<StopReason, VmError> {
+        self.run_traced_for(tracer, u64::MAX)
+    }
+
+    /// As `run_traced`, but executing at most `max_instructions` instructions
+    pub fn run_traced_for(&mut self, tracer: &mut dyn Tracer, max_instructions: u64) -> Result<StopReason, VmError> {
+        let mut executed = 0;
+        while !self.terminated {
+            if executed == max_instructions {
+                return Ok(StopReason::BudgetExhausted);
+            }
+            executed += 1;
+
+            match self.progress_state_traced(tracer) {
+                Ok(_) => (),
+                Err(VmError::NoInput) => return Ok(StopReason::NeedsInput),
+                Err(e) => return Err(e),
+            }
+
+            if let Some(reason) = self.should_stop() {
+                return Ok(reason);
+            }
+        }
+
+        Ok(StopReason::Terminated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace(source: &str, inputs: &[ProgramElement]) -> Vec<String> {
+        let mut program = ProgramState::load_program_str(source);
+        program.inputs.extend(inputs);
+
+        let mut events = Vec::new();
+        program.run_traced(&mut events).unwrap();
+        events.iter().map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn test_events() {
+        let expected = [
+            "    0: in 13 -> 3",
+            "    2: arb 3 -> 3",
+            "    4: mul 3, 5, 14 -> 15",
+            "    8: jz 0, 12 -> 12",
+            "   12: halt",
+        ];
+        assert_eq!(trace("3,13,9,13,1002,13,5,14,1006,15,12,99,99,0,0,0", &[3]), expected);
+    }
+
+    #[test]
+    fn test_run_traced_for() {
+        let mut program = ProgramState::load_program_str("1105,1,0");
+        let mut events = Vec::new();
+        assert_eq!(program.run_traced_for(&mut events, 3), Ok(StopReason::BudgetExhausted));
+        assert_eq!(program.run_traced_for(&mut events, 0), Ok(StopReason::BudgetExhausted));
+        assert_eq!(events.len(), 3);
+    }
+
+    #[test]
+    fn test_symbols() {
+        let mut program = ProgramState::load_program_str("3,13,9,13,1002,13,5,14,1006,15,12,99,99,0,0,0");
+        program.inputs.push_back(3);
+        let mut events = Vec::new();
+        program.run_traced(&mut events).unwrap();
+
+        let mut symbols = SymbolTable::default();
+        symbols.insert(12, "end");
+        symbols.insert(13, "x");
+        let lines = events.iter().map(|e| symbols.annotate(e).to_string()).collect::<Vec<_>>();
+        assert_eq!(lines[0], "    0: in x -> 3");
+        assert_eq!(lines[3], "    8: jz 0, end -> end");
+        assert_eq!(lines[4], "   12 end: halt");
+    }
+
+    #[test]
+    fn test_ring_buffer() {
+        // Outputs 0, 1, 2, ... forever, stopping at a breakpoint once per iteration
+        let mut program = ProgramState::load_program_str("4,9,1001,9,1,9,1105,1,0,0");
+        program.breakpoints.insert(0);
+
+        let mut tracer = RingBufferTracer::new(2);
+        for _ in 0..3 {
+            program.run_traced(&mut tracer).unwrap();
+        }
+
+        let events = tracer.events().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(events, ["    2: add 2, 1, 9 -> 3", "    6: jnz 1, 0 -> 0"]);
+    }
+
+    #[test]
+    fn test_first_divergence() {
+        let mut a = Vec::new();
+        let mut b = Vec::new();
+        ProgramState::load_program_str("104,1,104,2,99").run_traced(&mut a).unwrap();
+        ProgramState::load_program_str("104,1,104,3,99").run_traced(&mut b).unwrap();
+
+        assert_eq!(first_divergence(&a, &a), None);
+        assert_eq!(first_divergence(&a, &b), Some(1));
+        assert_eq!(first_divergence(&a, &a[..2]), Some(2));
+    }
+}
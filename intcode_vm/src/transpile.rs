@@ -0,0 +1,284 @@
+//! Translating a program into Rust source, for when it needs to be run far more times than even
+//! the block backend can manage, eg in a search over inputs.
+//!
+//! The generated function has a match arm for each block in the program's control flow graph,
+//! inside a loop, so jumps just set the block to run next. Parameters are still read from memory
+//! as they execute, so programs which index arrays by rewriting their own instructions still
+//! behave. Anything else the blocks can't cope with, ie an instruction whose opcode has been
+//! overwritten or a jump somewhere that `analysis::control_flow_graph` didn't find, is handled by
+//! a small interpreter included in the generated code.
+//!
+//! The generated source doesn't depend on this crate:
+//!
+//! ```
+//! use intcode_vm::transpile::transpile;
+//!
+//! let source = transpile(&[104, 7, 99], "seven");
+//! assert!(source.contains("pub const SEVEN_PROGRAM: "));
+//! assert!(source.contains("pub fn seven(mem: "));
+//! ```
+
+use crate::analysis::{self, DecodedInstruction};
+use crate::disasm::DisasmLine;
+use crate::{OpCode, ParameterMode, ProgramElement};
+
+/// Rust source for a standalone module containing the program, as `<NAME>_PROGRAM`, and a
+/// function `name` which runs it:
+///
+/// ```text
+/// pub fn name(
+///     mem: &mut Vec<i64>,
+///     input: impl FnMut() -> Option<i64>,
+///     output: impl FnMut(i64),
+/// ) -> Result<(), String>
+/// ```
+///
+/// `mem` should start as a copy of the program, possibly patched, and is left as the program
+/// left it. Running out of input is an error, as the generated code can't pause.
+pub fn transpile(program: &[ProgramElement], name: &str) -> String {
+    let element = std::any::type_name::<ProgramElement>();
+    let graph = analysis::control_flow_graph(program);
+
+    let mut source = String::new();
+    let mut line = |text: &str| {
+        source.push_str(text);
+        source.push('\n');
+    };
+
+    line(&format!("// Transpiled from a {} element Intcode program", program.len()));
+    line("");
+    line(&format!(
+        "pub const {}_PROGRAM: [{}; {}] = [{}];",
+        name.to_uppercase(),
+        element,
+        program.len(),
+        program.iter().map(|el| el.to_string()).collect::<Vec<_>>().join(", "),
+    ));
+    line("");
+    line("#[allow(unused_mut, unused_variables, unreachable_code, clippy::all)]");
+    line(&format!(
+        "pub fn {}(mem: &mut Vec<{e}>, mut input: impl FnMut() -> Option<{e}>, mut output: impl FnMut({e})) -> Result<(), String> {{",
+        name,
+        e = element,
+    ));
+    line(&format!("    type Element = {};", element));
+    line(HELPERS);
+    line("    let mut rb: Element = 0;");
+    line("    let mut pc: usize = 0;");
+    line(INTERPRET);
+    line("    loop {");
+    line("        match pc {");
+
+    for block in graph.blocks.values() {
+        line(&format!("            {} => {{", block.start));
+
+        let mut addr = block.start;
+        while addr < block.end {
+            let instruction = DecodedInstruction::decode(program, addr);
+            let length = instruction.length();
+            let disasm = DisasmLine {
+                addr,
+                raw: program[addr..(addr + length)].to_vec(),
+                instruction,
+            };
+
+            line(&format!("                // {}", disasm.to_string().trim()));
+            if let DecodedInstruction::Op { opcode, parameters } = &disasm.instruction {
+                line(&format!("                if mem[{}] != {} {{ interpret!({}) }}", addr, program[addr], addr));
+                for statement in translate(addr, *opcode, parameters) {
+                    line(&format!("                {}", statement));
+                }
+            }
+
+            addr += length;
+        }
+
+        line(&format!("                pc = {};", block.end));
+        line("            }");
+    }
+
+    line("            _ => interpret!(pc),");
+    line("        }");
+    line("    }");
+    line("}");
+
+    source
+}
+
+/// Declared at the start of the generated function, after `Element`. Anything that can't be run as
+/// part of a block is run by `step`, one instruction at a time, until execution reaches the start
+/// of a block again.
+const HELPERS: &str = r#"    fn addr(a: Element) -> Result<usize, String> {
+        if a < 0 { Err(format!("Accessed negative address {}", a)) } else { Ok(a as usize) }
+    }
+
+    fn read(mem: &[Element], a: Element) -> Result<Element, String> {
+        Ok(mem.get(addr(a)?).cloned().unwrap_or(0))
+    }
+
+    fn write(mem: &mut Vec<Element>, a: Element, value: Element) -> Result<(), String> {
+        let a = addr(a)?;
+        if a >= mem.len() {
+            mem.resize(a + 1, 0);
+        }
+        mem[a] = value;
+        Ok(())
+    }
+
+    /// Executes the instruction at `pc`, returning whether it was a halt
+    fn step(
+        mem: &mut Vec<Element>,
+        pc: &mut usize,
+        rb: &mut Element,
+        input: &mut impl FnMut() -> Option<Element>,
+        output: &mut impl FnMut(Element),
+    ) -> Result<bool, String> {
+        let at = *pc;
+        let base = *rb;
+        let instruction = read(mem, at as Element)?;
+        let mode = |idx: usize| instruction / [100, 1000, 10000][idx] % 10;
+        let raw = |mem: &[Element], idx: usize| read(mem, (at + idx + 1) as Element);
+        let value = |mem: &[Element], idx: usize| -> Result<Element, String> {
+            match mode(idx) {
+                0 => read(mem, raw(mem, idx)?),
+                1 => raw(mem, idx),
+                2 => read(mem, base + raw(mem, idx)?),
+                m => Err(format!("Unknown parameter mode {} at {}", m, at)),
+            }
+        };
+        let dest = |mem: &[Element], idx: usize| -> Result<Element, String> {
+            match mode(idx) {
+                0 => raw(mem, idx),
+                2 => Ok(base + raw(mem, idx)?),
+                m => Err(format!("Parameter mode {} can't be written to at {}", m, at)),
+            }
+        };
+
+        match instruction % 100 {
+            1 | 2 | 7 | 8 => {
+                let (a, b) = (value(mem, 0)?, value(mem, 1)?);
+                let result = match instruction % 100 {
+                    1 => a.wrapping_add(b),
+                    2 => a.wrapping_mul(b),
+                    7 => (a < b) as Element,
+                    _ => (a == b) as Element,
+                };
+                let d = dest(mem, 2)?;
+                write(mem, d, result)?;
+                *pc = at + 4;
+            }
+            3 => {
+                let v = input().ok_or_else(|| format!("Ran out of input at {}", at))?;
+                let d = dest(mem, 0)?;
+                write(mem, d, v)?;
+                *pc = at + 2;
+            }
+            4 => {
+                output(value(mem, 0)?);
+                *pc = at + 2;
+            }
+            5 | 6 => {
+                let jumps = (value(mem, 0)? != 0) == (instruction % 100 == 5);
+                *pc = if jumps { addr(value(mem, 1)?)? } else { at + 3 };
+            }
+            9 => {
+                *rb += value(mem, 0)?;
+                *pc = at + 2;
+            }
+            99 => return Ok(true),
+            _ => return Err(format!("Unknown opcode {} at {}", instruction, at)),
+        }
+
+        Ok(false)
+    }
+"#;
+
+/// Declared after `pc` and `rb`, so that it can refer to them
+const INTERPRET: &str = r#"
+    // Runs the instruction at the given address with `step`, then carries on from wherever that
+    // leaves the program counter
+    macro_rules! interpret {
+        ($pc:expr) => {{
+            pc = $pc;
+            if step(mem, &mut pc, &mut rb, &mut input, &mut output)? {
+                return Ok(());
+            }
+            continue;
+        }};
+    }
+"#;
+
+/// The statements executing a single instruction
+fn translate(addr: usize, opcode: OpCode, parameters: &[(ParameterMode, ProgramElement)]) -> Vec<String> {
+    // The parameters are read from memory rather than baked in, in case they've been rewritten
+    let raw = |idx: usize| format!("mem[{}]", addr + 1 + idx);
+    let value = |idx: usize| match parameters[idx].0 {
+        ParameterMode::Position => format!("read(mem, {})?", raw(idx)),
+        ParameterMode::Immediate => raw(idx),
+        ParameterMode::Relative => format!("read(mem, rb + {})?", raw(idx)),
+    };
+    let dest = |idx: usize| match parameters[idx].0 {
+        ParameterMode::Relative => format!("rb + {}", raw(idx)),
+        _ => raw(idx),
+    };
+
+    match opcode {
+        // Overflow wraps, the same as in the interpreter
+        OpCode::Add => vec![format!("let value = {}.wrapping_add({});", value(0), value(1)), format!("write(mem, {}, value)?;", dest(2))],
+        OpCode::Multiply => vec![format!("let value = {}.wrapping_mul({});", value(0), value(1)), format!("write(mem, {}, value)?;", dest(2))],
+        OpCode::LessThan => vec![format!("let value = ({} < {}) as Element;", value(0), value(1)), format!("write(mem, {}, value)?;", dest(2))],
+        OpCode::Equals => vec![format!("let value = ({} == {}) as Element;", value(0), value(1)), format!("write(mem, {}, value)?;", dest(2))],
+        OpCode::ReadInput => vec![
+            format!("let value = input().ok_or(\"Ran out of input at {}\")?;", addr),
+            format!("write(mem, {}, value)?;", dest(0)),
+        ],
+        OpCode::WriteOutput => vec![format!("output({});", value(0))],
+        OpCode::JumpIfTrue => vec![format!("if {} != 0 {{ pc = addr({})?; continue; }}", value(0), value(1))],
+        OpCode::JumpIfFalse => vec![format!("if {} == 0 {{ pc = addr({})?; continue; }}", value(0), value(1))],
+        OpCode::AdjustRelativeBase => vec![format!("rb += {};", value(0))],
+        OpCode::Terminate => vec!["return Ok(());".to_string()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate() {
+        let parameters = [
+            (ParameterMode::Relative, -1),
+            (ParameterMode::Immediate, 5),
+            (ParameterMode::Position, 3),
+        ];
+        assert_eq!(translate(10, OpCode::Add, &parameters), vec![
+            "let value = read(mem, rb + mem[11])?.wrapping_add(mem[12]);",
+            "write(mem, mem[13], value)?;",
+        ]);
+
+        let parameters = [(ParameterMode::Position, 0), (ParameterMode::Relative, 2)];
+        assert_eq!(translate(4, OpCode::JumpIfFalse, &parameters), vec![
+            "if read(mem, mem[5])? == 0 { pc = addr(read(mem, rb + mem[6])?)?; continue; }",
+        ]);
+    }
+
+    #[test]
+    fn test_blocks() {
+        // Outputs its input if it's non-zero
+        //   0: in [9]
+        //   2: jz [9], 8
+        //   5: out [9]
+        //   7: halt
+        //   8: halt
+        let source = transpile(&[3, 9, 1006, 9, 8, 4, 9, 99, 99, 0], "echo");
+        let arms = source
+            .lines()
+            .skip_while(|line| line.trim() != "match pc {")
+            .filter(|line| line.starts_with("            ") && line.ends_with("=> {"))
+            .map(|line| line.trim())
+            .collect::<Vec<_>>();
+        assert_eq!(arms, vec!["0 => {", "5 => {", "8 => {"]);
+        assert!(source.contains("                // 2: 1006 9 8         jz [9], 8\n"));
+        assert!(source.contains("                if mem[2] != 1006 { interpret!(2) }\n"));
+    }
+}
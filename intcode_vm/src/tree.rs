@@ -0,0 +1,217 @@
+//! A tree of VM states, for searches that want to try an input from some state without having to
+//! undo what it did afterwards, eg exploring day 15's maze or trying combinations of items.
+//!
+//! Forking a node clones its VM, which shares memory with the original until either writes to it,
+//! so keeping a node for every state visited is cheap:
+//!
+//! ```
+//! use intcode_vm::tree::SnapshotTree;
+//! use intcode_vm::{ProgramState, StopReason};
+//!
+//! // Outputs double each input, forever
+//! let mut tree = SnapshotTree::new(ProgramState::load_program_str("3,9,1002,9,2,9,4,9,1105,1,0"));
+//! let root = tree.root();
+//!
+//! let (left, reason) = tree.explore(root, &[1]).unwrap();
+//! assert_eq!(reason, StopReason::NeedsInput);
+//! let (right, _) = tree.explore(root, &[5]).unwrap();
+//! let (deeper, _) = tree.explore(left, &[2]).unwrap();
+//!
+//! assert_eq!(tree.get(right).unwrap().outputs, vec![10]);
+//! assert_eq!(tree.get(deeper).unwrap().outputs, vec![2, 4]);
+//! assert_eq!(tree.path(deeper), vec![root, left, deeper]);
+//!
+//! tree.discard(left);
+//! assert!(tree.get(deeper).is_none());
+//! assert_eq!(tree.leaves().collect::<Vec<_>>(), vec![right]);
+//! ```
+
+use alloc::collections::{BTreeMap, VecDeque};
+
+use crate::prelude::*;
+use crate::{OutputSink, ProgramElement, ProgramState, StopReason, VmError};
+
+/// Identifies a node of a `SnapshotTree`. Ids aren't reused once their node is discarded.
+pub type NodeId = usize;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TreeError {
+    NoSuchNode(NodeId),
+
+    /// The VM of the new node failed, which is discarded
+    Vm { parent: NodeId, error: VmError },
+}
+
+impl core::fmt::Display for TreeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            TreeError::NoSuchNode(id) => write!(f, "There's no node {} in the tree", id),
+            TreeError::Vm { parent, error } => write!(f, "Exploring from node {} failed: {}", parent, error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TreeError {}
+
+#[derive(Clone)]
+struct Node<I, O> {
+    state: ProgramState<I, O>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+}
+
+/// See the module docs
+#[derive(Clone)]
+pub struct SnapshotTree<I = VecDeque<ProgramElement>, O = VecDeque<ProgramElement>> {
+    nodes: BTreeMap<NodeId, Node<I, O>>,
+    next_id: NodeId,
+}
+
+impl<I, O> SnapshotTree<I, O> {
+    /// A tree with just `state` at its root
+    pub fn new(state: ProgramState<I, O>) -> Self {
+        let mut nodes = BTreeMap::new();
+        nodes.insert(0, Node { state, parent: None, children: Vec::new() });
+        Self { nodes, next_id: 1 }
+    }
+
+    pub fn root(&self) -> NodeId {
+        0
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&ProgramState<I, O>> {
+        self.nodes.get(&id).map(|node| &node.state)
+    }
+
+    /// Changing a node's VM doesn't change any of its children
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut ProgramState<I, O>> {
+        self.nodes.get_mut(&id).map(|node| &mut node.state)
+    }
+
+    /// `None` for the root, and for nodes that don't exist
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes.get(&id)?.parent
+    }
+
+    /// In the order they were forked
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        self.nodes.get(&id).map_or(&[], |node| &node.children)
+    }
+
+    /// Every node with no children, in the order they were forked
+    pub fn leaves(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes.iter().filter(|(_, node)| node.children.is_empty()).map(|(&id, _)| id)
+    }
+
+    /// The nodes from the root down to `id`, or nothing if there's no such node
+    pub fn path(&self, id: NodeId) -> Vec<NodeId> {
+        let mut path = Vec::new();
+        let mut next = self.nodes.get(&id).map(|_| id);
+        while let Some(id) = next {
+            path.push(id);
+            next = self.parent(id);
+        }
+        path.reverse();
+        path
+    }
+
+    /// How many nodes there are, including the root
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Always false, as the root can't be discarded
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Discards `id` and everything forked from it, returning whether it existed. The root can't
+    /// be discarded, only its children.
+    pub fn discard(&mut self, id: NodeId) -> bool {
+        let parent = match self.parent(id) {
+            Some(parent) => parent,
+            None => return false,
+        };
+        self.nodes.get_mut(&parent).unwrap().children.retain(|&child| child != id);
+
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if let Some(node) = self.nodes.remove(&id) {
+                stack.extend(node.children);
+            }
+        }
+        true
+    }
+}
+
+impl<I: Clone, O: Clone> SnapshotTree<I, O> {
+    /// Adds a child of `id` with a copy of its VM, returning the child's id
+    pub fn fork(&mut self, id: NodeId) -> Result<NodeId, TreeError> {
+        let state = self.get(id).ok_or(TreeError::NoSuchNode(id))?.clone();
+        let child = self.next_id;
+        self.next_id += 1;
+        self.nodes.insert(child, Node { state, parent: Some(id), children: Vec::new() });
+        self.nodes.get_mut(&id).unwrap().children.push(child);
+        Ok(child)
+    }
+}
+
+impl<O: OutputSink + Clone> SnapshotTree<VecDeque<ProgramElement>, O> {
+    /// Forks `id`, gives the child `inputs`, and runs it until it needs more. The child is
+    /// discarded if its VM fails.
+    pub fn explore(&mut self, id: NodeId, inputs: &[ProgramElement]) -> Result<(NodeId, StopReason), TreeError> {
+        let child = self.fork(id)?;
+        let state = self.get_mut(child).unwrap();
+        state.inputs.extend(inputs);
+        match state.run_to_next_input() {
+            Ok(reason) => Ok((child, reason)),
+            Err(error) => {
+                self.discard(child);
+                Err(TreeError::Vm { parent: id, error })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree() {
+        // Adds each input to a running total at 20, outputting it
+        let mut tree = SnapshotTree::new(ProgramState::load_program_str("3,19,1,19,20,20,4,20,1105,1,0"));
+        let root = tree.root();
+        let (a, _) = tree.explore(root, &[1]).unwrap();
+        let (b, _) = tree.explore(a, &[10]).unwrap();
+        let (c, _) = tree.explore(a, &[100, 1000]).unwrap();
+
+        // Siblings don't see each other's writes, nor does the parent
+        assert_eq!(tree.get(b).unwrap().mem.read_addr(20), 11);
+        assert_eq!(tree.get(c).unwrap().mem.read_addr(20), 1101);
+        assert_eq!(tree.get(a).unwrap().mem.read_addr(20), 1);
+        assert_eq!(tree.children(a), &[b, c]);
+        assert_eq!(tree.parent(c), Some(a));
+        assert_eq!(tree.path(c), vec![root, a, c]);
+
+        // Changing a node directly doesn't reach its existing children
+        tree.get_mut(a).unwrap().mem.write_addr(20, 0);
+        assert_eq!(tree.get(b).unwrap().mem.read_addr(20), 11);
+
+        assert!(tree.discard(b));
+        assert!(!tree.discard(b));
+        assert!(!tree.discard(root));
+        assert_eq!(tree.children(a), &[c]);
+        assert_eq!(tree.len(), 3);
+
+        // Ids aren't reused, and a failed child isn't kept
+        let d = tree.fork(root).unwrap();
+        assert!(d > c);
+        tree.get_mut(d).unwrap().program_counter = 11;
+        tree.get_mut(d).unwrap().mem.write_addr(11, 42);
+        assert!(matches!(tree.explore(d, &[]), Err(TreeError::Vm { parent, .. }) if parent == d));
+        assert_eq!(tree.children(d), &[]);
+        assert_eq!(tree.fork(b), Err(TreeError::NoSuchNode(b)));
+    }
+}
@@ -0,0 +1,187 @@
+//! Watchpoints, which stop execution when an instruction reads or writes chosen addresses.
+//!
+//! Only the memory accessed by position and relative mode parameters is watched, fetching the
+//! instructions themselves doesn't count as a read.
+
+use core::ops::Range;
+
+use crate::prelude::*;
+use crate::{
+    InputSource, Instruction, OpCode, OutputSink, ParameterMode, ProgramElement, ProgramState, StepEvent, VmError,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub addrs: Range<usize>,
+    pub on_read: bool,
+    pub on_write: bool,
+}
+
+impl Watchpoint {
+    pub fn reads(addrs: Range<usize>) -> Self {
+        Self { addrs, on_read: true, on_write: false }
+    }
+
+    pub fn writes(addrs: Range<usize>) -> Self {
+        Self { addrs, on_read: false, on_write: true }
+    }
+
+    /// Watches both reads and writes
+    pub fn accesses(addrs: Range<usize>) -> Self {
+        Self { addrs, on_read: true, on_write: true }
+    }
+
+    fn matches(&self, addr: usize, access: Access) -> bool {
+        let watched = match access {
+            Access::Read => self.on_read,
+            Access::Write => self.on_write,
+        };
+
+        watched && self.addrs.contains(&addr)
+    }
+}
+
+/// Details of an access to a watched address
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchHit {
+    /// Address of the instruction that made the access, which has finished executing
+    pub pc: usize,
+
+    pub addr: usize,
+    pub access: Access,
+
+    /// Value at `addr` before the instruction executed
+    pub old_value: ProgramElement,
+
+    /// Value at `addr` after the instruction executed, always the same as `old_value` for reads
+    pub new_value: ProgramElement,
+}
+
+impl core::fmt::Display for WatchHit {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self.access {
+            Access::Read => write!(f, "Instruction at {} read {} from {}", self.pc, self.old_value, self.addr),
+            Access::Write => write!(
+                f,
+                "Instruction at {} wrote to {}, changing it from {} to {}",
+                self.pc, self.addr, self.old_value, self.new_value,
+            ),
+        }
+    }
+}
+
+/// The memory accesses an instruction could make through its parameters, along with the value at
+/// each address before it executes
+fn accesses<I: InputSource, O: OutputSink>(
+    instr: &Instruction,
+    state: &ProgramState<I, O>,
+) -> Vec<(usize, usize, Access, ProgramElement)> {
+    (0..(instr.opcode.length() - 1))
+        .filter(|&idx| instr.modes[idx] != ParameterMode::Immediate)
+        .map(|idx| {
+            let access = match instr.opcode.write_param_idx() {
+                Some(write_idx) if write_idx == idx => Access::Write,
+                _ => Access::Read,
+            };
+            let addr = instr.param_addr(idx, state) as usize;
+            (idx, addr, access, state.mem.read_addr(addr))
+        })
+        .collect()
+}
+
+/// Executes a single instruction, returning what it did and the first access it made to a watched
+/// address. Writes are reported in preference to reads.
+pub(crate) fn step_watched<I: InputSource, O: OutputSink>(
+    state: &mut ProgramState<I, O>,
+) -> Result<(StepEvent, Option<WatchHit>), VmError> {
+    let pc = state.program_counter;
+    let instr = Instruction::fetch_and_decode(state)?;
+
+    // Addresses are resolved before executing, as the instruction can change the relative base
+    let accesses = accesses(&instr, state);
+    let event = instr.execute(state)?;
+
+    let mut hits = accesses
+        .into_iter()
+        .filter(|&(idx, _, _, _)| match instr.opcode {
+            // The target of a jump is only read if the jump is taken
+            OpCode::JumpIfTrue | OpCode::JumpIfFalse => idx == 0 || event == StepEvent::Jumped,
+            _ => true,
+        })
+        .filter(|&(_, addr, access, _)| state.watchpoints.iter().any(|w| w.matches(addr, access)))
+        .map(|(_, addr, access, old_value)| WatchHit {
+            pc,
+            addr,
+            access,
+            old_value,
+            new_value: match access {
+                Access::Read => old_value,
+                Access::Write => state.mem.read_addr(addr),
+            },
+        })
+        .collect::<Vec<_>>();
+
+    hits.sort_by_key(|hit| hit.access != Access::Write);
+    Ok((event, hits.into_iter().next()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StopReason;
+
+    // Counts the value at address 9 up from 0 forever, outputting each value
+    const COUNTER: &str = "4,9,1001,9,1,9,1105,1,0,0";
+
+    #[test]
+    fn test_write_watchpoint() {
+        let mut program = ProgramState::load_program_str(COUNTER);
+        program.watchpoints.push(Watchpoint::writes(9..10));
+
+        let hit = WatchHit { pc: 2, addr: 9, access: Access::Write, old_value: 0, new_value: 1 };
+        assert_eq!(program.run_to_completion(), Ok(StopReason::Watchpoint(hit)));
+        assert_eq!(program.program_counter, 6);
+        assert_eq!(program.outputs, vec![0]);
+
+        let hit = WatchHit { pc: 2, addr: 9, access: Access::Write, old_value: 1, new_value: 2 };
+        assert_eq!(program.run_to_completion(), Ok(StopReason::Watchpoint(hit)));
+        assert_eq!(program.outputs, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_read_watchpoint() {
+        let mut program = ProgramState::load_program_str(COUNTER);
+        program.watchpoints.push(Watchpoint::reads(5..10));
+
+        // The output reads address 9 before the add does
+        let hit = WatchHit { pc: 0, addr: 9, access: Access::Read, old_value: 0, new_value: 0 };
+        assert_eq!(program.run_to_completion(), Ok(StopReason::Watchpoint(hit)));
+
+        // The add both reads and writes address 9, writes take priority, but aren't watched
+        let hit = WatchHit { pc: 2, addr: 9, access: Access::Read, old_value: 0, new_value: 0 };
+        assert_eq!(program.run_to_completion(), Ok(StopReason::Watchpoint(hit)));
+
+        program.watchpoints = vec![Watchpoint::accesses(9..10)];
+        program.run_to_completion().unwrap();
+        let hit = WatchHit { pc: 2, addr: 9, access: Access::Write, old_value: 1, new_value: 2 };
+        assert_eq!(program.run_to_completion(), Ok(StopReason::Watchpoint(hit)));
+    }
+
+    #[test]
+    fn test_untaken_jump_target_not_read() {
+        // Jumps to the address stored at 6 if the value at 5 is non-zero, which it isn't
+        let mut program = ProgramState::load_program_str("5,5,6,99,0,0,0");
+        program.watchpoints.push(Watchpoint::reads(6..7));
+        assert_eq!(program.run_to_completion(), Ok(StopReason::Terminated));
+
+        program = ProgramState::load_program_str("5,5,6,99,0,0,0");
+        program.watchpoints.push(Watchpoint::reads(5..6));
+        assert!(matches!(program.run_to_completion(), Ok(StopReason::Watchpoint(_))));
+    }
+}
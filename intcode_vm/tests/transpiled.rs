@@ -0,0 +1,68 @@
+//! Runs programs transpiled by `intcode2rs`, which are checked in under `transpiled/`, comparing
+//! them against the interpreter. If the transpiler's output changes they need regenerating, eg
+//! with `cargo run --bin intcode2rs -- quine.txt quine > tests/transpiled/quine.rs`.
+
+#![cfg(not(feature = "i128"))]
+
+use intcode_vm::transpile::transpile;
+use intcode_vm::{ProgramElement, ProgramState};
+
+mod quine {
+    include!("transpiled/quine.rs");
+}
+
+mod compare_to_8 {
+    include!("transpiled/compare_to_8.rs");
+}
+
+/// Checks that the interpreter gives the same outputs and leaves the same memory as a transpiled
+/// run of `program`
+fn assert_interpreter_matches(
+    program: &[ProgramElement],
+    inputs: &[ProgramElement],
+    outputs: &[ProgramElement],
+    mem: &[ProgramElement],
+) {
+    let mut state = ProgramState::new(program.iter().cloned(), inputs.iter().cloned().collect());
+    state.run_to_completion().unwrap();
+
+    assert_eq!(state.outputs, outputs);
+    assert_eq!(state.mem.read_range(0, mem.len()), mem);
+    assert!(state.mem.extent() <= mem.len());
+}
+
+#[test]
+fn test_up_to_date() {
+    assert_eq!(transpile(&quine::QUINE_PROGRAM, "quine"), include_str!("transpiled/quine.rs"));
+    assert_eq!(
+        transpile(&compare_to_8::COMPARE_TO_8_PROGRAM, "compare_to_8"),
+        include_str!("transpiled/compare_to_8.rs"),
+    );
+}
+
+#[test]
+fn test_quine() {
+    let mut mem = quine::QUINE_PROGRAM.to_vec();
+    let mut outputs = Vec::new();
+    quine::quine(&mut mem, || None, |value| outputs.push(value)).unwrap();
+
+    assert_eq!(outputs, quine::QUINE_PROGRAM);
+    assert_interpreter_matches(&quine::QUINE_PROGRAM, &[], &outputs, &mem);
+}
+
+#[test]
+fn test_compare_to_8() {
+    for &input in &[7, 8, 9] {
+        let mut mem = compare_to_8::COMPARE_TO_8_PROGRAM.to_vec();
+        let mut inputs = vec![input].into_iter();
+        let mut outputs = Vec::new();
+        compare_to_8::compare_to_8(&mut mem, || inputs.next(), |value| outputs.push(value)).unwrap();
+
+        assert_eq!(outputs, vec![input + 992]);
+        assert_interpreter_matches(&compare_to_8::COMPARE_TO_8_PROGRAM, &[input], &outputs, &mem);
+    }
+
+    let mut mem = compare_to_8::COMPARE_TO_8_PROGRAM.to_vec();
+    let result = compare_to_8::compare_to_8(&mut mem, || None, |_| ());
+    assert!(result.is_err());
+}
@@ -0,0 +1,183 @@
+// Transpiled from a 47 element Intcode program
+
+pub const COMPARE_TO_8_PROGRAM: [i64; 47] = [3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0, 0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20, 1105, 1, 46, 98, 99];
+
+#[allow(unused_mut, unused_variables, unreachable_code, clippy::all)]
+pub fn compare_to_8(mem: &mut Vec<i64>, mut input: impl FnMut() -> Option<i64>, mut output: impl FnMut(i64)) -> Result<(), String> {
+    type Element = i64;
+    fn addr(a: Element) -> Result<usize, String> {
+        if a < 0 { Err(format!("Accessed negative address {}", a)) } else { Ok(a as usize) }
+    }
+
+    fn read(mem: &[Element], a: Element) -> Result<Element, String> {
+        Ok(mem.get(addr(a)?).cloned().unwrap_or(0))
+    }
+
+    fn write(mem: &mut Vec<Element>, a: Element, value: Element) -> Result<(), String> {
+        let a = addr(a)?;
+        if a >= mem.len() {
+            mem.resize(a + 1, 0);
+        }
+        mem[a] = value;
+        Ok(())
+    }
+
+    /// Executes the instruction at `pc`, returning whether it was a halt
+    fn step(
+        mem: &mut Vec<Element>,
+        pc: &mut usize,
+        rb: &mut Element,
+        input: &mut impl FnMut() -> Option<Element>,
+        output: &mut impl FnMut(Element),
+    ) -> Result<bool, String> {
+        let at = *pc;
+        let base = *rb;
+        let instruction = read(mem, at as Element)?;
+        let mode = |idx: usize| instruction / [100, 1000, 10000][idx] % 10;
+        let raw = |mem: &[Element], idx: usize| read(mem, (at + idx + 1) as Element);
+        let value = |mem: &[Element], idx: usize| -> Result<Element, String> {
+            match mode(idx) {
+                0 => read(mem, raw(mem, idx)?),
+                1 => raw(mem, idx),
+                2 => read(mem, base + raw(mem, idx)?),
+                m => Err(format!("Unknown parameter mode {} at {}", m, at)),
+            }
+        };
+        let dest = |mem: &[Element], idx: usize| -> Result<Element, String> {
+            match mode(idx) {
+                0 => raw(mem, idx),
+                2 => Ok(base + raw(mem, idx)?),
+                m => Err(format!("Parameter mode {} can't be written to at {}", m, at)),
+            }
+        };
+
+        match instruction % 100 {
+            1 | 2 | 7 | 8 => {
+                let (a, b) = (value(mem, 0)?, value(mem, 1)?);
+                let result = match instruction % 100 {
+                    1 => a.wrapping_add(b),
+                    2 => a.wrapping_mul(b),
+                    7 => (a < b) as Element,
+                    _ => (a == b) as Element,
+                };
+                let d = dest(mem, 2)?;
+                write(mem, d, result)?;
+                *pc = at + 4;
+            }
+            3 => {
+                let v = input().ok_or_else(|| format!("Ran out of input at {}", at))?;
+                let d = dest(mem, 0)?;
+                write(mem, d, v)?;
+                *pc = at + 2;
+            }
+            4 => {
+                output(value(mem, 0)?);
+                *pc = at + 2;
+            }
+            5 | 6 => {
+                let jumps = (value(mem, 0)? != 0) == (instruction % 100 == 5);
+                *pc = if jumps { addr(value(mem, 1)?)? } else { at + 3 };
+            }
+            9 => {
+                *rb += value(mem, 0)?;
+                *pc = at + 2;
+            }
+            99 => return Ok(true),
+            _ => return Err(format!("Unknown opcode {} at {}", instruction, at)),
+        }
+
+        Ok(false)
+    }
+
+    let mut rb: Element = 0;
+    let mut pc: usize = 0;
+
+    // Runs the instruction at the given address with `step`, then carries on from wherever that
+    // leaves the program counter
+    macro_rules! interpret {
+        ($pc:expr) => {{
+            pc = $pc;
+            if step(mem, &mut pc, &mut rb, &mut input, &mut output)? {
+                return Ok(());
+            }
+            continue;
+        }};
+    }
+
+    loop {
+        match pc {
+            0 => {
+                // 0: 3 21             in [21]
+                if mem[0] != 3 { interpret!(0) }
+                let value = input().ok_or("Ran out of input at 0")?;
+                write(mem, mem[1], value)?;
+                // 2: 1008 21 8 20     eq [21], 8, [20]
+                if mem[2] != 1008 { interpret!(2) }
+                let value = (read(mem, mem[3])? == mem[4]) as Element;
+                write(mem, mem[5], value)?;
+                // 6: 1005 20 22       jnz [20], 22
+                if mem[6] != 1005 { interpret!(6) }
+                if read(mem, mem[7])? != 0 { pc = addr(mem[8])?; continue; }
+                pc = 9;
+            }
+            9 => {
+                // 9: 107 8 21 20      lt 8, [21], [20]
+                if mem[9] != 107 { interpret!(9) }
+                let value = (mem[10] < read(mem, mem[11])?) as Element;
+                write(mem, mem[12], value)?;
+                // 13: 1006 20 31       jz [20], 31
+                if mem[13] != 1006 { interpret!(13) }
+                if read(mem, mem[14])? == 0 { pc = addr(mem[15])?; continue; }
+                pc = 16;
+            }
+            16 => {
+                // 16: 1106 0 36        jz 0, 36
+                if mem[16] != 1106 { interpret!(16) }
+                if mem[17] == 0 { pc = addr(mem[18])?; continue; }
+                pc = 19;
+            }
+            22 => {
+                // 22: 1002 21 125 20   mul [21], 125, [20]
+                if mem[22] != 1002 { interpret!(22) }
+                let value = read(mem, mem[23])?.wrapping_mul(mem[24]);
+                write(mem, mem[25], value)?;
+                // 26: 4 20             out [20]
+                if mem[26] != 4 { interpret!(26) }
+                output(read(mem, mem[27])?);
+                // 28: 1105 1 46        jnz 1, 46
+                if mem[28] != 1105 { interpret!(28) }
+                if mem[29] != 0 { pc = addr(mem[30])?; continue; }
+                pc = 31;
+            }
+            31 => {
+                // 31: 104 999          out 999
+                if mem[31] != 104 { interpret!(31) }
+                output(mem[32]);
+                // 33: 1105 1 46        jnz 1, 46
+                if mem[33] != 1105 { interpret!(33) }
+                if mem[34] != 0 { pc = addr(mem[35])?; continue; }
+                pc = 36;
+            }
+            36 => {
+                // 36: 1101 1000 1 20   add 1000, 1, [20]
+                if mem[36] != 1101 { interpret!(36) }
+                let value = mem[37].wrapping_add(mem[38]);
+                write(mem, mem[39], value)?;
+                // 40: 4 20             out [20]
+                if mem[40] != 4 { interpret!(40) }
+                output(read(mem, mem[41])?);
+                // 42: 1105 1 46        jnz 1, 46
+                if mem[42] != 1105 { interpret!(42) }
+                if mem[43] != 0 { pc = addr(mem[44])?; continue; }
+                pc = 45;
+            }
+            46 => {
+                // 46: 99               halt
+                if mem[46] != 99 { interpret!(46) }
+                return Ok(());
+                pc = 47;
+            }
+            _ => interpret!(pc),
+        }
+    }
+}
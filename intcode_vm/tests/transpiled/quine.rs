@@ -0,0 +1,138 @@
+// Transpiled from a 16 element Intcode program
+
+pub const QUINE_PROGRAM: [i64; 16] = [109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99];
+
+#[allow(unused_mut, unused_variables, unreachable_code, clippy::all)]
+pub fn quine(mem: &mut Vec<i64>, mut input: impl FnMut() -> Option<i64>, mut output: impl FnMut(i64)) -> Result<(), String> {
+    type Element = i64;
+    fn addr(a: Element) -> Result<usize, String> {
+        if a < 0 { Err(format!("Accessed negative address {}", a)) } else { Ok(a as usize) }
+    }
+
+    fn read(mem: &[Element], a: Element) -> Result<Element, String> {
+        Ok(mem.get(addr(a)?).cloned().unwrap_or(0))
+    }
+
+    fn write(mem: &mut Vec<Element>, a: Element, value: Element) -> Result<(), String> {
+        let a = addr(a)?;
+        if a >= mem.len() {
+            mem.resize(a + 1, 0);
+        }
+        mem[a] = value;
+        Ok(())
+    }
+
+    /// Executes the instruction at `pc`, returning whether it was a halt
+    fn step(
+        mem: &mut Vec<Element>,
+        pc: &mut usize,
+        rb: &mut Element,
+        input: &mut impl FnMut() -> Option<Element>,
+        output: &mut impl FnMut(Element),
+    ) -> Result<bool, String> {
+        let at = *pc;
+        let base = *rb;
+        let instruction = read(mem, at as Element)?;
+        let mode = |idx: usize| instruction / [100, 1000, 10000][idx] % 10;
+        let raw = |mem: &[Element], idx: usize| read(mem, (at + idx + 1) as Element);
+        let value = |mem: &[Element], idx: usize| -> Result<Element, String> {
+            match mode(idx) {
+                0 => read(mem, raw(mem, idx)?),
+                1 => raw(mem, idx),
+                2 => read(mem, base + raw(mem, idx)?),
+                m => Err(format!("Unknown parameter mode {} at {}", m, at)),
+            }
+        };
+        let dest = |mem: &[Element], idx: usize| -> Result<Element, String> {
+            match mode(idx) {
+                0 => raw(mem, idx),
+                2 => Ok(base + raw(mem, idx)?),
+                m => Err(format!("Parameter mode {} can't be written to at {}", m, at)),
+            }
+        };
+
+        match instruction % 100 {
+            1 | 2 | 7 | 8 => {
+                let (a, b) = (value(mem, 0)?, value(mem, 1)?);
+                let result = match instruction % 100 {
+                    1 => a.wrapping_add(b),
+                    2 => a.wrapping_mul(b),
+                    7 => (a < b) as Element,
+                    _ => (a == b) as Element,
+                };
+                let d = dest(mem, 2)?;
+                write(mem, d, result)?;
+                *pc = at + 4;
+            }
+            3 => {
+                let v = input().ok_or_else(|| format!("Ran out of input at {}", at))?;
+                let d = dest(mem, 0)?;
+                write(mem, d, v)?;
+                *pc = at + 2;
+            }
+            4 => {
+                output(value(mem, 0)?);
+                *pc = at + 2;
+            }
+            5 | 6 => {
+                let jumps = (value(mem, 0)? != 0) == (instruction % 100 == 5);
+                *pc = if jumps { addr(value(mem, 1)?)? } else { at + 3 };
+            }
+            9 => {
+                *rb += value(mem, 0)?;
+                *pc = at + 2;
+            }
+            99 => return Ok(true),
+            _ => return Err(format!("Unknown opcode {} at {}", instruction, at)),
+        }
+
+        Ok(false)
+    }
+
+    let mut rb: Element = 0;
+    let mut pc: usize = 0;
+
+    // Runs the instruction at the given address with `step`, then carries on from wherever that
+    // leaves the program counter
+    macro_rules! interpret {
+        ($pc:expr) => {{
+            pc = $pc;
+            if step(mem, &mut pc, &mut rb, &mut input, &mut output)? {
+                return Ok(());
+            }
+            continue;
+        }};
+    }
+
+    loop {
+        match pc {
+            0 => {
+                // 0: 109 1            arb 1
+                if mem[0] != 109 { interpret!(0) }
+                rb += mem[1];
+                // 2: 204 -1           out [rb-1]
+                if mem[2] != 204 { interpret!(2) }
+                output(read(mem, rb + mem[3])?);
+                // 4: 1001 100 1 100   add [100], 1, [100]
+                if mem[4] != 1001 { interpret!(4) }
+                let value = read(mem, mem[5])?.wrapping_add(mem[6]);
+                write(mem, mem[7], value)?;
+                // 8: 1008 100 16 101  eq [100], 16, [101]
+                if mem[8] != 1008 { interpret!(8) }
+                let value = (read(mem, mem[9])? == mem[10]) as Element;
+                write(mem, mem[11], value)?;
+                // 12: 1006 101 0       jz [101], 0
+                if mem[12] != 1006 { interpret!(12) }
+                if read(mem, mem[13])? == 0 { pc = addr(mem[14])?; continue; }
+                pc = 15;
+            }
+            15 => {
+                // 15: 99               halt
+                if mem[15] != 99 { interpret!(15) }
+                return Ok(());
+                pc = 16;
+            }
+            _ => interpret!(pc),
+        }
+    }
+}
@@ -0,0 +1,17 @@
+//! Generates the C header for the API in `src/lib.rs`. The checked-in copy in `include/` is
+//! compared against it by the crate's tests.
+
+fn main() {
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir))
+        .expect("Couldn't read cbindgen.toml");
+
+    cbindgen::generate_with_config(&crate_dir, config)
+        .expect("Couldn't generate the C header")
+        .write_to_file(format!("{}/intcode_vm.h", out_dir));
+}
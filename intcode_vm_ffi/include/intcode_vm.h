@@ -0,0 +1,102 @@
+/*
+ * C API for the intcode VM, see intcode_vm_ffi/src/lib.rs for the details.
+ *
+ * Generated by cbindgen from intcode_vm_ffi/build.rs, don't edit by hand. Link against the static
+ * or dynamic library that building intcode_vm_ffi produces, eg
+ * target/release/libintcode_vm_ffi.a. Every function is safe to call with a null VM pointer.
+ */
+
+#ifndef INTCODE_VM_H
+#define INTCODE_VM_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+typedef enum IntcodeStatus {
+  INTCODE_STATUS_OK = 0,
+  INTCODE_STATUS_TERMINATED = 1,
+  INTCODE_STATUS_NEEDS_INPUT = 2,
+  // The VM hit an error, see `intcode_vm_last_error`
+  INTCODE_STATUS_ERROR = -1,
+  // A pointer that was passed in was null
+  INTCODE_STATUS_INVALID_ARGUMENT = -2,
+} IntcodeStatus;
+
+// A VM along with the message for the last error it hit, so that the pointer returned by
+// `intcode_vm_last_error` stays valid until the next call
+typedef struct IntcodeVm IntcodeVm;
+
+// A value in memory, or an input or output, interchangeable with `ProgramElement`
+typedef int64_t intcode_element;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates a VM with a program that just terminates
+struct IntcodeVm *intcode_vm_create(void);
+
+// # Safety
+//
+// `vm` must be null or have come from `intcode_vm_create`, and mustn't be used again afterwards.
+void intcode_vm_destroy(struct IntcodeVm *vm);
+
+// Replaces the VM's program with the `len` elements at `program`, and resets everything else,
+// including any queued inputs and outputs
+//
+// # Safety
+//
+// `vm` must be null or a live VM, and `program` must be null or point to `len` elements.
+enum IntcodeStatus intcode_vm_load_program(struct IntcodeVm *vm,
+                                           const intcode_element *program,
+                                           size_t len);
+
+// As `intcode_vm_load_program`, but parsing the program from comma-separated source text. The
+// VM is left as it was if the source doesn't parse.
+//
+// # Safety
+//
+// `vm` must be null or a live VM, and `source` must be null or a nul-terminated string.
+enum IntcodeStatus intcode_vm_load_program_str(struct IntcodeVm *vm, const char *source);
+
+// # Safety
+//
+// `vm` must be null or a live VM.
+enum IntcodeStatus intcode_vm_push_input(struct IntcodeVm *vm, intcode_element value);
+
+// Runs until the program terminates or needs an input when there are none queued
+//
+// # Safety
+//
+// `vm` must be null or a live VM.
+enum IntcodeStatus intcode_vm_run(struct IntcodeVm *vm);
+
+// Takes the oldest output that hasn't been taken yet, writing it to `value`. Returns false,
+// leaving `value` untouched, if there aren't any.
+//
+// # Safety
+//
+// `vm` must be null or a live VM, and `value` must be null or valid to write to.
+bool intcode_vm_pop_output(struct IntcodeVm *vm, intcode_element *value);
+
+// How many outputs there are that haven't been taken yet
+//
+// # Safety
+//
+// `vm` must be null or a live VM.
+size_t intcode_vm_output_len(const struct IntcodeVm *vm);
+
+// A description of the last error, or null if the last run or load succeeded. The string is
+// owned by the VM, and is only valid until the VM is next used.
+//
+// # Safety
+//
+// `vm` must be null or a live VM.
+const char *intcode_vm_last_error(const struct IntcodeVm *vm);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* INTCODE_VM_H */
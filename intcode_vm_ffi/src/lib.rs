@@ -0,0 +1,288 @@
+//! A C API for embedding the VM in other tools, declared in `include/intcode_vm.h`, which
+//! `build.rs` generates with cbindgen. Building this crate also builds the static and dynamic
+//! libraries to link against.
+//!
+//! A VM is created with `intcode_vm_create` and must be freed with `intcode_vm_destroy`. Every
+//! other function takes a pointer to one, and does nothing but return
+//! `INTCODE_STATUS_INVALID_ARGUMENT` or an empty result if it's null. Nothing that's passed in is
+//! kept hold of after the call returns.
+//!
+//! Only builds when `ProgramElement` is `i64`, as there's no portable C type for the `i128`
+//! feature's elements.
+
+use std::collections::VecDeque;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+
+use intcode_vm::{parse_program, ProgramState, StopReason};
+
+/// A value in memory, or an input or output, interchangeable with `ProgramElement`
+pub type IntcodeElement = i64;
+
+// Something else in the build enabling intcode_vm's non-additive `i128` feature would otherwise
+// just show up as type errors below
+const _: () = assert!(
+    std::mem::size_of::<intcode_vm::ProgramElement>() == std::mem::size_of::<IntcodeElement>(),
+    "intcode_vm's i128 feature can't be used with its C API",
+);
+
+/// A VM along with the message for the last error it hit, so that the pointer returned by
+/// `intcode_vm_last_error` stays valid until the next call
+pub struct IntcodeVm {
+    state: ProgramState,
+    last_error: Option<CString>,
+}
+
+impl IntcodeVm {
+    fn fail(&mut self, message: impl std::fmt::Display) -> IntcodeStatus {
+        // Messages never contain a nul, but dropping one is better than losing the whole message
+        let message = message.to_string().replace('\0', "");
+        self.last_error = CString::new(message).ok();
+        IntcodeStatus::Error
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntcodeStatus {
+    Ok = 0,
+    Terminated = 1,
+    NeedsInput = 2,
+
+    /// The VM hit an error, see `intcode_vm_last_error`
+    Error = -1,
+
+    /// A pointer that was passed in was null
+    InvalidArgument = -2,
+}
+
+/// Creates a VM with a program that just terminates
+#[no_mangle]
+pub extern "C" fn intcode_vm_create() -> *mut IntcodeVm {
+    let vm = IntcodeVm {
+        state: ProgramState::new(vec![99], VecDeque::new()),
+        last_error: None,
+    };
+    Box::into_raw(Box::new(vm))
+}
+
+/// # Safety
+///
+/// `vm` must be null or have come from `intcode_vm_create`, and mustn't be used again afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_vm_destroy(vm: *mut IntcodeVm) {
+    if !vm.is_null() {
+        drop(Box::from_raw(vm));
+    }
+}
+
+/// Replaces the VM's program with the `len` elements at `program`, and resets everything else,
+/// including any queued inputs and outputs
+///
+/// # Safety
+///
+/// `vm` must be null or a live VM, and `program` must be null or point to `len` elements.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_vm_load_program(
+    vm: *mut IntcodeVm,
+    program: *const IntcodeElement,
+    len: usize,
+) -> IntcodeStatus {
+    let vm = match vm.as_mut() {
+        Some(vm) => vm,
+        None => return IntcodeStatus::InvalidArgument,
+    };
+    let program = match len {
+        0 => &[],
+        _ if program.is_null() => return IntcodeStatus::InvalidArgument,
+        _ => std::slice::from_raw_parts(program, len),
+    };
+
+    vm.state = ProgramState::new(program.iter().cloned(), VecDeque::new());
+    vm.last_error = None;
+    IntcodeStatus::Ok
+}
+
+/// As `intcode_vm_load_program`, but parsing the program from comma-separated source text. The
+/// VM is left as it was if the source doesn't parse.
+///
+/// # Safety
+///
+/// `vm` must be null or a live VM, and `source` must be null or a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_vm_load_program_str(vm: *mut IntcodeVm, source: *const c_char) -> IntcodeStatus {
+    let vm = match vm.as_mut() {
+        Some(vm) => vm,
+        None => return IntcodeStatus::InvalidArgument,
+    };
+    if source.is_null() {
+        return IntcodeStatus::InvalidArgument;
+    }
+
+    let source = match CStr::from_ptr(source).to_str() {
+        Ok(source) => source,
+        Err(e) => return vm.fail(e),
+    };
+    match parse_program(source) {
+        Ok(program) => {
+            vm.state = ProgramState::new(program, VecDeque::new());
+            vm.last_error = None;
+            IntcodeStatus::Ok
+        }
+        Err(e) => vm.fail(e),
+    }
+}
+
+/// # Safety
+///
+/// `vm` must be null or a live VM.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_vm_push_input(vm: *mut IntcodeVm, value: IntcodeElement) -> IntcodeStatus {
+    match vm.as_mut() {
+        Some(vm) => {
+            vm.state.inputs.push_back(value);
+            IntcodeStatus::Ok
+        }
+        None => IntcodeStatus::InvalidArgument,
+    }
+}
+
+/// Runs until the program terminates or needs an input when there are none queued
+///
+/// # Safety
+///
+/// `vm` must be null or a live VM.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_vm_run(vm: *mut IntcodeVm) -> IntcodeStatus {
+    let vm = match vm.as_mut() {
+        Some(vm) => vm,
+        None => return IntcodeStatus::InvalidArgument,
+    };
+
+    vm.last_error = None;
+    loop {
+        match vm.state.run_to_next_input() {
+            Ok(StopReason::Terminated) => return IntcodeStatus::Terminated,
+            Ok(StopReason::NeedsInput) => return IntcodeStatus::NeedsInput,
+            Ok(_) => continue,
+            Err(e) => return vm.fail(e),
+        }
+    }
+}
+
+/// Takes the oldest output that hasn't been taken yet, writing it to `value`. Returns false,
+/// leaving `value` untouched, if there aren't any.
+///
+/// # Safety
+///
+/// `vm` must be null or a live VM, and `value` must be null or valid to write to.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_vm_pop_output(vm: *mut IntcodeVm, value: *mut IntcodeElement) -> bool {
+    let vm = match vm.as_mut() {
+        Some(vm) => vm,
+        None => return false,
+    };
+    if value.is_null() || vm.state.outputs.is_empty() {
+        return false;
+    }
+
+    *value = vm.state.outputs.pop_front().unwrap();
+    true
+}
+
+/// How many outputs there are that haven't been taken yet
+///
+/// # Safety
+///
+/// `vm` must be null or a live VM.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_vm_output_len(vm: *const IntcodeVm) -> usize {
+    vm.as_ref().map_or(0, |vm| vm.state.outputs.len())
+}
+
+/// A description of the last error, or null if the last run or load succeeded. The string is
+/// owned by the VM, and is only valid until the VM is next used.
+///
+/// # Safety
+///
+/// `vm` must be null or a live VM.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_vm_last_error(vm: *const IntcodeVm) -> *const c_char {
+    match vm.as_ref().and_then(|vm| vm.last_error.as_ref()) {
+        Some(message) => message.as_ptr(),
+        None => std::ptr::null(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run() {
+        unsafe {
+            let vm = intcode_vm_create();
+            assert_eq!(intcode_vm_run(vm), IntcodeStatus::Terminated);
+
+            // Outputs its input
+            let program = [3, 5, 4, 5, 99, 0];
+            assert_eq!(intcode_vm_load_program(vm, program.as_ptr(), program.len()), IntcodeStatus::Ok);
+            assert_eq!(intcode_vm_run(vm), IntcodeStatus::NeedsInput);
+            intcode_vm_push_input(vm, 21);
+            assert_eq!(intcode_vm_run(vm), IntcodeStatus::Terminated);
+            assert_eq!(intcode_vm_output_len(vm), 1);
+
+            // Outputs double each input, until it's given 0
+            let source = CString::new("3,15,1006,15,14,1002,15,2,16,4,16,1105,1,0,99,0,0").unwrap();
+            assert_eq!(intcode_vm_load_program_str(vm, source.as_ptr()), IntcodeStatus::Ok);
+            assert_eq!(intcode_vm_output_len(vm), 0);
+            intcode_vm_push_input(vm, 21);
+            assert_eq!(intcode_vm_run(vm), IntcodeStatus::NeedsInput);
+
+            let mut value = 0;
+            assert!(intcode_vm_pop_output(vm, &mut value));
+            assert_eq!(value, 42);
+            assert!(!intcode_vm_pop_output(vm, &mut value));
+
+            intcode_vm_push_input(vm, 0);
+            assert_eq!(intcode_vm_run(vm), IntcodeStatus::Terminated);
+            assert!(intcode_vm_last_error(vm).is_null());
+
+            intcode_vm_destroy(vm);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        unsafe {
+            assert_eq!(intcode_vm_run(std::ptr::null_mut()), IntcodeStatus::InvalidArgument);
+            intcode_vm_destroy(std::ptr::null_mut());
+
+            let vm = intcode_vm_create();
+            let source = CString::new("1,2,x").unwrap();
+            assert_eq!(intcode_vm_load_program_str(vm, source.as_ptr()), IntcodeStatus::Error);
+            assert!(!intcode_vm_last_error(vm).is_null());
+
+            let program = [42];
+            intcode_vm_load_program(vm, program.as_ptr(), program.len());
+            assert!(intcode_vm_last_error(vm).is_null());
+            assert_eq!(intcode_vm_run(vm), IntcodeStatus::Error);
+            let message = CStr::from_ptr(intcode_vm_last_error(vm)).to_str().unwrap();
+            assert_eq!(message, "Unrecognized opcode in instruction 42 at address 0");
+
+            intcode_vm_destroy(vm);
+        }
+    }
+
+    #[test]
+    fn test_header_up_to_date() {
+        // The checked-in header has to match what build.rs generates from this module
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/intcode_vm.h"));
+        let header = include_str!("../include/intcode_vm.h");
+        assert!(
+            header == generated,
+            "include/intcode_vm.h is out of date, copy it from {}/intcode_vm.h",
+            env!("OUT_DIR"),
+        );
+    }
+}
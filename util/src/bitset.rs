@@ -0,0 +1,220 @@
+//! Compact sets of small indices, stored as the bits of a u64.
+
+/// A set of indices in the range 0..64
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SmallBitSet(pub u64);
+
+impl SmallBitSet {
+    pub const CAPACITY: usize = 64;
+
+    pub fn new() -> Self {
+        Self(0)
+    }
+
+    /// The set containing every index in 0..n
+    pub fn full(n: usize) -> Self {
+        assert!(n <= Self::CAPACITY, "SmallBitSet can't hold {} indices", n);
+        match n {
+            Self::CAPACITY => Self(u64::MAX),
+            n => Self((1 << n) - 1),
+        }
+    }
+
+    fn bit(idx: usize) -> u64 {
+        assert!(idx < Self::CAPACITY, "Index {} is out of range for a SmallBitSet", idx);
+        1 << idx
+    }
+
+    /// Adds an index to the set, returning whether it wasn't already present
+    pub fn insert(&mut self, idx: usize) -> bool {
+        let was_present = self.contains(idx);
+        self.0 |= Self::bit(idx);
+        !was_present
+    }
+
+    /// Removes an index from the set, returning whether it was present
+    pub fn remove(&mut self, idx: usize) -> bool {
+        let was_present = self.contains(idx);
+        self.0 &= !Self::bit(idx);
+        was_present
+    }
+
+    /// A copy of this set with the given index added
+    pub fn with(self, idx: usize) -> Self {
+        Self(self.0 | Self::bit(idx))
+    }
+
+    /// A copy of this set with the given index removed
+    pub fn without(self, idx: usize) -> Self {
+        Self(self.0 & !Self::bit(idx))
+    }
+
+    pub fn contains(&self, idx: usize) -> bool {
+        self.0 & Self::bit(idx) != 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    pub fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.0 & !other.0 == 0
+    }
+
+    /// The indices in the set, in ascending order
+    pub fn iter(&self) -> Iter {
+        Iter { bits: self.0 }
+    }
+
+    /// Every subset of this set, from the empty set up to the set itself.
+    ///
+    /// There are 2^len() of them, so this is only practical for small sets.
+    pub fn subsets(&self) -> Subsets {
+        Subsets {
+            mask: self.0,
+            next: Some(0),
+        }
+    }
+}
+
+impl std::fmt::Debug for SmallBitSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl std::iter::FromIterator<usize> for SmallBitSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for idx in iter {
+            set.insert(idx);
+        }
+        set
+    }
+}
+
+impl IntoIterator for SmallBitSet {
+    type Item = usize;
+    type IntoIter = Iter;
+
+    fn into_iter(self) -> Iter {
+        self.iter()
+    }
+}
+
+pub struct Iter {
+    bits: u64,
+}
+
+impl Iterator for Iter {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.bits == 0 {
+            return None;
+        }
+
+        let idx = self.bits.trailing_zeros() as usize;
+        self.bits &= self.bits - 1;
+        Some(idx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.bits.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+
+pub struct Subsets {
+    mask: u64,
+    next: Option<u64>,
+}
+
+impl Iterator for Subsets {
+    type Item = SmallBitSet;
+
+    fn next(&mut self) -> Option<SmallBitSet> {
+        let current = self.next?;
+
+        // Counts up through the bits of the mask only, wrapping back round to 0 after the mask
+        let following = current.wrapping_sub(self.mask) & self.mask;
+        self.next = if following == 0 { None } else { Some(following) };
+
+        Some(SmallBitSet(current))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_remove() {
+        let mut set = SmallBitSet::new();
+        assert!(set.insert(3));
+        assert!(!set.insert(3));
+        assert!(set.insert(63));
+        assert!(set.contains(3));
+        assert!(!set.contains(4));
+        assert_eq!(set.len(), 2);
+
+        assert!(set.remove(3));
+        assert!(!set.remove(3));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![63]);
+    }
+
+    #[test]
+    fn test_full() {
+        assert_eq!(SmallBitSet::full(0), SmallBitSet::new());
+        assert_eq!(SmallBitSet::full(3).iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert_eq!(SmallBitSet::full(64).len(), 64);
+    }
+
+    #[test]
+    fn test_set_ops() {
+        let a = [1, 2, 5].iter().cloned().collect::<SmallBitSet>();
+        let b = [2, 7].iter().cloned().collect::<SmallBitSet>();
+        assert_eq!(a.union(b), [1, 2, 5, 7].iter().cloned().collect());
+        assert_eq!(a.intersection(b), SmallBitSet::new().with(2));
+        assert_eq!(a.difference(b), SmallBitSet::new().with(1).with(5));
+        assert!(a.intersection(b).is_subset(&a));
+        assert!(!a.is_subset(&b));
+        assert_eq!(format!("{:?}", a), "{1, 2, 5}");
+    }
+
+    #[test]
+    fn test_subsets() {
+        let set = [1, 4, 6].iter().cloned().collect::<SmallBitSet>();
+        let subsets = set.subsets().collect::<Vec<_>>();
+        assert_eq!(subsets.len(), 8);
+        assert_eq!(subsets[0], SmallBitSet::new());
+        assert_eq!(subsets[7], set);
+        assert!(subsets.iter().all(|s| s.is_subset(&set)));
+
+        let mut sorted = subsets.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted, subsets);
+    }
+
+    #[test]
+    fn test_subsets_of_empty() {
+        assert_eq!(SmallBitSet::new().subsets().collect::<Vec<_>>(), vec![SmallBitSet::new()]);
+    }
+}
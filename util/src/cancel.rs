@@ -0,0 +1,91 @@
+//! Cooperative cancellation for long running computations.
+//!
+//! Long loops call `checkpoint` every so often with their current progress metrics. Once
+//! `request_cancel` has been called (eg, from a ctrl-c handler) the next checkpoint returns
+//! `Err(Cancelled)`, and whoever requested the cancellation can report how far the computation got
+//! with `last_progress`.
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+static CANCEL_REQUESTED: AtomicBool = AtomicBool::new(false);
+static PROGRESS: Mutex<Vec<(&str, String)>> = Mutex::new(Vec::new());
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Computation was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Asks any running computation to stop at its next checkpoint
+pub fn request_cancel() {
+    CANCEL_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+pub fn is_cancel_requested() -> bool {
+    CANCEL_REQUESTED.load(Ordering::Relaxed)
+}
+
+/// Clears any pending cancellation request and recorded progress, ready for a new computation
+pub fn reset() {
+    CANCEL_REQUESTED.store(false, Ordering::SeqCst);
+    PROGRESS.lock().unwrap().clear();
+}
+
+/// Records the latest value of each of the given progress metrics
+pub fn report_progress(metrics: &[(&'static str, &dyn Display)]) {
+    let mut progress = PROGRESS.lock().unwrap();
+    for (name, value) in metrics {
+        let value = value.to_string();
+        match progress.iter_mut().find(|(existing, _)| existing == name) {
+            Some(entry) => entry.1 = value,
+            None => progress.push((name, value)),
+        }
+    }
+}
+
+/// The most recently reported value of every progress metric, in the order they were first seen
+pub fn last_progress() -> Vec<(&'static str, String)> {
+    PROGRESS.lock().unwrap().clone()
+}
+
+/// Records progress, then bails out if cancellation has been requested
+pub fn checkpoint(metrics: &[(&'static str, &dyn Display)]) -> Result<(), Cancelled> {
+    report_progress(metrics);
+    if is_cancel_requested() {
+        Err(Cancelled)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The cancellation state is global, so everything is exercised from a single test to avoid
+    // interference between tests running in parallel.
+    #[test]
+    fn test_checkpoint() {
+        reset();
+        assert_eq!(checkpoint(&[("steps", &10), ("best", &"none")]), Ok(()));
+        assert_eq!(checkpoint(&[("steps", &20)]), Ok(()));
+
+        request_cancel();
+        assert_eq!(checkpoint(&[("steps", &30)]), Err(Cancelled));
+        assert_eq!(last_progress(), vec![
+            ("steps", "30".to_string()),
+            ("best", "none".to_string()),
+        ]);
+
+        reset();
+        assert!(!is_cancel_requested());
+        assert!(last_progress().is_empty());
+    }
+}
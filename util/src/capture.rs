@@ -0,0 +1,154 @@
+//! Pulling values out of lines of structured text, eg
+//!
+//! ```
+//! use util::capture;
+//!
+//! let (x, y, z) = capture!("<x=-1, y=0, z=2>", "<x={}, y={}, z={}>", i32, i32, i32).unwrap();
+//! assert_eq!((x, y, z), (-1, 0, 2));
+//! ```
+//!
+//! Each `{}` in the pattern captures everything up to the following piece of literal text. The
+//! input and each captured value are trimmed of surrounding whitespace, everything else has to
+//! match exactly.
+
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CaptureError {
+    pub pattern: String,
+    pub input: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Failed to match '{}' against '{}': {}", self.input, self.pattern, self.reason)
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+fn error(pattern: &str, input: &str, reason: String) -> CaptureError {
+    CaptureError {
+        pattern: pattern.to_string(),
+        input: input.to_string(),
+        reason,
+    }
+}
+
+/// Matches the input against the pattern, returning the text captured by each `{}`
+pub fn capture_strs<'a>(pattern: &str, input: &'a str) -> Result<Vec<&'a str>, CaptureError> {
+    let mut literals = pattern.split("{}");
+    let mut rest = input.trim();
+
+    let prefix = literals.next().unwrap();
+    rest = rest.strip_prefix(prefix)
+        .ok_or_else(|| error(pattern, input, format!("Expected it to start with '{}'", prefix)))?;
+
+    let mut captures = Vec::new();
+    let mut literals = literals.peekable();
+    while let Some(literal) = literals.next() {
+        let is_last = literals.peek().is_none();
+        let end = if is_last {
+            rest.strip_suffix(literal).map(str::len)
+        } else if literal.is_empty() {
+            return Err(error(pattern, input, "Pattern has two adjacent {}s".to_string()));
+        } else {
+            rest.find(literal)
+        };
+
+        let end = end.ok_or_else(|| error(
+            pattern,
+            input,
+            format!("Expected '{}' after capture {}", literal, captures.len() + 1),
+        ))?;
+
+        captures.push(rest[..end].trim());
+        rest = &rest[(end + literal.len())..];
+    }
+
+    if !rest.is_empty() {
+        return Err(error(pattern, input, format!("Unexpected trailing '{}'", rest)));
+    }
+
+    Ok(captures)
+}
+
+/// Parses a single captured value, used by `capture!`
+pub fn parse_capture<T: FromStr>(capture: &str, pattern: &str, input: &str) -> Result<T, CaptureError> {
+    capture.parse::<T>().map_err(|_| error(
+        pattern,
+        input,
+        format!("'{}' isn't a valid {}", capture, std::any::type_name::<T>()),
+    ))
+}
+
+/// `capture!(input, pattern, T1, T2, ...)` matches the input against the pattern and parses each
+/// `{}` capture as the corresponding type, returning `Result<(T1, T2, ...), CaptureError>`
+#[macro_export]
+macro_rules! capture {
+    ($input:expr, $pattern:expr, $($t:ty),+ $(,)?) => {
+        (|| -> ::std::result::Result<_, $crate::capture::CaptureError> {
+            let input: &str = $input;
+            let pattern: &str = $pattern;
+            let captures = $crate::capture::capture_strs(pattern, input)?;
+
+            let expected = [$(stringify!($t)),+].len();
+            if captures.len() != expected {
+                return Err($crate::capture::CaptureError {
+                    pattern: pattern.to_string(),
+                    input: input.to_string(),
+                    reason: format!("Pattern has {} captures, but {} types were given", captures.len(), expected),
+                });
+            }
+
+            let mut captures = captures.into_iter();
+            Ok(($(
+                $crate::capture::parse_capture::<$t>(captures.next().unwrap(), pattern, input)?,
+            )+))
+        })()
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_strs() {
+        assert_eq!(capture_strs("<x={}, y={}>", "<x=1, y=-2>"), Ok(vec!["1", "-2"]));
+        assert_eq!(capture_strs("{} {}", " 7 FUEL\n"), Ok(vec!["7", "FUEL"]));
+        assert_eq!(capture_strs("{}-{}", "10-20-30"), Ok(vec!["10", "20-30"]));
+        assert_eq!(capture_strs("no captures", "no captures"), Ok(vec![]));
+        assert_eq!(capture_strs("{}°", "é°"), Ok(vec!["é"]));
+    }
+
+    #[test]
+    fn test_capture_strs_errors() {
+        let reason = |pattern, input| capture_strs(pattern, input).unwrap_err().reason;
+        assert_eq!(reason("<x={}>", "x=1>"), "Expected it to start with '<x='");
+        assert_eq!(reason("<x={}, y={}>", "<x=1 y=2>"), "Expected ', y=' after capture 1");
+        assert_eq!(reason("<x={}>", "<x=1"), "Expected '>' after capture 1");
+        assert_eq!(reason("<x={}>", "<x=é"), "Expected '>' after capture 1");
+        assert_eq!(reason("{}{}", "12"), "Pattern has two adjacent {}s");
+        assert_eq!(reason("a", "ab"), "Unexpected trailing 'b'");
+    }
+
+    #[test]
+    fn test_capture_macro() {
+        let parsed = capture!("<x=-1, y=0, z=2>", "<x={}, y={}, z={}>", i64, i64, i64);
+        assert_eq!(parsed, Ok((-1, 0, 2)));
+
+        let parsed = capture!("10 ORE", "{} {}", u64, String);
+        assert_eq!(parsed, Ok((10, "ORE".to_string())));
+    }
+
+    #[test]
+    fn test_capture_macro_errors() {
+        let err = capture!("<x=a>", "<x={}>", i32).unwrap_err();
+        assert_eq!(err.to_string(), "Failed to match '<x=a>' against '<x={}>': 'a' isn't a valid i32");
+
+        let err = capture!("1 2", "{} {}", i32).unwrap_err();
+        assert_eq!(err.reason, "Pattern has 2 captures, but 1 types were given");
+    }
+}
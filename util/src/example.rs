@@ -0,0 +1,44 @@
+//! Worked examples from the puzzle statements, so they can be checked against the solutions.
+//!
+//! Each day's crate lists its examples, for either part, as `EXAMPLES`. `aoc2019` collects them
+//! into each `Solution`, and `aoc examples` checks them. A day's `EXAMPLES` only has docs of its
+//! own when there's something particular about its examples, eg why there aren't any.
+//!
+//! Some examples need something the puzzle input doesn't give, eg a smaller image than the real
+//! puzzle's. Those are solved by an entry point of their own, given with `Example::solved_by`,
+//! rather than the day's `part_1` or `part_2`.
+
+/// Solves a single example, returning its answer formatted the same way as the solution's answer
+/// is displayed, or a description of what was wrong with the input
+pub type Solver = fn(&str) -> Result<String, String>;
+
+/// A puzzle input given in a problem statement, along with its expected answer
+#[derive(Clone, Copy, Debug)]
+pub struct Example {
+    pub part: u32,
+    pub input: &'static str,
+
+    /// The expected answer, formatted the same way as the solution's answer is displayed
+    pub answer: &'static str,
+
+    /// Solves the example in place of the day's part function, if it needs to be
+    pub solver: Option<Solver>,
+}
+
+impl Example {
+    pub const fn new(part: u32, input: &'static str, answer: &'static str) -> Self {
+        Self {
+            part,
+            input,
+            answer,
+            solver: None,
+        }
+    }
+
+    pub const fn solved_by(self, solver: Solver) -> Self {
+        Self {
+            solver: Some(solver),
+            ..self
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! Dense 2D grids, indexed by `Vec2` with the origin at the top left and y increasing downwards,
+//! as the puzzles draw them.
+//!
+//! ```
+//! use util::grid::Grid;
+//! use util::vec2::Vec2;
+//!
+//! let mut grid = Grid::parse("#.#\n...\n", |c| Some(c == '#')).unwrap();
+//! assert_eq!((grid.width(), grid.height()), (3, 2));
+//!
+//! grid.set(Vec2::new(1, 1), true);
+//! assert_eq!(grid.get(Vec2::new(1, 1)), Some(&true));
+//! assert_eq!(grid.get(Vec2::new(3, 0)), None);
+//! assert_eq!(grid.render(|&wall| if wall { '#' } else { '.' }), "#.#\n.#.");
+//! ```
+
+use crate::vec2::Vec2;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+
+    /// Row by row, starting from the top
+    cells: Vec<T>,
+}
+
+/// Why a grid couldn't be parsed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GridError {
+    /// A line isn't the same length as the first, counting lines from 1
+    Ragged { line: usize, len: usize, expected: usize },
+
+    /// The character mapper didn't accept a character
+    UnexpectedChar { c: char, pos: Vec2 },
+}
+
+impl std::fmt::Display for GridError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GridError::Ragged { line, len, expected } => {
+                write!(f, "Line {} is {} characters long, rather than {}", line, len, expected)
+            }
+            GridError::UnexpectedChar { c, pos } => write!(f, "Unexpected character '{}' at {}", c, pos),
+        }
+    }
+}
+
+impl std::error::Error for GridError {}
+
+impl<T: Clone> Grid<T> {
+    /// A grid with every cell set to `value`
+    pub fn new(width: usize, height: usize, value: T) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![value; width * height],
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    /// A grid with each cell set to `f` of its position
+    pub fn from_fn(width: usize, height: usize, mut f: impl FnMut(Vec2) -> T) -> Self {
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| Vec2::new(x as i32, y as i32)))
+            .map(&mut f)
+            .collect();
+        Self { width, height, cells }
+    }
+
+    /// Parses a grid with a cell per character, mapped to a cell by `f`. Every line has to be the
+    /// same length, and blank lines at the end are ignored.
+    pub fn parse(source: &str, mut f: impl FnMut(char) -> Option<T>) -> Result<Self, GridError> {
+        let lines = source.trim_end_matches(&['\r', '\n'][..]).lines().collect::<Vec<_>>();
+        let width = lines.first().map_or(0, |line| line.chars().count());
+
+        let mut cells = Vec::with_capacity(width * lines.len());
+        for (y, line) in lines.iter().enumerate() {
+            let len = line.chars().count();
+            if len != width {
+                return Err(GridError::Ragged { line: y + 1, len, expected: width });
+            }
+
+            for (x, c) in line.chars().enumerate() {
+                let pos = Vec2::new(x as i32, y as i32);
+                cells.push(f(c).ok_or(GridError::UnexpectedChar { c, pos })?);
+            }
+        }
+
+        Ok(Self {
+            width,
+            height: lines.len(),
+            cells,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The bottom right corner, ie the width and height as a `Vec2`
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.width as i32, self.height as i32)
+    }
+
+    pub fn contains(&self, pos: Vec2) -> bool {
+        self.idx(pos).is_some()
+    }
+
+    fn idx(&self, pos: Vec2) -> Option<usize> {
+        if pos.x >= 0 && pos.y >= 0 && (pos.x as usize) < self.width && (pos.y as usize) < self.height {
+            Some(pos.y as usize * self.width + pos.x as usize)
+        } else {
+            None
+        }
+    }
+
+    /// The cell at `pos`, or `None` if it's outside the grid
+    pub fn get(&self, pos: Vec2) -> Option<&T> {
+        self.idx(pos).map(|idx| &self.cells[idx])
+    }
+
+    pub fn get_mut(&mut self, pos: Vec2) -> Option<&mut T> {
+        self.idx(pos).map(move |idx| &mut self.cells[idx])
+    }
+
+    /// Replaces the cell at `pos`, returning what was there. Panics if `pos` is outside the grid.
+    pub fn set(&mut self, pos: Vec2, value: T) -> T {
+        let idx = self.idx(pos)
+            .unwrap_or_else(|| panic!("{} is outside a {}x{} grid", pos, self.width, self.height));
+        std::mem::replace(&mut self.cells[idx], value)
+    }
+
+    /// Row `y`, from left to right
+    pub fn row(&self, y: usize) -> &[T] {
+        &self.cells[(y * self.width)..((y + 1) * self.width)]
+    }
+
+    /// Every row, from top to bottom
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> + '_ {
+        (0..self.height).map(move |y| self.row(y))
+    }
+
+    /// Column `x`, from top to bottom
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> + '_ {
+        assert!(x < self.width, "Column {} is outside a grid {} wide", x, self.width);
+        self.cells.iter().skip(x).step_by(self.width)
+    }
+
+    /// Every column, from left to right
+    pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = &T> + '_> + '_ {
+        (0..self.width).map(move |x| self.column(x))
+    }
+
+    /// Every cell along with its position, row by row
+    pub fn iter(&self) -> impl Iterator<Item = (Vec2, &T)> + '_ {
+        let width = self.width;
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(idx, cell)| (Vec2::new((idx % width) as i32, (idx / width) as i32), cell))
+    }
+
+    /// The position of every cell for which `f` is true, row by row
+    pub fn positions<'a>(&'a self, mut f: impl FnMut(&T) -> bool + 'a) -> impl Iterator<Item = Vec2> + 'a {
+        self.iter().filter(move |(_, cell)| f(cell)).map(|(pos, _)| pos)
+    }
+
+    /// A grid of the same size, with each cell mapped by `f`
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.iter().map(f).collect(),
+        }
+    }
+
+    /// Draws each cell as a character, with a line per row
+    pub fn render(&self, mut f: impl FnMut(&T) -> char) -> String {
+        let rows = self.rows().map(|row| row.iter().map(&mut f).collect::<String>());
+        rows.collect::<Vec<_>>().join("\n")
+    }
+}
+
+impl<T> std::ops::Index<Vec2> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, pos: Vec2) -> &T {
+        self.get(pos)
+            .unwrap_or_else(|| panic!("{} is outside a {}x{} grid", pos, self.width, self.height))
+    }
+}
+
+impl<T> std::ops::IndexMut<Vec2> for Grid<T> {
+    fn index_mut(&mut self, pos: Vec2) -> &mut T {
+        let (width, height) = (self.width, self.height);
+        self.get_mut(pos)
+            .unwrap_or_else(|| panic!("{} is outside a {}x{} grid", pos, width, height))
+    }
+}
+
+/// Displays each cell in turn, with a line per row
+impl<T: std::fmt::Display> std::fmt::Display for Grid<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (y, row) in self.rows().enumerate() {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            for cell in row {
+                write!(f, "{}", cell)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let grid = Grid::parse("123\n456\n\n", |c| c.to_digit(10)).unwrap();
+        assert_eq!(grid.size(), Vec2::new(3, 2));
+        assert_eq!(grid[Vec2::new(2, 1)], 6);
+        assert_eq!(grid.to_string(), "123\n456");
+
+        assert_eq!(
+            Grid::parse("12\n3", |c| c.to_digit(10)),
+            Err(GridError::Ragged { line: 2, len: 1, expected: 2 }),
+        );
+        assert_eq!(
+            Grid::parse("12\n3x", |c| c.to_digit(10)),
+            Err(GridError::UnexpectedChar { c: 'x', pos: Vec2::new(1, 1) }),
+        );
+        assert_eq!(Grid::parse("", |c| c.to_digit(10)).unwrap().size(), Vec2::new(0, 0));
+    }
+
+    #[test]
+    fn test_rows_and_columns() {
+        let mut grid = Grid::from_fn(3, 2, |pos| pos.x + 10 * pos.y);
+        assert_eq!(grid.rows().collect::<Vec<_>>(), vec![&[0, 1, 2][..], &[10, 11, 12][..]]);
+        assert_eq!(grid.column(1).collect::<Vec<_>>(), vec![&1, &11]);
+        assert_eq!(grid.columns().map(|column| column.sum::<i32>()).collect::<Vec<_>>(), vec![10, 12, 14]);
+
+        assert_eq!(grid.set(Vec2::new(0, 1), -1), 10);
+        grid[Vec2::new(2, 0)] += 5;
+        assert_eq!(grid.positions(|&cell| cell < 2).collect::<Vec<_>>(), vec![Vec2::new(0, 0), Vec2::new(1, 0), Vec2::new(0, 1)]);
+        assert_eq!(grid.map(|&cell| cell * 2).row(0), &[0, 2, 14]);
+        assert!(!grid.contains(Vec2::new(-1, 0)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_set_outside() {
+        Grid::new(2, 2, 0).set(Vec2::new(2, 0), 1);
+    }
+}
@@ -3,4 +3,11 @@ mod integer;
 pub mod vec3;
 pub mod vec2;
 pub mod math;
-pub mod geometry;
\ No newline at end of file
+pub mod geometry;
+pub mod rle;
+pub mod cancel;
+pub mod bitset;
+pub mod capture;
+pub mod example;
+pub mod paths;
+pub mod grid;
\ No newline at end of file
@@ -0,0 +1,85 @@
+//! Where puzzle data lives on disk.
+//!
+//! Everything lives under a single data directory, the workspace root by default:
+//!
+//! ```text
+//! inputs/day_N.txt    puzzle inputs
+//! artifacts/day_N/    files produced by solutions and tools, eg rendered images
+//! cache/              scratch space which is always safe to delete
+//! answers.toml        known answers to check solutions against
+//! ```
+//!
+//! `AOC_DATA_DIR` moves the whole tree, and `AOC_INPUTS_DIR`, `AOC_ARTIFACTS_DIR`, `AOC_CACHE_DIR`
+//! and `AOC_ANSWERS_FILE` override the individual locations.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+fn env_or(var: &str, default: impl FnOnce() -> PathBuf) -> PathBuf {
+    match std::env::var_os(var) {
+        Some(path) if !path.is_empty() => PathBuf::from(path),
+        _ => default(),
+    }
+}
+
+pub fn data_dir() -> PathBuf {
+    env_or("AOC_DATA_DIR", || {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .expect("util crate isn't inside the workspace")
+            .to_path_buf()
+    })
+}
+
+pub fn inputs_dir() -> PathBuf {
+    env_or("AOC_INPUTS_DIR", || data_dir().join("inputs"))
+}
+
+pub fn input_path(day: u32) -> PathBuf {
+    inputs_dir().join(format!("day_{}.txt", day))
+}
+
+/// Reads a day's puzzle input, including the path in any error
+pub fn read_input(day: u32) -> io::Result<String> {
+    let path = input_path(day);
+    std::fs::read_to_string(&path)
+        .map_err(|e| io::Error::new(e.kind(), format!("Failed to read {}: {}", path.display(), e)))
+}
+
+pub fn artifacts_dir() -> PathBuf {
+    env_or("AOC_ARTIFACTS_DIR", || data_dir().join("artifacts"))
+}
+
+/// Directory for a single day's artifacts, created if it doesn't exist yet
+pub fn create_artifacts_dir(day: u32) -> io::Result<PathBuf> {
+    let dir = artifacts_dir().join(format!("day_{}", day));
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+pub fn cache_dir() -> PathBuf {
+    env_or("AOC_CACHE_DIR", || data_dir().join("cache"))
+}
+
+pub fn answers_path() -> PathBuf {
+    env_or("AOC_ANSWERS_FILE", || data_dir().join("answers.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_layout() {
+        assert!(input_path(1).ends_with("inputs/day_1.txt"));
+        assert!(read_input(1).is_ok());
+
+        let err = read_input(99).unwrap_err();
+        assert!(err.to_string().contains("day_99.txt"));
+
+        assert_eq!(inputs_dir(), data_dir().join("inputs"));
+        assert_eq!(artifacts_dir(), data_dir().join("artifacts"));
+        assert_eq!(cache_dir(), data_dir().join("cache"));
+        assert_eq!(answers_path(), data_dir().join("answers.toml"));
+    }
+}
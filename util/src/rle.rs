@@ -0,0 +1,83 @@
+//! Run-length encoding helpers.
+//!
+//! `runs` groups consecutive equal items into `(item, run_length)` pairs, and `expand` is its
+//! inverse.
+
+use std::iter::Peekable;
+
+pub struct Runs<I: Iterator> {
+    inner: Peekable<I>,
+}
+
+impl<I> Iterator for Runs<I>
+where
+    I: Iterator,
+    I::Item: PartialEq,
+{
+    type Item = (I::Item, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.inner.next()?;
+        let mut len = 1;
+        while self.inner.next_if(|next| *next == item).is_some() {
+            len += 1;
+        }
+
+        Some((item, len))
+    }
+}
+
+/// Groups consecutive equal items, eg "aabccc" -> [('a', 2), ('b', 1), ('c', 3)]
+pub fn runs<I>(iter: I) -> Runs<I::IntoIter>
+where
+    I: IntoIterator,
+    I::Item: PartialEq,
+{
+    Runs {
+        inner: iter.into_iter().peekable(),
+    }
+}
+
+/// The inverse of `runs`, eg [('a', 2), ('b', 1)] -> "aab"
+pub fn expand<T, I>(runs: I) -> impl Iterator<Item = T>
+where
+    T: Clone,
+    I: IntoIterator<Item = (T, usize)>,
+{
+    runs.into_iter()
+        .flat_map(|(item, len)| std::iter::repeat_n(item, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runs() {
+        let encoded = runs("aabccc".chars()).collect::<Vec<_>>();
+        assert_eq!(encoded, vec![('a', 2), ('b', 1), ('c', 3)]);
+    }
+
+    #[test]
+    fn test_runs_empty() {
+        assert_eq!(runs(Vec::<u8>::new()).count(), 0);
+    }
+
+    #[test]
+    fn test_runs_single() {
+        assert_eq!(runs(vec![7]).collect::<Vec<_>>(), vec![(7, 1)]);
+    }
+
+    #[test]
+    fn test_expand() {
+        let decoded = expand(vec![('a', 2), ('b', 1), ('c', 3)]).collect::<String>();
+        assert_eq!(decoded, "aabccc");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let digits = vec![1, 1, 2, 2, 2, 3, 1, 1];
+        let roundtrip = expand(runs(digits.iter().cloned())).collect::<Vec<_>>();
+        assert_eq!(roundtrip, digits);
+    }
+}
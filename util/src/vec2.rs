@@ -21,6 +21,30 @@ impl Vec2 {
     pub fn l1_norm(&self) -> i32 {
         self.x.abs() + self.y.abs()
     }
+
+    /// Component-wise minimum
+    pub fn min(self, other: Self) -> Self {
+        Self {
+            x: self.x.min(other.x),
+            y: self.y.min(other.y),
+        }
+    }
+
+    /// Component-wise maximum
+    pub fn max(self, other: Self) -> Self {
+        Self {
+            x: self.x.max(other.x),
+            y: self.y.max(other.y),
+        }
+    }
+
+    /// Clamps each component to lie within the corresponding components of `min` and `max`
+    pub fn clamp(self, min: Self, max: Self) -> Self {
+        Self {
+            x: self.x.clamp(min.x, max.x),
+            y: self.y.clamp(min.y, max.y),
+        }
+    }
 }
 
 impl std::fmt::Display for Vec2 {
@@ -72,4 +96,24 @@ impl std::ops::SubAssign for Vec2 {
         self.x -= other.x;
         self.y -= other.y;
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_min_max() {
+        let a = Vec2::new(1, -5);
+        let b = Vec2::new(-2, 3);
+        assert_eq!(a.min(b), Vec2::new(-2, -5));
+        assert_eq!(a.max(b), Vec2::new(1, 3));
+    }
+
+    #[test]
+    fn test_clamp() {
+        let lo = Vec2::new(0, 0);
+        let hi = Vec2::new(10, 5);
+        assert_eq!(Vec2::new(-3, 7).clamp(lo, hi), Vec2::new(0, 5));
+        assert_eq!(Vec2::new(4, 2).clamp(lo, hi), Vec2::new(4, 2));
+    }
+}
@@ -25,6 +25,32 @@ impl Vec3 {
         self.x.abs() + self.y.abs() + self.z.abs()
     }
 
+    /// Component-wise minimum
+    pub fn min(self, other: Self) -> Self {
+        Self {
+            x: self.x.min(other.x),
+            y: self.y.min(other.y),
+            z: self.z.min(other.z),
+        }
+    }
+
+    /// Component-wise maximum
+    pub fn max(self, other: Self) -> Self {
+        Self {
+            x: self.x.max(other.x),
+            y: self.y.max(other.y),
+            z: self.z.max(other.z),
+        }
+    }
+
+    /// Clamps each component to lie within the corresponding components of `min` and `max`
+    pub fn clamp(self, min: Self, max: Self) -> Self {
+        Self {
+            x: self.x.clamp(min.x, max.x),
+            y: self.y.clamp(min.y, max.y),
+            z: self.z.clamp(min.z, max.z),
+        }
+    }
 }
 
 impl std::fmt::Display for Vec3 {
@@ -81,4 +107,17 @@ impl std::ops::SubAssign for Vec3 {
         self.y -= other.y;
         self.z -= other.z;
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_min_max_clamp() {
+        let a = Vec3::new(1, -5, 0);
+        let b = Vec3::new(-2, 3, 0);
+        assert_eq!(a.min(b), Vec3::new(-2, -5, 0));
+        assert_eq!(a.max(b), Vec3::new(1, 3, 0));
+        assert_eq!(Vec3::new(-3, 7, 2).clamp(a.min(b), a.max(b)), Vec3::new(-2, 3, 0));
+    }
+}